@@ -1,9 +1,27 @@
 //! Parsers for [apgcode](https://www.conwaylife.com/wiki/Apgcode) format
 //! and [Extended Wechsler format](https://www.conwaylife.com/wiki/Apgcode#Extended_Wechsler_Format).
 
-use crate::Coordinates;
+pub use crate::evolve::Evolver;
+use crate::{
+    evolve::{canonical, displacement},
+    extend_bounding_box,
+    input::SharedIoError,
+    rle::write_rle,
+    BoundingBox, CellData, Convention, Coordinates, Input, ParseOptions, RuleInfo, RuleOrigin,
+};
 use displaydoc::Display;
-use std::str::Bytes;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "smallvec")]
+use smallvec::SmallVec;
+use std::{
+    error::Error as StdError,
+    fmt::{self, Display, Formatter},
+    io::{Error as IoError, ErrorKind},
+    iter::{Copied, Filter},
+    slice::Iter as SliceIter,
+    str::{from_utf8, FromStr, Split},
+};
 use thiserror::Error;
 
 /// Errors that can be returned when parsing a apgcode string.
@@ -13,6 +31,182 @@ pub enum Error {
     UnexpectedChar(char),
     /// Pattern not encoded in extended Wechsler format
     Unencodable,
+    /// Invalid apgcode with context: {0}.
+    InvalidContext(String),
+    /// Pattern too large to be encoded in extended Wechsler format.
+    TooLarge,
+    /// Coordinates out of range in the convention: {0:?}.
+    CoordinateOverflow(Coordinates),
+    /// Error when writing the RLE: {0}.
+    IoError(#[from] SharedIoError),
+}
+
+impl From<IoError> for Error {
+    fn from(error: IoError) -> Self {
+        Error::IoError(error.into())
+    }
+}
+
+/// Maximum width and height of a pattern encoded by [`encode_wechsler`],
+/// so that a few distant cells don't produce a huge string.
+pub const MAX_WECHSLER_SPAN: u64 = 1 << 24;
+
+/// The bytes of a string without ASCII whitespaces, so that codes wrapped across lines
+/// can be parsed.
+type Compact<'a> = Filter<Copied<SliceIter<'a, u8>>, fn(&u8) -> bool>;
+
+/// The bytes of a string without ASCII whitespaces.
+fn compact(bytes: &[u8]) -> Compact<'_> {
+    bytes.iter().copied().filter(|c| !c.is_ascii_whitespace())
+}
+
+/// The living cells of an object, stored inline for objects with at most 64 cells,
+/// which are most of the objects in a census. Returned by [`ApgCode::decode_small`].
+#[cfg(feature = "smallvec")]
+#[cfg_attr(docs_rs, doc(cfg(feature = "smallvec")))]
+pub type SmallCells = SmallVec<[Coordinates; 64]>;
+
+/// How Catagolue emulates a rule with `B0`, where dead cells in the background are born.
+///
+/// The cells of an apgcode in such a rule are not the cells of the pattern
+/// in every generation, so they can't be evolved directly.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum B0Rule {
+    /// `B0` without `Smax`, e.g., `b0135s2`: the background alternates between dead and alive.
+    ///
+    /// The cells are the ones in the even generations, where the background is dead.
+    Alternating,
+    /// `B0` with `Smax`, e.g., `b0123478s34678`: the background stays alive.
+    ///
+    /// The cells are the ones of the complement pattern, i.e., the dead cells in the live background.
+    Complement,
+}
+
+impl B0Rule {
+    /// Classify a Catagolue rule slug, e.g., `b0135s2`.
+    ///
+    /// Returns `None` if the rule has no `B0`.
+    /// Hexagonal (`h`) and von Neumann (`v`) rules are supported.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use ca_formats::apgcode::B0Rule;
+    ///
+    /// assert_eq!(B0Rule::from_slug("b3s23"), None);
+    /// assert_eq!(B0Rule::from_slug("b0135s2"), Some(B0Rule::Alternating));
+    /// assert_eq!(B0Rule::from_slug("b0123478s34678"), Some(B0Rule::Complement));
+    /// assert_eq!(B0Rule::from_slug("b0123s0123456h"), Some(B0Rule::Complement));
+    /// ```
+    pub fn from_slug(slug: &str) -> Option<Self> {
+        let slug = slug.strip_prefix('g').map_or(slug, |rest| {
+            rest.trim_start_matches(|c: char| c.is_ascii_digit())
+        });
+        let rest = slug.strip_prefix("b0")?;
+        let max = match slug.as_bytes().last() {
+            Some(b'h') => '6',
+            Some(b'v') => '4',
+            _ => '8',
+        };
+        let (_, survival) = rest.split_once('s')?;
+        if survival.contains(max) {
+            Some(B0Rule::Complement)
+        } else {
+            Some(B0Rule::Alternating)
+        }
+    }
+}
+
+/// Displacement of a pattern in a period, e.g., `(1,1)c/4` for a glider.
+///
+/// It is written in the same notation by [`Display`].
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Velocity {
+    /// Displacement in the x direction.
+    pub dx: i64,
+    /// Displacement in the y direction.
+    pub dy: i64,
+    /// Period of the pattern.
+    pub period: u64,
+}
+
+impl Display for Velocity {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "({},{})c/{}", self.dx, self.dy, self.period)
+    }
+}
+
+/// The class of an object, i.e., a still life, an oscillator or a spaceship,
+/// with its period, velocity, population and bounding box.
+///
+/// It is a typed record for databases of objects, which is the same whichever format
+/// the object comes from: [`ApgCode::object_class`] reads it from an apgcode,
+/// and [`Pattern::object_class`](crate::Pattern::object_class) finds it by evolving a pattern.
+///
+/// # Example
+///
+/// ```rust
+/// use ca_formats::apgcode::{ApgCode, PatternType};
+///
+/// let glider = ApgCode::new("xq4_153").unwrap().object_class().unwrap();
+/// assert_eq!(glider.pattern_type, PatternType::Spaceship);
+/// assert_eq!((glider.period, glider.population), (4, 5));
+/// assert_eq!(glider.bounding_box, Some((0, 0, 2, 2)));
+/// ```
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ObjectClass {
+    /// Type of the object.
+    pub pattern_type: PatternType,
+    /// Period of the object, `1` for a still life.
+    pub period: u64,
+    /// Displacement of the object in a period.
+    ///
+    /// It is zero for a still life or an oscillator, and `None` if it is not known,
+    /// e.g., for a spaceship read from an apgcode without a simulator.
+    /// See [`ApgCode::velocity`].
+    pub velocity: Option<Velocity>,
+    /// Number of living cells in the given phase.
+    pub population: usize,
+    /// Bounding box of the living cells in the given phase, `None` if there is none.
+    pub bounding_box: Option<BoundingBox>,
+}
+
+impl ObjectClass {
+    /// The class of the living cells, whose displacement in a period is `(dx, dy)`.
+    pub(crate) fn new(cells: &[Coordinates], period: u64, (dx, dy): Coordinates) -> Self {
+        let pattern_type = if (dx, dy) != (0, 0) {
+            PatternType::Spaceship
+        } else if period == 1 {
+            PatternType::StillLife
+        } else {
+            PatternType::Oscillator
+        };
+        let mut bounding_box = None;
+        for &cell in cells {
+            extend_bounding_box(&mut bounding_box, cell);
+        }
+        ObjectClass {
+            pattern_type,
+            period,
+            velocity: Some(Velocity { dx, dy, period }),
+            population: cells.len(),
+            bounding_box,
+        }
+    }
+}
+
+/// Errors that can be returned by [`ApgCode::phases`] and [`ApgCode::velocity`].
+#[derive(Clone, Debug, Eq, Error, Display, PartialEq)]
+pub enum PhaseError<E: StdError + 'static> {
+    /// {0}
+    Apgcode(#[from] Error),
+    /// Error when evolving the pattern: {0}.
+    Evolver(#[source] E),
+    /// Can't evolve a pattern in a rule with B0: {0:?}.
+    B0Rule(B0Rule),
 }
 
 /// A parser for [Extended Wechsler format](https://www.conwaylife.com/wiki/Apgcode#Extended_Wechsler_Format).
@@ -21,6 +215,9 @@ pub enum Error {
 /// e.g. `153` in `xq4_153`.
 ///
 /// As an iterator, it iterates over the living cells.
+/// The cells are returned strip by strip, where each strip consists of 5 rows.
+/// In each strip, the cells are returned column by column, from top to bottom.
+/// Use [`sorted`](crate::sorted) to sort them in row-major order.
 ///
 /// Reading from files is not supported, since the format is usually short and not stored in a file.
 ///
@@ -39,8 +236,8 @@ pub enum Error {
 #[must_use]
 #[derive(Clone, Debug)]
 pub struct Wechsler<'a> {
-    /// An iterator over bytes of the string.
-    bytes: Bytes<'a>,
+    /// An iterator over bytes of the string, without whitespaces.
+    bytes: Compact<'a>,
 
     /// Coordinates of the current cell.
     position: Coordinates,
@@ -50,17 +247,185 @@ pub struct Wechsler<'a> {
 
     /// Index of the current cell in the current strip.
     index: u8,
+
+    /// Convention of the returned coordinates.
+    convention: Convention,
 }
 
 impl<'a> Wechsler<'a> {
     /// Creates a new parser instance from a string.
+    ///
+    /// ASCII whitespaces in the string, e.g., line breaks, are ignored.
     pub fn new(string: &'a str) -> Self {
+        Self::from_bytes(string.as_bytes())
+    }
+
+    /// Creates a new parser instance from bytes, e.g., a slice of a large buffer,
+    /// without checking that they are valid UTF-8.
+    ///
+    /// ASCII whitespaces are ignored, and other bytes that are not in Extended Wechsler format
+    /// are errors.
+    pub fn from_bytes(bytes: &'a [u8]) -> Self {
         Wechsler {
-            bytes: string.bytes(),
+            bytes: compact(bytes),
             position: (0, 0),
             current_strip: 0,
             index: 5,
+            convention: Convention::default(),
+        }
+    }
+
+    /// Creates a new parser instance from a string with the [`ParseOptions`]
+    /// that make sense for this format, i.e., the convention.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use ca_formats::{apgcode::Wechsler, Convention, ParseOptions};
+    ///
+    /// let options = ParseOptions::new().with_convention(Convention { yx: true, y_up: false });
+    /// let blinker = Wechsler::new_with_options("7", options);
+    /// let cells = blinker.collect::<Result<Vec<_>, _>>().unwrap();
+    /// assert_eq!(cells, vec![(0, 0), (1, 0), (2, 0)]);
+    /// ```
+    pub fn new_with_options(string: &'a str, options: ParseOptions) -> Self {
+        Self::new(string).with_convention(options.convention)
+    }
+
+    /// Use another [`Convention`] for the returned coordinates.
+    fn with_convention(mut self, convention: Convention) -> Self {
+        self.convention = convention;
+        self
+    }
+
+    /// Count the remaining living cells by the popcounts of the strip characters,
+    /// without computing the coordinates of each cell.
+    ///
+    /// This is faster than [`count`](Iterator::count) when only the population is needed.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use ca_formats::apgcode::Wechsler;
+    ///
+    /// let glider = Wechsler::new("153");
+    /// assert_eq!(glider.population().unwrap(), 5);
+    /// ```
+    pub fn population(mut self) -> Result<usize, Error> {
+        let mut population = 0;
+        if self.index < 5 {
+            population += (self.current_strip >> self.index).count_ones() as usize;
+        }
+        while let Some(c) = self.bytes.next() {
+            match c {
+                b'0'..=b'9' => population += (c - b'0').count_ones() as usize,
+                b'a'..=b'v' => population += (c - b'a' + 10).count_ones() as usize,
+                b'w' | b'x' | b'z' => (),
+                b'y' => match self.bytes.next() {
+                    Some(b'0'..=b'9' | b'a'..=b'z') => (),
+                    Some(c) => return Err(Error::UnexpectedChar(char::from(c))),
+                    None => return Err(Error::UnexpectedChar('y')),
+                },
+                _ => return Err(Error::UnexpectedChar(char::from(c))),
+            }
+        }
+        Ok(population)
+    }
+
+    /// Compute the bounding box of the remaining living cells from the strips,
+    /// without computing the coordinates of each cell.
+    ///
+    /// The convention is applied. Returns `Ok(None)` if there is no living cell.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use ca_formats::apgcode::Wechsler;
+    ///
+    /// let glider = Wechsler::new("153");
+    /// assert_eq!(glider.bounding_box().unwrap(), Some((0, 0, 2, 2)));
+    /// ```
+    pub fn bounding_box(mut self) -> Result<Option<BoundingBox>, Error> {
+        let mut bounding_box = None;
+        if self.index < 5 {
+            let strip = self.current_strip & !((1 << self.index) - 1);
+            self.extend_by_strip(&mut bounding_box, strip)?;
+            self.position.0 += 1;
+        }
+        while let Some(c) = self.bytes.next() {
+            match c {
+                b'0' => self.position.0 += 1,
+                b'1'..=b'9' | b'a'..=b'v' => {
+                    let strip = if c <= b'9' { c - b'0' } else { c - b'a' + 10 };
+                    self.extend_by_strip(&mut bounding_box, strip)?;
+                    self.position.0 += 1;
+                }
+                b'w' => self.position.0 += 2,
+                b'x' => self.position.0 += 3,
+                b'y' => {
+                    let n = match self.bytes.next() {
+                        Some(c @ b'0'..=b'9') => c - b'0',
+                        Some(c @ b'a'..=b'z') => c - b'a' + 10,
+                        Some(c) => return Err(Error::UnexpectedChar(char::from(c))),
+                        None => return Err(Error::UnexpectedChar('y')),
+                    };
+                    self.position.0 += 4 + n as i64;
+                }
+                b'z' => {
+                    self.position.0 = 0;
+                    self.position.1 += 5;
+                }
+                _ => return Err(Error::UnexpectedChar(char::from(c))),
+            }
+        }
+        Ok(bounding_box)
+    }
+
+    /// Collect the remaining living cells into a [`SmallCells`], without a heap allocation
+    /// if there are at most 64 of them.
+    #[cfg(feature = "smallvec")]
+    #[cfg_attr(docs_rs, doc(cfg(feature = "smallvec")))]
+    pub fn decode_small(self) -> Result<SmallCells, Error> {
+        self.collect()
+    }
+
+    /// Compute the width and the height of the remaining living cells from the strips,
+    /// without computing the coordinates of each cell.
+    ///
+    /// The convention is ignored. Returns `(0, 0)` if there is no living cell.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use ca_formats::apgcode::Wechsler;
+    ///
+    /// let twin_bee_shuttle = Wechsler::new("033y133zzzckgsxsgkczz0cc");
+    /// assert_eq!(twin_bee_shuttle.dimensions().unwrap(), (11, 29));
+    /// ```
+    pub fn dimensions(mut self) -> Result<(u64, u64), Error> {
+        self.convention = Convention::default();
+        Ok(dimensions(self.bounding_box()?))
+    }
+
+    /// Extend the bounding box by a strip at the current position.
+    fn extend_by_strip(
+        &self,
+        bounding_box: &mut Option<BoundingBox>,
+        strip: u8,
+    ) -> Result<(), Error> {
+        if strip != 0 {
+            let (x, y) = self.position;
+            let top = y + strip.trailing_zeros() as i64;
+            let bottom = y + 7 - strip.leading_zeros() as i64;
+            for cell in [(x, top), (x, bottom)] {
+                let position = self.convention.apply(cell);
+                extend_bounding_box(
+                    bounding_box,
+                    position.ok_or(Error::CoordinateOverflow(cell))?,
+                );
+            }
         }
+        Ok(())
     }
 }
 
@@ -84,7 +449,11 @@ impl<'a> Iterator for Wechsler<'a> {
                         if self.index == 5 {
                             self.position.0 += 1;
                         }
-                        return Some(Ok(cell));
+                        return Some(
+                            self.convention
+                                .apply(cell)
+                                .ok_or(Error::CoordinateOverflow(cell)),
+                        );
                     }
                 }
             } else if let Some(c) = self.bytes.next() {
@@ -125,8 +494,211 @@ impl<'a> Iterator for Wechsler<'a> {
     }
 }
 
+/// Characters that encode a strip of 5 cells in Extended Wechsler format.
+const STRIP_CHARS: &[u8; 32] = b"0123456789abcdefghijklmnopqrstuv";
+
+/// Append a run of empty strips, i.e., `0`, `w`, `x` or `y` followed by a character.
+fn push_zeros(string: &mut String, mut count: usize) {
+    while count > 0 {
+        match count {
+            1 => string.push('0'),
+            2 => string.push('w'),
+            3 => string.push('x'),
+            _ => {
+                let n = count.min(39);
+                string.push('y');
+                string.push(char::from(b"0123456789abcdefghijklmnopqrstuvwxyz"[n - 4]));
+                count -= n;
+                continue;
+            }
+        }
+        return;
+    }
+}
+
+/// Encodes living cells in [Extended Wechsler format](https://www.conwaylife.com/wiki/Apgcode#Extended_Wechsler_Format).
+///
+/// The cells can be given in any order, and duplicated cells are ignored.
+/// The cells are translated so that the upper left corner of the bounding box is `(0, 0)`.
+///
+/// Returns [`Error::TooLarge`] if the width or the height of the bounding box
+/// is larger than [`MAX_WECHSLER_SPAN`].
+///
+/// This is not the canonical apgcode: the canonical one is the shortest encoding
+/// among all orientations (and phases) of the pattern. See [`encode_canonical`].
+///
+/// # Example
+///
+/// ```rust
+/// use ca_formats::apgcode::encode_wechsler;
+///
+/// let glider = [(0, 0), (1, 0), (1, 2), (2, 0), (2, 1)];
+/// assert_eq!(encode_wechsler(glider), Ok(String::from("153")));
+/// ```
+pub fn encode_wechsler<C: IntoIterator<Item = Coordinates>>(cells: C) -> Result<String, Error> {
+    let mut cells = translate_to_origin(cells.into_iter().collect())?;
+    cells.sort_by_key(|&(x, y)| (y / 5, x, y));
+    cells.dedup();
+    let mut string = String::new();
+    let mut strip_y = 0;
+    let mut zeros = 0;
+    let mut columns = cells.chunk_by(|a, b| a.1 / 5 == b.1 / 5 && a.0 == b.0);
+    let mut next_column = columns.next();
+    let mut x = 0;
+    while let Some(column) = next_column {
+        let (column_x, column_y) = column[0];
+        let strip = column_y / 5;
+        if strip > strip_y {
+            for _ in strip_y..strip {
+                string.push('z');
+            }
+            strip_y = strip;
+            x = 0;
+            zeros = 0;
+        }
+        zeros += (column_x - x) as usize;
+        push_zeros(&mut string, zeros);
+        zeros = 0;
+        let bits = column.iter().fold(0, |bits, &(_, y)| bits | 1 << (y % 5));
+        string.push(char::from(STRIP_CHARS[bits]));
+        x = column_x + 1;
+        next_column = columns.next();
+    }
+    if string.is_empty() {
+        string.push('0');
+    }
+    Ok(string)
+}
+
+/// Translates the cells so that the upper left corner of the bounding box is `(0, 0)`.
+///
+/// Returns [`Error::TooLarge`] if the width or the height of the bounding box
+/// is larger than [`MAX_WECHSLER_SPAN`], so that the translated coordinates are small
+/// enough to be negated or added without overflow.
+fn translate_to_origin(cells: Vec<Coordinates>) -> Result<Vec<Coordinates>, Error> {
+    let (Some(x_min), Some(y_min)) = (
+        cells.iter().map(|&(x, _)| x).min(),
+        cells.iter().map(|&(_, y)| y).min(),
+    ) else {
+        return Ok(cells);
+    };
+    let offset = |value: i64, min: i64| {
+        i64::try_from(i128::from(value) - i128::from(min))
+            .ok()
+            .filter(|&offset| offset < MAX_WECHSLER_SPAN as i64)
+            .ok_or(Error::TooLarge)
+    };
+    cells
+        .into_iter()
+        .map(|(x, y)| Ok((offset(x, x_min)?, offset(y, y_min)?)))
+        .collect()
+}
+
+/// The 8 orientations of a pattern, i.e., its rotations and reflections.
+const ORIENTATIONS: [fn(Coordinates) -> Coordinates; 8] = [
+    |(x, y)| (x, y),
+    |(x, y)| (-x, y),
+    |(x, y)| (x, -y),
+    |(x, y)| (-x, -y),
+    |(x, y)| (y, x),
+    |(x, y)| (-y, x),
+    |(x, y)| (y, -x),
+    |(x, y)| (-y, -x),
+];
+
+/// Encodes a phase of a pattern as a canonical apgcode, as [Catagolue](https://catagolue.hatsya.com)
+/// does: the pattern is trimmed, and encoded in [Extended Wechsler format](encode_wechsler)
+/// in each of its 8 orientations, and the shortest code is chosen,
+/// or the lexicographically smallest one among the shortest codes.
+///
+/// The number in the prefix is the population for a still life, and `period` otherwise.
+///
+/// Catagolue also chooses the code among all the phases of an oscillator or a spaceship,
+/// so the code of a single phase is canonical only if it is the best phase.
+/// Use [`encode_canonical_phases`] with the phases from a simulator otherwise,
+/// e.g., from [`ApgCode::phases`].
+///
+/// # Example
+///
+/// ```rust
+/// use ca_formats::apgcode::{encode_canonical, PatternType};
+///
+/// // A glider flying to the lower left.
+/// let glider = [(1, 0), (0, 1), (0, 2), (1, 2), (2, 2)];
+/// let code = encode_canonical(glider, PatternType::Spaceship, 4).unwrap();
+/// assert_eq!(code.to_string(), "xq4_153");
+///
+/// let boat = [(0, 0), (1, 0), (0, 1), (2, 1), (1, 2)];
+/// let code = encode_canonical(boat, PatternType::StillLife, 1).unwrap();
+/// assert_eq!(code.to_string(), "xs5_253");
+/// ```
+pub fn encode_canonical<C>(
+    cells: C,
+    pattern_type: PatternType,
+    period: u64,
+) -> Result<Classification, Error>
+where
+    C: IntoIterator<Item = Coordinates>,
+{
+    encode_canonical_phases([cells], pattern_type, period)
+}
+
+/// Encodes a pattern as a canonical apgcode, choosing the best code among
+/// the 8 orientations of all the given phases. See [`encode_canonical`].
+///
+/// The population of a still life is the population of the first phase.
+/// Returns [`Error::TooLarge`] if a phase is too large for [`encode_wechsler`].
+///
+/// # Example
+///
+/// ```rust
+/// use ca_formats::apgcode::{encode_canonical, encode_canonical_phases, PatternType};
+///
+/// let blinker = [[(0, 1), (1, 1), (2, 1)], [(1, 0), (1, 1), (1, 2)]];
+/// let code = encode_canonical_phases(blinker, PatternType::Oscillator, 2).unwrap();
+/// assert_eq!(code.to_string(), "xp2_7");
+/// assert_eq!(Ok(code), encode_canonical(blinker[1], PatternType::Oscillator, 2));
+/// ```
+pub fn encode_canonical_phases<P, C>(
+    phases: P,
+    pattern_type: PatternType,
+    period: u64,
+) -> Result<Classification, Error>
+where
+    P: IntoIterator<Item = C>,
+    C: IntoIterator<Item = Coordinates>,
+{
+    let mut population = None;
+    let mut best: Option<String> = None;
+    for phase in phases {
+        let mut cells = translate_to_origin(phase.into_iter().collect())?;
+        cells.sort_unstable();
+        cells.dedup();
+        population.get_or_insert(cells.len() as u64);
+        for orientation in ORIENTATIONS {
+            let code = encode_wechsler(cells.iter().map(|&cell| orientation(cell)))?;
+            let better = best
+                .as_ref()
+                .is_none_or(|best| (code.len(), &code) < (best.len(), best));
+            if better {
+                best = Some(code);
+            }
+        }
+    }
+    let number = match pattern_type {
+        PatternType::StillLife => population.unwrap_or_default(),
+        _ => period,
+    };
+    Ok(Classification {
+        pattern_type,
+        number,
+        wechsler: best.unwrap_or_else(|| String::from("0")),
+    })
+}
+
 /// Type of a pattern.
 #[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum PatternType {
     /// [Still life](https://conwaylife.com/wiki/Still_life).
     StillLife,
@@ -136,6 +708,24 @@ pub enum PatternType {
     Spaceship,
 }
 
+/// Width and height of a bounding box.
+fn dimensions(bounding_box: Option<BoundingBox>) -> (u64, u64) {
+    match bounding_box {
+        Some((x_min, y_min, x_max, y_max)) => {
+            ((x_max - x_min + 1) as u64, (y_max - y_min + 1) as u64)
+        }
+        None => (0, 0),
+    }
+}
+
+/// Whether a string is a rule slug or a symmetry used by Catagolue, e.g., `b3s23` or `D8_4`.
+fn is_slug(string: &str) -> bool {
+    !string.is_empty()
+        && string
+            .bytes()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, b'_' | b'-' | b'+'))
+}
+
 /// A parser for [apgcode](https://www.conwaylife.com/wiki/Apgcode) format.
 ///
 /// Only supports patterns that are encoded in Extended Wechsler format,
@@ -143,6 +733,13 @@ pub enum PatternType {
 /// Rules with more than 2 states are not yet supported.
 ///
 /// As an iterator, it iterates over the living cells.
+/// The cells are returned in the same order as [`Wechsler`].
+///
+/// Some codes emitted by Catagolue have several components separated by `_`,
+/// e.g., `xp2_31a08zy0123cko_3`. The components are decoded one by one at the same origin,
+/// so the cells are the union of the cells of the [`components`](ApgCode::components).
+/// A cell that appears in several components is returned more than once;
+/// use [`Duplicates`](crate::duplicates::Duplicates) to skip them.
 ///
 /// Reading from files is not supported, since apgcode is usually short and not stored in a file.
 ///
@@ -163,17 +760,41 @@ pub enum PatternType {
 #[must_use]
 #[derive(Clone, Debug)]
 pub struct ApgCode<'a> {
+    code: &'a str,
     pattern_type: PatternType,
     period: u64,
     wechsler: Wechsler<'a>,
+    components: Split<'a, char>,
+    rule: Option<&'a str>,
+    symmetry: Option<&'a str>,
 }
 
 impl<'a> ApgCode<'a> {
     /// Creates a new parser instance from a string.
+    ///
+    /// Long apgcodes are sometimes wrapped across lines when they are displayed, so
+    /// whitespaces around and inside the code, e.g., line breaks, are ignored.
+    /// Use [`new_strict`](ApgCode::new_strict) to reject them.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use ca_formats::apgcode::ApgCode;
+    ///
+    /// let glider = ApgCode::new(" xq4_1\n53\n").unwrap();
+    /// assert_eq!(glider.to_string(), "xq4_153");
+    /// assert_eq!(glider.count(), 5);
+    /// ```
     pub fn new(string: &'a str) -> Result<Self, Error> {
+        let string = string.trim();
         let mut split = string.split('_');
-        let prefix = split.next().ok_or(Error::Unencodable)?;
-        if prefix[2..].bytes().any(|c| !c.is_ascii_digit()) {
+        let prefix = split
+            .next()
+            .ok_or(Error::Unencodable)?
+            .split_ascii_whitespace()
+            .collect::<String>();
+        let digits = prefix.get(2..).ok_or(Error::Unencodable)?;
+        if digits.bytes().any(|c| !c.is_ascii_digit()) {
             return Err(Error::Unencodable);
         }
         let pattern_type = match &prefix[..2] {
@@ -185,17 +806,271 @@ impl<'a> ApgCode<'a> {
         let period = if pattern_type == PatternType::StillLife {
             1
         } else {
-            prefix[2..].parse().map_err(|_| Error::Unencodable)?
+            digits.parse().map_err(|_| Error::Unencodable)?
         };
         let wechsler_string = split.next().ok_or(Error::Unencodable)?;
         let wechsler = Wechsler::new(wechsler_string);
         Ok(ApgCode {
+            code: string,
             pattern_type,
             period,
             wechsler,
+            components: split,
+            rule: None,
+            symmetry: None,
         })
     }
 
+    /// Creates a new parser instance from bytes, e.g., a slice of a large buffer,
+    /// without copying them. See [`new`](ApgCode::new).
+    ///
+    /// Bytes that are not valid UTF-8 are reported as [`Error::UnexpectedChar`].
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use ca_formats::apgcode::ApgCode;
+    ///
+    /// let buffer = b"xs4_33 xq4_153 xp2_7";
+    /// let glider = ApgCode::from_bytes(&buffer[7..14]).unwrap();
+    /// assert_eq!(glider.count(), 5);
+    /// assert!(ApgCode::from_bytes(b"xq4_\xff").is_err());
+    /// ```
+    pub fn from_bytes(bytes: &'a [u8]) -> Result<Self, Error> {
+        let string = from_utf8(bytes)
+            .map_err(|e| Error::UnexpectedChar(char::from(bytes[e.valid_up_to()])))?;
+        Self::new(string)
+    }
+
+    /// Creates a new parser instance from a string, like [`new`](ApgCode::new),
+    /// but returns an [`Error::UnexpectedChar`] if the string contains a whitespace.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use ca_formats::apgcode::{ApgCode, Error};
+    ///
+    /// assert!(ApgCode::new_strict("xq4_153").is_ok());
+    /// assert_eq!(ApgCode::new_strict("xq4_1\n53").unwrap_err(), Error::UnexpectedChar('\n'));
+    /// ```
+    pub fn new_strict(string: &'a str) -> Result<Self, Error> {
+        match string.chars().find(char::is_ascii_whitespace) {
+            Some(c) => Err(Error::UnexpectedChar(c)),
+            None => Self::new(string),
+        }
+    }
+
+    /// Creates a new parser instance from a string with the [`ParseOptions`]
+    /// that make sense for this format: the convention, and whether to reject whitespaces
+    /// as in [`new_strict`](ApgCode::new_strict).
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use ca_formats::{apgcode::ApgCode, Convention, ParseOptions};
+    ///
+    /// let options = ParseOptions::new().with_convention(Convention { yx: true, y_up: false });
+    /// let blinker = ApgCode::new_with_options("xp2_7", options).unwrap();
+    /// let cells = blinker.collect::<Result<Vec<_>, _>>().unwrap();
+    /// assert_eq!(cells, vec![(0, 0), (1, 0), (2, 0)]);
+    ///
+    /// assert!(ApgCode::new_with_options("xp2_\n7", options.strict(true)).is_err());
+    /// ```
+    pub fn new_with_options(string: &'a str, options: ParseOptions) -> Result<Self, Error> {
+        let mut apgcode = if options.strict {
+            Self::new_strict(string)?
+        } else {
+            Self::new(string)?
+        };
+        apgcode.wechsler = apgcode.wechsler.with_convention(options.convention);
+        Ok(apgcode)
+    }
+
+    /// Creates a new parser instance from an apgcode with its context,
+    /// i.e., the rule slug and the symmetry used by Catagolue.
+    ///
+    /// Accepts the following forms:
+    ///
+    /// - `xq4_153`, without context;
+    /// - `b3s23/xq4_153`, with a rule slug;
+    /// - `b3s23/C1/xq4_153`, with a rule slug and a symmetry;
+    /// - `https://catagolue.hatsya.com/object/xq4_153/b3s23`, a Catagolue object URL,
+    ///   where the rule slug is optional.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use ca_formats::apgcode::ApgCode;
+    ///
+    /// let glider = ApgCode::new_with_context("b3s23/C1/xq4_153").unwrap();
+    /// assert_eq!(glider.rule(), Some("b3s23"));
+    /// assert_eq!(glider.symmetry(), Some("C1"));
+    /// assert_eq!(glider.period(), 4);
+    ///
+    /// let url = "https://catagolue.hatsya.com/object/xq4_153/b3s23";
+    /// let glider = ApgCode::new_with_context(url).unwrap();
+    /// assert_eq!(glider.rule(), Some("b3s23"));
+    /// assert_eq!(glider.symmetry(), None);
+    /// assert_eq!(glider.count(), 5);
+    /// ```
+    pub fn new_with_context(string: &'a str) -> Result<Self, Error> {
+        Self::new_with_context_and_options(string, ParseOptions::default())
+    }
+
+    /// Creates a new parser instance from an apgcode with its context, like
+    /// [`new_with_context`](ApgCode::new_with_context), with the [`ParseOptions`]
+    /// as in [`new_with_options`](ApgCode::new_with_options).
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use ca_formats::{apgcode::ApgCode, ParseOptions};
+    ///
+    /// let options = ParseOptions::new().strict(true);
+    /// assert!(ApgCode::new_with_context_and_options("b3s23/xq4_153", options).is_ok());
+    /// assert!(ApgCode::new_with_context_and_options("b3s23/xq4_1 53", options).is_err());
+    /// ```
+    pub fn new_with_context_and_options(
+        string: &'a str,
+        options: ParseOptions,
+    ) -> Result<Self, Error> {
+        let invalid = || Error::InvalidContext(string.to_string());
+        let trimmed = string.trim();
+        let (rule, symmetry, code) = if let Some((_, path)) = trimmed.split_once("/object/") {
+            let path = path.split(['?', '#']).next().unwrap_or_default();
+            let mut segments = path.trim_end_matches('/').split('/');
+            let code = segments.next().ok_or_else(invalid)?;
+            let rule = segments.next();
+            if segments.next().is_some() {
+                return Err(invalid());
+            }
+            (rule, None, code)
+        } else {
+            match trimmed.split('/').collect::<Vec<_>>()[..] {
+                [code] => (None, None, code),
+                [rule, code] => (Some(rule), None, code),
+                [rule, symmetry, code] => (Some(rule), Some(symmetry), code),
+                _ => return Err(invalid()),
+            }
+        };
+        if !rule.into_iter().chain(symmetry).all(is_slug) {
+            return Err(invalid());
+        }
+        let mut apgcode = Self::new_with_options(code, options)?;
+        apgcode.rule = rule;
+        apgcode.symmetry = symmetry;
+        Ok(apgcode)
+    }
+
+    /// The owned [`Classification`] of the pattern, which can be used as a key in maps.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use ca_formats::apgcode::ApgCode;
+    ///
+    /// let glider = ApgCode::new("xq4_153").unwrap().classification().unwrap();
+    /// assert_eq!(glider.period(), 4);
+    /// assert_eq!(glider.to_string(), "xq4_153");
+    /// ```
+    pub fn classification(&self) -> Result<Classification, Error> {
+        self.code.parse()
+    }
+
+    /// The [`ObjectClass`] of the pattern, from the prefix and the decoded cells.
+    ///
+    /// The cells are decoded in the [convention](ParseOptions::convention), and
+    /// a cell that appears in several components is counted once.
+    /// The velocity of a spaceship is not known without a simulator, so it is `None`;
+    /// it can be filled in with [`velocity`](ApgCode::velocity).
+    pub fn object_class(&self) -> Result<ObjectClass, Error> {
+        let mut cells = self.clone().collect::<Result<Vec<_>, _>>()?;
+        cells.sort_unstable();
+        cells.dedup();
+        let mut object_class = ObjectClass::new(&cells, self.period, (0, 0));
+        object_class.pattern_type = self.pattern_type;
+        if self.pattern_type == PatternType::Spaceship {
+            object_class.velocity = None;
+        }
+        Ok(object_class)
+    }
+
+    /// How the rule with `B0` is emulated, if the [`rule`](ApgCode::rule) slug has `B0`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use ca_formats::apgcode::{ApgCode, B0Rule};
+    ///
+    /// let apgcode = ApgCode::new_with_context("b0135s2/C1/xp2_7").unwrap();
+    /// assert_eq!(apgcode.b0_rule(), Some(B0Rule::Alternating));
+    /// ```
+    pub fn b0_rule(&self) -> Option<B0Rule> {
+        self.rule.and_then(B0Rule::from_slug)
+    }
+
+    /// Collect the living cells into a [`SmallCells`], without a heap allocation
+    /// if there are at most 64 of them, e.g., when decoding millions of codes in a census.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use ca_formats::apgcode::ApgCode;
+    ///
+    /// let glider = ApgCode::new("xq4_153").unwrap().decode_small().unwrap();
+    /// assert_eq!(glider.len(), 5);
+    /// assert!(!glider.spilled());
+    /// ```
+    #[cfg(feature = "smallvec")]
+    #[cfg_attr(docs_rs, doc(cfg(feature = "smallvec")))]
+    pub fn decode_small(self) -> Result<SmallCells, Error> {
+        self.collect()
+    }
+
+    /// The common name of the pattern in Conway's Game of Life, e.g., `Glider` for `xq4_153`.
+    ///
+    /// Only a small table of well-known objects is included. Returns `None` for other objects,
+    /// or if the [`rule`](ApgCode::rule) slug is not `b3s23`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use ca_formats::apgcode::ApgCode;
+    ///
+    /// assert_eq!(ApgCode::new("xq4_153").unwrap().common_name(), Some("Glider"));
+    /// assert_eq!(ApgCode::new("xp2_7").unwrap().common_name(), Some("Blinker"));
+    ///
+    /// let blinker = ApgCode::new_with_context("b36s23/C1/xp2_7").unwrap();
+    /// assert_eq!(blinker.common_name(), None);
+    /// ```
+    #[cfg(feature = "names")]
+    #[cfg_attr(docs_rs, doc(cfg(feature = "names")))]
+    pub fn common_name(&self) -> Option<&'static str> {
+        if self.rule.is_some_and(|rule| rule != "b3s23") {
+            return None;
+        }
+        crate::names::common_name(&self.to_string())
+    }
+
+    /// The rule slug, e.g., `b3s23`.
+    ///
+    /// `None` unless it is given to [`new_with_context`](ApgCode::new_with_context).
+    pub const fn rule(&self) -> Option<&'a str> {
+        self.rule
+    }
+
+    /// The rule slug in the prefix. See [`RuleInfo`].
+    pub fn rule_info(&self) -> Option<RuleInfo> {
+        RuleInfo::from_parser(self.rule, RuleOrigin::ApgcodePrefix)
+    }
+
+    /// The symmetry, e.g., `C1`.
+    ///
+    /// `None` unless it is given to [`new_with_context`](ApgCode::new_with_context).
+    pub const fn symmetry(&self) -> Option<&'a str> {
+        self.symmetry
+    }
+
     /// Period of the pattern.
     pub const fn period(&self) -> u64 {
         self.period
@@ -205,6 +1080,198 @@ impl<'a> ApgCode<'a> {
     pub const fn pattern_type(&self) -> PatternType {
         self.pattern_type
     }
+
+    /// Parsers of the components of the code, separated by `_`,
+    /// with the convention of this parser.
+    ///
+    /// Most codes have only one component.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use ca_formats::apgcode::ApgCode;
+    ///
+    /// let apgcode = ApgCode::new("xp2_31a08zy0123cko_3").unwrap();
+    /// let counts = apgcode.components().map(Iterator::count).collect::<Vec<_>>();
+    /// assert_eq!(counts, vec![16, 2]);
+    /// ```
+    pub fn components(&self) -> impl Iterator<Item = Wechsler<'a>> + '_ {
+        let convention = self.wechsler.convention;
+        self.code
+            .split('_')
+            .skip(1)
+            .map(move |component| Wechsler::new(component).with_convention(convention))
+    }
+
+    /// Compute the bounding box of the remaining living cells.
+    ///
+    /// See [`Wechsler::bounding_box`].
+    pub fn bounding_box(self) -> Result<Option<BoundingBox>, Error> {
+        let convention = self.wechsler.convention;
+        let mut bounding_box = self.wechsler.bounding_box()?;
+        for component in self.components {
+            let component = Wechsler::new(component).with_convention(convention);
+            if let Some((x_min, y_min, x_max, y_max)) = component.bounding_box()? {
+                extend_bounding_box(&mut bounding_box, (x_min, y_min));
+                extend_bounding_box(&mut bounding_box, (x_max, y_max));
+            }
+        }
+        Ok(bounding_box)
+    }
+
+    /// Compute the width and the height of the remaining living cells.
+    ///
+    /// See [`Wechsler::dimensions`].
+    pub fn dimensions(mut self) -> Result<(u64, u64), Error> {
+        self.wechsler.convention = Convention::default();
+        Ok(dimensions(self.bounding_box()?))
+    }
+
+    /// Decode the remaining living cells, and evolve them with an [`Evolver`]
+    /// to get all the phases in a period.
+    ///
+    /// Returns [`period`](ApgCode::period) phases, starting from the decoded cells.
+    /// The convention is ignored.
+    ///
+    /// Returns [`PhaseError::B0Rule`] if the rule slug has `B0`,
+    /// since the decoded cells are not the cells in every generation. See [`B0Rule`].
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use ca_formats::{apgcode::ApgCode, CellData};
+    /// use std::convert::Infallible;
+    ///
+    /// // A blinker rotates by 90 degrees around its center in each generation.
+    /// let rotate = |cells: &[CellData], _: &str| {
+    ///     let rotated = cells
+    ///         .iter()
+    ///         .map(|cell| CellData::from((1 - cell.position.1, cell.position.0 + 1)))
+    ///         .collect();
+    ///     Ok::<_, Infallible>(rotated)
+    /// };
+    /// let phases = ApgCode::new("xp2_7").unwrap().phases(rotate).unwrap();
+    /// assert_eq!(phases.len(), 2);
+    /// assert!(phases[1].iter().all(|cell| cell.position.1 == 1));
+    /// ```
+    pub fn phases<E: Evolver>(
+        self,
+        mut evolver: E,
+    ) -> Result<Vec<Vec<CellData>>, PhaseError<E::Error>> {
+        let generations = self.period.saturating_sub(1);
+        self.evolve(&mut evolver, generations)
+    }
+
+    /// Decode the remaining living cells, and evolve them with an [`Evolver`]
+    /// for a period to get the displacement of the pattern.
+    ///
+    /// Returns `Ok(None)` if the pattern after a period is not a translation of the
+    /// decoded cells, e.g., when the evolver simulates another rule.
+    /// The displacement of a still life or an oscillator is zero.
+    /// The convention is ignored.
+    ///
+    /// Returns [`PhaseError::B0Rule`] if the rule slug has `B0`, like [`phases`](ApgCode::phases).
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use ca_formats::{
+    ///     apgcode::{ApgCode, Velocity},
+    ///     CellData,
+    /// };
+    /// use std::convert::Infallible;
+    ///
+    /// // Not a real simulator: it moves the glider by a quarter of its displacement.
+    /// let mut generation = 0;
+    /// let evolver = |cells: &[CellData], _: &str| {
+    ///     generation += 1;
+    ///     let dx = (generation % 4 == 0) as i64;
+    ///     let moved = cells
+    ///         .iter()
+    ///         .map(|cell| CellData::from((cell.position.0 + dx, cell.position.1 + dx)))
+    ///         .collect();
+    ///     Ok::<_, Infallible>(moved)
+    /// };
+    /// let velocity = ApgCode::new("xq4_153").unwrap().velocity(evolver).unwrap();
+    /// assert_eq!(velocity, Some(Velocity { dx: 1, dy: 1, period: 4 }));
+    /// assert_eq!(velocity.unwrap().to_string(), "(1,1)c/4");
+    /// ```
+    pub fn velocity<E: Evolver>(
+        self,
+        mut evolver: E,
+    ) -> Result<Option<Velocity>, PhaseError<E::Error>> {
+        let period = self.period;
+        let mut phases = self.evolve(&mut evolver, period)?;
+        let last = canonical(phases.pop().unwrap_or_default());
+        let first = canonical(phases.into_iter().next().unwrap_or_default());
+        Ok(displacement(&first, &last).map(|(dx, dy)| Velocity { dx, dy, period }))
+    }
+
+    /// Decode the remaining living cells, and evolve them for some generations
+    /// in the [`rule`](ApgCode::rule), or `b3s23` if there is no rule slug.
+    ///
+    /// Returns the cells in all the generations, starting from the decoded cells.
+    fn evolve<E: Evolver>(
+        mut self,
+        evolver: &mut E,
+        generations: u64,
+    ) -> Result<Vec<Vec<CellData>>, PhaseError<E::Error>> {
+        if let Some(b0_rule) = self.b0_rule() {
+            return Err(PhaseError::B0Rule(b0_rule));
+        }
+        self.wechsler.convention = Convention::default();
+        let rule = self.rule.unwrap_or("b3s23");
+        let cells = self
+            .map(|cell| cell.map(CellData::from))
+            .collect::<Result<Vec<_>, _>>()?;
+        let mut phases = vec![cells];
+        for _ in 0..generations {
+            let cells = evolver
+                .step(&phases[phases.len() - 1], rule)
+                .map_err(PhaseError::Evolver)?;
+            phases.push(cells);
+        }
+        Ok(phases)
+    }
+
+    /// Converts the remaining living cells into an RLE string with a header line,
+    /// e.g., to paste into Golly.
+    ///
+    /// The pattern is translated so that the upper left corner of its bounding box
+    /// is at `(0, 0)`. The convention is ignored.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use ca_formats::apgcode::ApgCode;
+    ///
+    /// let glider = ApgCode::new("xq4_153").unwrap();
+    /// assert_eq!(
+    ///     glider.to_rle_string("B3/S23").unwrap(),
+    ///     "x = 3, y = 3, rule = B3/S23\n3o$2bo$bo!\n"
+    /// );
+    /// ```
+    pub fn to_rle_string(mut self, rule: &str) -> Result<String, Error> {
+        self.wechsler.convention = Convention::default();
+        let cells = self.collect::<Result<Vec<_>, _>>()?;
+        let x_min = cells.iter().map(|&(x, _)| x).min().unwrap_or_default();
+        let y_min = cells.iter().map(|&(_, y)| y).min().unwrap_or_default();
+        let cells = cells
+            .into_iter()
+            .map(|(x, y)| CellData::from((x - x_min, y - y_min)));
+        let mut rle = Vec::new();
+        write_rle(&mut rle, Some(rule), cells)?;
+        String::from_utf8(rle).map_err(|e| IoError::new(ErrorKind::InvalidData, e).into())
+    }
+}
+
+/// Writes the apgcode, without the rule slug, the symmetry and the whitespaces.
+impl Display for ApgCode<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        self.code
+            .split_ascii_whitespace()
+            .try_for_each(|part| f.write_str(part))
+    }
 }
 
 /// An iterator over living cells in an apgcode string.
@@ -212,7 +1279,176 @@ impl<'a> Iterator for ApgCode<'a> {
     type Item = Result<Coordinates, Error>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        self.wechsler.next()
+        loop {
+            if let Some(item) = self.wechsler.next() {
+                return Some(item);
+            }
+            let component = self.components.next()?;
+            self.wechsler = Wechsler::new(component).with_convention(self.wechsler.convention);
+        }
+    }
+}
+
+/// Reads apgcodes from an input, one on each line, e.g., a census file with millions of codes,
+/// and calls a function with each of them.
+///
+/// Each line is read into the buffer of the input, and the [`ApgCode`] borrows it,
+/// so that no string is allocated for each code. The code is the first field of the line,
+/// which ends at a whitespace or a comma, and may have a context as in
+/// [`new_with_context`](ApgCode::new_with_context). The rest of the line, e.g.,
+/// the number of occurrences, is passed to the function with the whitespaces around it removed.
+///
+/// Blank lines are skipped. Other lines that are not apgcodes, e.g., the header of a CSV file,
+/// are passed to the function as errors. Returns an error if the input can't be read.
+///
+/// # Example
+///
+/// ```rust
+/// use ca_formats::apgcode::for_each_apgcode;
+///
+/// let census = "apgcode,occurrences\nxs4_33,1000\n\nxq4_153,250\n";
+/// let mut population = 0;
+/// let mut errors = 0;
+/// for_each_apgcode(census.as_bytes(), |apgcode, rest| match apgcode {
+///     Ok(apgcode) => population += apgcode.count() * rest.parse::<usize>().unwrap(),
+///     Err(_) => errors += 1,
+/// })
+/// .unwrap();
+/// assert_eq!((population, errors), (5250, 1));
+/// ```
+pub fn for_each_apgcode<I, F>(input: I, mut f: F) -> Result<(), IoError>
+where
+    I: Input,
+    F: FnMut(Result<ApgCode<'_>, Error>, &str),
+{
+    for item in input.lines() {
+        let line = I::line(item)?;
+        let line = line.as_ref();
+        match from_utf8(line) {
+            Ok(line) => {
+                let line = line.trim();
+                if line.is_empty() {
+                    continue;
+                }
+                let (code, rest) = line
+                    .split_once(|c: char| c.is_ascii_whitespace() || c == ',')
+                    .unwrap_or((line, ""));
+                f(ApgCode::new_with_context(code), rest.trim());
+            }
+            Err(e) => f(
+                Err(Error::UnexpectedChar(char::from(line[e.valid_up_to()]))),
+                "",
+            ),
+        }
+    }
+    Ok(())
+}
+
+/// An owned and validated apgcode, e.g., `xq4_153`, without the cells decoded.
+///
+/// Unlike [`ApgCode`], which is a parser, it can be used as a key in maps.
+/// It is written by [`Display`], and read by [`FromStr`], which reproduce the original code.
+/// With the `serde` feature, it is serialized as a string.
+///
+/// # Example
+///
+/// ```rust
+/// use ca_formats::apgcode::{Classification, PatternType};
+/// use std::collections::HashMap;
+///
+/// let block = "xs4_33".parse::<Classification>().unwrap();
+/// assert_eq!(block.pattern_type, PatternType::StillLife);
+/// assert_eq!(block.number, 4);
+/// assert_eq!(block.period(), 1);
+///
+/// let mut counts = HashMap::new();
+/// *counts.entry(block.clone()).or_insert(0) += 1;
+/// assert_eq!(counts[&block], 1);
+/// assert_eq!(block.to_string(), "xs4_33");
+/// ```
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(into = "String", try_from = "String")
+)]
+pub struct Classification {
+    /// Type of the pattern.
+    pub pattern_type: PatternType,
+    /// The number after the prefix, i.e., the population of a still life,
+    /// or the period of an oscillator or a spaceship.
+    pub number: u64,
+    /// The cells in Extended Wechsler format.
+    ///
+    /// The components of a code with several components are separated by `_`.
+    pub wechsler: String,
+}
+
+impl Classification {
+    /// Period of the pattern.
+    pub const fn period(&self) -> u64 {
+        match self.pattern_type {
+            PatternType::StillLife => 1,
+            _ => self.number,
+        }
+    }
+
+    /// The common name of the pattern in Conway's Game of Life, e.g., `Block` for `xs4_33`.
+    ///
+    /// See [`ApgCode::common_name`].
+    #[cfg(feature = "names")]
+    #[cfg_attr(docs_rs, doc(cfg(feature = "names")))]
+    pub fn common_name(&self) -> Option<&'static str> {
+        crate::names::common_name(&self.to_string())
+    }
+
+    /// An iterator over the living cells of all the components, like [`ApgCode`].
+    pub fn cells(&self) -> impl Iterator<Item = Result<Coordinates, Error>> + '_ {
+        self.wechsler.split('_').flat_map(Wechsler::new)
+    }
+}
+
+impl Display for Classification {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let prefix = match self.pattern_type {
+            PatternType::StillLife => "xs",
+            PatternType::Oscillator => "xp",
+            PatternType::Spaceship => "xq",
+        };
+        write!(f, "{}{}_{}", prefix, self.number, self.wechsler)
+    }
+}
+
+impl FromStr for Classification {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.split_ascii_whitespace().collect::<String>();
+        let apgcode = ApgCode::new(&s)?;
+        let (prefix, wechsler) = s.split_once('_').ok_or(Error::Unencodable)?;
+        let number = prefix[2..].parse().map_err(|_| Error::Unencodable)?;
+        for component in wechsler.split('_') {
+            Wechsler::new(component).try_for_each(|cell| cell.map(drop))?;
+        }
+        Ok(Classification {
+            pattern_type: apgcode.pattern_type,
+            number,
+            wechsler: wechsler.to_string(),
+        })
+    }
+}
+
+impl From<Classification> for String {
+    fn from(classification: Classification) -> Self {
+        classification.to_string()
+    }
+}
+
+impl TryFrom<String> for Classification {
+    type Error = Error;
+
+    fn try_from(string: String) -> Result<Self, Self::Error> {
+        string.parse()
     }
 }
 
@@ -271,6 +1507,26 @@ mod tests {
                 (2, 28)
             ]
         );
+
+        assert_eq!(Wechsler::new(TWIN_BEE_SHUTTLE).population()?, 28);
+        let mut partial = Wechsler::new(TWIN_BEE_SHUTTLE);
+        partial.nth(2);
+        assert_eq!(partial.population()?, 25);
+        assert!(Wechsler::new("1y").population().is_err());
+
+        let bounding_box = Wechsler::new(TWIN_BEE_SHUTTLE).bounding_box()?;
+        assert_eq!(bounding_box, Some((0, 0, 10, 28)));
+        let mut partial = Wechsler::new(TWIN_BEE_SHUTTLE);
+        partial.nth(7);
+        assert_eq!(partial.bounding_box()?, Some((0, 17, 10, 28)));
+        let convention = Convention {
+            yx: true,
+            y_up: true,
+        };
+        let options = ParseOptions::new().with_convention(convention);
+        let flipped = Wechsler::new_with_options(TWIN_BEE_SHUTTLE, options);
+        assert_eq!(flipped.bounding_box()?, Some((-28, 0, 0, 10)));
+        assert_eq!(Wechsler::new("0w0").bounding_box()?, None);
         Ok(())
     }
 
@@ -288,4 +1544,460 @@ mod tests {
         assert_eq!(cells, vec![(0, 0), (1, 0), (1, 2), (2, 0), (2, 1)]);
         Ok(())
     }
+
+    #[test]
+    fn wechsler_encode() -> Result<(), Error> {
+        const TWIN_BEE_SHUTTLE: &str = "033y133zzzckgsxsgkczz0cc";
+        let cells = Wechsler::new(TWIN_BEE_SHUTTLE).collect::<Result<Vec<_>, _>>()?;
+        assert_eq!(encode_wechsler(cells.iter().copied())?, TWIN_BEE_SHUTTLE);
+
+        let translated = cells.iter().map(|&(x, y)| (x - 7, y + 3));
+        assert_eq!(encode_wechsler(translated)?, TWIN_BEE_SHUTTLE);
+
+        assert_eq!(encode_wechsler([(0, 0), (45, 0)])?, "1yzy11");
+        assert_eq!(encode_wechsler([])?, "0");
+
+        let corner = [(i64::MAX, i64::MIN), (i64::MAX - 1, i64::MIN + 1)];
+        assert_eq!(encode_wechsler(corner)?, "21");
+        let far = [(i64::MIN, i64::MIN), (i64::MAX, i64::MAX)];
+        assert_eq!(encode_wechsler(far), Err(Error::TooLarge));
+        let span = MAX_WECHSLER_SPAN as i64;
+        assert_eq!(encode_wechsler([(0, 0), (0, span)]), Err(Error::TooLarge));
+        assert!(encode_wechsler([(0, 0), (0, span - 1)]).is_ok());
+        Ok(())
+    }
+
+    #[test]
+    fn apgcode_to_rle_string() -> Result<(), Error> {
+        let options = ParseOptions::new().with_convention(Convention {
+            yx: true,
+            y_up: true,
+        });
+        let block = ApgCode::new_with_options("xs4_0cc", options)?;
+        assert_eq!(
+            block.to_rle_string("B3/S23")?,
+            "x = 2, y = 2, rule = B3/S23\n2o$2o!\n"
+        );
+
+        let rle = ApgCode::new("xp2_7")?.to_rle_string("B36/S23")?;
+        assert_eq!(rle, "x = 1, y = 3, rule = B36/S23\no$o$o!\n");
+
+        assert!(ApgCode::new("xs1_1!")?.to_rle_string("B3/S23").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn apgcode_with_context() -> Result<(), Error> {
+        let glider = ApgCode::new_with_context("xq4_153")?;
+        assert_eq!((glider.rule(), glider.symmetry()), (None, None));
+
+        let glider = ApgCode::new_with_context("b3s23/xq4_153")?;
+        assert_eq!((glider.rule(), glider.symmetry()), (Some("b3s23"), None));
+
+        let block = ApgCode::new_with_context(" g3b2s23/D8_4/xs4_33\n")?;
+        assert_eq!(block.rule(), Some("g3b2s23"));
+        assert_eq!(block.symmetry(), Some("D8_4"));
+        assert_eq!(block.count(), 4);
+
+        let url = "https://catagolue.hatsya.com/object/xp2_7/b36s23/";
+        let blinker = ApgCode::new_with_context(url)?;
+        assert_eq!((blinker.rule(), blinker.period()), (Some("b36s23"), 2));
+        let url = "catagolue.appspot.com/object/xp2_7?foo=bar";
+        assert_eq!(ApgCode::new_with_context(url)?.rule(), None);
+
+        assert!(matches!(
+            ApgCode::new_with_context("b3s23/C1/xq4_153/x"),
+            Err(Error::InvalidContext(_))
+        ));
+        assert!(matches!(
+            ApgCode::new_with_context("b3 s23/xq4_153"),
+            Err(Error::InvalidContext(_))
+        ));
+        assert_eq!(
+            ApgCode::new_with_context("b3s23/C1/").err(),
+            Some(Error::Unencodable)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn apgcode_classification() -> Result<(), Error> {
+        for code in ["xs4_33", "xp2_7", "xq4_153", "xp30_w33z8kqrqk8zzzx33"] {
+            let classification = code.parse::<Classification>()?;
+            assert_eq!(classification.to_string(), code);
+            let apgcode = ApgCode::new(code)?;
+            assert_eq!(apgcode.to_string(), code);
+            assert_eq!(apgcode.classification()?, classification);
+            assert_eq!(classification.period(), apgcode.period());
+            assert_eq!(classification.cells().count(), apgcode.count());
+        }
+        assert_eq!(
+            ApgCode::new_with_context("b3s23/C1/xq4_153")?.to_string(),
+            "xq4_153"
+        );
+
+        assert_eq!("xs_33".parse::<Classification>(), Err(Error::Unencodable));
+        assert_eq!(
+            "xq4_15!".parse::<Classification>(),
+            Err(Error::UnexpectedChar('!'))
+        );
+        assert_eq!(
+            "yl144_1_16_afb5f3db909e60548f086e22ee3353ac".parse::<Classification>(),
+            Err(Error::Unencodable)
+        );
+        Ok(())
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn apgcode_classification_serde() -> Result<(), Box<dyn std::error::Error>> {
+        let glider = "xq4_153".parse::<Classification>()?;
+        let json = serde_json::to_string(&glider)?;
+        assert_eq!(json, "\"xq4_153\"");
+        assert_eq!(serde_json::from_str::<Classification>(&json)?, glider);
+        assert!(serde_json::from_str::<Classification>("\"xq4\"").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn apgcode_components() -> Result<(), Error> {
+        const CODE: &str = "xp2_31a08zy0123cko_3";
+        let apgcode = ApgCode::new(CODE)?;
+        assert_eq!(apgcode.components().count(), 2);
+        let cells = apgcode.clone().collect::<Result<Vec<_>, _>>()?;
+        assert_eq!(cells.len(), 18);
+        assert_eq!(cells[16..], [(0, 0), (0, 1)]);
+        assert_eq!(apgcode.bounding_box()?, Some((0, 0, 9, 9)));
+
+        let classification = CODE.parse::<Classification>()?;
+        assert_eq!(classification.wechsler, "31a08zy0123cko_3");
+        assert_eq!(classification.to_string(), CODE);
+        assert_eq!(
+            classification.cells().collect::<Result<Vec<_>, _>>()?,
+            cells
+        );
+
+        let mut apgcode = ApgCode::new("xs2_3_!")?;
+        assert_eq!(apgcode.nth(2), Some(Err(Error::UnexpectedChar('!'))));
+        assert_eq!(
+            "xs2_3_!".parse::<Classification>(),
+            Err(Error::UnexpectedChar('!'))
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn wechsler_dimensions() -> Result<(), Error> {
+        assert_eq!(Wechsler::new("153").dimensions()?, (3, 3));
+        assert_eq!(Wechsler::new("0").dimensions()?, (0, 0));
+        assert_eq!(Wechsler::new("w8").dimensions()?, (1, 1));
+        assert_eq!(Wechsler::new("1zz1").dimensions()?, (1, 11));
+
+        let yx = ParseOptions::new().with_convention(Convention {
+            yx: true,
+            y_up: false,
+        });
+        let sirrobin = ApgCode::new_with_options("xq6_yyocxukcy6gocs20h0a38bac2qq73uszyjo4w8y0e4mo0vu0o606s6444u08clav0h03g440qq1333333x11zy9ecec2ik032i210sw3f0hy011w70401011033547442zy0emj896he1e1kif6q2gc50ew9qb30dzgo403gg066m32w11z34407q441n6zy311", yx)?;
+        assert_eq!(sirrobin.dimensions()?, (78, 31));
+        assert_eq!(
+            ApgCode::new("xp2_31a08zy0123cko_3")?.dimensions()?,
+            (10, 10)
+        );
+        assert!(Wechsler::new("1y").dimensions().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn apgcode_phases() -> Result<(), PhaseError<std::fmt::Error>> {
+        let mut steps = 0;
+        let shift = |cells: &[CellData], _: &str| {
+            steps += 1;
+            Ok(cells
+                .iter()
+                .map(|cell| CellData::from((cell.position.0 + 1, cell.position.1)))
+                .collect())
+        };
+        let phases = ApgCode::new("xq4_153")?.phases(shift)?;
+        assert_eq!(steps, 3);
+        assert_eq!(phases.len(), 4);
+        assert_eq!(phases[3][0], CellData::from((3, 0)));
+
+        let phases =
+            ApgCode::new("xs4_33")?.phases(|_: &[CellData], _: &str| Err(std::fmt::Error))?;
+        assert_eq!(phases.len(), 1);
+        assert!(matches!(
+            ApgCode::new("xp2_7")?.phases(|_: &[CellData], _: &str| Err(std::fmt::Error)),
+            Err(PhaseError::Evolver(_))
+        ));
+        let mut rules = Vec::new();
+        let record = |cells: &[CellData], rule: &str| {
+            rules.push(rule.to_string());
+            Ok(cells.to_vec())
+        };
+        ApgCode::new_with_context("b36s23/C1/xp2_7")?.phases(record)?;
+        ApgCode::new("xp2_7")?.phases(|cells: &[CellData], rule: &str| {
+            rules.push(rule.to_string());
+            Ok(cells.to_vec())
+        })?;
+        assert_eq!(rules, ["b36s23", "b3s23"]);
+        assert!(matches!(
+            ApgCode::new("xp2_7!")?.phases(|_: &[CellData], _: &str| Err(std::fmt::Error)),
+            Err(PhaseError::Apgcode(_))
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn apgcode_velocity() -> Result<(), PhaseError<std::fmt::Error>> {
+        let shift = |cells: &[CellData], _: &str| {
+            Ok(cells
+                .iter()
+                .map(|cell| CellData::from((cell.position.0, cell.position.1 - 1)))
+                .collect())
+        };
+        let velocity = ApgCode::new("xq4_153")?.velocity(shift)?;
+        assert_eq!(
+            velocity,
+            Some(Velocity {
+                dx: 0,
+                dy: -4,
+                period: 4
+            })
+        );
+
+        let still = |cells: &[CellData], _: &str| Ok(cells.to_vec());
+        let velocity = ApgCode::new("xs4_33")?.velocity(still)?;
+        assert_eq!(velocity.map(|v| (v.dx, v.dy, v.period)), Some((0, 0, 1)));
+
+        let die = |_: &[CellData], _: &str| Ok(Vec::new());
+        assert_eq!(ApgCode::new("xp2_7")?.velocity(die)?, None);
+        let grow = |cells: &[CellData], _: &str| {
+            let mut cells = cells.to_vec();
+            cells.push(CellData::from((10, 10)));
+            Ok(cells)
+        };
+        assert_eq!(ApgCode::new("xp2_7")?.velocity(grow)?, None);
+        Ok(())
+    }
+
+    #[test]
+    fn apgcode_encode_canonical() -> Result<(), Error> {
+        const CODES: [&str; 8] = [
+            "xs4_33",
+            "xs5_253",
+            "xs6_696",
+            "xs7_2596",
+            "xq4_153",
+            "xq4_6frc",
+            "xp2_7",
+            "xp3_co9nas0san9oczgoldlo0oldlogz1047210127401",
+        ];
+        for code in CODES {
+            let apgcode = ApgCode::new(code)?;
+            let (pattern_type, period) = (apgcode.pattern_type(), apgcode.period());
+            let cells = apgcode.collect::<Result<Vec<_>, _>>()?;
+            for orientation in ORIENTATIONS {
+                let moved = cells.iter().map(|&cell| {
+                    let (x, y) = orientation(cell);
+                    (x - 7, y + 100)
+                });
+                let classification = encode_canonical(moved, pattern_type, period)?;
+                assert_eq!(classification.to_string(), code);
+            }
+        }
+
+        // The phases of a glider, and of a pulsar, in Conway's Game of Life.
+        let life = |cells: &[Coordinates]| {
+            let mut neighbors = std::collections::HashMap::new();
+            for &(x, y) in cells {
+                for dy in -1..=1 {
+                    for dx in -1..=1 {
+                        *neighbors.entry((x + dx, y + dy)).or_insert(0) += 1;
+                    }
+                }
+            }
+            let mut next = neighbors
+                .into_iter()
+                .filter(|&(cell, count)| count == 3 || (count == 4 && cells.contains(&cell)))
+                .map(|(cell, _)| cell)
+                .collect::<Vec<_>>();
+            next.sort_unstable();
+            next
+        };
+        for (code, (pattern_type, period)) in [
+            ("xq4_153", (PatternType::Spaceship, 4)),
+            (
+                "xp3_co9nas0san9oczgoldlo0oldlogz1047210127401",
+                (PatternType::Oscillator, 3),
+            ),
+        ] {
+            let mut phases = vec![ApgCode::new(code)?.collect::<Result<Vec<_>, _>>()?];
+            for _ in 1..period {
+                phases.push(life(&phases[phases.len() - 1]));
+            }
+            phases.rotate_left(1);
+            assert_ne!(
+                encode_canonical(phases[0].clone(), pattern_type, period)?.to_string(),
+                code
+            );
+            let classification = encode_canonical_phases(phases, pattern_type, period)?;
+            assert_eq!(classification.to_string(), code);
+        }
+
+        let empty = encode_canonical([], PatternType::StillLife, 1)?;
+        assert_eq!(empty.to_string(), "xs0_0");
+        let twice = encode_canonical(
+            [(0, 0), (1, 0), (0, 1), (1, 1), (0, 0)],
+            PatternType::StillLife,
+            1,
+        )?;
+        assert_eq!(twice.to_string(), "xs4_33");
+        let corner = [(i64::MIN, i64::MIN), (i64::MIN + 1, i64::MIN)];
+        let domino = encode_canonical(corner, PatternType::StillLife, 1)?;
+        assert_eq!(domino.to_string(), "xs2_3");
+        assert_eq!(
+            encode_canonical([(i64::MIN, 0), (i64::MAX, 0)], PatternType::StillLife, 1),
+            Err(Error::TooLarge)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn apgcode_object_class() -> Result<(), Error> {
+        let pulsar = ApgCode::new("xp3_co9nas0san9oczgoldlo0oldlogz1047210127401")?;
+        let object_class = pulsar.object_class()?;
+        assert_eq!(object_class.pattern_type, PatternType::Oscillator);
+        assert_eq!(object_class.population, 72);
+        assert_eq!(object_class.bounding_box, Some((0, 0, 12, 12)));
+        assert_eq!(
+            object_class.velocity.map(|v| v.to_string()).as_deref(),
+            Some("(0,0)c/3")
+        );
+
+        let block = ApgCode::new("xs4_33")?.object_class()?;
+        assert_eq!(
+            (block.pattern_type, block.period),
+            (PatternType::StillLife, 1)
+        );
+        let overlapping = ApgCode::new("xp2_7_7")?.object_class()?;
+        assert_eq!(overlapping.population, 3);
+        let options = ParseOptions::new().with_convention(Convention {
+            yx: true,
+            ..Convention::default()
+        });
+        let glider = ApgCode::new_with_options("xq4_153", options)?;
+        let object_class = glider.object_class()?;
+        assert_eq!(object_class.velocity, None);
+        assert_eq!(object_class.bounding_box, Some((0, 0, 2, 2)));
+        Ok(())
+    }
+
+    #[test]
+    fn apgcode_b0_rule() -> Result<(), Error> {
+        for (slug, b0_rule) in [
+            ("b3s23", None),
+            ("b36s23", None),
+            ("b2n3s23-q", None),
+            ("b0135s2", Some(B0Rule::Alternating)),
+            ("b01245s01234", Some(B0Rule::Alternating)),
+            ("b0123478s34678", Some(B0Rule::Complement)),
+            ("b02ck3s8", Some(B0Rule::Complement)),
+            ("g3b0s8", Some(B0Rule::Complement)),
+            ("b0s4v", Some(B0Rule::Complement)),
+            ("b0s4h", Some(B0Rule::Alternating)),
+            ("b0", None),
+        ] {
+            assert_eq!(B0Rule::from_slug(slug), b0_rule, "{}", slug);
+        }
+
+        let code = "b0135s2/C1/xs2_3";
+        let domino = ApgCode::new_with_context(code)?;
+        assert_eq!(domino.b0_rule(), Some(B0Rule::Alternating));
+        assert_eq!(domino.clone().count(), 2);
+        let still = |cells: &[CellData], _: &str| Ok::<_, std::fmt::Error>(cells.to_vec());
+        assert!(matches!(
+            domino.velocity(still),
+            Err(PhaseError::B0Rule(B0Rule::Alternating))
+        ));
+        assert_eq!(ApgCode::new("xs2_3")?.b0_rule(), None);
+        Ok(())
+    }
+
+    #[test]
+    fn apgcode_whitespace() -> Result<(), Error> {
+        const WRAPPED: &str = "\r\n xp\t30_ 033y1\n33zzzckgsxsgkczz0cc_\r\n\n";
+        let apgcode = ApgCode::new(WRAPPED)?;
+        let expected = ApgCode::new("xp30_033y133zzzckgsxsgkczz0cc_")?;
+        assert_eq!(apgcode.period(), 30);
+        assert_eq!(apgcode.to_string(), expected.to_string());
+        assert_eq!(apgcode.classification()?, expected.classification()?);
+        assert_eq!(
+            apgcode.clone().collect::<Result<Vec<_>, _>>()?,
+            expected.collect::<Result<Vec<_>, _>>()?
+        );
+        assert_eq!(Wechsler::new("1 5\n3").population()?, 5);
+        assert_eq!(Wechsler::new("y\n1 1").bounding_box()?, Some((5, 0, 5, 0)));
+
+        let context = ApgCode::new_with_context("b3s23/C1/\nxq4_\n153")?;
+        assert_eq!(context.rule(), Some("b3s23"));
+        assert_eq!(
+            ApgCode::new_strict(WRAPPED).unwrap_err(),
+            Error::UnexpectedChar('\r')
+        );
+        assert_eq!(
+            ApgCode::new_strict("xq4 _153").unwrap_err(),
+            Error::UnexpectedChar(' ')
+        );
+        assert!(ApgCode::new_strict("xq4_153").is_ok());
+        Ok(())
+    }
+
+    #[test]
+    fn apgcode_for_each() -> Result<(), Box<dyn std::error::Error>> {
+        let census = b"xs4_33 12\r\nb3s23/C1/xq4_153\n  \nxp2_\xff 1\nxp2_7\t3 phases\nyl144_1_16_afb5f3db909e60548f086e22ee3353ac";
+        let mut codes = Vec::new();
+        let reader = std::io::BufReader::new(&census[..]);
+        for_each_apgcode(reader, |apgcode, rest| {
+            codes.push((apgcode.map(|apgcode| apgcode.to_string()), rest.to_string()))
+        })?;
+        assert_eq!(
+            codes,
+            [
+                (Ok(String::from("xs4_33")), String::from("12")),
+                (Ok(String::from("xq4_153")), String::new()),
+                (Err(Error::UnexpectedChar('\u{ff}')), String::new()),
+                (Ok(String::from("xp2_7")), String::from("3 phases")),
+                (Err(Error::Unencodable), String::new()),
+            ]
+        );
+
+        assert_eq!(Wechsler::from_bytes(b"15\n3").population()?, 5);
+        assert!(Wechsler::from_bytes(b"1\x80").any(|cell| cell.is_err()));
+        let glider = ApgCode::from_bytes(&b"xq4_153 xp2_7"[..7])?;
+        assert_eq!(glider.classification()?.to_string(), "xq4_153");
+        assert_eq!(
+            ApgCode::from_bytes(b"xp\xc3").unwrap_err(),
+            Error::UnexpectedChar('\u{c3}')
+        );
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(feature = "smallvec")]
+    fn apgcode_decode_small() -> Result<(), Error> {
+        let code = "xp30_033y133zzzckgsxsgkczz0cc";
+        let cells = ApgCode::new(code)?.decode_small()?;
+        assert_eq!(
+            cells.as_slice(),
+            ApgCode::new(code)?.collect::<Result<Vec<_>, _>>()?
+        );
+        assert!(!cells.spilled());
+        let large = ApgCode::new("xs80_vvvvvvvv")?.decode_small()?;
+        assert_eq!(large.len(), 40);
+        let spilled = ApgCode::new("xs80_vvvvvvvvzvvvvvvvv")?.decode_small()?;
+        assert!(spilled.len() == 80 && spilled.spilled());
+        assert_eq!(Wechsler::new("1 5 3").decode_small()?.len(), 5);
+        assert!(ApgCode::new("xs1_!")?.decode_small().is_err());
+        Ok(())
+    }
 }
@@ -0,0 +1,116 @@
+//! Conversions between patterns and 2-dimensional arrays of the
+//! [`ndarray`](https://crates.io/crates/ndarray) crate.
+//!
+//! The element `[r, c]` of an array is the state of the cell at `(x0 + c, y0 + r)`,
+//! where `(x0, y0)` is the origin of the array.
+
+use crate::{CellData, Coordinates, Pattern, State};
+use ndarray::{Array2, ArrayBase, Data, Ix2};
+
+impl<S: State> Pattern<S> {
+    /// The states of the cells as an array whose element `[0, 0]` is the cell at `origin`.
+    ///
+    /// The array extends to the lower right corner of the bounding box of the living cells.
+    /// Cells above or to the left of the origin are ignored, and the array is empty
+    /// if there is no living cell.
+    /// Use the upper left corner of the [bounding box](Pattern::bounding_box) as the origin
+    /// to get the smallest array that contains the whole pattern.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use ca_formats::{rle::Rle, Pattern};
+    /// use ndarray::array;
+    ///
+    /// let glider = Pattern::from_rle(Rle::new("#CXRLE Pos=-1,-1\nbo$2bo$3o!").unwrap()).unwrap();
+    /// let (x0, y0, _, _) = glider.bounding_box().unwrap();
+    /// assert_eq!(glider.to_array2((x0, y0)), array![[0, 1, 0], [0, 0, 1], [1, 1, 1]]);
+    /// assert_eq!(glider.to_array2((0, 0)), array![[0, 1], [1, 1]]);
+    /// ```
+    #[cfg_attr(docs_rs, doc(cfg(feature = "ndarray")))]
+    pub fn to_array2(&self, origin: Coordinates) -> Array2<S> {
+        let (x0, y0) = origin;
+        let shape = self.bounding_box().map_or((0, 0), |(_, _, x1, y1)| {
+            (
+                usize::try_from(y1 - y0 + 1).unwrap_or(0),
+                usize::try_from(x1 - x0 + 1).unwrap_or(0),
+            )
+        });
+        let mut array = Array2::from_elem(shape, S::default());
+        for cell in &self.cells {
+            let (x, y) = cell.position;
+            if let (Ok(row), Ok(column)) = (usize::try_from(y - y0), usize::try_from(x - x0)) {
+                if let Some(state) = array.get_mut((row, column)) {
+                    *state = cell.state;
+                }
+            }
+        }
+        array
+    }
+
+    /// Builds a pattern from an array whose element `[0, 0]` is the cell at `origin`.
+    ///
+    /// Elements with state `0` are skipped, and the other cells are in row-major order.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use ca_formats::Pattern;
+    /// use ndarray::array;
+    ///
+    /// let blinker = Pattern::from_array2(&array![[0_u8, 0, 0], [1, 1, 1]], (-1, -1));
+    /// assert_eq!(blinker.bounding_box(), Some((-1, 0, 1, 0)));
+    /// ```
+    #[cfg_attr(docs_rs, doc(cfg(feature = "ndarray")))]
+    pub fn from_array2<D: Data<Elem = S>>(array: &ArrayBase<D, Ix2>, origin: Coordinates) -> Self {
+        let (x0, y0) = origin;
+        let cells = array
+            .indexed_iter()
+            .filter(|&(_, &state)| state != S::default())
+            .map(|((row, column), &state)| CellData {
+                position: (x0 + column as i64, y0 + row as i64),
+                state,
+            })
+            .collect();
+        Pattern {
+            cells,
+            ..Pattern::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rle::Rle;
+    use ndarray::array;
+    use std::error::Error as StdError;
+
+    #[test]
+    fn array_roundtrip() -> Result<(), Box<dyn StdError>> {
+        const RLE: &str = "#CXRLE Pos=3,-2\nx = 4, y = 3, rule = 23/3/3\nA2.B$.B$3.A!";
+        let pattern = Pattern::from_rle(Rle::new(RLE)?)?;
+
+        let array = pattern.to_array2((3, -2));
+        assert_eq!(array, array![[1, 0, 0, 2], [0, 2, 0, 0], [0, 0, 0, 1]]);
+        assert_eq!(Pattern::from_array2(&array, (3, -2)).cells, pattern.cells);
+        assert_eq!(
+            pattern.to_array2((1, -3)),
+            array![
+                [0, 0, 0, 0, 0, 0],
+                [0, 0, 1, 0, 0, 2],
+                [0, 0, 0, 2, 0, 0],
+                [0, 0, 0, 0, 0, 1]
+            ]
+        );
+        assert_eq!(pattern.to_array2((6, 0)), array![[1]]);
+        assert!(pattern.to_array2((7, 0)).is_empty());
+        assert!(Pattern::<u8>::default().to_array2((0, 0)).is_empty());
+
+        let wide = Pattern::<u16>::from_array2(&array![[0, 300], [7, 0]].t(), (0, 0));
+        assert_eq!(wide.cells.len(), 2);
+        assert_eq!(wide.cells[0].position, (1, 0));
+        assert_eq!(wide.cells[0].state, 7);
+        Ok(())
+    }
+}
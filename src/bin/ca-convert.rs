@@ -0,0 +1,515 @@
+//! Converts patterns between the formats supported by `ca-formats`.
+//!
+//! ```text
+//! ca-convert glider.rle --to plaintext
+//! cat pattern.mc | ca-convert --to rle --trim > pattern.rle
+//! ca-convert view gosper.rle
+//! ```
+//!
+//! The `view` subcommand shows a pattern in the terminal. The arrow keys or `hjkl` pan,
+//! `+` and `-` zoom, `f` fits the pattern to the screen, and `q` quits.
+
+use ca_formats::{
+    apgcode::ApgCode,
+    input::Normalized,
+    life::{Life105, Life106},
+    macrocell::Macrocell,
+    plaintext::Plaintext,
+    rle::{CxrlePolicy, Rle},
+    source::Format,
+    BoundingBox, CellData, CommentPolicy, Coordinates, Input, Pattern, RuleString,
+};
+use clap::{Parser, Subcommand, ValueEnum};
+use crossterm::{
+    cursor::{Hide, MoveTo, Show},
+    event::{read, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers},
+    execute, queue,
+    style::Print,
+    terminal::{
+        disable_raw_mode, enable_raw_mode, size, Clear, ClearType, EnterAlternateScreen,
+        LeaveAlternateScreen,
+    },
+};
+use std::{
+    error::Error,
+    fs::{read_to_string, File},
+    io::{stdin, stdout, BufWriter, Error as IoError, IsTerminal, Read, Write},
+    path::{Path, PathBuf},
+};
+
+/// What to do with the comment lines of the input.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, ValueEnum)]
+enum Comments {
+    /// Drop all comment lines.
+    Drop,
+    /// Keep the comment lines as they are, after a comment marker if needed.
+    Verbatim,
+    /// Rewrite the name, the author and the comments in the syntax of the output format.
+    Rewrap,
+}
+
+impl From<Comments> for CommentPolicy {
+    fn from(comments: Comments) -> Self {
+        match comments {
+            Comments::Drop => CommentPolicy::Drop,
+            Comments::Verbatim => CommentPolicy::Verbatim,
+            Comments::Rewrap => CommentPolicy::Rewrap,
+        }
+    }
+}
+
+/// When to write the `#CXRLE` line in RLE output.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, ValueEnum)]
+enum Cxrle {
+    /// Write it if the pattern is not at (0, 0), or if there is a generation.
+    Auto,
+    /// Always write it, so that the coordinates are kept.
+    Always,
+    /// Never write it, so that the upper left corner is moved to (0, 0).
+    Never,
+}
+
+impl From<Cxrle> for CxrlePolicy {
+    fn from(cxrle: Cxrle) -> Self {
+        match cxrle {
+            Cxrle::Auto => CxrlePolicy::Auto,
+            Cxrle::Always => CxrlePolicy::Always,
+            Cxrle::Never => CxrlePolicy::Never,
+        }
+    }
+}
+
+/// Convert patterns between RLE, Plaintext, Life 1.05, Life 1.06, Macrocell and apgcode.
+#[derive(Debug, Parser)]
+#[command(version, args_conflicts_with_subcommands = true)]
+struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    /// Input file. Reads from the standard input if omitted.
+    input: Option<PathBuf>,
+
+    /// Output file. Writes to the standard output if omitted.
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+
+    /// Format of the input. Detected from the content if omitted.
+    #[arg(short, long, value_enum)]
+    from: Option<Format>,
+
+    /// Format of the output.
+    #[arg(short, long, value_enum, default_value = "rle")]
+    to: Format,
+
+    /// Rulestring of the output, overriding the rule in the input.
+    #[arg(short, long)]
+    rule: Option<RuleString>,
+
+    /// Translate the pattern so that the upper left corner of its bounding box is at (0, 0).
+    #[arg(long)]
+    trim: bool,
+
+    /// Translate the pattern by `X,Y`, after trimming.
+    #[arg(long, value_name = "X,Y", value_parser = parse_offset, allow_hyphen_values = true)]
+    offset: Option<Coordinates>,
+
+    /// What to do with the comment lines of the input.
+    #[arg(long, value_enum, default_value = "rewrap")]
+    comments: Comments,
+
+    /// When to write the `#CXRLE` line in RLE output.
+    #[arg(long, value_enum, default_value = "auto")]
+    cxrle: Cxrle,
+
+    /// Prefix of the apgcode output, e.g., `xp2`. Defaults to the prefix of the input
+    /// if it is an apgcode; otherwise the type of the pattern is unknown, and it is required.
+    #[arg(long)]
+    prefix: Option<String>,
+}
+
+/// Subcommands other than converting.
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Show a pattern in the terminal. Arrow keys or hjkl: pan, + and -: zoom, f: fit, q: quit.
+    View {
+        /// Input file. Reads from the standard input if omitted.
+        input: Option<PathBuf>,
+
+        /// Format of the input. Detected from the content if omitted.
+        #[arg(short, long, value_enum)]
+        from: Option<Format>,
+    },
+}
+
+/// Parse an offset like `-3,4`.
+fn parse_offset(s: &str) -> Result<Coordinates, String> {
+    let (x, y) = s
+        .split_once(',')
+        .ok_or_else(|| format!("expected `X,Y`, found `{}`", s))?;
+    let x = x.trim().parse().map_err(|e| format!("invalid x: {}", e))?;
+    let y = y.trim().parse().map_err(|e| format!("invalid y: {}", e))?;
+    Ok((x, y))
+}
+
+/// Remove a byte order mark, classic Mac OS line endings and trailing whitespaces.
+fn normalize(text: &str) -> Result<String, Box<dyn Error>> {
+    let mut normalized = String::with_capacity(text.len());
+    for line in Input::lines(Normalized::new(text)) {
+        normalized.push_str(&String::from_utf8_lossy(&line?));
+        normalized.push('\n');
+    }
+    Ok(normalized)
+}
+
+/// A pattern read from the input.
+#[derive(Clone, Debug)]
+struct Parsed {
+    /// The pattern.
+    pattern: Pattern,
+    /// The prefix of the apgcode, e.g., `xp2`, if the input is an apgcode.
+    prefix: Option<String>,
+}
+
+/// Parse a pattern.
+fn parse(text: &str, format: Format) -> Result<Parsed, Box<dyn Error>> {
+    let pattern = match format {
+        Format::Rle => Pattern::from_rle(Rle::new(text)?)?,
+        Format::Plaintext => Pattern::from_plaintext(Plaintext::new(text)?)?,
+        Format::Life105 => Pattern::from_life105(Life105::new(text)?)?,
+        Format::Life106 => Pattern::from_life106(Life106::new(text))?,
+        Format::Macrocell => Pattern::from_macrocell(Macrocell::new(text)?)?,
+        Format::Apgcode => {
+            let apgcode = ApgCode::new_with_context(text.trim())?;
+            let code = apgcode.to_string();
+            let prefix = code.split('_').next().map(str::to_owned);
+            let pattern = Pattern::from_apgcode(apgcode)?;
+            return Ok(Parsed { pattern, prefix });
+        }
+    };
+    Ok(Parsed {
+        pattern,
+        prefix: None,
+    })
+}
+
+/// Read and parse a pattern from a file or the standard input.
+fn read_pattern(input: Option<&Path>, from: Option<Format>) -> Result<Parsed, Box<dyn Error>> {
+    let text = match input {
+        Some(path) => read_to_string(path)?,
+        None => {
+            let mut text = String::new();
+            stdin().read_to_string(&mut text)?;
+            text
+        }
+    };
+    let text = normalize(&text)?;
+    let format = from.unwrap_or_else(|| Format::detect(&text));
+    parse(&text, format)
+}
+
+/// Translate a pattern.
+///
+/// Returns an error, and leaves the pattern unchanged, if a coordinate overflows.
+fn translate(pattern: &mut Pattern, (dx, dy): Coordinates) -> Result<(), Box<dyn Error>> {
+    let positions = pattern
+        .cells
+        .iter()
+        .map(|cell| {
+            let (x, y) = cell.position;
+            x.checked_add(dx).zip(y.checked_add(dy))
+        })
+        .collect::<Option<Vec<_>>>()
+        .ok_or("the offset moves the pattern out of range")?;
+    for (cell, position) in pattern.cells.iter_mut().zip(positions) {
+        cell.position = position;
+    }
+    Ok(())
+}
+
+/// Write a pattern.
+fn write<W: Write>(
+    parsed: &Parsed,
+    mut writer: W,
+    format: Format,
+    args: &Args,
+) -> Result<(), Box<dyn Error>> {
+    let pattern = &parsed.pattern;
+    match format {
+        Format::Rle => pattern.write_rle(writer)?,
+        Format::Plaintext => pattern.write_plaintext(writer)?,
+        Format::Life105 => pattern.write_life105(writer)?,
+        Format::Life106 => pattern.write_life106(writer)?,
+        Format::Macrocell => pattern.write_macrocell(writer)?,
+        Format::Apgcode => {
+            let prefix = args.prefix.as_ref().or(parsed.prefix.as_ref()).ok_or(
+                "the type of the pattern is unknown without a simulator; \
+                 give the prefix of the apgcode with --prefix, e.g., --prefix xs4",
+            )?;
+            writeln!(writer, "{}_{}", prefix, pattern.to_wechsler()?)?;
+        }
+    }
+    Ok(())
+}
+
+/// A view of a pattern in the terminal.
+///
+/// Each character shows two pixels, one above the other, and each pixel
+/// is a square of `scale` × `scale` cells.
+#[derive(Clone, Debug)]
+struct Viewer {
+    /// Positions of the living cells.
+    cells: Vec<Coordinates>,
+    /// The bounding box of the living cells.
+    bounding_box: Option<BoundingBox>,
+    /// The cell at the center of the screen.
+    center: Coordinates,
+    /// Width and height of a pixel, in cells. Always a power of 2.
+    scale: i64,
+}
+
+impl Viewer {
+    /// The largest scale.
+    const MAX_SCALE: i64 = 1 << 40;
+
+    /// A viewer of the living cells of a pattern.
+    fn new(pattern: &Pattern) -> Self {
+        let cells = pattern
+            .cells
+            .iter()
+            .filter(|cell| cell.state != 0)
+            .map(|cell| cell.position)
+            .collect();
+        Viewer {
+            cells,
+            bounding_box: pattern.bounding_box(),
+            center: (0, 0),
+            scale: 1,
+        }
+    }
+
+    /// Center the pattern, and choose the smallest scale that fits it in
+    /// `width` × `height` pixels.
+    fn fit(&mut self, width: i64, height: i64) {
+        let Some((x0, y0, x1, y1)) = self.bounding_box else {
+            self.center = (0, 0);
+            self.scale = 1;
+            return;
+        };
+        // The spans may not fit in an `i64`, so they are computed in `i128`.
+        let (x0, y0, x1, y1) = (x0.into(), y0.into(), x1.into(), y1.into());
+        let middle = |min: i128, max: i128| (min + (max - min) / 2) as i64;
+        self.center = (middle(x0, x1), middle(y0, y1));
+        self.scale = 1;
+        let fits = |span: i128, pixels: i64, scale: i64| {
+            span < i128::from(pixels.max(1)) * i128::from(scale)
+        };
+        while self.scale < Self::MAX_SCALE
+            && !(fits(x1 - x0, width, self.scale) && fits(y1 - y0, height, self.scale))
+        {
+            self.scale *= 2;
+        }
+    }
+
+    /// Move the view by a quarter of `width` × `height` pixels in a direction.
+    ///
+    /// The center stops at the bounds of `i64`.
+    fn pan(&mut self, (dx, dy): Coordinates, width: i64, height: i64) {
+        let step =
+            |d: i64, pixels: i64| d.saturating_mul((pixels / 4).max(1).saturating_mul(self.scale));
+        self.center.0 = self.center.0.saturating_add(step(dx, width));
+        self.center.1 = self.center.1.saturating_add(step(dy, height));
+    }
+
+    /// Draw `width` × `2 * rows` pixels as `rows` lines of block characters.
+    fn render(&self, width: i64, rows: i64) -> Result<String, IoError> {
+        let height = 2 * rows;
+        let scale = i128::from(self.scale);
+        let left = i128::from(self.center.0) - i128::from(width / 2) * scale;
+        let top = i128::from(self.center.1) - i128::from(height / 2) * scale;
+        let pixels = Pattern {
+            cells: self
+                .cells
+                .iter()
+                .filter_map(|&(x, y)| {
+                    let x = (i128::from(x) - left).div_euclid(scale);
+                    let y = (i128::from(y) - top).div_euclid(scale);
+                    ((0..width.into()).contains(&x) && (0..height.into()).contains(&y))
+                        .then(|| CellData::from((x as i64, y as i64)))
+                })
+                .collect(),
+            ..Pattern::default()
+        };
+        pixels.to_blocks_in((0, 0, width - 1, height - 1))
+    }
+}
+
+/// Show a pattern in the terminal until the user quits.
+///
+/// If the standard output is not a terminal, the whole pattern is printed once.
+fn view(pattern: &Pattern) -> Result<(), Box<dyn Error>> {
+    let mut out = stdout();
+    if !out.is_terminal() {
+        out.write_all(pattern.to_blocks()?.as_bytes())?;
+        return Ok(());
+    }
+    let mut viewer = Viewer::new(pattern);
+    enable_raw_mode()?;
+    execute!(out, EnterAlternateScreen, Hide)?;
+    let result = run_viewer(&mut viewer, &mut out);
+    execute!(out, Show, LeaveAlternateScreen)?;
+    disable_raw_mode()?;
+    result
+}
+
+/// The event loop of [`view`].
+fn run_viewer<W: Write>(viewer: &mut Viewer, out: &mut W) -> Result<(), Box<dyn Error>> {
+    let (mut width, mut rows) = size()?;
+    // The last row is the status line.
+    let pixels = |width: u16, rows: u16| (i64::from(width), 2 * i64::from(rows.max(2) - 1));
+    let (w, h) = pixels(width, rows);
+    viewer.fit(w, h);
+    loop {
+        let (w, h) = pixels(width, rows);
+        queue!(out, Clear(ClearType::All))?;
+        for (i, line) in viewer.render(w, h / 2)?.split_terminator('\n').enumerate() {
+            queue!(out, MoveTo(0, i as u16), Print(line))?;
+        }
+        let status = format!(
+            "({}, {})  1:{}  arrows/hjkl: pan  +/-: zoom  f: fit  q: quit",
+            viewer.center.0, viewer.center.1, viewer.scale
+        );
+        let status = status.chars().take(width.into()).collect::<String>();
+        queue!(out, MoveTo(0, rows.saturating_sub(1)), Print(status))?;
+        out.flush()?;
+
+        match read()? {
+            Event::Key(KeyEvent {
+                code,
+                modifiers,
+                kind: KeyEventKind::Press,
+                ..
+            }) => match code {
+                KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+                KeyCode::Char('c') if modifiers.contains(KeyModifiers::CONTROL) => return Ok(()),
+                KeyCode::Left | KeyCode::Char('h') => viewer.pan((-1, 0), w, h),
+                KeyCode::Right | KeyCode::Char('l') => viewer.pan((1, 0), w, h),
+                KeyCode::Up | KeyCode::Char('k') => viewer.pan((0, -1), w, h),
+                KeyCode::Down | KeyCode::Char('j') => viewer.pan((0, 1), w, h),
+                KeyCode::Char('+') | KeyCode::Char('=') => viewer.scale = (viewer.scale / 2).max(1),
+                KeyCode::Char('-') => viewer.scale = (viewer.scale * 2).min(Viewer::MAX_SCALE),
+                KeyCode::Char('f') => viewer.fit(w, h),
+                _ => {}
+            },
+            Event::Resize(new_width, new_rows) => (width, rows) = (new_width, new_rows),
+            _ => {}
+        }
+    }
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let args = Args::parse();
+
+    if let Some(Command::View { input, from }) = &args.command {
+        let parsed = read_pattern(input.as_deref(), *from)?;
+        return view(&parsed.pattern);
+    }
+
+    let mut parsed = read_pattern(args.input.as_deref(), args.from)?;
+    let pattern = &mut parsed.pattern;
+    pattern.comment_policy = args.comments.into();
+    pattern.cxrle_policy = args.cxrle.into();
+
+    if args.rule.is_some() {
+        pattern.rule.clone_from(&args.rule);
+    }
+    if args.trim {
+        pattern.trim()?;
+    }
+    if let Some(offset) = args.offset {
+        translate(pattern, offset)?;
+    }
+
+    match &args.output {
+        Some(path) => {
+            let mut writer = BufWriter::new(File::create(path)?);
+            write(&parsed, &mut writer, args.to, &args)?;
+            writer.flush()?;
+        }
+        None => {
+            let mut writer = BufWriter::new(stdout().lock());
+            write(&parsed, &mut writer, args.to, &args)?;
+            writer.flush()?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn convert_parse_offset() {
+        assert_eq!(parse_offset("-3, 4"), Ok((-3, 4)));
+        assert!(parse_offset("3").is_err());
+        assert!(parse_offset("3,y").is_err());
+    }
+
+    #[test]
+    fn convert_translate_and_prefix() -> Result<(), Box<dyn Error>> {
+        let mut parsed = parse("2o!", Format::Rle)?;
+        translate(&mut parsed.pattern, (3, -1))?;
+        assert_eq!(parsed.pattern.bounding_box(), Some((3, -1, 4, -1)));
+        assert!(translate(&mut parsed.pattern, (i64::MAX, 0)).is_err());
+        assert_eq!(parsed.pattern.bounding_box(), Some((3, -1, 4, -1)));
+        assert_eq!(parsed.prefix, None);
+
+        let args = Args::parse_from(["ca-convert", "--to", "apgcode"]);
+        assert!(write(&parsed, Vec::new(), Format::Apgcode, &args).is_err());
+        let mut output = Vec::new();
+        let blinker = parse("xp2_7", Format::Apgcode)?;
+        assert_eq!(blinker.prefix.as_deref(), Some("xp2"));
+        write(&blinker, &mut output, Format::Apgcode, &args)?;
+        assert_eq!(output, b"xp2_7\n");
+
+        let args = Args::parse_from(["ca-convert", "--prefix", "xs2"]);
+        let mut output = Vec::new();
+        write(&parsed, &mut output, Format::Apgcode, &args)?;
+        assert_eq!(output, b"xs2_11\n");
+        Ok(())
+    }
+
+    #[test]
+    fn convert_viewer() -> Result<(), Box<dyn Error>> {
+        let glider = parse("bo$2bo$3o!", Format::Rle)?.pattern;
+        let mut viewer = Viewer::new(&glider);
+        viewer.fit(4, 4);
+        assert_eq!((viewer.center, viewer.scale), ((1, 1), 1));
+        assert_eq!(viewer.render(4, 2)?, "  ▄ \n ▄▄█\n");
+        viewer.scale = 2;
+        assert_eq!(viewer.render(4, 1)?, " ▄█ \n");
+        viewer.pan((1, 0), 4, 2);
+        assert_eq!(viewer.center, (3, 1));
+
+        viewer.fit(1, 2);
+        assert_eq!(viewer.scale, 4);
+        let mut empty = Viewer::new(&Pattern::default());
+        empty.fit(4, 4);
+        assert_eq!(empty.render(2, 1)?, "  \n");
+
+        let far = Pattern {
+            cells: vec![(i64::MIN, i64::MIN).into(), (i64::MAX, i64::MAX).into()],
+            ..Pattern::default()
+        };
+        let mut viewer = Viewer::new(&far);
+        viewer.fit(4, 4);
+        assert_eq!((viewer.center, viewer.scale), ((-1, -1), Viewer::MAX_SCALE));
+        viewer.center = (i64::MAX, i64::MAX);
+        assert_eq!(viewer.render(2, 1)?, " ▄\n");
+        viewer.pan((1, 1), 4, 4);
+        assert_eq!(viewer.center, (i64::MAX, i64::MAX));
+        viewer.center = (i64::MIN, i64::MIN);
+        assert_eq!(viewer.render(2, 1)?, " ▄\n");
+        Ok(())
+    }
+}
@@ -0,0 +1,142 @@
+//! Conversions between 2-state patterns and row-major bit grids of the
+//! [`bitvec`](https://crates.io/crates/bitvec) crate.
+//!
+//! Fast Life engines usually store a pattern as a grid of bits, where the cell at `(x, y)`
+//! in a grid of width `w` is the bit at index `y * w + x`.
+
+use crate::{crop::contains, BoundingBox, CellData, Pattern};
+use bitvec::{order::BitOrder, slice::BitSlice, store::BitStore, vec::BitVec};
+use std::convert::Infallible;
+
+/// Collects the living cells of a parser into a row-major bit grid of a rectangle,
+/// given as a [`BoundingBox`], i.e., `(min_x, min_y, max_x, max_y)`.
+///
+/// The width of the grid is `max_x - min_x + 1`, and the bit of the cell at `(x, y)`
+/// is at index `(y - min_y) * width + (x - min_x)`. Cells outside the rectangle are ignored.
+///
+/// # Example
+///
+/// ```rust
+/// use bitvec::prelude::*;
+/// use ca_formats::{bits::to_bitvec, rle::Rle};
+///
+/// let glider = Rle::new("bo$2bo$3o!").unwrap();
+/// let grid = to_bitvec(glider, (0, 0, 2, 2)).unwrap();
+/// assert_eq!(grid, bits![0, 1, 0, 0, 0, 1, 1, 1, 1]);
+/// ```
+pub fn to_bitvec<E, C>(cells: C, rect: BoundingBox) -> Result<BitVec, E>
+where
+    C: IntoIterator<Item = Result<CellData, E>>,
+{
+    let (min_x, min_y, max_x, max_y) = rect;
+    let width = usize::try_from(max_x - min_x + 1).unwrap_or(0);
+    let height = usize::try_from(max_y - min_y + 1).unwrap_or(0);
+    let mut grid = BitVec::repeat(false, width * height);
+    for cell in cells {
+        let cell = cell?;
+        let (x, y) = cell.position;
+        if cell.state != 0 && contains(rect, (x, y)) {
+            grid.set((y - min_y) as usize * width + (x - min_x) as usize, true);
+        }
+    }
+    Ok(grid)
+}
+
+/// The living cells in a row-major bit grid of the given width, in row-major order.
+///
+/// The upper left corner of the grid is at `(0, 0)`. If the length of the grid
+/// is not a multiple of the width, the last row is shorter.
+///
+/// # Example
+///
+/// ```rust
+/// use bitvec::prelude::*;
+/// use ca_formats::bits::from_bitslice;
+///
+/// let grid = bits![0, 1, 0, 0, 0, 1, 1, 1, 1];
+/// let cells = from_bitslice(grid, 3).map(|cell| cell.position).collect::<Vec<_>>();
+/// assert_eq!(cells, vec![(1, 0), (2, 1), (0, 2), (1, 2), (2, 2)]);
+/// ```
+pub fn from_bitslice<T: BitStore, O: BitOrder>(
+    bits: &BitSlice<T, O>,
+    width: usize,
+) -> impl Iterator<Item = CellData> + '_ {
+    bits.iter_ones()
+        .filter(move |_| width > 0)
+        .map(move |index| CellData::from(((index % width) as i64, (index / width) as i64)))
+}
+
+impl Pattern {
+    /// Builds a 2-state pattern from a row-major bit grid of the given width.
+    /// See [`from_bitslice`].
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use bitvec::prelude::*;
+    /// use ca_formats::Pattern;
+    ///
+    /// let blinker = Pattern::from_bitslice(bits![0, 0, 0, 1, 1, 1], 3);
+    /// assert_eq!(blinker.bounding_box(), Some((0, 1, 2, 1)));
+    /// ```
+    #[cfg_attr(docs_rs, doc(cfg(feature = "bitvec")))]
+    pub fn from_bitslice<T: BitStore, O: BitOrder>(bits: &BitSlice<T, O>, width: usize) -> Self {
+        Pattern {
+            cells: from_bitslice(bits, width).collect(),
+            ..Pattern::default()
+        }
+    }
+
+    /// The living cells in a rectangle as a row-major bit grid. See [`to_bitvec`].
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use bitvec::prelude::*;
+    /// use ca_formats::{rle::Rle, Pattern};
+    ///
+    /// let glider = Pattern::from_rle(Rle::new("bo$2bo$3o!").unwrap()).unwrap();
+    /// assert_eq!(glider.to_bitvec((1, 1, 2, 2)), bits![0, 1, 1, 1]);
+    /// ```
+    #[cfg_attr(docs_rs, doc(cfg(feature = "bitvec")))]
+    pub fn to_bitvec(&self, rect: BoundingBox) -> BitVec {
+        let cells = self.cells.iter().copied().map(Ok::<_, Infallible>);
+        match to_bitvec(cells, rect) {
+            Ok(grid) => grid,
+            Err(never) => match never {},
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rle::Rle;
+    use bitvec::prelude::*;
+    use std::error::Error as StdError;
+
+    #[test]
+    fn bits_roundtrip() -> Result<(), Box<dyn StdError>> {
+        const RLE: &str = "#CXRLE Pos=-2,-1\nx = 5, y = 3\n2o$obo$2b3o!";
+        let pattern = Pattern::from_rle(Rle::new(RLE)?)?;
+
+        let grid = to_bitvec(Rle::new(RLE)?, (-2, -1, 2, 1))?;
+        assert_eq!(grid, bits![1, 1, 0, 0, 0, 1, 0, 1, 0, 0, 0, 0, 1, 1, 1]);
+        assert_eq!(pattern.to_bitvec((-2, -1, 2, 1)), grid);
+        let mut parsed = Pattern::from_bitslice(&grid, 5);
+        assert_eq!(parsed.trim()?, Some((0, 0)));
+        let mut trimmed = pattern.clone();
+        trimmed.trim()?;
+        assert_eq!(parsed, trimmed);
+
+        assert_eq!(pattern.to_bitvec((0, 0, 1, 0)), bits![1, 0]);
+        assert!(pattern.to_bitvec((1, 0, 0, 0)).is_empty());
+        assert!(to_bitvec(Rle::new("2z!")?, (0, 0, 1, 1)).is_err());
+
+        let grid = bitvec![u8, Msb0; 0, 1, 1, 0, 1];
+        let cells = from_bitslice(&grid, 2).map(|cell| cell.position);
+        assert_eq!(cells.collect::<Vec<_>>(), [(1, 0), (0, 1), (0, 2)]);
+        assert_eq!(from_bitslice(&grid, 0).count(), 0);
+        Ok(())
+    }
+}
@@ -0,0 +1,170 @@
+//! Cooperative cancellation of long parses.
+//!
+//! Parsing a huge file can take a while. [`Cancellable`] wraps the iterator of a parser,
+//! and stops with [`Error::Cancelled`] once its [`CancelToken`] is cancelled,
+//! e.g., from the UI thread of a GUI, without killing the parsing thread.
+
+use displaydoc::Display;
+use std::{
+    error::Error as StdError,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
+use thiserror::Error;
+
+/// A shared flag that asks the parsers wrapped by [`Cancellable`] to stop.
+///
+/// Clones of a token share the same flag.
+#[derive(Clone, Debug, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    /// Creates a new token that is not cancelled.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Cancels the parses that hold a clone of this token.
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    /// Whether the token has been cancelled.
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// Errors that can be returned by [`Cancellable`].
+#[derive(Clone, Debug, Eq, Error, Display, PartialEq)]
+pub enum Error<E: StdError + 'static> {
+    /// The parse is cancelled.
+    Cancelled,
+    /// {0}
+    Parser(#[source] E),
+}
+
+/// An iterator adapter that stops when a [`CancelToken`] is cancelled.
+///
+/// The token is checked before the first item, and then once every
+/// [`interval`](Cancellable::with_interval) items, i.e., cells or nodes.
+/// After a cancellation, it returns one [`Error::Cancelled`] and then `None`.
+///
+/// # Example
+///
+/// ```rust
+/// use ca_formats::{
+///     cancel::{CancelToken, Cancellable, Error},
+///     rle::Rle,
+/// };
+///
+/// let token = CancelToken::new();
+/// let rle = Rle::new("x = 3, y = 3\nbo$2bo$3o!").unwrap();
+/// let mut cells = Cancellable::new(rle, token.clone()).with_interval(2);
+///
+/// assert!(cells.next().unwrap().is_ok());
+/// token.cancel();
+/// assert!(cells.next().unwrap().is_ok());
+/// assert!(matches!(cells.next(), Some(Err(Error::Cancelled))));
+/// assert!(cells.next().is_none());
+/// ```
+#[must_use]
+#[derive(Clone, Debug)]
+pub struct Cancellable<I> {
+    /// The wrapped iterator.
+    iter: I,
+    /// The token to check.
+    token: CancelToken,
+    /// Number of items between two checks.
+    interval: usize,
+    /// Number of items since the last check.
+    count: usize,
+    /// Whether the iteration has stopped.
+    stopped: bool,
+}
+
+impl<I> Cancellable<I> {
+    /// Number of items between two checks of the token by default.
+    pub const DEFAULT_INTERVAL: usize = 1024;
+
+    /// Wraps an iterator of cells or nodes, e.g., a parser.
+    pub fn new<C: IntoIterator<IntoIter = I>>(cells: C, token: CancelToken) -> Self {
+        Self {
+            iter: cells.into_iter(),
+            token,
+            interval: Self::DEFAULT_INTERVAL,
+            count: 0,
+            stopped: false,
+        }
+    }
+
+    /// Set the number of items between two checks of the token.
+    ///
+    /// An interval of `0` is treated as `1`, i.e., checking before every item.
+    pub fn with_interval(mut self, interval: usize) -> Self {
+        self.interval = interval.max(1);
+        self
+    }
+
+    /// The token checked by this iterator.
+    pub fn token(&self) -> &CancelToken {
+        &self.token
+    }
+}
+
+impl<I, T, E> Iterator for Cancellable<I>
+where
+    I: Iterator<Item = Result<T, E>>,
+    E: StdError + 'static,
+{
+    type Item = Result<T, Error<E>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.stopped {
+            return None;
+        }
+        if self.count == 0 && self.token.is_cancelled() {
+            self.stopped = true;
+            return Some(Err(Error::Cancelled));
+        }
+        self.count = (self.count + 1) % self.interval;
+        match self.iter.next() {
+            Some(item) => Some(item.map_err(Error::Parser)),
+            None => {
+                self.stopped = true;
+                None
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{macrocell::Macrocell, rle::Rle};
+
+    #[test]
+    fn cancel_token() -> Result<(), Box<dyn StdError>> {
+        let token = CancelToken::new();
+        let rle = Rle::new("100o!")?;
+        let mut cells = Cancellable::new(rle, token.clone()).with_interval(10);
+        assert_eq!(cells.by_ref().take(15).count(), 15);
+        token.cancel();
+        assert!(cells.token().is_cancelled());
+        let rest = cells.collect::<Vec<_>>();
+        assert_eq!(rest.len(), 6);
+        assert!(matches!(rest[5], Err(Error::Cancelled)));
+
+        let rle = Rle::new("100o!")?;
+        let cells = Cancellable::new(rle, CancelToken::new()).collect::<Result<Vec<_>, _>>()?;
+        assert_eq!(cells.len(), 100);
+
+        let macrocell = Macrocell::new("[M2]\n$$$$$$*$.*$\n")?;
+        let mut nodes = Cancellable::new(macrocell, token).with_interval(0);
+        assert!(matches!(nodes.next(), Some(Err(Error::Cancelled))));
+        assert!(nodes.next().is_none());
+        Ok(())
+    }
+}
@@ -0,0 +1,109 @@
+//! Content-addressed identifiers of patterns, hashed with
+//! [BLAKE3](https://crates.io/crates/blake3).
+
+use crate::{Pattern, State};
+use blake3::{Hash, Hasher};
+
+/// Prefix of the hashed data, to be changed if the encoding ever changes.
+const DOMAIN: &[u8] = b"ca-formats content id v1\n";
+
+impl<S: State> Pattern<S> {
+    /// A stable identifier of the pattern, which only depends on its rule and its living cells,
+    /// for deduplicating pattern archives, or as a key to cache expensive analyses.
+    ///
+    /// The rule is [normalized](crate::RuleString::normalized), and the cells are translated
+    /// so that the upper left corner of their bounding box is at `(0, 0)`, and sorted.
+    /// If a position appears more than once, the largest state is used. The metadata,
+    /// e.g., the name and the comments, and the dead cells are ignored.
+    ///
+    /// The identifier is the [BLAKE3](https://crates.io/crates/blake3) hash of an encoding
+    /// of the rule and the cells, which is not expected to change between versions.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use ca_formats::{plaintext::Plaintext, rle::Rle, Pattern};
+    ///
+    /// let rle = Pattern::from_rle(Rle::new("x = 3, y = 1, rule = 23/3\n3o!").unwrap()).unwrap();
+    /// let mut plaintext = Pattern::from_plaintext(Plaintext::new("!Name: Blinker\nOOO").unwrap()).unwrap();
+    /// assert_ne!(rle.content_id(), plaintext.content_id());
+    ///
+    /// plaintext.rule = Some("B3/S23".parse().unwrap());
+    /// assert_eq!(rle.content_id(), plaintext.content_id());
+    /// assert_eq!(rle.content_id().to_hex().len(), 64);
+    /// ```
+    #[cfg_attr(docs_rs, doc(cfg(feature = "blake3")))]
+    pub fn content_id(&self) -> Hash {
+        let mut cells = self
+            .cells
+            .iter()
+            .filter(|cell| cell.state != S::default())
+            .map(|cell| (cell.position, cell.state.into()))
+            .collect::<Vec<(_, u32)>>();
+        cells.sort_unstable_by_key(|&((x, y), state)| (y, x, u32::MAX - state));
+        cells.dedup_by_key(|&mut (position, _)| position);
+        let (x0, y0) = self
+            .bounding_box()
+            .map_or((0, 0), |(x0, y0, _, _)| (x0, y0));
+
+        let mut hasher = Hasher::new();
+        hasher.update(DOMAIN);
+        let rule = self.rule.as_ref().map(|rule| rule.normalized());
+        let rule = rule.as_ref().map_or("", |rule| rule.as_str());
+        hasher.update(&(rule.len() as u64).to_le_bytes());
+        hasher.update(rule.as_bytes());
+        hasher.update(&(cells.len() as u64).to_le_bytes());
+        for ((x, y), state) in cells {
+            hasher.update(&x.wrapping_sub(x0).to_le_bytes());
+            hasher.update(&y.wrapping_sub(y0).to_le_bytes());
+            hasher.update(&state.to_le_bytes());
+        }
+        hasher.finalize()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{rle::Rle, CellData, Pattern};
+    use std::error::Error;
+
+    #[test]
+    fn content_id_normalized() -> Result<(), Box<dyn Error>> {
+        let glider = Pattern::from_rle(Rle::new("x = 3, y = 3, rule = B3/S23\nbo$2bo$3o!")?)?;
+        let id = glider.content_id();
+
+        let mut moved = Pattern::from_rle(Rle::new(
+            "#CXRLE Pos=-7,40\nx = 3, y = 3, rule = b3s23\n2bo$obo$b2o!",
+        )?)?;
+        assert_ne!(moved.content_id(), id);
+        moved = Pattern::from_rle(Rle::new(
+            "#N Glider\n#CXRLE Pos=-7,40\nx = 3, y = 3, rule = 23/3\nbo$2bo$3o!",
+        )?)?;
+        moved.cells.reverse();
+        moved.cells.push(CellData {
+            position: (0, 0),
+            state: 0,
+        });
+        assert_eq!(moved.content_id(), id);
+
+        let mut duplicated = glider.clone();
+        duplicated.cells.insert(0, CellData::from((1, 0)));
+        assert_eq!(duplicated.content_id(), id);
+
+        let mut other_rule = glider.clone();
+        other_rule.rule = Some("B36/S23".parse()?);
+        assert_ne!(other_rule.content_id(), id);
+        other_rule.rule = Some("B3/S23é".parse()?);
+        assert_ne!(other_rule.content_id(), id);
+        other_rule.rule = None;
+        assert_ne!(other_rule.content_id(), id);
+
+        let multistate = Pattern::from_rle(Rle::new("x = 3, y = 3, rule = B3/S23\nbA$2bA$2AB!")?)?;
+        assert_ne!(multistate.content_id(), id);
+        assert_eq!(
+            Pattern::<u8>::default().content_id(),
+            Pattern::<u8>::default().content_id()
+        );
+        Ok(())
+    }
+}
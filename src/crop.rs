@@ -0,0 +1,213 @@
+//! Cropping and trimming of patterns.
+//!
+//! [`Crop`] keeps the cells in a rectangle, and [`Trim`] moves the living cells so that
+//! their bounding box starts at `(0, 0)`, removing the empty rows and columns on the border.
+//! The same operations on a [`Pattern`](crate::Pattern) are
+//! [`Pattern::crop`](crate::Pattern::crop) and [`Pattern::trim`](crate::Pattern::trim).
+
+use crate::{extend_bounding_box, BoundingBox, CellData, Coordinates, Positioned, State};
+use std::{
+    io::{Error as IoError, ErrorKind},
+    vec::IntoIter,
+};
+
+/// Whether the coordinates are in the rectangle, including its border.
+pub(crate) const fn contains(
+    (min_x, min_y, max_x, max_y): BoundingBox,
+    (x, y): Coordinates,
+) -> bool {
+    min_x <= x && x <= max_x && min_y <= y && y <= max_y
+}
+
+/// Trims the cells in place, and returns the upper left corner of the bounding box
+/// of the living cells before trimming.
+///
+/// Dead cells outside the bounding box are removed. If there is no living cell,
+/// all the cells are removed, and `None` is returned.
+///
+/// Returns an error of kind [`InvalidInput`](ErrorKind::InvalidInput), and leaves
+/// the cells unchanged, if the trimmed coordinates would be out of range.
+pub(crate) fn trim_cells<S: State>(
+    cells: &mut Vec<CellData<S>>,
+) -> Result<Option<Coordinates>, IoError> {
+    let mut bounding_box = None;
+    for cell in cells.iter().filter(|cell| cell.state != S::default()) {
+        extend_bounding_box(&mut bounding_box, cell.position);
+    }
+    let Some(bounding_box) = bounding_box else {
+        cells.clear();
+        return Ok(None);
+    };
+    let (x, y, max_x, max_y) = bounding_box;
+    if max_x.checked_sub(x).is_none() || max_y.checked_sub(y).is_none() {
+        return Err(IoError::new(
+            ErrorKind::InvalidInput,
+            format!("the bounding box {:?} is too large to trim", bounding_box),
+        ));
+    }
+    cells.retain(|cell| contains(bounding_box, cell.position));
+    for cell in cells.iter_mut() {
+        cell.position = (cell.position.0 - x, cell.position.1 - y);
+    }
+    Ok(Some((x, y)))
+}
+
+/// An iterator adapter that keeps the cells in a rectangle, given as a [`BoundingBox`],
+/// i.e., `(min_x, min_y, max_x, max_y)`, including its border.
+///
+/// The coordinates of the cells are not changed. Errors are returned as they are.
+///
+/// # Example
+///
+/// ```rust
+/// use ca_formats::{crop::Crop, rle::Rle};
+///
+/// let glider = Rle::new("bo$2bo$3o!").unwrap();
+/// let cells = Crop::new(glider, (1, 1, 5, 5)).map(|cell| cell.unwrap().position);
+/// assert_eq!(cells.collect::<Vec<_>>(), vec![(2, 1), (1, 2), (2, 2)]);
+/// ```
+#[must_use]
+#[derive(Clone, Debug)]
+pub struct Crop<I> {
+    /// The wrapped iterator.
+    iter: I,
+    /// The rectangle.
+    rect: BoundingBox,
+}
+
+impl<I> Crop<I> {
+    /// Wraps an iterator of cells, e.g., a parser.
+    pub fn new<C: IntoIterator<IntoIter = I>>(cells: C, rect: BoundingBox) -> Self {
+        Self {
+            iter: cells.into_iter(),
+            rect,
+        }
+    }
+}
+
+impl<I, T, E> Iterator for Crop<I>
+where
+    I: Iterator<Item = Result<T, E>>,
+    T: Positioned,
+{
+    type Item = Result<T, E>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let rect = self.rect;
+        self.iter.find(|cell| {
+            cell.as_ref()
+                .map_or(true, |cell| contains(rect, cell.position()))
+        })
+    }
+}
+
+/// An iterator over trimmed cells, i.e., translated so that the upper left corner
+/// of the bounding box of the living cells is at `(0, 0)`.
+///
+/// All the cells are read when it is created. Dead cells outside the bounding box
+/// of the living cells are removed.
+///
+/// # Example
+///
+/// ```rust
+/// use ca_formats::{crop::Trim, rle::Rle};
+///
+/// let blinker = Rle::new("#CXRLE Pos=-5,3\n3o!").unwrap();
+/// let trimmed = Trim::new(blinker).unwrap();
+/// assert_eq!(trimmed.offset(), Some((-5, 3)));
+/// let cells = trimmed.map(|cell| cell.position).collect::<Vec<_>>();
+/// assert_eq!(cells, vec![(0, 0), (1, 0), (2, 0)]);
+/// ```
+#[must_use]
+#[derive(Clone, Debug)]
+pub struct Trim<S = u8> {
+    /// The trimmed cells.
+    cells: IntoIter<CellData<S>>,
+    /// The upper left corner of the bounding box before trimming.
+    offset: Option<Coordinates>,
+}
+
+impl<S: State> Trim<S> {
+    /// Reads and trims the cells of an iterator, e.g., a parser.
+    ///
+    /// Returns the first error of the iterator, if any, or an error converted from
+    /// an [`InvalidInput`](ErrorKind::InvalidInput) I/O error if the bounding box of
+    /// the living cells is wider or taller than `i64::MAX`.
+    pub fn new<E, C>(cells: C) -> Result<Self, E>
+    where
+        C: IntoIterator<Item = Result<CellData<S>, E>>,
+        E: From<IoError>,
+    {
+        let mut cells = cells.into_iter().collect::<Result<Vec<_>, _>>()?;
+        let offset = trim_cells(&mut cells)?;
+        Ok(Self {
+            cells: cells.into_iter(),
+            offset,
+        })
+    }
+
+    /// The upper left corner of the bounding box of the living cells before trimming,
+    /// i.e., the translation that was removed.
+    ///
+    /// `None` if there is no living cell.
+    pub const fn offset(&self) -> Option<Coordinates> {
+        self.offset
+    }
+}
+
+impl<S> Iterator for Trim<S> {
+    type Item = CellData<S>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.cells.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.cells.size_hint()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{rle::Rle, ParseOptions};
+    use std::error::Error as StdError;
+
+    #[test]
+    fn crop_trim() -> Result<(), Box<dyn StdError>> {
+        const RLE: &str = "x = 5, y = 4\n5b$b2o$b.o$5b!";
+
+        let cells = Crop::new(
+            Rle::new_with_options(RLE, ParseOptions::new().include_dead(true))?,
+            (1, 1, 2, 1),
+        )
+        .collect::<Result<Vec<_>, _>>()?;
+        assert_eq!(cells, [(1, 1), (2, 1)].map(CellData::from));
+        let mut cells = Crop::new(Rle::new("o$2z!")?, (5, 5, 6, 6));
+        assert!(matches!(cells.next(), Some(Err(_))));
+
+        let trimmed = Trim::new(Rle::new_with_options(
+            RLE,
+            ParseOptions::new().include_dead(true),
+        )?)?;
+        assert_eq!(trimmed.offset(), Some((1, 1)));
+        assert_eq!(
+            trimmed.collect::<Vec<_>>(),
+            [((0, 0), 1), ((1, 0), 1), ((0, 1), 0), ((1, 1), 1)]
+                .map(|(position, state)| { CellData { position, state } })
+        );
+
+        let trimmed = Trim::new(Rle::new_with_options(
+            "x = 2, y = 2\n2b$2b!",
+            ParseOptions::new().include_dead(true),
+        )?)?;
+        assert_eq!(trimmed.offset(), None);
+        assert_eq!(trimmed.count(), 0);
+        assert!(Trim::new(Rle::new("2z!")?).is_err());
+
+        let far = [(i64::MIN, 0), (i64::MAX, 0)].map(|position| Ok(CellData::from(position)));
+        let trimmed = Trim::<u8>::new::<IoError, _>(far);
+        assert!(trimmed.is_err_and(|e| e.kind() == ErrorKind::InvalidInput));
+        Ok(())
+    }
+}
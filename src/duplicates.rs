@@ -0,0 +1,143 @@
+//! Detection of cells that appear more than once.
+//!
+//! Hand-written files and some formats, e.g., apgcode, can encode the same cell twice.
+//! [`Duplicates`] wraps the iterator of a parser, and handles such cells according to
+//! a [`Policy`].
+
+use crate::{Coordinates, Positioned};
+use displaydoc::Display;
+use std::{collections::HashSet, error::Error as StdError};
+use thiserror::Error;
+
+/// What to do with a cell whose position has already been returned.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Default, Hash)]
+pub enum Policy {
+    /// Skip the cell.
+    #[default]
+    Skip,
+    /// Return an [`Error::Duplicate`].
+    Error,
+    /// Return the cell, and record its position in [`Duplicates::duplicates`].
+    Report,
+}
+
+/// Errors that can be returned by [`Duplicates`].
+#[derive(Clone, Debug, Eq, Error, Display, PartialEq)]
+pub enum Error<E: StdError + 'static> {
+    /// Duplicated cell: {0:?}.
+    Duplicate(Coordinates),
+    /// {0}
+    Parser(#[source] E),
+}
+
+/// An iterator adapter that detects cells that appear more than once.
+///
+/// The positions of skipped or reported cells are recorded in
+/// [`duplicates`](Duplicates::duplicates) under all policies.
+///
+/// # Example
+///
+/// ```rust
+/// use ca_formats::{
+///     apgcode::Wechsler,
+///     duplicates::{Duplicates, Policy},
+/// };
+///
+/// let mut cells = Duplicates::new(Wechsler::new("11"), Policy::Report);
+/// assert_eq!(cells.by_ref().count(), 2);
+/// assert_eq!(cells.duplicates(), []);
+///
+/// let cells = Duplicates::new([Ok::<_, std::fmt::Error>((0, 0)), Ok((0, 0))], Policy::Skip);
+/// assert_eq!(cells.count(), 1);
+/// ```
+#[must_use]
+#[derive(Clone, Debug)]
+pub struct Duplicates<I> {
+    /// The wrapped iterator.
+    iter: I,
+    /// What to do with duplicated cells.
+    policy: Policy,
+    /// Positions of the cells returned so far.
+    seen: HashSet<Coordinates>,
+    /// Positions of the duplicated cells found so far.
+    duplicates: Vec<Coordinates>,
+}
+
+impl<I> Duplicates<I> {
+    /// Wraps an iterator of cells, e.g., a parser.
+    pub fn new<C: IntoIterator<IntoIter = I>>(cells: C, policy: Policy) -> Self {
+        Self {
+            iter: cells.into_iter(),
+            policy,
+            seen: HashSet::new(),
+            duplicates: Vec::new(),
+        }
+    }
+
+    /// Positions of the duplicated cells found so far, in the order they are found.
+    pub fn duplicates(&self) -> &[Coordinates] {
+        &self.duplicates
+    }
+}
+
+impl<I, T, E> Iterator for Duplicates<I>
+where
+    I: Iterator<Item = Result<T, E>>,
+    T: Positioned,
+    E: StdError + 'static,
+{
+    type Item = Result<T, Error<E>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let cell = match self.iter.next()? {
+                Ok(cell) => cell,
+                Err(e) => return Some(Err(Error::Parser(e))),
+            };
+            let position = cell.position();
+            if self.seen.insert(position) {
+                return Some(Ok(cell));
+            }
+            self.duplicates.push(position);
+            match self.policy {
+                Policy::Skip => continue,
+                Policy::Error => return Some(Err(Error::Duplicate(position))),
+                Policy::Report => return Some(Ok(cell)),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{plaintext::Plaintext, rle::Rle, CellData};
+
+    #[test]
+    fn duplicates_policies() -> Result<(), Box<dyn StdError>> {
+        const CELLS: [(i64, i64); 5] = [(0, 0), (1, 0), (0, 0), (2, 0), (1, 0)];
+        let cells = || CELLS.map(Ok::<_, std::fmt::Error>);
+
+        let mut skip = Duplicates::new(cells(), Policy::Skip);
+        let skipped = skip.by_ref().collect::<Result<Vec<_>, _>>()?;
+        assert_eq!(skipped, vec![(0, 0), (1, 0), (2, 0)]);
+        assert_eq!(skip.duplicates(), [(0, 0), (1, 0)]);
+
+        let mut report = Duplicates::new(cells(), Policy::Report);
+        assert_eq!(report.by_ref().count(), 5);
+        assert_eq!(report.duplicates(), [(0, 0), (1, 0)]);
+
+        let mut error = Duplicates::new(cells(), Policy::Error);
+        assert!(matches!(error.nth(2), Some(Err(Error::Duplicate((0, 0))))));
+
+        let rle = Rle::new("3o!")?;
+        let cells = Duplicates::new(rle, Policy::Error).collect::<Result<Vec<_>, _>>()?;
+        assert_eq!(cells[2], CellData::from((2, 0)));
+
+        let plaintext = Plaintext::new("O?")?;
+        let mut cells = Duplicates::new(plaintext, Policy::Skip);
+        cells.next().unwrap()?;
+        assert!(matches!(cells.next(), Some(Err(Error::Parser(_)))));
+        Ok(())
+    }
+}
@@ -0,0 +1,135 @@
+//! A standard way for simulators to plug into this crate.
+//!
+//! This crate does not simulate patterns. Features that need the next generations of
+//! a pattern, e.g., [`ApgCode::phases`](crate::apgcode::ApgCode::phases),
+//! [`find_period`] and [`Pattern::advance`](crate::Pattern::advance),
+//! call an [`Evolver`] provided by a simulator crate.
+
+use crate::{CellData, Coordinates};
+use std::error::Error as StdError;
+
+/// The rule of a pattern without a rulestring, i.e., Conway's Game of Life.
+pub const DEFAULT_RULE: &str = "B3/S23";
+
+/// A simulator that evolves a pattern by one generation.
+///
+/// It is implemented for closures that take the living cells and the rulestring,
+/// and return the living cells in the next generation.
+///
+/// The rulestring is passed as it is written in the source, e.g., a rule slug like `b3s23`
+/// for apgcodes, and [`DEFAULT_RULE`] if there is no rulestring.
+pub trait Evolver {
+    /// Error returned by the simulator, e.g., for an unsupported rule.
+    type Error: StdError + 'static;
+
+    /// Evolves the living cells by one generation in the rule.
+    fn step(&mut self, cells: &[CellData], rule: &str) -> Result<Vec<CellData>, Self::Error>;
+}
+
+impl<F, E> Evolver for F
+where
+    F: FnMut(&[CellData], &str) -> Result<Vec<CellData>, E>,
+    E: StdError + 'static,
+{
+    type Error = E;
+
+    fn step(&mut self, cells: &[CellData], rule: &str) -> Result<Vec<CellData>, Self::Error> {
+        self(cells, rule)
+    }
+}
+
+/// Evolves the cells with an [`Evolver`] to find their period, e.g., to verify an oscillator.
+///
+/// Returns the smallest number of generations, up to `max_period`, after which the
+/// living cells are the same, ignoring their order. Returns `Ok(None)` if there is none.
+/// The period of a still life is `1`.
+///
+/// # Example
+///
+/// ```rust
+/// use ca_formats::{evolve::find_period, CellData};
+/// use std::convert::Infallible;
+///
+/// // A blinker rotates by 90 degrees around its center in each generation.
+/// let mut rotate = |cells: &[CellData], _: &str| {
+///     let rotated = cells
+///         .iter()
+///         .map(|cell| CellData::from((2 - cell.position.1, cell.position.0)))
+///         .collect();
+///     Ok::<_, Infallible>(rotated)
+/// };
+/// let blinker = [(0, 1), (1, 1), (2, 1)].map(CellData::from);
+/// assert_eq!(find_period(&mut rotate, &blinker, "B3/S23", 10), Ok(Some(2)));
+/// assert_eq!(find_period(&mut rotate, &blinker, "B3/S23", 1), Ok(None));
+/// ```
+pub fn find_period<E: Evolver>(
+    evolver: &mut E,
+    cells: &[CellData],
+    rule: &str,
+    max_period: u64,
+) -> Result<Option<u64>, E::Error> {
+    let start = canonical(cells.to_vec());
+    let mut current = cells.to_vec();
+    for period in 1..=max_period {
+        current = evolver.step(&current, rule)?;
+        if canonical(current.clone()) == start {
+            return Ok(Some(period));
+        }
+    }
+    Ok(None)
+}
+
+/// Removes the dead cells and the duplicates, and sorts the cells in row-major order,
+/// so that the same living cells can be compared.
+pub(crate) fn canonical(mut cells: Vec<CellData>) -> Vec<CellData> {
+    cells.retain(|cell| cell.state != 0);
+    cells.sort_by_key(|cell| (cell.position.1, cell.position.0));
+    cells.dedup();
+    cells
+}
+
+/// The displacement from some cells to a translation of them, if any.
+///
+/// Both must be [`canonical`]. Returns `None` if either is empty.
+pub(crate) fn displacement(from: &[CellData], to: &[CellData]) -> Option<Coordinates> {
+    let (start, end) = (from.first()?, to.first()?);
+    let dx = end.position.0 - start.position.0;
+    let dy = end.position.1 - start.position.1;
+    let translated = from.len() == to.len()
+        && from.iter().zip(to).all(|(a, b)| {
+            a.state == b.state && (a.position.0 + dx, a.position.1 + dy) == b.position
+        });
+    translated.then_some((dx, dy))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fmt::Error as FmtError;
+
+    #[test]
+    fn evolve_find_period() -> Result<(), FmtError> {
+        let mut still = |cells: &[CellData], _: &str| Ok(cells.iter().rev().copied().collect());
+        let block = [(0, 0), (1, 0), (0, 1), (1, 1)].map(CellData::from);
+        assert_eq!(find_period(&mut still, &block, DEFAULT_RULE, 1)?, Some(1));
+        assert_eq!(find_period(&mut still, &block, DEFAULT_RULE, 0)?, None);
+
+        let mut rules = Vec::new();
+        let mut shift = |cells: &[CellData], rule: &str| {
+            rules.push(rule.to_string());
+            Ok(cells
+                .iter()
+                .map(|cell| CellData::from((cell.position.0 + 1, cell.position.1)))
+                .collect())
+        };
+        assert_eq!(find_period(&mut shift, &block, "B36/S23", 3)?, None);
+        assert_eq!(rules, ["B36/S23"; 3]);
+
+        let mut fail = |_: &[CellData], _: &str| Err(FmtError);
+        assert_eq!(
+            find_period(&mut fail, &block, DEFAULT_RULE, 3),
+            Err(FmtError)
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,208 @@
+//! Helpers for fuzzing, using the [`arbitrary`](https://crates.io/crates/arbitrary) crate.
+//!
+//! Besides the [`Arbitrary`] implementations of [`CellData`](crate::CellData) and
+//! [`HeaderData`], this module provides generators of valid pattern strings,
+//! together with the cells they encode, so that parsers and writers can be
+//! checked against each other.
+
+use crate::{
+    rle::{CxrleData, HeaderData},
+    CellData, Coordinates,
+};
+use arbitrary::{Arbitrary, Result, Unstructured};
+use std::{collections::BTreeMap, fmt::Write};
+
+/// Characters that may appear in a generated rulestring.
+const RULE_CHARS: &[u8] = b"BSbs0123456789/-:HTV";
+
+/// Generate an arbitrary rulestring that fits in a header line.
+fn arbitrary_rule(u: &mut Unstructured<'_>) -> Result<String> {
+    let len = u.int_in_range(1..=16)?;
+    let mut rule = String::with_capacity(len);
+    for _ in 0..len {
+        rule.push(char::from(*u.choose(RULE_CHARS)?));
+    }
+    Ok(rule)
+}
+
+/// The rulestring never contains line breaks, commas or leading / trailing spaces,
+/// and there are no extra pairs, so that the header is always a valid header line.
+impl<'a> Arbitrary<'a> for HeaderData {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(Self {
+            x: u.arbitrary()?,
+            y: u.arbitrary()?,
+            rule: if u.arbitrary()? {
+                Some(arbitrary_rule(u)?)
+            } else {
+                None
+            },
+            extra: BTreeMap::new(),
+        })
+    }
+}
+
+/// Encode a state in RLE, e.g., `A` for `1`, `pA` for `25`.
+fn push_state(text: &mut String, state: u8) {
+    let (prefix, suffix) = ((state - 1) / 24, (state - 1) % 24);
+    if prefix > 0 {
+        text.push(char::from(b'o' + prefix));
+    }
+    text.push(char::from(b'A' + suffix));
+}
+
+/// A valid RLE string, together with the living cells it encodes.
+///
+/// The string may contain comments, a `#CXRLE` line, a header line,
+/// multi-char states and line breaks at arbitrary places in the body.
+/// The header is not required to match the actual size of the pattern.
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub struct RleString {
+    /// The RLE string.
+    pub text: String,
+    /// Living cells in the order they appear in the string.
+    pub cells: Vec<CellData>,
+}
+
+impl<'a> Arbitrary<'a> for RleString {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        let mut text = String::new();
+        let mut cells = Vec::new();
+        let mut position = (0, 0);
+        if u.arbitrary()? {
+            text.push_str("#N Arbitrary\n#C Generated for fuzzing.\n");
+        }
+        if u.arbitrary()? {
+            let x = i64::from(u.arbitrary::<i32>()?);
+            let y = i64::from(u.arbitrary::<i32>()?);
+            let cxrle_data = CxrleData {
+                pos: Some((x, y)),
+                ..Default::default()
+            };
+            writeln!(text, "{}", cxrle_data).unwrap();
+            position = (x, y);
+        }
+        let x_start = position.0;
+        if u.arbitrary()? {
+            let header = HeaderData::arbitrary(u)?;
+            writeln!(text, "{}", header).unwrap();
+        }
+        let runs = u.int_in_range(0..=64)?;
+        for _ in 0..runs {
+            let count = u.int_in_range(1..=16_i64)?;
+            if count > 1 || u.ratio(1, 8)? {
+                write!(text, "{}", count).unwrap();
+            }
+            match u.int_in_range(0..=4)? {
+                0 => {
+                    text.push(*u.choose(&['b', '.'])?);
+                    position.0 += count;
+                }
+                1 | 2 => {
+                    text.push('o');
+                    for _ in 0..count {
+                        cells.push(CellData::from(position));
+                        position.0 += 1;
+                    }
+                }
+                3 => {
+                    let state = u.int_in_range(1..=255)?;
+                    push_state(&mut text, state);
+                    for _ in 0..count {
+                        cells.push(CellData { position, state });
+                        position.0 += 1;
+                    }
+                }
+                _ => {
+                    text.push('$');
+                    position = (x_start, position.1 + count);
+                }
+            }
+            if u.ratio(1, 8)? {
+                text.push('\n');
+            }
+        }
+        text.push('!');
+        Ok(Self { text, cells })
+    }
+}
+
+/// A valid Plaintext string, together with the living cells it encodes.
+///
+/// The string may start with some comment lines, and the rows may have different lengths.
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub struct PlaintextString {
+    /// The Plaintext string.
+    pub text: String,
+    /// Living cells in the order they appear in the string.
+    pub cells: Vec<Coordinates>,
+}
+
+impl<'a> Arbitrary<'a> for PlaintextString {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        let mut text = String::new();
+        let mut cells = Vec::new();
+        if u.arbitrary()? {
+            text.push_str("!Name: Arbitrary\n!\n");
+        }
+        let height = u.int_in_range(1..=32)?;
+        for y in 0..height {
+            if y > 0 {
+                text.push('\n');
+            }
+            let width = u.int_in_range(0..=32)?;
+            for x in 0..width {
+                if u.arbitrary()? {
+                    text.push(*u.choose(&['O', '*'])?);
+                    cells.push((x, y));
+                } else {
+                    text.push('.');
+                }
+            }
+        }
+        Ok(Self { text, cells })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{plaintext::Plaintext, rle::Rle};
+
+    /// Some deterministic bytes for [`Unstructured`].
+    fn bytes(seed: u64) -> Vec<u8> {
+        let mut state = seed;
+        (0..4096)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                state as u8
+            })
+            .collect()
+    }
+
+    #[test]
+    fn fuzz_rle_string() -> std::result::Result<(), Box<dyn std::error::Error>> {
+        for seed in 1..=64 {
+            let data = bytes(seed);
+            let rle_string = RleString::arbitrary(&mut Unstructured::new(&data))?;
+            let cells =
+                Rle::new(rle_string.text.as_str())?.collect::<std::result::Result<Vec<_>, _>>()?;
+            assert_eq!(cells, rle_string.cells, "{}", rle_string.text);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn fuzz_plaintext_string() -> std::result::Result<(), Box<dyn std::error::Error>> {
+        for seed in 1..=64 {
+            let data = bytes(seed);
+            let plaintext_string = PlaintextString::arbitrary(&mut Unstructured::new(&data))?;
+            let cells = Plaintext::new(plaintext_string.text.as_str())?
+                .collect::<std::result::Result<Vec<_>, _>>()?;
+            assert_eq!(cells, plaintext_string.cells, "{}", plaintext_string.text);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,84 @@
+//! Resolution of include directives, e.g., `#I` lines in XLife-style Life 1.05 files.
+//!
+//! A parser that supports include directives asks an [`IncludeResolver`] for the content
+//! of each included pattern, and inlines its cells. [`PathResolver`] reads the files
+//! relative to a directory. Implement the trait to restrict what can be read,
+//! e.g., to serve patterns from memory in a sandbox.
+
+use std::{
+    fs,
+    io::{Error as IoError, ErrorKind},
+    path::{Component, Path, PathBuf},
+};
+
+/// Maximum depth of nested includes, to stop include cycles.
+pub const MAX_DEPTH: usize = 16;
+
+/// Maximum number of includes resolved for a pattern in total, counting the nested ones,
+/// so that a pattern including the same pattern many times can't expand exponentially.
+pub const MAX_INCLUDES: usize = 4096;
+
+/// A source of the patterns named by include directives.
+///
+/// It is implemented for closures that take the name and return the content.
+pub trait IncludeResolver {
+    /// Returns the content of the pattern named `name`.
+    fn resolve(&mut self, name: &str) -> Result<String, IoError>;
+}
+
+impl<F> IncludeResolver for F
+where
+    F: FnMut(&str) -> Result<String, IoError>,
+{
+    fn resolve(&mut self, name: &str) -> Result<String, IoError> {
+        self(name)
+    }
+}
+
+/// An [`IncludeResolver`] that reads files relative to a directory.
+///
+/// Absolute paths and paths containing `..` are refused, so that a pattern can only
+/// include files under the directory. Symbolic links are still followed.
+///
+/// # Example
+///
+/// ```rust
+/// use ca_formats::include::{IncludeResolver, PathResolver};
+///
+/// let mut resolver = PathResolver::new("tests");
+/// assert!(resolver.resolve("sirrobin.rle").is_ok());
+/// assert!(resolver.resolve("../Cargo.toml").is_err());
+/// ```
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct PathResolver {
+    /// The directory.
+    dir: PathBuf,
+}
+
+impl PathResolver {
+    /// Creates a resolver that reads files relative to `dir`.
+    pub fn new<P: Into<PathBuf>>(dir: P) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// The directory.
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+}
+
+impl IncludeResolver for PathResolver {
+    fn resolve(&mut self, name: &str) -> Result<String, IoError> {
+        let path = Path::new(name);
+        if !path
+            .components()
+            .all(|component| matches!(component, Component::Normal(_) | Component::CurDir))
+        {
+            return Err(IoError::new(
+                ErrorKind::PermissionDenied,
+                format!("path outside the directory: {}", name),
+            ));
+        }
+        fs::read_to_string(self.dir.join(path))
+    }
+}
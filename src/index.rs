@@ -0,0 +1,244 @@
+//! Summaries of many pattern files, for building a search index of a pattern archive.
+//!
+//! [`scan`] reads only the metadata that is cheap to get from each file. An RLE file
+//! is read up to its header line with [`read_header`], and a Macrocell file up to
+//! its first node, so that large files are not decoded.
+
+use crate::{
+    apgcode::ApgCode,
+    input::Normalized,
+    life::{Life105, Life106},
+    macrocell::Macrocell,
+    plaintext::Plaintext,
+    rle::{read_header, span, Metadata},
+    source::{DynError, Format},
+    Input, Pattern, RuleString,
+};
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::{read_to_string, File},
+    io::{BufReader, Error as IoError},
+    path::{Path, PathBuf},
+};
+
+/// A summary of a pattern file, returned by [`scan`].
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Record {
+    /// Path of the file.
+    pub path: PathBuf,
+    /// Format of the file.
+    pub format: Format,
+    /// The rulestring, or the rule slug of an apgcode.
+    pub rule: Option<RuleString>,
+    /// Name of the pattern, e.g., from the `#N` line of an RLE.
+    pub name: Option<String>,
+    /// Width and height of the pattern.
+    ///
+    /// For RLE, they are the size in the header line. `None` for Macrocell.
+    pub size: Option<(u64, u64)>,
+    /// Number of living cells.
+    ///
+    /// For RLE, it is the population in a `#C population = N` comment, if any.
+    /// `None` for Macrocell.
+    pub population: Option<u64>,
+}
+
+impl Record {
+    /// A record of a file without any metadata.
+    fn new(path: &Path, format: Format) -> Self {
+        Record {
+            path: path.to_path_buf(),
+            format,
+            rule: None,
+            name: None,
+            size: None,
+            population: None,
+        }
+    }
+
+    /// Fill in the metadata of an RLE.
+    fn with_rle(mut self, metadata: Metadata) -> Self {
+        self.name = find_name(&metadata.comments, "#N");
+        if let Some(header_data) = metadata.header_data {
+            self.rule = header_data.rule.and_then(|rule| RuleString::new(rule).ok());
+            self.size = Some((header_data.x, header_data.y));
+        }
+        self.population = metadata.declared_population;
+        self
+    }
+
+    /// Fill in the metadata of a Macrocell file.
+    fn with_macrocell<I: Input>(mut self, macrocell: &Macrocell<I>) -> Self {
+        self.name = find_name(macrocell.comments(), "#N");
+        self.rule = macrocell.rule_info().map(|info| info.rule);
+        self
+    }
+
+    /// Fill in the metadata of a pattern that is read entirely.
+    ///
+    /// Returns an error if the width or the height doesn't fit in a `u64`.
+    fn with_pattern(mut self, pattern: Pattern) -> Result<Self, IoError> {
+        self.size = Some(match pattern.bounding_box() {
+            Some((x0, y0, x1, y1)) => (span(x0, x1)?, span(y0, y1)?),
+            None => (0, 0),
+        });
+        self.population = Some(pattern.cells.iter().filter(|cell| cell.state != 0).count() as u64);
+        self.rule = pattern.rule;
+        self.name = pattern.name;
+        Ok(self)
+    }
+}
+
+/// The name in a comment line with the given prefix, e.g., `#N Glider`.
+fn find_name(comments: &[String], prefix: &str) -> Option<String> {
+    comments
+        .iter()
+        .find_map(|comment| comment.strip_prefix(prefix))
+        .map(|name| name.trim().to_string())
+        .filter(|name| !name.is_empty())
+}
+
+/// Summarize a pattern in a string.
+fn scan_text(path: &Path, text: &str, format: Format) -> Result<Record, DynError> {
+    let record = Record::new(path, format);
+    Ok(match format {
+        Format::Rle => record.with_rle(read_header(Normalized::new(text))?),
+        Format::Macrocell => record.with_macrocell(&Macrocell::new(Normalized::new(text))?),
+        Format::Plaintext => record.with_pattern(Pattern::from_plaintext(Plaintext::new(
+            Normalized::new(text),
+        )?)?)?,
+        Format::Life105 => {
+            record.with_pattern(Pattern::from_life105(Life105::new(Normalized::new(text))?)?)?
+        }
+        Format::Life106 => {
+            record.with_pattern(Pattern::from_life106(Life106::new(Normalized::new(text)))?)?
+        }
+        Format::Apgcode => record.with_pattern(Pattern::from_apgcode(
+            ApgCode::new_with_context(text.trim())?,
+        )?)?,
+    })
+}
+
+/// Summarize a pattern file. See [`scan`].
+///
+/// # Example
+///
+/// ```rust
+/// use ca_formats::{index::scan_file, source::Format};
+///
+/// let record = scan_file("tests/sirrobin.rle").unwrap();
+/// assert_eq!(record.format, Format::Rle);
+/// assert_eq!(record.name.as_deref(), Some("Sir Robin"));
+/// assert_eq!(record.size, Some((31, 79)));
+/// ```
+pub fn scan_file<P: AsRef<Path>>(path: P) -> Result<Record, DynError> {
+    let path = path.as_ref();
+    let format = path
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .and_then(Format::from_extension);
+    match format {
+        Some(Format::Rle) => {
+            let reader = Normalized::new(BufReader::new(File::open(path)?));
+            Ok(Record::new(path, Format::Rle).with_rle(read_header(reader)?))
+        }
+        Some(Format::Macrocell) => {
+            let macrocell = Macrocell::new(Normalized::new(BufReader::new(File::open(path)?)))?;
+            Ok(Record::new(path, Format::Macrocell).with_macrocell(&macrocell))
+        }
+        _ => {
+            let text = read_to_string(path)?;
+            scan_text(path, &text, format.unwrap_or_else(|| Format::detect(&text)))
+        }
+    }
+}
+
+/// Summarize many pattern files, e.g., all the files in an archive.
+///
+/// The format of each file is given by its extension, if it is
+/// [known](Format::from_extension), and [detected](Format::detect) from the content otherwise.
+/// Only the metadata that is cheap to get is read: the name, the rule, and the size
+/// and the population, where the format gives them without decoding the whole pattern.
+///
+/// Returns a result for each path, in the same order, so that a broken file
+/// doesn't stop the scan.
+///
+/// # Example
+///
+/// ```rust
+/// use ca_formats::index::scan;
+///
+/// let records = scan(["tests/sirrobin.rle", "tests/sirrobin.cells", "tests/missing.rle"]);
+/// assert_eq!(records[0].as_ref().unwrap().population, None);
+/// assert_eq!(records[1].as_ref().unwrap().population, Some(282));
+/// assert!(records[2].is_err());
+/// ```
+pub fn scan<I, P>(paths: I) -> Vec<Result<Record, DynError>>
+where
+    I: IntoIterator<Item = P>,
+    P: AsRef<Path>,
+{
+    paths.into_iter().map(scan_file).collect()
+}
+
+/// Summarize many pattern files in parallel, using [rayon](https://crates.io/crates/rayon).
+/// See [`scan`].
+#[cfg(feature = "rayon")]
+#[cfg_attr(docs_rs, doc(cfg(feature = "rayon")))]
+pub fn par_scan<I, P>(paths: I) -> Vec<Result<Record, DynError>>
+where
+    I: IntoParallelIterator<Item = P>,
+    I::Iter: IndexedParallelIterator,
+    P: AsRef<Path>,
+{
+    paths.into_par_iter().map(scan_file).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn index_scan() -> Result<(), DynError> {
+        let rle = scan_file("tests/sirrobin.rle")?;
+        let macrocell = scan_file("tests/sirrobin.mc")?;
+        let plaintext = scan_file("tests/sirrobin.cells")?;
+        assert_eq!(rle.rule, Some(RuleString::new("B3/S23")?));
+        assert_eq!(macrocell.format, Format::Macrocell);
+        assert_eq!(macrocell.rule, rle.rule);
+        assert_eq!((macrocell.size, macrocell.population), (None, None));
+        assert_eq!(plaintext.name, None);
+        assert_eq!(plaintext.size, rle.size);
+        assert_eq!(plaintext.population, Some(282));
+
+        let path = Path::new("glider");
+        let record = scan_text(path, "#C population = 5\nbo$2bo$3o!", Format::Rle)?;
+        assert_eq!((record.size, record.population), (None, Some(5)));
+        let record = scan_text(path, "xq4_153", Format::Apgcode)?;
+        assert_eq!(record.size, Some((3, 3)));
+        let record = scan_text(path, "b3s23/C1/xq4_153", Format::Apgcode)?;
+        assert_eq!(record.rule, Some(RuleString::new("b3s23")?));
+        let record = scan_text(path, "#Life 1.06\n0 0\n1 0\n2 0", Format::Life106)?;
+        assert_eq!((record.size, record.population), (Some((3, 1)), Some(3)));
+        assert!(scan_text(path, "2z!", Format::Plaintext).is_err());
+        let far = "#Life 1.06\n-9223372036854775808 0\n9223372036854775807 0";
+        assert!(scan_text(path, far, Format::Life106).is_err());
+        let wide = "#Life 1.06\n-9223372036854775808 0\n9223372036854775806 0";
+        let record = scan_text(path, wide, Format::Life106)?;
+        assert_eq!(record.size, Some((u64::MAX, 1)));
+
+        let records = scan(["tests/sirrobin.rle", "tests/missing.rle"]);
+        assert_eq!(records[0].as_ref().ok(), Some(&rle));
+        assert!(records[1].is_err());
+        #[cfg(feature = "rayon")]
+        assert_eq!(
+            par_scan(vec!["tests/sirrobin.mc"])[0].as_ref().ok(),
+            Some(&macrocell)
+        );
+        Ok(())
+    }
+}
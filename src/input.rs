@@ -1,6 +1,16 @@
+//! Types that can be passed to parsers as input.
+
+use memchr::memchr;
 use std::{
-    io::{BufRead, BufReader, Error, Lines as IoLines, Read},
+    collections::VecDeque,
+    error::Error as StdError,
+    fmt::{self, Debug, Display, Formatter},
+    io::{BufRead, BufReader, Error, ErrorKind, Lines as IoLines, Read, Seek},
+    iter::Copied,
+    mem,
+    slice::Iter as SliceIter,
     str::{Bytes, Lines},
+    sync::Arc,
     vec::IntoIter,
 };
 
@@ -10,22 +20,81 @@ use std::{
 /// and `&mut B` for every type `B` that implements [`BufRead`].
 ///
 /// When parsing a file, you can take a [`BufReader<File>`] as input.
+///
+/// It is also implemented for [`String`], [`Vec<u8>`], [`Arc<str>`] and [`Arc<[u8]>`](Arc),
+/// so that the parser owns its input,
+/// and can be returned from a function that reads the input by itself:
+///
+/// ```rust
+/// use ca_formats::rle::Rle;
+///
+/// fn load() -> Rle<String> {
+///     let text = String::from("x = 3, y = 3, rule = B3/S23\nbo$2bo$3o!");
+///     Rle::new(text).unwrap()
+/// }
+///
+/// assert_eq!(load().count(), 5);
+/// ```
+///
+/// Patterns that are already split into lines can be passed as [`vec::IntoIter`](IntoIter)
+/// or [`slice::Iter`](SliceIter) of `String`s or `&str`s. Any other iterator over strings
+/// can be wrapped in an [`IterLines`]. The lines should not contain line breaks.
+///
+/// Lines are read as bytes. Byte inputs, e.g., `&[u8]` and readers, are never validated as UTF-8,
+/// since the bodies of pattern files are pure ASCII. Non-ASCII bytes in the body are reported
+/// as unexpected characters by the parsers, and lines in error messages are decoded lossily.
+///
+/// Files with a byte order mark, classic Mac OS line endings, or trailing whitespaces
+/// can be wrapped in a [`Normalized`].
 pub trait Input {
     /// An iterator over lines of the input.
     type Lines: Iterator;
-    /// A string or a reference to a string, which represents a line of the input.
-    type Line: AsRef<str>;
+    /// A line of the input, as bytes, without the line ending.
+    type Line: AsRef<[u8]>;
     /// An iterator over bytes of a line.
     type Bytes: Iterator<Item = u8>;
 
     /// Creates an iterator over lines from the input.
     fn lines(self) -> Self::Lines;
 
-    /// Converts a item in the lines iterator to a string.
+    /// Converts a item in the lines iterator to a line.
     fn line(item: <Self::Lines as Iterator>::Item) -> Result<Self::Line, Error>;
 
     /// Creates an iterator over bytes from a line.
     fn bytes(line: Self::Line) -> Self::Bytes;
+
+    /// Gives back the iterator over bytes of a line which the parser has finished reading,
+    /// so that its buffer can be reused for the next line.
+    ///
+    /// The default implementation simply drops it.
+    fn recycle(_lines: &mut Self::Lines, _bytes: Self::Bytes) {}
+
+    /// Counts the remaining lines that satisfy a predicate, without consuming them.
+    ///
+    /// Returns `None` if the lines can't be counted cheaply, e.g., when they are read
+    /// from a reader. Inputs that are already in memory, e.g., `&str`, `&[u8]`,
+    /// [`String`] and [`Vec<u8>`], count them by cloning the iterator over lines.
+    ///
+    /// The default implementation returns `None`.
+    fn count_lines<F: FnMut(&[u8]) -> bool>(_lines: &Self::Lines, _predicate: F) -> Option<usize> {
+        None
+    }
+}
+
+/// Count the lines that satisfy a predicate by cloning the iterator over lines,
+/// for the implementations of [`Input::count_lines`].
+fn count_cloned<L, F>(lines: &L, mut predicate: F) -> Option<usize>
+where
+    L: Iterator + Clone,
+    L::Item: AsRef<[u8]>,
+    F: FnMut(&[u8]) -> bool,
+{
+    Some(
+        lines
+            .clone()
+            .filter(|line| predicate(line.as_ref()))
+            .count(),
+    )
 }
 
 impl<'a> Input for &'a str {
@@ -44,6 +113,10 @@ impl<'a> Input for &'a str {
     fn bytes(line: Self::Line) -> Self::Bytes {
         line.bytes()
     }
+
+    fn count_lines<F: FnMut(&[u8]) -> bool>(lines: &Self::Lines, predicate: F) -> Option<usize> {
+        count_cloned(lines, predicate)
+    }
 }
 
 impl<'a> Input for Lines<'a> {
@@ -65,30 +138,34 @@ impl<'a> Input for Lines<'a> {
 }
 
 impl<'a> Input for &'a [u8] {
-    type Lines = IoLines<Self>;
-    type Line = String;
-    type Bytes = IntoIter<u8>;
+    type Lines = ByteLines<'a>;
+    type Line = &'a [u8];
+    type Bytes = Copied<SliceIter<'a, u8>>;
 
     fn lines(self) -> Self::Lines {
-        BufRead::lines(self)
+        ByteLines::new(self)
     }
 
     fn line(item: <Self::Lines as Iterator>::Item) -> Result<Self::Line, Error> {
-        item
+        Ok(item)
     }
 
     fn bytes(line: Self::Line) -> Self::Bytes {
-        line.into_bytes().into_iter()
+        line.iter().copied()
+    }
+
+    fn count_lines<F: FnMut(&[u8]) -> bool>(lines: &Self::Lines, predicate: F) -> Option<usize> {
+        count_cloned(lines, predicate)
     }
 }
 
 impl<R: Read> Input for BufReader<R> {
-    type Lines = IoLines<Self>;
-    type Line = String;
-    type Bytes = IntoIter<u8>;
+    type Lines = ReaderLines<Self>;
+    type Line = Vec<u8>;
+    type Bytes = LineBytes<Vec<u8>>;
 
     fn lines(self) -> Self::Lines {
-        BufRead::lines(self)
+        ReaderLines::new(self)
     }
 
     fn line(item: <Self::Lines as Iterator>::Item) -> Result<Self::Line, Error> {
@@ -96,17 +173,21 @@ impl<R: Read> Input for BufReader<R> {
     }
 
     fn bytes(line: Self::Line) -> Self::Bytes {
-        line.into_bytes().into_iter()
+        LineBytes::new(line)
+    }
+
+    fn recycle(lines: &mut Self::Lines, bytes: Self::Bytes) {
+        lines.recycle(bytes.into_inner());
     }
 }
 
-impl<'a, B: BufRead> Input for &'a mut B {
-    type Lines = IoLines<Self>;
-    type Line = String;
-    type Bytes = IntoIter<u8>;
+impl<B: BufRead> Input for &mut B {
+    type Lines = ReaderLines<Self>;
+    type Line = Vec<u8>;
+    type Bytes = LineBytes<Vec<u8>>;
 
     fn lines(self) -> Self::Lines {
-        BufRead::lines(self)
+        ReaderLines::new(self)
     }
 
     fn line(item: <Self::Lines as Iterator>::Item) -> Result<Self::Line, Error> {
@@ -114,7 +195,11 @@ impl<'a, B: BufRead> Input for &'a mut B {
     }
 
     fn bytes(line: Self::Line) -> Self::Bytes {
-        line.into_bytes().into_iter()
+        LineBytes::new(line)
+    }
+
+    fn recycle(lines: &mut Self::Lines, bytes: Self::Bytes) {
+        lines.recycle(bytes.into_inner());
     }
 }
 
@@ -135,3 +220,733 @@ impl<B: BufRead> Input for IoLines<B> {
         line.into_bytes().into_iter()
     }
 }
+
+/// A line in a shared buffer, returned by [`SharedLines`].
+#[derive(Clone, Debug)]
+pub struct SharedLine {
+    /// The whole buffer.
+    text: Arc<[u8]>,
+    /// Start of the line.
+    start: usize,
+    /// End of the line, excluding the line ending.
+    end: usize,
+}
+
+impl AsRef<[u8]> for SharedLine {
+    fn as_ref(&self) -> &[u8] {
+        &self.text[self.start..self.end]
+    }
+}
+
+/// An iterator over lines of a shared buffer, which owns the buffer.
+///
+/// Lines are split in the same way as [`str::lines`].
+#[derive(Clone, Debug)]
+pub struct SharedLines {
+    /// The whole buffer.
+    text: Arc<[u8]>,
+    /// Start of the next line.
+    position: usize,
+}
+
+impl SharedLines {
+    /// Creates an iterator over lines of a shared buffer.
+    pub fn new(text: Arc<[u8]>) -> Self {
+        SharedLines { text, position: 0 }
+    }
+}
+
+impl Iterator for SharedLines {
+    type Item = SharedLine;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let rest = &self.text[self.position..];
+        if rest.is_empty() {
+            return None;
+        }
+        let start = self.position;
+        let next = memchr(b'\n', rest).map_or(self.text.len(), |i| start + i + 1);
+        let end = start + strip_line_ending(&self.text[start..next]).len();
+        self.position = next;
+        Some(SharedLine {
+            text: self.text.clone(),
+            start,
+            end,
+        })
+    }
+}
+
+/// An iterator over bytes of a line, which owns the line.
+#[derive(Clone, Debug)]
+pub struct LineBytes<L> {
+    /// The line.
+    line: L,
+    /// Index of the next byte.
+    index: usize,
+}
+
+impl<L: AsRef<[u8]>> LineBytes<L> {
+    /// Creates an iterator over bytes of a line.
+    pub fn new(line: L) -> Self {
+        LineBytes { line, index: 0 }
+    }
+
+    /// Unwraps the line.
+    pub fn into_inner(self) -> L {
+        self.line
+    }
+}
+
+impl<L: AsRef<[u8]>> Iterator for LineBytes<L> {
+    type Item = u8;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let byte = *self.line.as_ref().get(self.index)?;
+        self.index += 1;
+        Some(byte)
+    }
+}
+
+impl Input for SharedLines {
+    type Lines = Self;
+    type Line = SharedLine;
+    type Bytes = LineBytes<SharedLine>;
+
+    fn lines(self) -> Self::Lines {
+        self
+    }
+
+    fn line(item: <Self::Lines as Iterator>::Item) -> Result<Self::Line, Error> {
+        Ok(item)
+    }
+
+    fn bytes(line: Self::Line) -> Self::Bytes {
+        LineBytes::new(line)
+    }
+}
+
+impl Input for Arc<[u8]> {
+    type Lines = SharedLines;
+    type Line = SharedLine;
+    type Bytes = LineBytes<SharedLine>;
+
+    fn lines(self) -> Self::Lines {
+        SharedLines::new(self)
+    }
+
+    fn line(item: <Self::Lines as Iterator>::Item) -> Result<Self::Line, Error> {
+        Ok(item)
+    }
+
+    fn bytes(line: Self::Line) -> Self::Bytes {
+        LineBytes::new(line)
+    }
+
+    fn count_lines<F: FnMut(&[u8]) -> bool>(lines: &Self::Lines, predicate: F) -> Option<usize> {
+        count_cloned(lines, predicate)
+    }
+}
+
+impl Input for Arc<str> {
+    type Lines = SharedLines;
+    type Line = SharedLine;
+    type Bytes = LineBytes<SharedLine>;
+
+    fn lines(self) -> Self::Lines {
+        SharedLines::new(Arc::from(self))
+    }
+
+    fn line(item: <Self::Lines as Iterator>::Item) -> Result<Self::Line, Error> {
+        Ok(item)
+    }
+
+    fn bytes(line: Self::Line) -> Self::Bytes {
+        LineBytes::new(line)
+    }
+
+    fn count_lines<F: FnMut(&[u8]) -> bool>(lines: &Self::Lines, predicate: F) -> Option<usize> {
+        count_cloned(lines, predicate)
+    }
+}
+
+impl Input for Vec<u8> {
+    type Lines = SharedLines;
+    type Line = SharedLine;
+    type Bytes = LineBytes<SharedLine>;
+
+    fn lines(self) -> Self::Lines {
+        SharedLines::new(Arc::from(self))
+    }
+
+    fn line(item: <Self::Lines as Iterator>::Item) -> Result<Self::Line, Error> {
+        Ok(item)
+    }
+
+    fn bytes(line: Self::Line) -> Self::Bytes {
+        LineBytes::new(line)
+    }
+
+    fn count_lines<F: FnMut(&[u8]) -> bool>(lines: &Self::Lines, predicate: F) -> Option<usize> {
+        count_cloned(lines, predicate)
+    }
+}
+
+impl Input for String {
+    type Lines = SharedLines;
+    type Line = SharedLine;
+    type Bytes = LineBytes<SharedLine>;
+
+    fn lines(self) -> Self::Lines {
+        SharedLines::new(Arc::from(self.into_bytes()))
+    }
+
+    fn line(item: <Self::Lines as Iterator>::Item) -> Result<Self::Line, Error> {
+        Ok(item)
+    }
+
+    fn bytes(line: Self::Line) -> Self::Bytes {
+        LineBytes::new(line)
+    }
+
+    fn count_lines<F: FnMut(&[u8]) -> bool>(lines: &Self::Lines, predicate: F) -> Option<usize> {
+        count_cloned(lines, predicate)
+    }
+}
+
+impl<'a> Input for IntoIter<&'a str> {
+    type Lines = Self;
+    type Line = &'a str;
+    type Bytes = Bytes<'a>;
+
+    fn lines(self) -> Self::Lines {
+        self
+    }
+
+    fn line(item: <Self::Lines as Iterator>::Item) -> Result<Self::Line, Error> {
+        Ok(item)
+    }
+
+    fn bytes(line: Self::Line) -> Self::Bytes {
+        line.bytes()
+    }
+}
+
+impl Input for IntoIter<String> {
+    type Lines = Self;
+    type Line = String;
+    type Bytes = LineBytes<String>;
+
+    fn lines(self) -> Self::Lines {
+        self
+    }
+
+    fn line(item: <Self::Lines as Iterator>::Item) -> Result<Self::Line, Error> {
+        Ok(item)
+    }
+
+    fn bytes(line: Self::Line) -> Self::Bytes {
+        LineBytes::new(line)
+    }
+}
+
+impl<'a> Input for SliceIter<'a, &'a str> {
+    type Lines = Self;
+    type Line = &'a str;
+    type Bytes = Bytes<'a>;
+
+    fn lines(self) -> Self::Lines {
+        self
+    }
+
+    fn line(item: <Self::Lines as Iterator>::Item) -> Result<Self::Line, Error> {
+        Ok(item)
+    }
+
+    fn bytes(line: Self::Line) -> Self::Bytes {
+        line.bytes()
+    }
+}
+
+impl<'a> Input for SliceIter<'a, String> {
+    type Lines = Self;
+    type Line = &'a String;
+    type Bytes = Bytes<'a>;
+
+    fn lines(self) -> Self::Lines {
+        self
+    }
+
+    fn line(item: <Self::Lines as Iterator>::Item) -> Result<Self::Line, Error> {
+        Ok(item)
+    }
+
+    fn bytes(line: Self::Line) -> Self::Bytes {
+        line.bytes()
+    }
+}
+
+/// A wrapper that turns any iterator over strings (or byte strings) into an [`Input`],
+/// each item being a line.
+///
+/// The lines should not contain line breaks.
+///
+/// # Example
+///
+/// ```rust
+/// use ca_formats::{input::IterLines, rle::Rle};
+///
+/// let lines = ["x = 3, y = 3", "bo$2bo$", "3o!"].into_iter().map(String::from);
+/// let glider = Rle::new(IterLines::new(lines)).unwrap();
+/// assert_eq!(glider.count(), 5);
+/// ```
+#[derive(Clone, Debug)]
+pub struct IterLines<I> {
+    /// The inner iterator.
+    iter: I,
+}
+
+impl<I> IterLines<I>
+where
+    I: Iterator,
+    I::Item: AsRef<[u8]>,
+{
+    /// Wraps an iterator over lines.
+    pub fn new<T: IntoIterator<IntoIter = I>>(iter: T) -> Self {
+        IterLines {
+            iter: iter.into_iter(),
+        }
+    }
+}
+
+impl<I: Iterator> Iterator for IterLines<I> {
+    type Item = I::Item;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.iter.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.iter.size_hint()
+    }
+}
+
+impl<I> Input for IterLines<I>
+where
+    I: Iterator,
+    I::Item: AsRef<[u8]>,
+{
+    type Lines = Self;
+    type Line = I::Item;
+    type Bytes = LineBytes<I::Item>;
+
+    fn lines(self) -> Self::Lines {
+        self
+    }
+
+    fn line(item: <Self::Lines as Iterator>::Item) -> Result<Self::Line, Error> {
+        Ok(item)
+    }
+
+    fn bytes(line: Self::Line) -> Self::Bytes {
+        LineBytes::new(line)
+    }
+}
+
+/// Strip the line ending, i.e., `\n` or `\r\n`, at the end of a line.
+///
+/// A lone `\r` at the end of the last line is kept, as in [`str::lines`].
+fn strip_line_ending(line: &[u8]) -> &[u8] {
+    match line.strip_suffix(b"\n") {
+        Some(line) => line.strip_suffix(b"\r").unwrap_or(line),
+        None => line,
+    }
+}
+
+/// An iterator over lines of a byte slice, without copying.
+///
+/// Lines are split in the same way as [`str::lines`].
+#[derive(Clone, Debug)]
+pub struct ByteLines<'a> {
+    /// The remaining bytes.
+    bytes: &'a [u8],
+}
+
+impl<'a> ByteLines<'a> {
+    /// Creates an iterator over lines of a byte slice.
+    pub const fn new(bytes: &'a [u8]) -> Self {
+        ByteLines { bytes }
+    }
+}
+
+impl<'a> Iterator for ByteLines<'a> {
+    type Item = &'a [u8];
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.bytes.is_empty() {
+            return None;
+        }
+        let (line, rest) = match memchr(b'\n', self.bytes) {
+            Some(i) => self.bytes.split_at(i + 1),
+            None => (self.bytes, &[][..]),
+        };
+        self.bytes = rest;
+        Some(strip_line_ending(line))
+    }
+}
+
+impl<'a> Input for ByteLines<'a> {
+    type Lines = Self;
+    type Line = &'a [u8];
+    type Bytes = Copied<SliceIter<'a, u8>>;
+
+    fn lines(self) -> Self::Lines {
+        self
+    }
+
+    fn line(item: <Self::Lines as Iterator>::Item) -> Result<Self::Line, Error> {
+        Ok(item)
+    }
+
+    fn bytes(line: Self::Line) -> Self::Bytes {
+        line.iter().copied()
+    }
+}
+
+/// An iterator over lines of a reader.
+///
+/// Unlike [`BufRead::lines`], the lines are not validated as UTF-8,
+/// and the buffer of a line can be given back to the iterator with [`Input::recycle`]
+/// and reused for the next line, so that reading a large file does not allocate
+/// for every line.
+#[derive(Debug)]
+pub struct ReaderLines<B> {
+    /// The reader.
+    reader: B,
+    /// A buffer for the next line.
+    buffer: Vec<u8>,
+}
+
+impl<B: BufRead> ReaderLines<B> {
+    /// Creates an iterator over lines of a reader.
+    pub fn new(reader: B) -> Self {
+        ReaderLines {
+            reader,
+            buffer: Vec::new(),
+        }
+    }
+
+    /// Seek the reader to its beginning.
+    pub(crate) fn rewind(&mut self) -> Result<(), Error>
+    where
+        B: Seek,
+    {
+        self.reader.rewind()
+    }
+
+    /// Reuse the buffer of a line for the next line.
+    fn recycle(&mut self, mut buffer: Vec<u8>) {
+        if buffer.capacity() > self.buffer.capacity() {
+            buffer.clear();
+            self.buffer = buffer;
+        }
+    }
+}
+
+impl<B: BufRead> Iterator for ReaderLines<B> {
+    type Item = Result<Vec<u8>, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut buffer = mem::take(&mut self.buffer);
+        match self.reader.read_until(b'\n', &mut buffer) {
+            Ok(0) => {
+                self.buffer = buffer;
+                None
+            }
+            Ok(_) => {
+                let len = strip_line_ending(&buffer).len();
+                buffer.truncate(len);
+                Some(Ok(buffer))
+            }
+            Err(e) => Some(Err(e)),
+        }
+    }
+}
+
+impl<B: BufRead> Input for ReaderLines<B> {
+    type Lines = Self;
+    type Line = Vec<u8>;
+    type Bytes = LineBytes<Vec<u8>>;
+
+    fn lines(self) -> Self::Lines {
+        self
+    }
+
+    fn line(item: <Self::Lines as Iterator>::Item) -> Result<Self::Line, Error> {
+        item
+    }
+
+    fn bytes(line: Self::Line) -> Self::Bytes {
+        LineBytes::new(line)
+    }
+
+    fn recycle(lines: &mut Self::Lines, bytes: Self::Bytes) {
+        lines.recycle(bytes.into_inner());
+    }
+}
+
+/// A wrapper that normalizes the lines of another input, so that all kinds of inputs
+/// behave in the same way on files saved by various editors.
+///
+/// - A UTF-8 byte order mark at the beginning of the input is removed.
+/// - A lone `\r`, i.e., the line ending of classic Mac OS, also ends a line,
+///   in addition to `\n` and `\r\n`.
+/// - Whitespaces at the end of each line are removed.
+///
+/// The lines are copied into owned buffers.
+///
+/// # Example
+///
+/// ```rust
+/// use ca_formats::{input::Normalized, rle::Rle};
+///
+/// let rle = Rle::new(Normalized::new("\u{feff}#N Glider \rx = 3, y = 3\rbo$2bo$3o!")).unwrap();
+/// assert_eq!(rle.comments(), ["#N Glider"]);
+/// assert_eq!(rle.count(), 5);
+/// ```
+#[derive(Clone, Debug)]
+pub struct Normalized<I>(I);
+
+impl<I: Input> Normalized<I> {
+    /// Wraps an input.
+    pub fn new(input: I) -> Self {
+        Normalized(input)
+    }
+
+    /// The wrapped input.
+    pub fn into_inner(self) -> I {
+        self.0
+    }
+}
+
+impl<R: Read> Normalized<BufReader<R>> {
+    /// Wraps something that implements [`Read`] trait, e.g., a [`File`](std::fs::File).
+    pub fn new_from_file(file: R) -> Self {
+        Self::new(BufReader::new(file))
+    }
+}
+
+/// An iterator over lines of a [`Normalized`] input.
+pub struct NormalizedLines<I: Input> {
+    /// Lines of the wrapped input.
+    lines: I::Lines,
+    /// Lines split at a lone `\r` which have not been returned.
+    pending: VecDeque<Vec<u8>>,
+    /// Whether no line has been read.
+    first: bool,
+}
+
+impl<I: Input> Debug for NormalizedLines<I>
+where
+    I::Lines: Debug,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("NormalizedLines")
+            .field("lines", &self.lines)
+            .field("pending", &self.pending)
+            .field("first", &self.first)
+            .finish()
+    }
+}
+
+impl<I: Input> Clone for NormalizedLines<I>
+where
+    I::Lines: Clone,
+{
+    fn clone(&self) -> Self {
+        Self {
+            lines: self.lines.clone(),
+            pending: self.pending.clone(),
+            first: self.first,
+        }
+    }
+}
+
+impl<I: Input> Iterator for NormalizedLines<I> {
+    type Item = Result<Vec<u8>, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(line) = self.pending.pop_front() {
+            return Some(Ok(line));
+        }
+        let line = match I::line(self.lines.next()?) {
+            Ok(line) => line,
+            Err(e) => return Some(Err(e)),
+        };
+        let mut bytes = line.as_ref();
+        if mem::take(&mut self.first) {
+            bytes = bytes.strip_prefix(b"\xef\xbb\xbf").unwrap_or(bytes);
+        }
+        let bytes = bytes.strip_suffix(b"\r").unwrap_or(bytes);
+        self.pending.extend(
+            bytes
+                .split(|&c| c == b'\r')
+                .map(|line| line.trim_ascii_end().to_vec()),
+        );
+        I::recycle(&mut self.lines, I::bytes(line));
+        self.pending.pop_front().map(Ok)
+    }
+}
+
+impl<I: Input> Input for Normalized<I> {
+    type Lines = NormalizedLines<I>;
+    type Line = Vec<u8>;
+    type Bytes = LineBytes<Vec<u8>>;
+
+    fn lines(self) -> Self::Lines {
+        NormalizedLines {
+            lines: self.0.lines(),
+            pending: VecDeque::new(),
+            first: true,
+        }
+    }
+
+    fn line(item: <Self::Lines as Iterator>::Item) -> Result<Self::Line, Error> {
+        item
+    }
+
+    fn bytes(line: Self::Line) -> Self::Bytes {
+        LineBytes::new(line)
+    }
+}
+
+/// An I/O error shared behind an [`Arc`], so that it can be cloned and compared.
+///
+/// The error types of the parsers wrap the I/O errors of their inputs in it,
+/// so that they implement [`Clone`], [`PartialEq`] and [`Eq`].
+/// Two errors are equal if they have the same [`ErrorKind`] and the same message.
+///
+/// # Example
+///
+/// ```rust
+/// use ca_formats::input::SharedIoError;
+/// use std::io::{Error, ErrorKind};
+///
+/// let error = SharedIoError::from(Error::new(ErrorKind::InvalidData, "bad byte"));
+/// assert_eq!(error.clone(), error);
+/// assert_eq!(error.kind(), ErrorKind::InvalidData);
+/// assert_ne!(error, SharedIoError::from(Error::from(ErrorKind::InvalidData)));
+/// ```
+#[derive(Clone, Debug)]
+pub struct SharedIoError(Arc<Error>);
+
+impl SharedIoError {
+    /// The kind of the error.
+    pub fn kind(&self) -> ErrorKind {
+        self.0.kind()
+    }
+
+    /// The wrapped error.
+    pub fn get_ref(&self) -> &Error {
+        &self.0
+    }
+}
+
+impl From<Error> for SharedIoError {
+    fn from(error: Error) -> Self {
+        Self(Arc::new(error))
+    }
+}
+
+impl Display for SharedIoError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        Display::fmt(&self.0, f)
+    }
+}
+
+impl StdError for SharedIoError {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        self.0.source()
+    }
+}
+
+impl PartialEq for SharedIoError {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+            || self.kind() == other.kind() && self.0.to_string() == other.0.to_string()
+    }
+}
+
+impl Eq for SharedIoError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn input_shared_lines() {
+        for text in ["a\r\n\nbc\r\r\nd\n", "a\r\nb\rc\r"] {
+            let expected = text.lines().map(str::as_bytes).collect::<Vec<_>>();
+            let shared = Arc::<str>::from(text)
+                .lines()
+                .map(|line| line.as_ref().to_owned())
+                .collect::<Vec<_>>();
+            assert_eq!(shared, expected);
+            let owned = Input::lines(String::from(text))
+                .map(|line| line.as_ref().to_owned())
+                .collect::<Vec<_>>();
+            assert_eq!(owned, expected);
+        }
+
+        let bytes = LineBytes::new(String::from("b2o")).collect::<Vec<_>>();
+        assert_eq!(bytes, b"b2o");
+    }
+
+    #[test]
+    fn input_byte_lines() {
+        for text in ["a\r\n\nbc\r\r\nd\n", "a\r\nb\rc\r"] {
+            let expected = text.lines().map(str::as_bytes).collect::<Vec<_>>();
+
+            let lines = Input::lines(text.as_bytes())
+                .map(|item| <&[u8]>::line(item).unwrap())
+                .collect::<Vec<_>>();
+            assert_eq!(lines, expected);
+
+            let mut lines = Input::lines(BufReader::new(text.as_bytes()));
+            let mut reader_lines = Vec::new();
+            while let Some(item) = lines.next() {
+                let line = BufReader::<&[u8]>::line(item).unwrap();
+                reader_lines.push(line.clone());
+                BufReader::<&[u8]>::recycle(&mut lines, LineBytes::new(line));
+            }
+            assert_eq!(reader_lines, expected);
+        }
+
+        let lines = Input::lines(&b"\xff\n\xfe"[..]).collect::<Vec<_>>();
+        assert_eq!(lines, vec![b"\xff", b"\xfe"]);
+    }
+
+    #[test]
+    fn input_normalized() {
+        const TEXT: &str = "\u{feff}a \r\n\nbc\rd\t\r\r\u{feff}e\r";
+        let expected: Vec<&[u8]> = vec![b"a", b"", b"bc", b"d", b"", b"\xef\xbb\xbfe"];
+
+        let lines = Input::lines(Normalized::new(TEXT))
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(lines, expected);
+        let lines = Input::lines(Normalized::new(TEXT.as_bytes()))
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(lines, expected);
+        let lines = Input::lines(Normalized::new(BufReader::new(TEXT.as_bytes())))
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(lines, expected);
+        let lines = Input::lines(Normalized::new(BufRead::lines(BufReader::new(
+            TEXT.as_bytes(),
+        ))))
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+        assert_eq!(lines, expected);
+    }
+}
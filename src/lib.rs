@@ -9,6 +9,8 @@ The parsers read a string and return an iterator of coordinates of living cells.
 - [Plaintext](https://www.conwaylife.com/wiki/Plaintext)
 - [apgcode](https://www.conwaylife.com/wiki/Apgcode)
 - [Macrocell](https://www.conwaylife.com/wiki/Macrocell)
+- [Life 1.05](https://www.conwaylife.com/wiki/Life_1.05)
+- [Life 1.06](https://www.conwaylife.com/wiki/Life_1.06)
 
 ## Example
 
@@ -49,34 +51,352 @@ assert_eq!(sirrobin.count(), 282);
 
 - [ca-rules](https://crates.io/crates/ca-rules) - A parser for rule strings.
 - [game-of-life-parsers](https://crates.io/crates/game-of-life-parsers)
-    by René Perschon - Parsers for [Life 1.05](https://www.conwaylife.com/wiki/Life_1.05)
-    and [Life 1.06](https://www.conwaylife.com/wiki/Life_1.06) format.
+  by René Perschon - Parsers for [Life 1.05](https://www.conwaylife.com/wiki/Life_1.05)
+  and [Life 1.06](https://www.conwaylife.com/wiki/Life_1.06) format.
 
 */
 
 #![cfg_attr(docs_rs, feature(doc_cfg))]
 
 pub mod apgcode;
-mod input;
+#[cfg(feature = "ndarray")]
+mod array;
+#[cfg(feature = "bitvec")]
+#[cfg_attr(docs_rs, doc(cfg(feature = "bitvec")))]
+pub mod bits;
+pub mod cancel;
+#[cfg(feature = "blake3")]
+mod content_id;
+pub mod crop;
+pub mod duplicates;
+pub mod evolve;
+#[cfg(feature = "arbitrary")]
+#[cfg_attr(docs_rs, doc(cfg(feature = "arbitrary")))]
+pub mod fuzz;
+pub mod include;
+pub mod index;
+pub mod input;
+pub mod life;
+pub mod lint;
 pub mod macrocell;
+#[cfg(feature = "names")]
+mod names;
+pub mod order;
+mod output;
+pub mod parser;
+pub mod pattern;
 pub mod plaintext;
+pub mod planes;
+#[cfg(feature = "image")]
+#[cfg_attr(docs_rs, doc(cfg(feature = "image")))]
+pub mod render;
 pub mod rle;
+pub mod rulefile;
+#[cfg(feature = "ca-rules")]
+#[cfg_attr(docs_rs, doc(cfg(feature = "ca-rules")))]
+pub mod rules;
+pub mod rulestring;
+mod scan;
+pub mod source;
+pub mod svg;
+#[cfg(feature = "testing")]
+#[cfg_attr(docs_rs, doc(cfg(feature = "testing")))]
+pub mod testing;
+pub mod topology;
+#[cfg(feature = "wasm")]
+#[cfg_attr(docs_rs, doc(cfg(feature = "wasm")))]
+pub mod wasm;
 
 pub use input::Input;
+pub use lint::lint;
+pub use order::reorder;
+pub use pattern::{CommentPolicy, Pattern, PatternBuilder};
+pub use rulestring::{RuleInfo, RuleOrigin, RuleString};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+pub use source::open;
+use std::{fmt::Debug, hash::Hash};
 
 pub type Coordinates = (i64, i64);
 
+/// Convention of the coordinates returned by the parsers.
+///
+/// By default, the coordinates are `(x, y)`, where the x axis points right
+/// and the y axis points down, as in the files. It can be changed with
+/// [`ParseOptions::with_convention`].
+///
+/// # Example
+///
+/// ```rust
+/// use ca_formats::{plaintext::Plaintext, Convention, ParseOptions};
+///
+/// let convention = Convention {
+///     yx: true,
+///     y_up: true,
+/// };
+/// let options = ParseOptions::new().with_convention(convention);
+/// let glider = Plaintext::new_with_options(".O.\n..O\nOOO", options).unwrap();
+/// let cells = glider.map(|cell| cell.unwrap()).collect::<Vec<_>>();
+/// assert_eq!(cells, vec![(0, 1), (-1, 2), (-2, 0), (-2, 1), (-2, 2)]);
+/// ```
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Default, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Convention {
+    /// Whether the coordinates are `(y, x)` instead of `(x, y)`.
+    pub yx: bool,
+    /// Whether the y axis points up instead of down, i.e., whether the y coordinates are negated.
+    pub y_up: bool,
+}
+
+impl Convention {
+    /// Converts coordinates in the default convention into this convention.
+    ///
+    /// Returns `None` if the y axis points up and the y coordinate is `i64::MIN`,
+    /// which can't be negated.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use ca_formats::Convention;
+    ///
+    /// let convention = Convention { yx: true, y_up: true };
+    /// assert_eq!(convention.apply((1, 2)), Some((-2, 1)));
+    /// assert_eq!(convention.apply((1, i64::MIN)), None);
+    /// ```
+    pub const fn apply(self, (x, y): Coordinates) -> Option<Coordinates> {
+        let y = if self.y_up {
+            match y.checked_neg() {
+                Some(y) => y,
+                None => return None,
+            }
+        } else {
+            y
+        };
+        Some(if self.yx { (y, x) } else { (x, y) })
+    }
+}
+
+/// Options shared by the parsers, so that a configuration can be given once
+/// and applied to any format.
+///
+/// Each parser has a `new_with_options` constructor, e.g.,
+/// [`Rle::new_with_options`](rle::Rle::new_with_options), which applies the options
+/// that make sense for its format and ignores the others.
+/// [`source::from_string_with_options`] applies them to a pattern of any format.
+///
+/// The options are independent of each other, so the order of the builder methods
+/// doesn't matter. New options may be added in later versions, so this struct
+/// can't be constructed with a struct expression outside this crate; use
+/// [`new`](ParseOptions::new) or [`default`](Default::default) and the builder methods.
+///
+/// # Example
+///
+/// ```rust
+/// use ca_formats::{plaintext::Plaintext, rle::Rle, Convention, ParseOptions};
+///
+/// let options = ParseOptions::new()
+///     .with_convention(Convention { yx: true, y_up: false })
+///     .include_dead(true)
+///     .lenient(true);
+///
+/// let rle = Rle::new_with_options("x = 2, y = 1\nbo!", options).unwrap();
+/// let cells = rle.map(|cell| cell.unwrap().position).collect::<Vec<_>>();
+/// assert_eq!(cells, vec![(0, 0), (0, 1)]);
+///
+/// let plaintext = Plaintext::new_with_options("-*", options).unwrap();
+/// let cells = plaintext.cells().map(|cell| cell.unwrap().position).collect::<Vec<_>>();
+/// assert_eq!(cells, vec![(0, 0), (0, 1)]);
+/// ```
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Default, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+#[non_exhaustive]
+pub struct ParseOptions {
+    /// Convention of the returned coordinates.
+    pub convention: Convention,
+    /// Whether to also return the dead cells in the bounding box declared by the header line.
+    ///
+    /// For RLE. It has no further effect if unknown cells are allowed,
+    /// because the dead cells are then returned explicitly.
+    ///
+    /// Plaintext files have no header line, so only the dead cells written explicitly
+    /// are returned, by [`Plaintext::cells`](plaintext::Plaintext::cells).
+    pub include_dead: bool,
+    /// Whether to allow unknown cells.
+    ///
+    /// Only for RLE. See [`Rle::new_with_options`](rle::Rle::new_with_options).
+    pub unknown: bool,
+    /// Whether to accept other characters for living and dead cells.
+    ///
+    /// Only for Plaintext. See [`Glyphs::LENIENT`](plaintext::Glyphs::LENIENT).
+    pub lenient: bool,
+    /// Whether to reject inputs that are accepted with a warning or a fix-up.
+    ///
+    /// For RLE, the [warnings](rle::Rle::warnings) become [`rle::Error::Strict`] errors.
+    /// For apgcodes, whitespaces are rejected, as in [`ApgCode::new_strict`](apgcode::ApgCode::new_strict).
+    pub strict: bool,
+    /// The maximum number of cells that a parser returns before it stops with an error.
+    ///
+    /// Only for RLE and Macrocell, where a short input can encode a huge number of cells.
+    /// See [`rle::Error::TooManyCells`] and [`macrocell::Error::TooManyCells`].
+    pub max_cells: Option<u64>,
+}
+
+impl ParseOptions {
+    /// The default options.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the convention of the returned coordinates.
+    pub fn with_convention(mut self, convention: Convention) -> Self {
+        self.convention = convention;
+        self
+    }
+
+    /// Also return the dead cells in the bounding box declared by the header line.
+    pub fn include_dead(mut self, include: bool) -> Self {
+        self.include_dead = include;
+        self
+    }
+
+    /// Allow unknown cells.
+    pub fn unknown(mut self, unknown: bool) -> Self {
+        self.unknown = unknown;
+        self
+    }
+
+    /// Accept other characters for living and dead cells.
+    pub fn lenient(mut self, lenient: bool) -> Self {
+        self.lenient = lenient;
+        self
+    }
+
+    /// Reject inputs that are accepted with a warning or a fix-up.
+    pub fn strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    /// Stop with an error after returning `max_cells` cells.
+    pub fn with_max_cells(mut self, max_cells: u64) -> Self {
+        self.max_cells = Some(max_cells);
+        self
+    }
+}
+
+/// Bounding box of the living cells, as `(min_x, min_y, max_x, max_y)`.
+///
+/// Returned by the `bounding_box` methods of the parsers, e.g.,
+/// [`Rle::bounding_box`](rle::Rle::bounding_box).
+pub type BoundingBox = (i64, i64, i64, i64);
+
+/// Extends a bounding box so that it contains the given cell.
+pub(crate) fn extend_bounding_box(bounding_box: &mut Option<BoundingBox>, (x, y): Coordinates) {
+    *bounding_box = Some(match *bounding_box {
+        Some((min_x, min_y, max_x, max_y)) => {
+            (min_x.min(x), min_y.min(y), max_x.max(x), max_y.max(y))
+        }
+        None => (x, y, x, y),
+    });
+}
+
+/// Types that can be used as the state of a cell.
+///
+/// Implemented for `u8`, `u16` and `u32`.
+pub trait State: Copy + Debug + Default + Ord + Hash + TryFrom<u32> + Into<u32> {}
+
+impl State for u8 {}
+impl State for u16 {}
+impl State for u32 {}
+
 /// Position and state of a cell.
 ///
-/// Rules with more than 256 states are not supported.
+/// The state type defaults to `u8`, which is enough for rules with at most 256 states.
+/// Rules with more states can use a wider type, e.g., `CellData<u16>`.
 #[derive(Copy, Clone, Debug, Ord, PartialOrd, Eq, PartialEq, Default, Hash)]
-pub struct CellData {
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub struct CellData<S = u8> {
     /// Coordinates of the cell.
     pub position: Coordinates,
     /// State of the cell.
     ///
     /// For rules with only 2 states, `0` means dead and `1` means alive.
-    pub state: u8,
+    pub state: S,
+}
+
+impl<S> CellData<S> {
+    /// Converts the state of the cell with a function.
+    pub fn map_state<T, F: FnOnce(S) -> T>(self, f: F) -> CellData<T> {
+        CellData {
+            position: self.position,
+            state: f(self.state),
+        }
+    }
+}
+
+/// Widen the state of a cell from `u8` to `u16`.
+impl From<CellData> for CellData<u16> {
+    fn from(cell: CellData) -> Self {
+        cell.map_state(u16::from)
+    }
+}
+
+/// Widen the state of a cell from `u8` to `u32`.
+impl From<CellData> for CellData<u32> {
+    fn from(cell: CellData) -> Self {
+        cell.map_state(u32::from)
+    }
+}
+
+/// Items returned by the parsers, i.e., [`Coordinates`] and [`CellData`].
+pub trait Positioned {
+    /// Coordinates of the cell.
+    fn position(&self) -> Coordinates;
+}
+
+impl Positioned for Coordinates {
+    fn position(&self) -> Coordinates {
+        *self
+    }
+}
+
+impl<S> Positioned for CellData<S> {
+    fn position(&self) -> Coordinates {
+        self.position
+    }
+}
+
+/// Collects the cells returned by a parser, and sorts them in row-major order,
+/// i.e., by the y coordinate, and then by the x coordinate.
+///
+/// The parsers return the cells in different orders, e.g., RLE and Plaintext are row-major,
+/// while apgcode is column-major in each strip of 5 rows. Sorting them gives the same
+/// order for the same pattern in any format. Cells at the same position keep their order.
+/// Use [`reorder`] to sort them into other orders.
+///
+/// # Example
+///
+/// ```rust
+/// use ca_formats::{apgcode::ApgCode, plaintext::Plaintext, sorted};
+///
+/// let apgcode = sorted(ApgCode::new("xq4_153").unwrap()).unwrap();
+/// let plaintext = sorted(Plaintext::new("OOO\n..O\n.O.").unwrap()).unwrap();
+/// assert_eq!(apgcode, plaintext);
+/// assert_eq!(apgcode, vec![(0, 0), (1, 0), (2, 0), (2, 1), (1, 2)]);
+/// ```
+pub fn sorted<T, E, C>(cells: C) -> Result<Vec<T>, E>
+where
+    T: Positioned,
+    C: IntoIterator<Item = Result<T, E>>,
+{
+    let mut cells = cells.into_iter().collect::<Result<Vec<_>, _>>()?;
+    cells.sort_by_key(|cell| {
+        let (x, y) = cell.position();
+        (y, x)
+    });
+    Ok(cells)
 }
 
 /// Convert the coordinates into a [`CellData`] with state `1`.
@@ -0,0 +1,916 @@
+//! Parsers for [Life 1.05](https://www.conwaylife.com/wiki/Life_1.05)
+//! and [Life 1.06](https://www.conwaylife.com/wiki/Life_1.06) formats.
+
+#[cfg(feature = "ca-rules")]
+use crate::rules::{ParseRuleError, Rule};
+use crate::{
+    extend_bounding_box,
+    include::{IncludeResolver, MAX_DEPTH, MAX_INCLUDES},
+    input::SharedIoError,
+    BoundingBox, Convention, Coordinates, Input, ParseOptions, RuleInfo, RuleOrigin,
+};
+use displaydoc::Display;
+use std::{
+    io::{BufReader, Error as IoError, Read, Write},
+    str::from_utf8,
+    sync::Arc,
+};
+use thiserror::Error;
+
+/// Errors that can be returned when parsing a Life 1.05 or Life 1.06 file.
+#[derive(Clone, Debug, Eq, Error, Display, PartialEq)]
+pub enum Error {
+    /// Unexpected character: {0}.
+    UnexpectedChar(char),
+    /// Invalid line: {0}.
+    InvalidLine(String),
+    /// Error when including {0}: {1}.
+    Include(String, #[source] SharedIoError),
+    /// Includes nested too deeply when including {0}.
+    IncludeDepth(String),
+    /// Too many includes when including {0}.
+    TooManyIncludes(String),
+    /// Coordinates out of range near {0:?}.
+    CoordinateOverflow(Coordinates),
+    /// Error when reading from input: {0}.
+    IoError(#[from] SharedIoError),
+}
+
+impl From<IoError> for Error {
+    fn from(error: IoError) -> Self {
+        Error::IoError(error.into())
+    }
+}
+
+/// Parse two integers separated by whitespace, e.g., `-1 2`.
+fn parse_coordinates(line: &str) -> Option<Coordinates> {
+    let mut split = line.split_ascii_whitespace();
+    let x = split.next()?.parse().ok()?;
+    let y = split.next()?.parse().ok()?;
+    if split.next().is_some() {
+        return None;
+    }
+    Some((x, y))
+}
+
+/// Parse a `#P` line in Life 1.05 format.
+fn parse_block(line: &[u8]) -> Result<Coordinates, Error> {
+    from_utf8(&line[2..])
+        .ok()
+        .and_then(parse_coordinates)
+        .ok_or_else(|| Error::InvalidLine(String::from_utf8_lossy(line).into_owned()))
+}
+
+/// Parse an XLife-style `#I name x y` line in Life 1.05 format.
+///
+/// The coordinates default to `0 0`. XLife also allows a rotation, a flip and a delay
+/// after the coordinates; only their default values `0 1 0` are accepted.
+fn parse_include(line: &[u8]) -> Result<(String, Coordinates), Error> {
+    let invalid = || Error::InvalidLine(String::from_utf8_lossy(line).into_owned());
+    let rest = from_utf8(&line[2..]).map_err(|_| invalid())?;
+    let mut split = rest.split_ascii_whitespace();
+    let name = split.next().ok_or_else(invalid)?.to_string();
+    let mut numbers = split.map(str::parse::<i64>);
+    let x = numbers.next().transpose().map_err(|_| invalid())?;
+    let y = numbers.next().transpose().map_err(|_| invalid())?;
+    let position = match (x, y) {
+        (Some(x), Some(y)) => (x, y),
+        (None, None) => (0, 0),
+        _ => return Err(invalid()),
+    };
+    for default in [0, 1, 0] {
+        match numbers.next() {
+            Some(Ok(n)) if n == default => (),
+            Some(_) => return Err(invalid()),
+            None => break,
+        }
+    }
+    if numbers.next().is_some() {
+        return Err(invalid());
+    }
+    Ok((name, position))
+}
+
+/// A parser for [Life 1.05](https://www.conwaylife.com/wiki/Life_1.05) format.
+///
+/// As an iterator, it iterates over the living cells.
+/// The cells are returned block by block, in the order of the `#P` lines,
+/// and in row-major order in each block.
+///
+/// Each `#P` line starts a new block of cells, whose upper left corner is given by the line.
+/// Cells before the first `#P` line are placed at `(0, 0)`.
+///
+/// XLife-style `#I` lines, which include other patterns, are skipped by the iterator.
+/// Use [`resolve_includes`](Life105::resolve_includes) to inline them.
+///
+/// # Example
+///
+/// ```rust
+/// use ca_formats::life::Life105;
+///
+/// const GLIDER: &str = r"#Life 1.05
+/// #D Glider
+/// #N
+/// #P -1 -1
+/// .*.
+/// ..*
+/// ***";
+///
+/// let glider = Life105::new(GLIDER).unwrap();
+/// assert_eq!(glider.rule(), None);
+///
+/// let cells = glider.map(|cell| cell.unwrap()).collect::<Vec<_>>();
+/// assert_eq!(cells, vec![(0, -1), (1, 0), (-1, 1), (0, 1), (1, 1)]);
+/// ```
+#[must_use]
+#[derive(Debug)]
+pub struct Life105<I: Input> {
+    /// Comment lines before the cells, e.g., `#D Glider`.
+    comments: Vec<String>,
+
+    /// Rulestring in the `#R` line.
+    rule: Option<String>,
+
+    /// An iterator over lines of a Life 1.05 file.
+    lines: I::Lines,
+
+    /// An iterator over bytes of the current line.
+    current_line: Option<I::Bytes>,
+
+    /// Coordinates of the current cell.
+    ///
+    /// The x coordinate is `None` if it is out of range, i.e., after a cell at `i64::MAX`.
+    position: (Option<i64>, i64),
+
+    /// X coordinates of the upper left corner of the current block.
+    x_start: i64,
+
+    /// Y coordinate of the next line of cells, or `None` if it is out of range.
+    next_y: Option<i64>,
+
+    /// Names and positions in the `#I` lines read so far.
+    includes: Vec<(String, Coordinates)>,
+
+    /// Convention of the returned coordinates.
+    convention: Convention,
+}
+
+impl<I: Input> Life105<I> {
+    /// Creates a new parser instance from input, and try to read the `#R` line.
+    pub fn new(input: I) -> Result<Self, Error> {
+        let mut lines = input.lines();
+        let mut comments = Vec::new();
+        let mut rule = None;
+        let mut current_line = None;
+        let mut position = (Some(0), 0);
+        let mut x_start = 0;
+        let mut next_y = Some(0);
+        let mut includes = Vec::new();
+        for item in &mut lines {
+            let line = I::line(item)?;
+            if line.as_ref().starts_with(b"#R") {
+                let rule_string = from_utf8(&line.as_ref()[2..]).map_err(|_| {
+                    Error::InvalidLine(String::from_utf8_lossy(line.as_ref()).into_owned())
+                })?;
+                rule = Some(rule_string.trim().to_string());
+            } else if line.as_ref().starts_with(b"#P") {
+                let (x, y) = parse_block(line.as_ref())?;
+                x_start = x;
+                next_y = Some(y);
+            } else if line.as_ref().starts_with(b"#I") {
+                includes.push(parse_include(line.as_ref())?);
+            } else if line.as_ref().starts_with(b"#") {
+                if !line.as_ref().starts_with(b"#Life") && !line.as_ref().starts_with(b"#N") {
+                    comments.push(String::from_utf8_lossy(line.as_ref()).into_owned());
+                }
+            } else {
+                let y = next_y.ok_or(Error::CoordinateOverflow((x_start, i64::MAX)))?;
+                position = (Some(x_start), y);
+                next_y = y.checked_add(1);
+                current_line = Some(I::bytes(line));
+                break;
+            }
+        }
+        Ok(Self {
+            comments,
+            rule,
+            lines,
+            current_line,
+            position,
+            x_start,
+            next_y,
+            includes,
+            convention: Convention::default(),
+        })
+    }
+
+    /// Creates a new parser instance from input with the [`ParseOptions`]
+    /// that make sense for this format, i.e., the convention.
+    pub fn new_with_options(input: I, options: ParseOptions) -> Result<Self, Error> {
+        let mut life = Self::new(input)?;
+        life.convention = options.convention;
+        Ok(life)
+    }
+
+    /// Comment lines before the cells, e.g., `#D Glider`,
+    /// except the `#Life`, `#R`, `#N`, `#P` and `#I` lines.
+    pub fn comments(&self) -> &[String] {
+        &self.comments
+    }
+
+    /// The rulestring in the `#R` line, e.g., `23/3`.
+    ///
+    /// `None` if the rule is not specified, or specified by a `#N` line,
+    /// i.e., Conway's Game of Life.
+    pub fn rule(&self) -> Option<&str> {
+        self.rule.as_deref()
+    }
+
+    /// The rule in the `#R` line. See [`RuleInfo`].
+    pub fn rule_info(&self) -> Option<RuleInfo> {
+        RuleInfo::from_parser(self.rule(), RuleOrigin::RLine)
+    }
+
+    /// The rulestring in the `#R` line parsed by [`ca-rules`](https://crates.io/crates/ca-rules).
+    ///
+    /// `None` if [`rule`](Life105::rule) is `None`.
+    #[cfg(feature = "ca-rules")]
+    #[cfg_attr(docs_rs, doc(cfg(feature = "ca-rules")))]
+    pub fn parsed_rule(&self) -> Option<Result<Rule, ParseRuleError>> {
+        self.rule.as_deref().map(Rule::parse)
+    }
+
+    /// Number of states of the rule.
+    ///
+    /// `None` if the rule is not specified, or can't be parsed.
+    #[cfg(feature = "ca-rules")]
+    #[cfg_attr(docs_rs, doc(cfg(feature = "ca-rules")))]
+    pub fn state_count(&self) -> Option<usize> {
+        self.parsed_rule()?.ok().map(|rule| rule.state_count())
+    }
+
+    /// Move to the next line, reading `#P` and `#I` lines and skipping other comment lines.
+    ///
+    /// Returns `None` at the end of the input.
+    fn next_line(&mut self) -> Option<Result<(), Error>> {
+        if let Some(bytes) = self.current_line.take() {
+            I::recycle(&mut self.lines, bytes);
+        }
+        let item = self.lines.next()?;
+        let line = match I::line(item) {
+            Ok(line) => line,
+            Err(e) => return Some(Err(Error::from(e))),
+        };
+        if line.as_ref().starts_with(b"#P") {
+            match parse_block(line.as_ref()) {
+                Ok((x, y)) => {
+                    self.x_start = x;
+                    self.next_y = Some(y);
+                }
+                Err(e) => return Some(Err(e)),
+            }
+            I::recycle(&mut self.lines, I::bytes(line));
+        } else if line.as_ref().starts_with(b"#I") {
+            match parse_include(line.as_ref()) {
+                Ok(include) => self.includes.push(include),
+                Err(e) => return Some(Err(e)),
+            }
+            I::recycle(&mut self.lines, I::bytes(line));
+        } else if line.as_ref().starts_with(b"#") {
+            I::recycle(&mut self.lines, I::bytes(line));
+        } else {
+            let Some(y) = self.next_y else {
+                return Some(Err(Error::CoordinateOverflow((self.x_start, i64::MAX))));
+            };
+            self.position = (Some(self.x_start), y);
+            self.next_y = y.checked_add(1);
+            self.current_line = Some(I::bytes(line));
+        }
+        Some(Ok(()))
+    }
+
+    /// Compute the bounding box of the remaining living cells line by line,
+    /// without computing the coordinates of each cell.
+    ///
+    /// The convention is applied. Returns `Ok(None)` if there is no living cell.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use ca_formats::life::Life105;
+    ///
+    /// let glider = Life105::new("#Life 1.05\n#P -1 -1\n.*.\n..*\n***").unwrap();
+    /// assert_eq!(glider.bounding_box().unwrap(), Some((-1, -1, 1, 1)));
+    /// ```
+    pub fn bounding_box(mut self) -> Result<Option<BoundingBox>, Error> {
+        let mut bounding_box = None;
+        loop {
+            if let Some(bytes) = self.current_line.as_mut() {
+                let (mut next_x, y) = self.position;
+                let mut row = None;
+                for c in bytes {
+                    match c {
+                        b'*' | b'.' => {
+                            let x = next_x.ok_or(Error::CoordinateOverflow((i64::MAX, y)))?;
+                            if c == b'*' {
+                                row = Some((row.map_or(x, |(start, _)| start), x));
+                            }
+                            next_x = x.checked_add(1);
+                        }
+                        _ if c.is_ascii_whitespace() => (),
+                        _ => return Err(Error::UnexpectedChar(char::from(c))),
+                    }
+                }
+                if let Some((start, end)) = row {
+                    for cell in [(start, y), (end, y)] {
+                        let position = self
+                            .convention
+                            .apply(cell)
+                            .ok_or(Error::CoordinateOverflow(cell))?;
+                        extend_bounding_box(&mut bounding_box, position);
+                    }
+                }
+            }
+            match self.next_line() {
+                Some(result) => result?,
+                None => return Ok(bounding_box),
+            }
+        }
+    }
+
+    /// Collect the remaining living cells, and inline the patterns included by `#I` lines.
+    ///
+    /// Each `#I name x y` line is resolved by `resolver`, parsed as a Life 1.05 file,
+    /// with its own includes inlined, and translated by `(x, y)`.
+    /// The cells of the included patterns follow the cells of this pattern,
+    /// in the order of the `#I` lines. Includes nested more than [`MAX_DEPTH`]
+    /// levels deep, e.g., an include cycle, are an [`Error::IncludeDepth`],
+    /// and more than [`MAX_INCLUDES`] includes in total are an [`Error::TooManyIncludes`].
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use ca_formats::life::Life105;
+    /// use std::io::{Error, ErrorKind};
+    ///
+    /// let mut resolver = |name: &str| match name {
+    ///     "block.life" => Ok(String::from("#Life 1.05\n**\n**")),
+    ///     _ => Err(Error::from(ErrorKind::NotFound)),
+    /// };
+    /// let life = Life105::new("#Life 1.05\n#I block.life 10 0\n*").unwrap();
+    /// let cells = life.resolve_includes(&mut resolver).unwrap();
+    /// assert_eq!(cells, vec![(0, 0), (10, 0), (11, 0), (10, 1), (11, 1)]);
+    /// ```
+    pub fn resolve_includes<R: IncludeResolver>(
+        self,
+        resolver: &mut R,
+    ) -> Result<Vec<Coordinates>, Error> {
+        let convention = self.convention;
+        let mut cells = self.inline_includes(resolver, 0, &mut 0)?;
+        for cell in &mut cells {
+            *cell = convention
+                .apply(*cell)
+                .ok_or(Error::CoordinateOverflow(*cell))?;
+        }
+        Ok(cells)
+    }
+
+    /// Collect the cells with the default convention, inlining the includes
+    /// of a pattern included at the given depth.
+    ///
+    /// `count` is the number of includes resolved so far for the outermost pattern.
+    fn inline_includes<R: IncludeResolver>(
+        mut self,
+        resolver: &mut R,
+        depth: usize,
+        count: &mut usize,
+    ) -> Result<Vec<Coordinates>, Error> {
+        self.convention = Convention::default();
+        let mut cells = self.by_ref().collect::<Result<Vec<_>, _>>()?;
+        for (name, (dx, dy)) in std::mem::take(&mut self.includes) {
+            if depth >= MAX_DEPTH {
+                return Err(Error::IncludeDepth(name));
+            } else if *count >= MAX_INCLUDES {
+                return Err(Error::TooManyIncludes(name));
+            }
+            *count += 1;
+            let content = resolver
+                .resolve(&name)
+                .map_err(|e| Error::Include(name, e.into()))?;
+            let included =
+                Life105::new(content.as_str())?.inline_includes(resolver, depth + 1, count)?;
+            for (x, y) in included {
+                let cell = x
+                    .checked_add(dx)
+                    .zip(y.checked_add(dy))
+                    .ok_or(Error::CoordinateOverflow((x, y)))?;
+                cells.push(cell);
+            }
+        }
+        Ok(cells)
+    }
+}
+
+impl<R: Read> Life105<BufReader<R>> {
+    /// Creates a new parser instance from something that implements [`Read`] trait, e.g., a [`File`](std::fs::File).
+    pub fn new_from_file(file: R) -> Result<Self, Error> {
+        Self::new(BufReader::new(file))
+    }
+}
+
+impl<I: Input> Clone for Life105<I>
+where
+    I::Lines: Clone,
+    I::Bytes: Clone,
+{
+    fn clone(&self) -> Self {
+        Self {
+            comments: self.comments.clone(),
+            rule: self.rule.clone(),
+            lines: self.lines.clone(),
+            current_line: self.current_line.clone(),
+            position: self.position,
+            x_start: self.x_start,
+            next_y: self.next_y,
+            includes: self.includes.clone(),
+            convention: self.convention,
+        }
+    }
+}
+
+/// An iterator over living cells in a Life 1.05 file.
+impl<I: Input> Iterator for Life105<I> {
+    type Item = Result<Coordinates, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(c) = self.current_line.as_mut().and_then(Iterator::next) {
+                match c {
+                    b'*' | b'.' => {
+                        let (x, y) = self.position;
+                        let Some(x) = x else {
+                            return Some(Err(Error::CoordinateOverflow((i64::MAX, y))));
+                        };
+                        self.position.0 = x.checked_add(1);
+                        if c == b'*' {
+                            return Some(
+                                self.convention
+                                    .apply((x, y))
+                                    .ok_or(Error::CoordinateOverflow((x, y))),
+                            );
+                        }
+                    }
+                    _ if c.is_ascii_whitespace() => continue,
+                    _ => return Some(Err(Error::UnexpectedChar(char::from(c)))),
+                }
+            } else if let Err(e) = self.next_line()? {
+                return Some(Err(e));
+            }
+        }
+    }
+}
+
+/// Whether a line of a Life 1.06 file contains a cell, i.e., is not a comment or blank.
+fn is_cell_line(line: &[u8]) -> bool {
+    !line.starts_with(b"#") && !line.iter().all(u8::is_ascii_whitespace)
+}
+
+/// A parser for [Life 1.06](https://www.conwaylife.com/wiki/Life_1.06) format.
+///
+/// As an iterator, it iterates over the living cells.
+/// The cells are returned in the order of the lines, which is not necessarily sorted.
+/// For inputs in memory, e.g., `&str`, `&[u8]`, [`String`] and [`Vec<u8>`],
+/// it is an [`ExactSizeIterator`], so that the cells can be collected without reallocation.
+///
+/// # Example
+///
+/// ```rust
+/// use ca_formats::life::Life106;
+///
+/// const GLIDER: &str = r"#Life 1.06
+/// 0 -1
+/// 1 0
+/// -1 1
+/// 0 1
+/// 1 1";
+///
+/// let glider = Life106::new(GLIDER);
+///
+/// let cells = glider.map(|cell| cell.unwrap()).collect::<Vec<_>>();
+/// assert_eq!(cells, vec![(0, -1), (1, 0), (-1, 1), (0, 1), (1, 1)]);
+/// ```
+#[must_use]
+#[derive(Debug)]
+pub struct Life106<I: Input> {
+    /// An iterator over lines of a Life 1.06 file.
+    lines: I::Lines,
+
+    /// Number of the remaining lines that are not comments or blank,
+    /// if the input can count them.
+    remaining: Option<usize>,
+
+    /// Convention of the returned coordinates.
+    convention: Convention,
+}
+
+impl<I: Input> Life106<I> {
+    /// Creates a new parser instance from input.
+    ///
+    /// If the input is in memory, e.g., `&str` or `&[u8]`, the lines are counted here,
+    /// so that the parser is an [`ExactSizeIterator`].
+    pub fn new(input: I) -> Self {
+        let lines = input.lines();
+        let remaining = I::count_lines(&lines, is_cell_line);
+        Self {
+            lines,
+            remaining,
+            convention: Convention::default(),
+        }
+    }
+
+    /// Creates a new parser instance from input with the [`ParseOptions`]
+    /// that make sense for this format, i.e., the convention.
+    pub fn new_with_options(input: I, options: ParseOptions) -> Self {
+        Self {
+            convention: options.convention,
+            ..Self::new(input)
+        }
+    }
+
+    /// Compute the bounding box of the remaining living cells.
+    ///
+    /// Since Life 1.06 files list the cells one by one, this reads every line,
+    /// but does not allocate.
+    ///
+    /// The convention is applied. Returns `Ok(None)` if there is no living cell.
+    pub fn bounding_box(self) -> Result<Option<BoundingBox>, Error> {
+        let mut bounding_box = None;
+        for cell in self {
+            extend_bounding_box(&mut bounding_box, cell?);
+        }
+        Ok(bounding_box)
+    }
+}
+
+impl<R: Read> Life106<BufReader<R>> {
+    /// Creates a new parser instance from something that implements [`Read`] trait, e.g., a [`File`](std::fs::File).
+    pub fn new_from_file(file: R) -> Self {
+        Self::new(BufReader::new(file))
+    }
+}
+
+impl<I: Input> Clone for Life106<I>
+where
+    I::Lines: Clone,
+{
+    fn clone(&self) -> Self {
+        Self {
+            lines: self.lines.clone(),
+            remaining: self.remaining,
+            convention: self.convention,
+        }
+    }
+}
+
+/// An iterator over living cells in a Life 1.06 file.
+impl<I: Input> Iterator for Life106<I> {
+    type Item = Result<Coordinates, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let item = self.lines.next()?;
+            let line = match I::line(item) {
+                Ok(line) => line,
+                Err(e) => return Some(Err(Error::from(e))),
+            };
+            let bytes = line.as_ref();
+            if !is_cell_line(bytes) {
+                I::recycle(&mut self.lines, I::bytes(line));
+                continue;
+            }
+            if let Some(remaining) = &mut self.remaining {
+                *remaining -= 1;
+            }
+            let cell = from_utf8(bytes).ok().and_then(parse_coordinates);
+            return Some(match cell {
+                Some(cell) => {
+                    I::recycle(&mut self.lines, I::bytes(line));
+                    self.convention
+                        .apply(cell)
+                        .ok_or(Error::CoordinateOverflow(cell))
+                }
+                None => Err(Error::InvalidLine(
+                    String::from_utf8_lossy(bytes).into_owned(),
+                )),
+            });
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match self.remaining {
+            Some(remaining) => (remaining, Some(remaining)),
+            None => (0, None),
+        }
+    }
+}
+
+// Each line that is not a comment or blank is either a cell or an error,
+// so the number of the remaining items is known for inputs in memory.
+impl ExactSizeIterator for Life106<&str> {}
+impl ExactSizeIterator for Life106<&[u8]> {}
+impl ExactSizeIterator for Life106<String> {}
+impl ExactSizeIterator for Life106<Vec<u8>> {}
+impl ExactSizeIterator for Life106<Arc<str>> {}
+impl ExactSizeIterator for Life106<Arc<[u8]>> {}
+
+/// Writes living cells in Life 1.05 format.
+///
+/// The cells can be given in any order, and duplicated cells are ignored.
+/// All cells are written in a single `#P` block, whose upper left corner is
+/// the upper left corner of the bounding box.
+///
+/// If the rule is `None`, a `#N` line is written.
+///
+/// # Example
+///
+/// ```rust
+/// use ca_formats::life::write_life105;
+///
+/// let mut life = Vec::new();
+/// write_life105(&mut life, None, [(0, -1), (1, 0), (-1, 1), (0, 1), (1, 1)]).unwrap();
+/// assert_eq!(life, b"#Life 1.05\n#N\n#P -1 -1\n.*\n..*\n***\n");
+/// ```
+pub fn write_life105<W, C>(writer: W, rule: Option<&str>, cells: C) -> Result<(), IoError>
+where
+    W: Write,
+    C: IntoIterator<Item = Coordinates>,
+{
+    write_life105_with_comments(writer, &[], rule, cells)
+}
+
+/// Writes living cells in Life 1.05 format, with comment lines after the first line.
+///
+/// The comment lines should start with `#D`.
+pub(crate) fn write_life105_with_comments<W, C>(
+    mut writer: W,
+    comments: &[String],
+    rule: Option<&str>,
+    cells: C,
+) -> Result<(), IoError>
+where
+    W: Write,
+    C: IntoIterator<Item = Coordinates>,
+{
+    let mut cells = cells.into_iter().collect::<Vec<_>>();
+    cells.sort_by_key(|&(x, y)| (y, x));
+    cells.dedup();
+    writeln!(writer, "#Life 1.05")?;
+    for comment in comments {
+        writeln!(writer, "{}", comment)?;
+    }
+    match rule {
+        Some(rule) => writeln!(writer, "#R {}", rule)?,
+        None => writeln!(writer, "#N")?,
+    }
+    let x_min = cells.iter().map(|&(x, _)| x).min().unwrap_or_default();
+    let mut y = cells.first().map_or(0, |&(_, y)| y);
+    writeln!(writer, "#P {} {}", x_min, y)?;
+    let mut line = Vec::new();
+    for (cell_x, cell_y) in cells {
+        while y < cell_y {
+            if line.is_empty() {
+                line.push(b'.');
+            }
+            line.push(b'\n');
+            writer.write_all(&line)?;
+            line.clear();
+            y += 1;
+        }
+        line.resize((cell_x - x_min) as usize, b'.');
+        line.push(b'*');
+    }
+    if !line.is_empty() {
+        line.push(b'\n');
+        writer.write_all(&line)?;
+    }
+    Ok(())
+}
+
+/// Writes living cells in Life 1.06 format.
+///
+/// The cells are written in row-major order, and duplicated cells are ignored.
+///
+/// # Example
+///
+/// ```rust
+/// use ca_formats::life::write_life106;
+///
+/// let mut life = Vec::new();
+/// write_life106(&mut life, [(1, 0), (0, -1)]).unwrap();
+/// assert_eq!(life, b"#Life 1.06\n0 -1\n1 0\n");
+/// ```
+pub fn write_life106<W, C>(mut writer: W, cells: C) -> Result<(), IoError>
+where
+    W: Write,
+    C: IntoIterator<Item = Coordinates>,
+{
+    let mut cells = cells.into_iter().collect::<Vec<_>>();
+    cells.sort_by_key(|&(x, y)| (y, x));
+    cells.dedup();
+    writeln!(writer, "#Life 1.06")?;
+    for (x, y) in cells {
+        writeln!(writer, "{} {}", x, y)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn life105_blocks() -> Result<(), Error> {
+        const LIFE: &str =
+            "#Life 1.05\n#D Two blocks\n#R 23/36\n#P -3 -1\n**\n**\n\n#P 2 5\n.**\r\n.**";
+
+        let life = Life105::new(LIFE)?;
+        assert_eq!(life.rule(), Some("23/36"));
+        assert_eq!(life.comments(), ["#D Two blocks"]);
+
+        let _ = life.clone();
+
+        let cells = life.collect::<Result<Vec<_>, _>>()?;
+        assert_eq!(
+            cells,
+            vec![
+                (-3, -1),
+                (-2, -1),
+                (-3, 0),
+                (-2, 0),
+                (3, 5),
+                (4, 5),
+                (3, 6),
+                (4, 6)
+            ]
+        );
+        assert_eq!(Life105::new(LIFE)?.bounding_box()?, Some((-3, -1, 4, 6)));
+
+        let mut life = Life105::new("#P 0 0\n*o")?;
+        assert!(matches!(life.nth(1), Some(Err(Error::UnexpectedChar('o')))));
+        assert!(matches!(
+            Life105::new("#P 0 x\n*"),
+            Err(Error::InvalidLine(_))
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn life105_overflow() -> Result<(), Error> {
+        let life = Life105::new("#Life 1.05\n#P 9223372036854775807 -9223372036854775808\n*")?;
+        assert_eq!(
+            life.collect::<Result<Vec<_>, _>>()?,
+            vec![(i64::MAX, i64::MIN)]
+        );
+
+        let mut life = Life105::new("#Life 1.05\n#P 9223372036854775807 0\n.*")?;
+        assert!(matches!(
+            life.clone().bounding_box(),
+            Err(Error::CoordinateOverflow((i64::MAX, 0)))
+        ));
+        assert!(matches!(
+            life.next(),
+            Some(Err(Error::CoordinateOverflow((i64::MAX, 0))))
+        ));
+
+        let mut life = Life105::new("#Life 1.05\n#P 0 9223372036854775807\n*\n*")?;
+        assert_eq!(life.next(), Some(Ok((0, i64::MAX))));
+        assert!(matches!(
+            life.next(),
+            Some(Err(Error::CoordinateOverflow((0, i64::MAX))))
+        ));
+
+        let options = ParseOptions::new().with_convention(Convention {
+            yx: false,
+            y_up: true,
+        });
+        let life = Life105::new_with_options("#Life 1.05\n#P 0 -9223372036854775808\n*", options)?;
+        assert!(matches!(
+            life.bounding_box(),
+            Err(Error::CoordinateOverflow((0, i64::MIN)))
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn life105_includes() -> Result<(), Error> {
+        let mut resolver = |name: &str| match name {
+            "blinker" => Ok(String::from("#Life 1.05\n#I dot 0 2\n***")),
+            "dot" => Ok(String::from("*")),
+            "loop" => Ok(String::from("#I loop")),
+            _ => Err(IoError::from(std::io::ErrorKind::NotFound)),
+        };
+
+        let life = Life105::new("#Life 1.05\n#D Include\n#P 0 0\n*\n#I blinker -1 5 0 1 0")?;
+        assert_eq!(life.comments(), ["#D Include"]);
+        let cells = life.resolve_includes(&mut resolver)?;
+        assert_eq!(cells, vec![(0, 0), (-1, 5), (0, 5), (1, 5), (-1, 7)]);
+
+        let options = ParseOptions::new().with_convention(Convention {
+            yx: true,
+            y_up: false,
+        });
+        let life = Life105::new_with_options("#I blinker 1 0\n.*", options)?;
+        let cells = life.resolve_includes(&mut resolver)?;
+        assert_eq!(cells, vec![(0, 1), (0, 1), (0, 2), (0, 3), (2, 1)]);
+
+        let life = Life105::new("#I loop")?;
+        assert!(matches!(
+            life.resolve_includes(&mut resolver),
+            Err(Error::IncludeDepth(_))
+        ));
+        let mut resolver = |name: &str| match name.parse::<usize>() {
+            Ok(depth) if depth < MAX_DEPTH => Ok(format!("#I {0}\n#I {0}", depth + 1)),
+            Ok(_) => Ok(String::from("*")),
+            Err(_) if name == "far" => Ok(String::from("#P 9223372036854775807 0\n*")),
+            Err(_) => Err(IoError::from(std::io::ErrorKind::NotFound)),
+        };
+        let life = Life105::new("#I 1")?;
+        assert!(matches!(
+            life.resolve_includes(&mut resolver),
+            Err(Error::TooManyIncludes(_))
+        ));
+        let life = Life105::new("#I far 1 0")?;
+        assert!(matches!(
+            life.resolve_includes(&mut resolver),
+            Err(Error::CoordinateOverflow((i64::MAX, 0)))
+        ));
+        let life = Life105::new("#I missing")?;
+        assert!(matches!(
+            life.resolve_includes(&mut resolver),
+            Err(Error::Include(name, _)) if name == "missing"
+        ));
+        for line in ["#I", "#I a 1", "#I a 1 2 1", "#I a 1 2 0 1 0 0"] {
+            assert!(matches!(Life105::new(line), Err(Error::InvalidLine(_))));
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn life106_glider() -> Result<(), Error> {
+        const GLIDER: &str = "#Life 1.06\n0 -1\n1 0\r\n\n-1 1\n0  1\n1\t1\n";
+
+        let glider = Life106::new(GLIDER);
+
+        let _ = glider.clone();
+
+        let cells = glider.collect::<Result<Vec<_>, _>>()?;
+        assert_eq!(cells, vec![(0, -1), (1, 0), (-1, 1), (0, 1), (1, 1)]);
+        assert_eq!(Life106::new(GLIDER).bounding_box()?, Some((-1, -1, 1, 1)));
+
+        let mut glider = Life106::new(GLIDER);
+        assert_eq!(glider.len(), 5);
+        glider.next();
+        assert_eq!(glider.len(), 4);
+        assert_eq!(Life106::new(GLIDER.as_bytes()).len(), 5);
+        assert_eq!(Life106::new(GLIDER.to_string()).len(), 5);
+        assert_eq!(
+            Life106::new(BufReader::new(GLIDER.as_bytes())).size_hint(),
+            (0, None)
+        );
+
+        let mut life = Life106::new("1 2 3");
+        assert!(matches!(life.next(), Some(Err(Error::InvalidLine(_)))));
+        Ok(())
+    }
+
+    #[test]
+    fn life_convention_overflow() -> Result<(), Error> {
+        let options = ParseOptions::new().with_convention(Convention {
+            yx: false,
+            y_up: true,
+        });
+        let mut life = Life106::new_with_options("#Life 1.06\n0 -9223372036854775808\n", options);
+        assert!(matches!(
+            life.next(),
+            Some(Err(Error::CoordinateOverflow((0, i64::MIN))))
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn life_write() -> Result<(), Error> {
+        let cells = vec![(5, 3), (-2, 7), (0, 0), (5, 3)];
+
+        let mut life = Vec::new();
+        write_life105(&mut life, Some("23/3"), cells.iter().copied())?;
+        let mut parsed = Life105::new(life.as_slice())?.collect::<Result<Vec<_>, _>>()?;
+        parsed.sort_by_key(|&(x, y)| (y, x));
+        assert_eq!(parsed, vec![(0, 0), (5, 3), (-2, 7)]);
+
+        let mut life = Vec::new();
+        write_life106(&mut life, cells)?;
+        let parsed = Life106::new(life.as_slice()).collect::<Result<Vec<_>, _>>()?;
+        assert_eq!(parsed, vec![(0, 0), (5, 3), (-2, 7)]);
+        Ok(())
+    }
+}
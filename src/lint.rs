@@ -0,0 +1,212 @@
+//! Structural checks of RLE files, e.g., for checking pattern submissions.
+//!
+//! [`lint`] reads the whole input, and returns a [`LintReport`] listing every
+//! [`Issue`] it finds, instead of stopping at the first error.
+
+#[cfg(not(feature = "ca-rules"))]
+use crate::rle::normalize_rule;
+use crate::{
+    rle::{HeaderData, Rle, Warning},
+    Input,
+};
+use displaydoc::Display;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::io::Error as IoError;
+
+/// Maximum length of a line in an RLE file.
+pub const MAX_LINE_LENGTH: usize = 70;
+
+/// A problem found by [`lint`].
+///
+/// Lines are numbered from `1`.
+#[derive(Clone, Debug, Eq, PartialEq, Hash, Display)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Issue {
+    /// {0}
+    Warning(Warning),
+    /// The pattern can't be parsed: {0}
+    ParseError(String),
+    /// There is no header line.
+    MissingHeader,
+    /// Invalid rule: {0}.
+    InvalidRule(String),
+    /// The rule can't be checked without the "ca-rules" feature: {0}.
+    UncheckedRule(String),
+    /// Line {line} has {length} characters, more than 70.
+    LineTooLong {
+        /// Number of the line.
+        line: usize,
+        /// Number of characters in the line.
+        length: usize,
+    },
+    /// Line {line} contains a tab.
+    Tab {
+        /// Number of the line.
+        line: usize,
+    },
+}
+
+/// Issues found by [`lint`], in the order they are found.
+#[derive(Clone, Debug, Eq, PartialEq, Default, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct LintReport {
+    /// The issues.
+    pub issues: Vec<Issue>,
+}
+
+impl LintReport {
+    /// Whether no issue is found.
+    pub fn is_clean(&self) -> bool {
+        self.issues.is_empty()
+    }
+}
+
+/// Run all the structural checks on an RLE file.
+///
+/// It checks that:
+///
+/// - the pattern can be parsed;
+/// - there is a header line, and the size it declares matches the pattern;
+/// - the rule in the header line is valid;
+/// - the body ends with a `!`, with nothing after it on the same line;
+/// - no line is longer than [`MAX_LINE_LENGTH`] characters, or contains a tab;
+/// - the [warnings](Rle::warnings) of the parser, e.g., unknown comment directives.
+///
+/// Without the `ca-rules` feature, only totalistic rules can be checked,
+/// and other rules are reported as [`Issue::UncheckedRule`].
+///
+/// Only an error when reading from the input is returned as an `Err`.
+///
+/// # Example
+///
+/// ```rust
+/// use ca_formats::lint::{lint, Issue};
+///
+/// let report = lint("x = 3, y = 3, rule = B3/S23\nbo$2bo$3o!").unwrap();
+/// assert!(report.is_clean());
+///
+/// let report = lint("x = 3, y = 2, rule = B9/S23\nbo$2bo\t$3o").unwrap();
+/// assert_eq!(report.issues.len(), 4);
+/// assert_eq!(report.issues[0], Issue::Tab { line: 2 });
+/// ```
+pub fn lint<I: Input>(input: I) -> Result<LintReport, IoError> {
+    let mut issues = Vec::new();
+    let mut text = Vec::new();
+    for (index, item) in input.lines().enumerate() {
+        let line = I::line(item)?;
+        let line = line.as_ref();
+        let length = String::from_utf8_lossy(line).chars().count();
+        if length > MAX_LINE_LENGTH {
+            issues.push(Issue::LineTooLong {
+                line: index + 1,
+                length,
+            });
+        }
+        if line.contains(&b'\t') {
+            issues.push(Issue::Tab { line: index + 1 });
+        }
+        text.extend_from_slice(line);
+        text.push(b'\n');
+    }
+
+    let mut rle = match Rle::new(text.as_slice()) {
+        Ok(rle) => rle.with_state_type::<u32>(),
+        Err(e) => {
+            issues.push(Issue::ParseError(e.to_string()));
+            return Ok(LintReport { issues });
+        }
+    };
+    match rle.header_data() {
+        None => issues.push(Issue::MissingHeader),
+        Some(header_data) => {
+            if let Some(issue) = check_rule(header_data) {
+                issues.push(issue);
+            }
+        }
+    }
+    if let Some(Err(e)) = rle.by_ref().find(Result::is_err) {
+        issues.push(Issue::ParseError(e.to_string()));
+    }
+    issues.extend(rle.warnings().iter().cloned().map(Issue::Warning));
+    Ok(LintReport { issues })
+}
+
+/// Check the rule in the header line with `ca-rules`.
+#[cfg(feature = "ca-rules")]
+fn check_rule(header_data: &HeaderData) -> Option<Issue> {
+    match header_data.parsed_rule()? {
+        Ok(_) => None,
+        Err(_) => header_data.rule.clone().map(Issue::InvalidRule),
+    }
+}
+
+/// Check the rule in the header line, if it looks totalistic.
+#[cfg(not(feature = "ca-rules"))]
+fn check_rule(header_data: &HeaderData) -> Option<Issue> {
+    let rule = header_data.rule.as_ref()?;
+    if normalize_rule(rule.trim()).is_some() {
+        None
+    } else if looks_totalistic(rule) {
+        Some(Issue::InvalidRule(rule.clone()))
+    } else {
+        Some(Issue::UncheckedRule(rule.clone()))
+    }
+}
+
+/// Whether a rulestring only uses the characters of totalistic rules,
+/// so that failing to normalize it means that it is invalid.
+#[cfg(not(feature = "ca-rules"))]
+fn looks_totalistic(rule: &str) -> bool {
+    rule.bytes()
+        .all(|c| c.is_ascii_digit() || b"/BSCGHVbscghv".contains(&c))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lint_rle() -> Result<(), IoError> {
+        const GLIDER: &str = "#N Glider\nx = 3, y = 3, rule = B3/S23\nbo$2bo$3o!\n";
+        assert!(lint(GLIDER)?.is_clean());
+
+        let long = format!("#C {}\nx = 1, y = 1, rule = B3/S23\no!", "a".repeat(68));
+        assert_eq!(
+            lint(long.as_str())?.issues,
+            [Issue::LineTooLong {
+                line: 1,
+                length: 71
+            }]
+        );
+
+        let report = lint("#Z\nbo$2bo$3o! 2o")?;
+        assert_eq!(
+            report.issues,
+            [
+                Issue::MissingHeader,
+                Issue::Warning(Warning::UnknownDirective(String::from("#Z"))),
+                Issue::Warning(Warning::TrailingGarbage(String::from("2o"))),
+            ]
+        );
+
+        let report = lint("x = 1, y = 1, rule = B3/S23\n2o$z!")?;
+        assert!(matches!(report.issues[..], [Issue::ParseError(_)]));
+        let report = lint("x = 1 y = 1\no!")?;
+        assert!(matches!(report.issues[..], [Issue::ParseError(_)]));
+        let report = lint("x = 1, y = 1, rule = B3/S23é\no!")?;
+        assert_eq!(report.issues.len(), 1);
+
+        #[cfg(not(feature = "ca-rules"))]
+        {
+            let report = lint("x = 1, y = 1, rule = B3/S29\no!")?;
+            assert_eq!(report.issues, [Issue::InvalidRule(String::from("B3/S29"))]);
+            let report = lint("x = 1, y = 1, rule = B2-a/S12\no!")?;
+            assert_eq!(
+                report.issues,
+                [Issue::UncheckedRule(String::from("B2-a/S12"))]
+            );
+        }
+        Ok(())
+    }
+}
@@ -1,24 +1,46 @@
 //! A parser for [Macrocell](http://golly.sourceforge.net/Help/formats.html#mc) format.
 
-use crate::Input;
+#[cfg(feature = "ca-rules")]
+use crate::rules::{ParseRuleError, Rule};
+use crate::{
+    extend_bounding_box, input::SharedIoError, output::Sink, scan::Scanner, BoundingBox, CellData,
+    Convention, Coordinates, Input, ParseOptions, RuleInfo, RuleOrigin, State,
+};
 use displaydoc::Display;
-use lazy_regex::regex;
-use std::io::{BufReader, Error as IoError, Read};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    io::{BufReader, Error as IoError, ErrorKind, Read, Write},
+};
 use thiserror::Error;
 
 /// Errors that can be returned when parsing a Macrocell file.
-#[derive(Debug, Error, Display)]
+#[derive(Clone, Debug, Eq, Error, Display, PartialEq)]
 pub enum Error {
-    /// Invalid header line: {0}.
-    InvalidHeaderLine(String),
-    /// Invalid node line: {0}.
-    InvalidNodeLine(String),
+    /// Invalid header line at column {1}: {0}.
+    InvalidHeaderLine(String, usize),
+    /// Invalid node line at column {1}: {0}.
+    InvalidNodeLine(String, usize),
+    /// Invalid node: {0}.
+    InvalidNode(usize),
+    /// More than {0} cells.
+    TooManyCells(u64),
+    /// Coordinates out of range in the convention: {0:?}.
+    CoordinateOverflow(Coordinates),
     /// Error when reading from input: {0}.
-    IoError(#[from] IoError),
+    IoError(#[from] SharedIoError),
+}
+
+impl From<IoError> for Error {
+    fn from(error: IoError) -> Self {
+        Error::IoError(error.into())
+    }
 }
 
 /// A node in [HashLife](https://conwaylife.com/wiki/HashLife)'s quadtree.
 #[derive(Copy, Clone, Debug, Ord, PartialOrd, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Node {
     pub id: usize,
     pub data: NodeData,
@@ -26,6 +48,7 @@ pub struct Node {
 
 /// Data in a `Node`.
 #[derive(Copy, Clone, Debug, Ord, PartialOrd, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum NodeData {
     /// A level 1 leaf, representing a 2x2 square, in rules with more than 2 states.
     ///
@@ -59,15 +82,17 @@ impl NodeData {
 }
 
 /// Parse a level 3 leaf.
-fn parse_level3(line: &str) -> Option<NodeData> {
+///
+/// Returns the column of the error.
+fn parse_level3(line: &[u8]) -> Result<NodeData, usize> {
     let mut node = 0;
     let (mut x, mut y) = (0_u8, 0_u8);
-    for char in line.bytes() {
+    for (i, &char) in line.iter().enumerate() {
         match char {
             b'.' => x += 1,
             b'*' => {
                 if x >= 8 || y >= 8 {
-                    return None;
+                    return Err(i + 1);
                 }
                 node |= 1 << ((7 - y) * 8 + (7 - x));
                 x += 1;
@@ -77,55 +102,69 @@ fn parse_level3(line: &str) -> Option<NodeData> {
                 y += 1;
             }
             c if c.is_ascii_whitespace() => (),
-            _ => return None,
+            _ => return Err(i + 1),
         }
     }
-    Some(NodeData::Level3(node))
+    Ok(NodeData::Level3(node))
 }
 
-/// Parse a level 1 leaf.
-fn parse_level1(line: &str) -> Option<NodeData> {
-    let re = regex!(r"^1\s+(\d+)\s+(\d+)\s+(\d+)\s+(\d+)");
-    let cap = re.captures(line)?;
-    let nw = cap[1].parse().ok()?;
-    let ne = cap[2].parse().ok()?;
-    let sw = cap[3].parse().ok()?;
-    let se = cap[4].parse().ok()?;
-    Some(NodeData::Level1 { nw, ne, sw, se })
+/// Parse a level 1 leaf, e.g., `1 0 1 2 3`. Text after the last number is ignored.
+///
+/// Returns the column of the error.
+fn parse_level1(line: &[u8]) -> Result<NodeData, usize> {
+    let mut scanner = Scanner::new(line);
+    scanner.expect(b"1")?;
+    let mut next = || {
+        scanner.expect_whitespace()?;
+        scanner.number(false)
+    };
+    Ok(NodeData::Level1 {
+        nw: next()?,
+        ne: next()?,
+        sw: next()?,
+        se: next()?,
+    })
 }
 
-/// Parse a non-leaf node.
-fn parse_node(line: &str) -> Option<NodeData> {
-    let re = regex!(r"^(\d+)\s+(\d+)\s+(\d+)\s+(\d+)\s+(\d+)");
-    let cap = re.captures(line)?;
-    let level = cap[1].parse().ok()?;
-    let nw = cap[2].parse().ok()?;
-    let ne = cap[3].parse().ok()?;
-    let sw = cap[4].parse().ok()?;
-    let se = cap[5].parse().ok()?;
-    Some(NodeData::Node {
+/// Parse a non-leaf node, e.g., `4 0 1 2 3`. Text after the last number is ignored.
+///
+/// Returns the column of the error.
+fn parse_node(line: &[u8]) -> Result<NodeData, usize> {
+    let mut scanner = Scanner::new(line);
+    let level = scanner.number(false)?;
+    let mut next = || {
+        scanner.expect_whitespace()?;
+        scanner.number(false)
+    };
+    Ok(NodeData::Node {
         level,
-        nw,
-        ne,
-        sw,
-        se,
+        nw: next()?,
+        ne: next()?,
+        sw: next()?,
+        se: next()?,
     })
 }
 
-/// Parse the rulestring.
-fn parse_rule(line: &str) -> Option<String> {
-    let re = regex!(r"^#R\s*(?P<rule>.*\S)\s*$");
-    let cap = re.captures(line)?;
-    let rule = cap["rule"].to_string();
-    Some(rule)
+/// Parse the `#R` line, e.g., `#R B3/S23`.
+///
+/// Returns the column of the error.
+fn parse_rule(line: &[u8]) -> Result<String, usize> {
+    let mut scanner = Scanner::new(line);
+    scanner.expect(b"#R")?;
+    scanner.skip_whitespace();
+    Ok(scanner.rest()?.to_owned())
 }
 
-/// Parse the current generation.
-fn parse_gen(line: &str) -> Option<u64> {
-    let re = regex!(r"^#G\s*(?P<gen>\d+)\s*$");
-    let cap = re.captures(line)?;
-    let gen = cap["gen"].parse().ok()?;
-    Some(gen)
+/// Parse the `#G` line, e.g., `#G 42`.
+///
+/// Returns the column of the error.
+fn parse_gen(line: &[u8]) -> Result<u64, usize> {
+    let mut scanner = Scanner::new(line);
+    scanner.expect(b"#G")?;
+    scanner.skip_whitespace();
+    let gen = scanner.number(false)?;
+    scanner.end()?;
+    Ok(gen)
 }
 
 /// A parser for [Macrocell](http://golly.sourceforge.net/Help/formats.html#mc) format.
@@ -178,6 +217,8 @@ fn parse_gen(line: &str) -> Option<u64> {
 #[must_use]
 #[derive(Debug)]
 pub struct Macrocell<I: Input> {
+    /// Comment lines before the nodes, except the `#R` and `#G` lines.
+    comments: Vec<String>,
     /// Rulestring.
     rule: Option<String>,
     /// Current generation.
@@ -188,52 +229,430 @@ pub struct Macrocell<I: Input> {
     current_line: Option<I::Line>,
     /// The current node id.
     id: usize,
+    /// Convention of the coordinates returned by [`cells`](Macrocell::cells).
+    convention: Convention,
+    /// The maximum number of cells returned by [`cells`](Macrocell::cells).
+    max_cells: Option<u64>,
 }
 
 impl<I: Input> Macrocell<I> {
     /// Create a new parser instance from input, and try to read the header lines.
     pub fn new(input: I) -> Result<Self, Error> {
         let mut lines = input.lines();
+        let mut comments = Vec::new();
         let mut rule = None;
         let mut gen = None;
         let mut current_line = None;
         for item in &mut lines {
             let line = I::line(item)?;
-            if line.as_ref().starts_with("[M2]") {
+            if line.as_ref().starts_with(b"[M2]") {
                 continue;
-            } else if line.as_ref().starts_with("#R") {
-                rule.replace(
-                    parse_rule(line.as_ref())
-                        .ok_or_else(|| Error::InvalidHeaderLine(line.as_ref().to_string()))?,
-                );
-            } else if line.as_ref().starts_with("#G") {
-                gen.replace(
-                    parse_gen(line.as_ref())
-                        .ok_or_else(|| Error::InvalidHeaderLine(line.as_ref().to_string()))?,
-                );
-            } else if !line.as_ref().starts_with('#') {
+            } else if line.as_ref().starts_with(b"#R") {
+                rule.replace(parse_rule(line.as_ref()).map_err(|column| {
+                    Error::InvalidHeaderLine(
+                        String::from_utf8_lossy(line.as_ref()).into_owned(),
+                        column,
+                    )
+                })?);
+            } else if line.as_ref().starts_with(b"#G") {
+                gen.replace(parse_gen(line.as_ref()).map_err(|column| {
+                    Error::InvalidHeaderLine(
+                        String::from_utf8_lossy(line.as_ref()).into_owned(),
+                        column,
+                    )
+                })?);
+            } else if line.as_ref().starts_with(b"#") {
+                comments.push(String::from_utf8_lossy(line.as_ref()).into_owned());
+            } else {
                 current_line = Some(line);
                 break;
             }
         }
         Ok(Self {
+            comments,
             rule,
             gen,
             lines,
             current_line,
             id: 1,
+            convention: Convention::default(),
+            max_cells: None,
         })
     }
 
+    /// Create a new parser instance from input with the [`ParseOptions`] that make sense
+    /// for this format: the convention and the maximum number of cells,
+    /// which are used by [`cells`](Macrocell::cells).
+    ///
+    /// A Macrocell file with a few nodes can encode a huge number of cells,
+    /// so [`max_cells`](ParseOptions::max_cells) is worth setting for untrusted input.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use ca_formats::{
+    ///     macrocell::{Error, Macrocell},
+    ///     ParseOptions,
+    /// };
+    ///
+    /// const GLIDER: &str = "[M2]\n$$$$$$*$.*$\n.......*$\n**$\n4 0 1 2 3";
+    ///
+    /// let options = ParseOptions::new().with_max_cells(4);
+    /// let macrocell = Macrocell::new_with_options(GLIDER, options).unwrap();
+    /// assert_eq!(macrocell.cells(), Err(Error::TooManyCells(4)));
+    /// ```
+    pub fn new_with_options(input: I, options: ParseOptions) -> Result<Self, Error> {
+        let mut macrocell = Self::new(input)?;
+        macrocell.convention = options.convention;
+        macrocell.max_cells = options.max_cells;
+        Ok(macrocell)
+    }
+
+    /// Comment lines before the nodes, e.g., `#C A comment`, except the `#R` and `#G` lines.
+    pub fn comments(&self) -> &[String] {
+        &self.comments
+    }
+
     /// The rulestring.
     pub fn rule(&self) -> Option<&str> {
         self.rule.as_deref()
     }
 
+    /// The rule in the `#R` line. See [`RuleInfo`].
+    pub fn rule_info(&self) -> Option<RuleInfo> {
+        RuleInfo::from_parser(self.rule(), RuleOrigin::RLine)
+    }
+
     /// The current generation.
     pub const fn gen(&self) -> Option<u64> {
         self.gen
     }
+
+    /// The rulestring parsed by [`ca-rules`](https://crates.io/crates/ca-rules).
+    ///
+    /// `None` if the rule is not specified.
+    #[cfg(feature = "ca-rules")]
+    #[cfg_attr(docs_rs, doc(cfg(feature = "ca-rules")))]
+    pub fn parsed_rule(&self) -> Option<Result<Rule, ParseRuleError>> {
+        self.rule.as_deref().map(Rule::parse)
+    }
+
+    /// Number of states of the rule.
+    ///
+    /// `None` if the rule is not specified, or can't be parsed.
+    #[cfg(feature = "ca-rules")]
+    #[cfg_attr(docs_rs, doc(cfg(feature = "ca-rules")))]
+    pub fn state_count(&self) -> Option<usize> {
+        self.parsed_rule()?.ok().map(|rule| rule.state_count())
+    }
+
+    /// Reads all the nodes, and returns the living cells in the quadtree.
+    ///
+    /// The last node is the root. Following Golly, the center of the root is placed at `(0, 0)`,
+    /// i.e., the upper left corner of a level `n` root is at `(-2^(n-1), -2^(n-1))`.
+    ///
+    /// The cells are returned in [`Ordering::Quadtree`](crate::order::Ordering::Quadtree),
+    /// quadrant by quadrant.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use ca_formats::macrocell::Macrocell;
+    ///
+    /// const GLIDER: &str = "[M2]\n$$$$$$*$.*$\n.......*$\n**$\n4 0 1 2 3";
+    ///
+    /// let cells = Macrocell::new(GLIDER).unwrap().cells().unwrap();
+    /// let cells = cells.into_iter().map(|cell| cell.position).collect::<Vec<_>>();
+    /// assert_eq!(cells, vec![(0, -2), (1, -1), (-1, 0), (0, 0), (1, 0)]);
+    /// ```
+    pub fn cells(mut self) -> Result<Vec<CellData>, Error> {
+        self.expand()
+    }
+
+    /// Reads all the nodes into a [`NodeSink`], and returns the living cells in the quadtree.
+    ///
+    /// The same as [`cells`](Macrocell::cells), except that the nodes are stored in the sink,
+    /// instead of a `Vec` in memory.
+    pub fn cells_with<S: NodeSink>(mut self, sink: &mut S) -> Result<Vec<CellData>, Error> {
+        self.expand_with(sink)
+    }
+
+    /// Reads the remaining nodes into a [`NodeSink`], one at a time, and returns the root,
+    /// i.e., the last node.
+    ///
+    /// Each node is checked before it is stored: its children must be stored nodes
+    /// of the level below. Returns `Ok(None)` if there is no remaining node.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use ca_formats::macrocell::{Macrocell, NodeData};
+    ///
+    /// const GLIDER: &str = "[M2]\n$$$$$$*$.*$\n.......*$\n**$\n4 0 1 2 3";
+    ///
+    /// let mut nodes = Vec::<NodeData>::new();
+    /// let root = Macrocell::new(GLIDER).unwrap().read_into(&mut nodes).unwrap();
+    /// assert_eq!(root.map(|node| node.id), Some(4));
+    /// assert_eq!(nodes.len(), 4);
+    /// ```
+    pub fn read_into<S: NodeSink>(&mut self, sink: &mut S) -> Result<Option<Node>, Error> {
+        let mut root = None;
+        for node in self.by_ref() {
+            let node = node?;
+            if let NodeData::Node {
+                level,
+                nw,
+                ne,
+                sw,
+                se,
+            } = node.data
+            {
+                if level < 2 {
+                    return Err(Error::InvalidNode(node.id));
+                }
+                for child in [nw, ne, sw, se] {
+                    if child >= node.id
+                        || (child > 0
+                            && sink.get(child)?.map(|data| data.level()) != Some(level - 1))
+                    {
+                        return Err(Error::InvalidNode(node.id));
+                    }
+                }
+            }
+            sink.push(node)?;
+            root = Some(node);
+        }
+        Ok(root)
+    }
+
+    /// Reads the remaining nodes, and returns the living cells in the quadtree.
+    ///
+    /// See [`cells`](Macrocell::cells).
+    pub(crate) fn expand(&mut self) -> Result<Vec<CellData>, Error> {
+        self.expand_with(&mut Vec::new())
+    }
+
+    /// Reads the remaining nodes into a [`NodeSink`], and returns the living cells in the quadtree.
+    fn expand_with<S: NodeSink>(&mut self, sink: &mut S) -> Result<Vec<CellData>, Error> {
+        let convention = self.convention;
+        let max_cells = self.max_cells.unwrap_or(u64::MAX);
+        let root = match self.read_into(sink)? {
+            Some(root) => root,
+            None => return Ok(Vec::new()),
+        };
+        if !(1..=63).contains(&root.data.level()) {
+            return Err(Error::InvalidNode(root.id));
+        }
+        let corner = -(1 << (root.data.level() - 1));
+        let mut cells = Vec::new();
+        let mut stack = vec![(root.id, (corner, corner))];
+        while let Some((id, (x, y))) = stack.pop() {
+            match sink.get(id)?.ok_or(Error::InvalidNode(id))? {
+                NodeData::Level1 { nw, ne, sw, se } => {
+                    for (state, position) in [
+                        (nw, (x, y)),
+                        (ne, (x + 1, y)),
+                        (sw, (x, y + 1)),
+                        (se, (x + 1, y + 1)),
+                    ] {
+                        if state != 0 {
+                            cells.push(CellData { position, state });
+                        }
+                    }
+                }
+                NodeData::Level3(leaf) => {
+                    for z in 0..64 {
+                        let (dx, dy) = (
+                            z & 1 | z >> 1 & 2 | z >> 2 & 4,
+                            z >> 1 & 1 | z >> 2 & 2 | z >> 3 & 4,
+                        );
+                        if leaf & 1 << (63 - dy * 8 - dx) != 0 {
+                            cells.push(CellData::from((x + dx, y + dy)));
+                        }
+                    }
+                }
+                NodeData::Node {
+                    level,
+                    nw,
+                    ne,
+                    sw,
+                    se,
+                } => {
+                    let half = 1 << (level - 1);
+                    for (child, position) in [
+                        (se, (x + half, y + half)),
+                        (sw, (x, y + half)),
+                        (ne, (x + half, y)),
+                        (nw, (x, y)),
+                    ] {
+                        if child > 0 {
+                            stack.push((child, position));
+                        }
+                    }
+                }
+            }
+            if cells.len() as u64 > max_cells {
+                return Err(Error::TooManyCells(max_cells));
+            }
+        }
+        for cell in &mut cells {
+            cell.position = convention
+                .apply(cell.position)
+                .ok_or(Error::CoordinateOverflow(cell.position))?;
+        }
+        Ok(cells)
+    }
+
+    /// Reads all the nodes, and computes the bounding box of the living cells in the quadtree,
+    /// without visiting each cell.
+    ///
+    /// The bounding box of each node is computed from the bounding boxes of its children,
+    /// so that a large pattern with many shared nodes is handled quickly.
+    /// The root is placed as in [`cells`](Macrocell::cells), and the convention is applied.
+    /// Returns `Ok(None)` if there is no living cell.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use ca_formats::macrocell::Macrocell;
+    ///
+    /// const GLIDER: &str = "[M2]\n$$$$$$*$.*$\n.......*$\n**$\n4 0 1 2 3";
+    ///
+    /// let glider = Macrocell::new(GLIDER).unwrap();
+    /// assert_eq!(glider.bounding_box().unwrap(), Some((-1, -2, 1, 0)));
+    /// ```
+    pub fn bounding_box(self) -> Result<Option<BoundingBox>, Error> {
+        let convention = self.convention;
+        let nodes = self
+            .map(|node| node.map(|node| node.data))
+            .collect::<Result<Vec<_>, _>>()?;
+        let root_level = match nodes.last() {
+            Some(root) => root.level(),
+            None => return Ok(None),
+        };
+        if !(1..=63).contains(&root_level) {
+            return Err(Error::InvalidNode(nodes.len()));
+        }
+        let mut boxes: Vec<Option<BoundingBox>> = Vec::with_capacity(nodes.len());
+        for (index, data) in nodes.iter().enumerate() {
+            let id = index + 1;
+            let mut bounding_box = None;
+            match *data {
+                NodeData::Level1 { nw, ne, sw, se } => {
+                    for (state, position) in
+                        [(nw, (0, 0)), (ne, (1, 0)), (sw, (0, 1)), (se, (1, 1))]
+                    {
+                        if state != 0 {
+                            extend_bounding_box(&mut bounding_box, position);
+                        }
+                    }
+                }
+                NodeData::Level3(leaf) => {
+                    for i in 0..64 {
+                        if leaf & 1 << (63 - i) != 0 {
+                            extend_bounding_box(&mut bounding_box, (i % 8, i / 8));
+                        }
+                    }
+                }
+                NodeData::Node {
+                    level,
+                    nw,
+                    ne,
+                    sw,
+                    se,
+                } => {
+                    if !(2..=63).contains(&level) {
+                        return Err(Error::InvalidNode(id));
+                    }
+                    let half = 1 << (level - 1);
+                    for (child, (dx, dy)) in [
+                        (nw, (0, 0)),
+                        (ne, (half, 0)),
+                        (sw, (0, half)),
+                        (se, (half, half)),
+                    ] {
+                        if child >= id || (child > 0 && nodes[child - 1].level() != level - 1) {
+                            return Err(Error::InvalidNode(id));
+                        } else if let Some(Some((min_x, min_y, max_x, max_y))) =
+                            child.checked_sub(1).map(|i| boxes[i])
+                        {
+                            extend_bounding_box(&mut bounding_box, (min_x + dx, min_y + dy));
+                            extend_bounding_box(&mut bounding_box, (max_x + dx, max_y + dy));
+                        }
+                    }
+                }
+            }
+            boxes.push(bounding_box);
+        }
+        let corner = -(1 << (root_level - 1));
+        let mut bounding_box = None;
+        if let Some(Some((min_x, min_y, max_x, max_y))) = boxes.pop() {
+            for cell in [
+                (min_x + corner, min_y + corner),
+                (max_x + corner, max_y + corner),
+            ] {
+                let position = convention
+                    .apply(cell)
+                    .ok_or(Error::CoordinateOverflow(cell))?;
+                extend_bounding_box(&mut bounding_box, position);
+            }
+        }
+        Ok(bounding_box)
+    }
+
+    /// Reads all the nodes, and describes the quadtree in the
+    /// [DOT language](https://graphviz.org/doc/info/lang.html) of GraphViz.
+    ///
+    /// Each node is labeled with its id, its level and its population, and each edge
+    /// with the quadrant of the child. The empty node `0` is omitted.
+    /// This is useful for teaching, and for comparing a HashLife implementation with Golly.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use ca_formats::macrocell::Macrocell;
+    ///
+    /// const GLIDER: &str = "[M2]\n$$$$$$*$.*$\n.......*$\n**$\n4 0 1 2 3";
+    ///
+    /// let dot = Macrocell::new(GLIDER).unwrap().to_dot().unwrap();
+    /// assert!(dot.starts_with("digraph macrocell {\n"));
+    /// assert!(dot.contains("  4 [label=\"4\\nlevel 4\\npopulation 5\"];\n"));
+    /// assert!(dot.contains("  4 -> 1 [label=\"ne\"];\n"));
+    /// ```
+    pub fn to_dot(mut self) -> Result<String, Error> {
+        let mut nodes = Vec::<NodeData>::new();
+        self.read_into(&mut nodes)?;
+        let mut populations = Vec::<u128>::with_capacity(nodes.len());
+        let mut dot = String::from("digraph macrocell {\n  node [shape=box];\n");
+        let mut edges = String::new();
+        for (index, data) in nodes.iter().enumerate() {
+            let id = index + 1;
+            let population = match *data {
+                NodeData::Level1 { nw, ne, sw, se } => {
+                    [nw, ne, sw, se].iter().filter(|&&state| state != 0).count() as u128
+                }
+                NodeData::Level3(leaf) => u128::from(leaf.count_ones()),
+                NodeData::Node { nw, ne, sw, se, .. } => {
+                    let mut population = 0_u128;
+                    for (child, quadrant) in [(nw, "nw"), (ne, "ne"), (sw, "sw"), (se, "se")] {
+                        if let Some(index) = child.checked_sub(1) {
+                            population = population.saturating_add(populations[index]);
+                            edges.push_str(&format!("  {id} -> {child} [label=\"{quadrant}\"];\n"));
+                        }
+                    }
+                    population
+                }
+            };
+            populations.push(population);
+            dot.push_str(&format!(
+                "  {id} [label=\"{id}\\nlevel {}\\npopulation {population}\"];\n",
+                data.level()
+            ));
+        }
+        dot.push_str(&edges);
+        dot.push_str("}\n");
+        Ok(dot)
+    }
 }
 
 impl<I, L> Macrocell<I>
@@ -243,7 +662,10 @@ where
 {
     /// Parse the remaining unparsed lines as a new Macrocell.
     pub fn remains(self) -> Result<Macrocell<L>, Error> {
-        Macrocell::new(self.lines)
+        let mut macrocell = Macrocell::new(self.lines)?;
+        macrocell.convention = self.convention;
+        macrocell.max_cells = self.max_cells;
+        Ok(macrocell)
     }
 }
 
@@ -261,11 +683,14 @@ where
 {
     fn clone(&self) -> Self {
         Self {
+            comments: self.comments.clone(),
             rule: self.rule.clone(),
             gen: self.gen,
             lines: self.lines.clone(),
             current_line: self.current_line.clone(),
             id: self.id,
+            convention: self.convention,
+            max_cells: self.max_cells,
         }
     }
 }
@@ -277,30 +702,29 @@ impl<I: Input> Iterator for Macrocell<I> {
     fn next(&mut self) -> Option<Self::Item> {
         loop {
             if let Some(line) = self.current_line.take() {
-                if line.as_ref().starts_with('#') {
+                let data = if line.as_ref().starts_with(b"#") {
+                    I::recycle(&mut self.lines, I::bytes(line));
                     continue;
-                } else if line.as_ref().starts_with(&['.', '*', '$'][..]) {
-                    if let Some(data) = parse_level3(line.as_ref()) {
+                } else if matches!(line.as_ref().first(), Some(b'.' | b'*' | b'$')) {
+                    parse_level3(line.as_ref())
+                } else if line.as_ref().starts_with(b"1 ") {
+                    parse_level1(line.as_ref())
+                } else {
+                    parse_node(line.as_ref())
+                };
+                match data {
+                    Ok(data) => {
+                        I::recycle(&mut self.lines, I::bytes(line));
                         let node = Node { id: self.id, data };
                         self.id += 1;
                         return Some(Ok(node));
-                    } else {
-                        return Some(Err(Error::InvalidNodeLine(line.as_ref().to_string())));
                     }
-                } else if line.as_ref().starts_with("1 ") {
-                    if let Some(data) = parse_level1(line.as_ref()) {
-                        let node = Node { id: self.id, data };
-                        self.id += 1;
-                        return Some(Ok(node));
-                    } else {
-                        return Some(Err(Error::InvalidNodeLine(line.as_ref().to_string())));
+                    Err(column) => {
+                        return Some(Err(Error::InvalidNodeLine(
+                            String::from_utf8_lossy(line.as_ref()).into_owned(),
+                            column,
+                        )));
                     }
-                } else if let Some(data) = parse_node(line.as_ref()) {
-                    let node = Node { id: self.id, data };
-                    self.id += 1;
-                    return Some(Ok(node));
-                } else {
-                    return Some(Err(Error::InvalidNodeLine(line.as_ref().to_string())));
                 }
             } else if let Some(item) = self.lines.next() {
                 match I::line(item) {
@@ -308,7 +732,7 @@ impl<I: Input> Iterator for Macrocell<I> {
                         self.current_line = Some(line);
                     }
                     Err(e) => {
-                        return Some(Err(Error::IoError(e)));
+                        return Some(Err(Error::from(e)));
                     }
                 }
             } else {
@@ -318,6 +742,417 @@ impl<I: Input> Iterator for Macrocell<I> {
     }
 }
 
+/// Storage for the nodes read by [`Macrocell::read_into`], e.g., a memory-mapped arena
+/// or a database, for patterns whose quadtrees don't fit in memory.
+///
+/// Errors of the storage are returned as [`Error::IoError`].
+///
+/// A `Vec<NodeData>` stores the node with id `n` at index `n - 1`,
+/// so it only works if it is empty, and no node has been read from the parser.
+///
+/// # Example
+///
+/// ```rust
+/// use ca_formats::macrocell::{Macrocell, Node, NodeData, NodeSink};
+/// use std::{collections::HashMap, io::Error};
+///
+/// #[derive(Default)]
+/// struct Storage(HashMap<usize, NodeData>);
+///
+/// impl NodeSink for Storage {
+///     fn push(&mut self, node: Node) -> Result<(), Error> {
+///         self.0.insert(node.id, node.data);
+///         Ok(())
+///     }
+///
+///     fn get(&mut self, id: usize) -> Result<Option<NodeData>, Error> {
+///         Ok(self.0.get(&id).copied())
+///     }
+/// }
+///
+/// const GLIDER: &str = "[M2]\n$$$$$$*$.*$\n.......*$\n**$\n4 0 1 2 3";
+///
+/// let mut storage = Storage::default();
+/// let cells = Macrocell::new(GLIDER).unwrap().cells_with(&mut storage).unwrap();
+/// assert_eq!(cells.len(), 5);
+/// assert_eq!(storage.0.len(), 4);
+/// ```
+pub trait NodeSink {
+    /// Stores a node. The nodes are pushed in the order of their ids.
+    fn push(&mut self, node: Node) -> Result<(), IoError>;
+
+    /// Data of a stored node, or `None` if there is no node with this id.
+    fn get(&mut self, id: usize) -> Result<Option<NodeData>, IoError>;
+}
+
+impl NodeSink for Vec<NodeData> {
+    fn push(&mut self, node: Node) -> Result<(), IoError> {
+        self.push(node.data);
+        Ok(())
+    }
+
+    fn get(&mut self, id: usize) -> Result<Option<NodeData>, IoError> {
+        Ok(id
+            .checked_sub(1)
+            .and_then(|index| self.as_slice().get(index))
+            .copied())
+    }
+}
+
+/// Builds a quadtree with shared nodes, for [`write_macrocell`].
+#[derive(Debug, Default)]
+struct TreeBuilder {
+    /// Data of the nodes, in the order of their ids, starting from `1`.
+    nodes: Vec<NodeData>,
+    /// Ids of the nodes.
+    ids: HashMap<NodeData, usize>,
+    /// Level of the leaves, `3` for 2-state patterns, `1` otherwise.
+    leaf_level: u8,
+}
+
+impl TreeBuilder {
+    /// Get the id of a node, adding it to the tree if necessary.
+    fn insert(&mut self, data: NodeData) -> usize {
+        let nodes = &mut self.nodes;
+        *self.ids.entry(data).or_insert_with(|| {
+            nodes.push(data);
+            nodes.len()
+        })
+    }
+
+    /// Build a node whose upper left corner is `corner`, returning its id.
+    ///
+    /// The id of an empty node is `0`.
+    fn build(&mut self, level: u8, corner: Coordinates, cells: Vec<(Coordinates, u8)>) -> usize {
+        if cells.is_empty() {
+            return 0;
+        }
+        let (x, y) = corner;
+        if level == 1 && self.leaf_level == 1 {
+            let mut states = [0; 4];
+            for ((cell_x, cell_y), state) in cells {
+                states[((cell_y - y) * 2 + cell_x - x) as usize] = state;
+            }
+            let [nw, ne, sw, se] = states;
+            return self.insert(NodeData::Level1 { nw, ne, sw, se });
+        } else if level == 3 && self.leaf_level == 3 {
+            let mut leaf = 0;
+            for ((cell_x, cell_y), _) in cells {
+                leaf |= 1 << ((7 - (cell_y - y)) * 8 + (7 - (cell_x - x)));
+            }
+            return self.insert(NodeData::Level3(leaf));
+        }
+        let half = 1 << (level - 1);
+        let mut quadrants = [Vec::new(), Vec::new(), Vec::new(), Vec::new()];
+        for cell in cells {
+            let ((cell_x, cell_y), _) = cell;
+            let index = usize::from(cell_x >= x + half) + 2 * usize::from(cell_y >= y + half);
+            quadrants[index].push(cell);
+        }
+        let [nw, ne, sw, se] = quadrants;
+        let nw = self.build(level - 1, (x, y), nw);
+        let ne = self.build(level - 1, (x + half, y), ne);
+        let sw = self.build(level - 1, (x, y + half), sw);
+        let se = self.build(level - 1, (x + half, y + half), se);
+        self.insert(NodeData::Node {
+            level,
+            nw,
+            ne,
+            sw,
+            se,
+        })
+    }
+}
+
+/// Write a node line.
+fn write_node<W: Write>(writer: &mut W, data: NodeData) -> Result<(), IoError> {
+    match data {
+        NodeData::Level1 { nw, ne, sw, se } => writeln!(writer, "1 {} {} {} {}", nw, ne, sw, se),
+        NodeData::Level3(leaf) => {
+            let mut line = String::new();
+            let rows = 8 - (leaf.trailing_zeros() / 8) as usize;
+            for row in leaf.to_be_bytes().iter().take(rows) {
+                let width = 8 - row.trailing_zeros() as usize;
+                for x in 0..width {
+                    line.push(if row & 0x80 >> x != 0 { '*' } else { '.' });
+                }
+                line.push('$');
+            }
+            writeln!(writer, "{}", line)
+        }
+        NodeData::Node {
+            level,
+            nw,
+            ne,
+            sw,
+            se,
+        } => writeln!(writer, "{} {} {} {} {}", level, nw, ne, sw, se),
+    }
+}
+
+/// Writes cells in Macrocell format.
+///
+/// The cells can be given in any order. Cells with state `0` are ignored.
+/// If a position appears more than once, only the first cell is written.
+///
+/// If all the cells have state `1`, the quadtree uses level 3 leaves (8x8 squares),
+/// otherwise level 1 leaves (2x2 squares). The center of the root is placed at `(0, 0)`,
+/// as in [`Macrocell::cells`].
+///
+/// Returns an error of kind [`InvalidInput`](ErrorKind::InvalidInput) if a state is larger than `255`.
+///
+/// # Example
+///
+/// ```rust
+/// use ca_formats::{macrocell::write_macrocell, CellData};
+///
+/// let glider = [(0, -2), (1, -1), (-1, 0), (0, 0), (1, 0)].map(CellData::from);
+/// let mut macrocell = Vec::new();
+/// write_macrocell(&mut macrocell, Some("B3/S23"), glider).unwrap();
+/// assert_eq!(
+///     String::from_utf8(macrocell).unwrap(),
+///     "[M2] (ca-formats)\n#R B3/S23\n$$$$$$*$.*$\n.......*$\n**$\n4 0 1 2 3\n"
+/// );
+/// ```
+pub fn write_macrocell<W, S, C>(writer: W, rule: Option<&str>, cells: C) -> Result<(), IoError>
+where
+    W: Write,
+    S: State,
+    C: IntoIterator<Item = CellData<S>>,
+{
+    write_macrocell_with_comments(writer, &[], rule, None, cells)
+}
+
+/// Writes cells in Macrocell format, with comment lines after the first line,
+/// and the generation in the `#G` line.
+///
+/// The comment lines should start with `#`.
+pub(crate) fn write_macrocell_with_comments<W, S, C>(
+    writer: W,
+    comments: &[String],
+    rule: Option<&str>,
+    gen: Option<u64>,
+    cells: C,
+) -> Result<(), IoError>
+where
+    W: Write,
+    S: State,
+    C: IntoIterator<Item = CellData<S>>,
+{
+    let mut macrocell_writer = MacrocellWriter::new(writer).with_comments(comments);
+    if let Some(rule) = rule {
+        macrocell_writer = macrocell_writer.with_rule(rule);
+    }
+    if let Some(gen) = gen {
+        macrocell_writer = macrocell_writer.with_gen(gen);
+    }
+    macrocell_writer.write_cells(cells).map(drop)
+}
+
+/// A writer that writes cells in Macrocell format, with a rule, a generation and comments.
+///
+/// The quadtree is built with shared nodes, and the nodes are written in the same order
+/// as in Golly: children before their parents, in the order of their first use in
+/// a depth-first traversal of the quadtree, visiting the quadrants in the order
+/// `nw`, `ne`, `sw`, `se`. See [`write_macrocell`] for the other details.
+///
+/// With [`canonical`](MacrocellWriter::canonical), identical trees are written
+/// as byte-identical files, which is useful for diffing and content-addressed storage.
+///
+/// # Example
+///
+/// ```rust
+/// use ca_formats::{macrocell::MacrocellWriter, CellData};
+///
+/// let glider = [(0, -2), (1, -1), (-1, 0), (0, 0), (1, 0)].map(CellData::from);
+/// let macrocell = MacrocellWriter::new(Vec::new())
+///     .with_rule("B3/S23")
+///     .with_gen(4)
+///     .write_cells(glider)
+///     .unwrap();
+/// assert_eq!(
+///     String::from_utf8(macrocell).unwrap(),
+///     "[M2] (ca-formats)\n#R B3/S23\n#G 4\n$$$$$$*$.*$\n.......*$\n**$\n4 0 1 2 3\n"
+/// );
+/// ```
+#[must_use]
+#[derive(Clone, Debug)]
+pub struct MacrocellWriter<W: Write> {
+    /// The sink.
+    writer: W,
+
+    /// Comment lines after the first line.
+    comments: Vec<String>,
+
+    /// Rulestring in the `#R` line.
+    rule: Option<String>,
+
+    /// Generation in the `#G` line.
+    gen: Option<u64>,
+
+    /// Whether to write a canonical file.
+    canonical: bool,
+
+    /// Whether to compress the output with gzip.
+    #[cfg(feature = "flate2")]
+    gzip: bool,
+}
+
+impl<W: Write> MacrocellWriter<W> {
+    /// Creates a new writer that writes to a sink, e.g., a [`File`](std::fs::File).
+    pub fn new(writer: W) -> Self {
+        MacrocellWriter {
+            writer,
+            comments: Vec::new(),
+            rule: None,
+            gen: None,
+            canonical: false,
+            #[cfg(feature = "flate2")]
+            gzip: false,
+        }
+    }
+
+    /// Write comment lines after the first line. They should start with `#`.
+    pub fn with_comments<T: AsRef<str>>(mut self, comments: &[T]) -> Self {
+        self.comments = comments.iter().map(|c| c.as_ref().to_owned()).collect();
+        self
+    }
+
+    /// Write a rulestring in the `#R` line.
+    pub fn with_rule(mut self, rule: &str) -> Self {
+        self.rule = Some(rule.to_owned());
+        self
+    }
+
+    /// Write a generation in the `#G` line.
+    pub fn with_gen(mut self, gen: u64) -> Self {
+        self.gen = Some(gen);
+        self
+    }
+
+    /// Whether to write a canonical file, whose bytes only depend on the rule and
+    /// the living cells, so that identical trees give byte-identical files.
+    ///
+    /// Comment lines and the generation are not written, and if a position appears
+    /// more than once, the cell with the largest state is written,
+    /// regardless of the order of the cells.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use ca_formats::{macrocell::MacrocellWriter, CellData};
+    ///
+    /// let cells = [((0, 0), 2), ((1, 0), 1), ((0, 0), 1)]
+    ///     .map(|(position, state)| CellData { position, state });
+    /// let write = |cells: &[CellData]| {
+    ///     MacrocellWriter::new(Vec::new())
+    ///         .with_comments(&["#N Domino"])
+    ///         .canonical(true)
+    ///         .write_cells(cells.iter().copied())
+    ///         .unwrap()
+    /// };
+    /// let macrocell = write(&cells);
+    /// assert_eq!(macrocell, b"[M2] (ca-formats)\n1 2 1 0 0\n2 0 0 0 1\n");
+    /// assert_eq!(write(&[cells[2], cells[1], cells[0]]), macrocell);
+    /// ```
+    pub fn canonical(mut self, canonical: bool) -> Self {
+        self.canonical = canonical;
+        self
+    }
+
+    /// Compress the output with gzip, e.g., to write a `.mc.gz` file, as Golly does.
+    ///
+    /// The compressed file can be read by [`Macrocell`] through a
+    /// [`GzDecoder`](https://docs.rs/flate2/latest/flate2/read/struct.GzDecoder.html).
+    #[cfg(feature = "flate2")]
+    #[cfg_attr(docs_rs, doc(cfg(feature = "flate2")))]
+    pub fn gzip(mut self, gzip: bool) -> Self {
+        self.gzip = gzip;
+        self
+    }
+
+    /// Writes the cells, and returns the sink.
+    ///
+    /// The cells can be given in any order. Cells with state `0` are ignored.
+    ///
+    /// Returns an error of kind [`InvalidInput`](ErrorKind::InvalidInput) if a state
+    /// is larger than `255`, or if the pattern is too large.
+    pub fn write_cells<S, C>(self, cells: C) -> Result<W, IoError>
+    where
+        S: State,
+        C: IntoIterator<Item = CellData<S>>,
+    {
+        let mut cells = cells
+            .into_iter()
+            .filter(|cell| cell.state != S::default())
+            .map(|cell| {
+                u8::try_from(cell.state.into())
+                    .map(|state| (cell.position, state))
+                    .map_err(|_| {
+                        IoError::new(
+                            ErrorKind::InvalidInput,
+                            format!("state {} can't be encoded in Macrocell", cell.state.into()),
+                        )
+                    })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        if self.canonical {
+            cells.sort_by_key(|&((x, y), state)| (y, x, u8::MAX - state));
+        } else {
+            cells.sort_by_key(|&((x, y), _)| (y, x));
+        }
+        cells.dedup_by_key(|&mut (position, _)| position);
+        let leaf_level = if cells.iter().all(|&(_, state)| state == 1) {
+            3
+        } else {
+            1
+        };
+        let mut level = leaf_level + 1;
+        while cells.iter().any(|&((x, y), _)| {
+            let half = 1_i64 << (level - 1);
+            x < -half || y < -half || x >= half || y >= half
+        }) {
+            if level == 63 {
+                return Err(IoError::new(
+                    ErrorKind::InvalidInput,
+                    "pattern too large for Macrocell",
+                ));
+            }
+            level += 1;
+        }
+        let mut builder = TreeBuilder {
+            leaf_level,
+            ..TreeBuilder::default()
+        };
+        let corner = -(1 << (level - 1));
+        if !cells.is_empty() {
+            builder.build(level, (corner, corner), cells);
+        }
+        let mut sink = Sink::new(self.writer);
+        #[cfg(feature = "flate2")]
+        if self.gzip {
+            sink.compress();
+        }
+        let writer = &mut sink;
+        writeln!(writer, "[M2] (ca-formats)")?;
+        if !self.canonical {
+            for comment in &self.comments {
+                writeln!(writer, "{}", comment)?;
+            }
+        }
+        if let Some(rule) = &self.rule {
+            writeln!(writer, "#R {}", rule)?;
+        }
+        if let Some(gen) = self.gen.filter(|_| !self.canonical) {
+            writeln!(writer, "#G {}", gen)?;
+        }
+        for data in builder.nodes {
+            write_node(writer, data)?;
+        }
+        sink.finish()
+    }
+}
+
 #[allow(clippy::unusual_byte_groupings)]
 #[cfg(test)]
 mod tests {
@@ -326,25 +1161,25 @@ mod tests {
     #[test]
     fn macrocell_parse_line() {
         assert_eq!(
-            parse_level3("$$..*$...*$.***$$$$"),
-            Some(NodeData::Level3(
+            parse_level3(b"$$..*$...*$.***$$$$"),
+            Ok(NodeData::Level3(
                 0b_00000000_00000000_00100000_00010000_01110000_00000000_00000000_00000000
             ))
         );
-        assert_eq!(parse_level3("$$..*$...*$.***$$$$*"), None);
+        assert_eq!(parse_level3(b"$$..*$...*$.***$$$$*"), Err(20));
         assert_eq!(
-            parse_level1("1 2 3 4 255"),
-            Some(NodeData::Level1 {
+            parse_level1(b"1 2 3 4 255"),
+            Ok(NodeData::Level1 {
                 nw: 2,
                 ne: 3,
                 sw: 4,
                 se: 255,
             })
         );
-        assert_eq!(parse_level1("1 2 3 4 256"), None);
+        assert_eq!(parse_level1(b"1 2 3 4 256"), Err(9));
         assert_eq!(
-            parse_node("10 20 30 40 50"),
-            Some(NodeData::Node {
+            parse_node(b"10 20 30 40 50"),
+            Ok(NodeData::Node {
                 level: 10,
                 nw: 20,
                 ne: 30,
@@ -352,7 +1187,11 @@ mod tests {
                 se: 50,
             })
         );
-        assert_eq!(parse_node("10 20 30 40"), None);
+        assert_eq!(parse_node(b"10 20 30 40"), Err(12));
+        assert_eq!(parse_rule(b"#R  B3/S23 "), Ok(String::from("B3/S23")));
+        assert_eq!(parse_rule(b"#R "), Err(4));
+        assert_eq!(parse_gen(b"#G 42"), Ok(42));
+        assert_eq!(parse_gen(b"#G 42x"), Err(6));
     }
 
     #[test]
@@ -404,6 +1243,218 @@ $$$$$$*$.*$
                 }
             ]
         );
+
+        for invalid in ["[M2]\n0 0 0 0 0", "[M2]\n**$\n0 1 0 0 0"] {
+            assert!(Macrocell::new(invalid)?.bounding_box().is_err());
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn macrocell_write() -> Result<(), Error> {
+        let cells: Vec<CellData> = vec![
+            CellData {
+                position: (-100, 3),
+                state: 2,
+            },
+            CellData {
+                position: (7, -20),
+                state: 1,
+            },
+            CellData {
+                position: (7, -20),
+                state: 3,
+            },
+            CellData {
+                position: (0, 0),
+                state: 0,
+            },
+        ];
+        let mut macrocell = Vec::new();
+        write_macrocell(&mut macrocell, None, cells)?;
+        let mut parsed = Macrocell::new(macrocell.as_slice())?.cells()?;
+        parsed.sort_by_key(|cell| (cell.position.1, cell.position.0));
+        assert_eq!(
+            parsed,
+            vec![
+                CellData {
+                    position: (7, -20),
+                    state: 1,
+                },
+                CellData {
+                    position: (-100, 3),
+                    state: 2,
+                },
+            ]
+        );
+
+        let mut macrocell = Vec::new();
+        write_macrocell(&mut macrocell, Some("B3/S23"), Vec::<CellData>::new())?;
+        assert_eq!(macrocell, b"[M2] (ca-formats)\n#R B3/S23\n");
+        assert!(Macrocell::new(macrocell.as_slice())?.cells()?.is_empty());
+
+        assert!(matches!(
+            Macrocell::new("[M2]\n*$\n5 1 0 0 0")?.cells(),
+            Err(Error::InvalidNode(2))
+        ));
+        assert_eq!(
+            Macrocell::new("[M2]\n0 0 0 0 0")?.cells(),
+            Err(Error::InvalidNode(1))
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn macrocell_canonical() -> Result<(), Error> {
+        let glider = [(0, -2), (1, -1), (-1, 0), (0, 0), (1, 0)].map(CellData::from);
+        let write = |cells: &[CellData], canonical| {
+            MacrocellWriter::new(Vec::new())
+                .with_comments(&["#N Glider", "#C A comment"])
+                .with_rule("B3/S23")
+                .with_gen(42)
+                .canonical(canonical)
+                .write_cells(cells.iter().copied())
+        };
+        let mut shuffled = glider;
+        shuffled.reverse();
+        let canonical = write(&glider, true)?;
+        assert_eq!(write(&shuffled, true)?, canonical);
+        assert_eq!(
+            canonical,
+            b"[M2] (ca-formats)\n#R B3/S23\n$$$$$$*$.*$\n.......*$\n**$\n4 0 1 2 3\n"
+        );
+        let verbose = write(&glider, false)?;
+        assert!(
+            verbose.starts_with(b"[M2] (ca-formats)\n#N Glider\n#C A comment\n#R B3/S23\n#G 42\n")
+        );
+        assert_eq!(
+            Macrocell::new(verbose.as_slice())?.cells()?,
+            Macrocell::new(canonical.as_slice())?.cells()?
+        );
+
+        let far = [(1000, 0), (-3, 7), (0, 0), (1000, 0)].map(CellData::from);
+        let mut reordered = far;
+        reordered.rotate_left(1);
+        assert_eq!(write(&far, true)?, write(&reordered, true)?);
+        Ok(())
+    }
+
+    #[cfg(feature = "flate2")]
+    #[test]
+    fn macrocell_gzip() -> Result<(), Error> {
+        use flate2::read::GzDecoder;
+        let glider = [(0, -2), (1, -1), (-1, 0), (0, 0), (1, 0)].map(CellData::from);
+        let write = |gzip| {
+            MacrocellWriter::new(Vec::new())
+                .with_rule("B3/S23")
+                .gzip(gzip)
+                .write_cells(glider)
+        };
+        let gz = write(true)?;
+        assert_eq!(&gz[..2], b"\x1f\x8b");
+        let macrocell = Macrocell::new(BufReader::new(GzDecoder::new(gz.as_slice())))?;
+        assert_eq!(macrocell.rule(), Some("B3/S23"));
+        assert_eq!(macrocell.cells()?.len(), 5);
+        assert_eq!(
+            std::io::read_to_string(GzDecoder::new(gz.as_slice()))?.as_bytes(),
+            write(false)?
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn macrocell_max_cells() -> Result<(), Error> {
+        let block = format!("[M2]\n{}\n4 1 1 1 1\n", "********$".repeat(8));
+        let options = ParseOptions::new().with_max_cells(256);
+        let cells = Macrocell::new_with_options(block.as_str(), options)?.cells()?;
+        assert_eq!(cells.len(), 256);
+
+        let mut huge = block;
+        for level in 5..=40 {
+            let child = level - 3;
+            huge.push_str(&format!("{level} {child} {child} {child} {child}\n"));
+        }
+        let macrocell = Macrocell::new_with_options(huge.as_str(), options)?;
+        assert_eq!(macrocell.cells(), Err(Error::TooManyCells(256)));
+        Ok(())
+    }
+
+    #[test]
+    fn macrocell_to_dot() -> Result<(), Error> {
+        const TREE: &str = "[M2]\n#R 3\n1 1 0 0 2\n1 0 0 0 0\n2 1 0 2 1\n";
+        let dot = Macrocell::new(TREE)?.to_dot()?;
+        assert_eq!(
+            dot,
+            "digraph macrocell {
+  node [shape=box];
+  1 [label=\"1\\nlevel 1\\npopulation 2\"];
+  2 [label=\"2\\nlevel 1\\npopulation 0\"];
+  3 [label=\"3\\nlevel 2\\npopulation 4\"];
+  3 -> 1 [label=\"nw\"];
+  3 -> 2 [label=\"sw\"];
+  3 -> 1 [label=\"se\"];
+}
+"
+        );
+        assert_eq!(
+            Macrocell::new("[M2]\n2 0 0 0 1")?.to_dot(),
+            Err(Error::InvalidNode(1))
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn macrocell_node_sink() -> Result<(), Error> {
+        /// Fails after storing some nodes.
+        struct Full(Vec<NodeData>, usize);
+
+        impl NodeSink for Full {
+            fn push(&mut self, node: Node) -> Result<(), IoError> {
+                if self.0.len() == self.1 {
+                    return Err(IoError::new(ErrorKind::OutOfMemory, "full"));
+                }
+                NodeSink::push(&mut self.0, node)
+            }
+
+            fn get(&mut self, id: usize) -> Result<Option<NodeData>, IoError> {
+                self.0.get(id)
+            }
+        }
+
+        const GLIDER: &str = "[M2]\n$$$$$$*$.*$\n.......*$\n**$\n4 0 1 2 3";
+
+        let mut sink = Full(Vec::new(), 4);
+        let cells = Macrocell::new(GLIDER)?.cells_with(&mut sink)?;
+        assert_eq!(cells, Macrocell::new(GLIDER)?.cells()?);
+        assert_eq!(sink.0.len(), 4);
+
+        let mut sink = Full(Vec::new(), 2);
+        assert!(matches!(
+            Macrocell::new(GLIDER)?.read_into(&mut sink),
+            Err(Error::IoError(_))
+        ));
+        assert_eq!(sink.0.len(), 2);
+
+        let mut nodes = Vec::new();
+        assert_eq!(
+            Macrocell::new("[M2]\n*$\n5 1 0 0 0\n6 2 0 0 0")?.read_into(&mut nodes),
+            Err(Error::InvalidNode(2))
+        );
+        assert_eq!(nodes, [NodeData::Level3(1 << 63)]);
+        let mut nodes = Vec::new();
+        assert_eq!(
+            Macrocell::new("[M2]\n**$\n0 1 0 0 0")?.read_into(&mut nodes),
+            Err(Error::InvalidNode(2))
+        );
+        assert_eq!(
+            Macrocell::new("[M2]\n**$\n0 1 0 0 0")?.cells(),
+            Err(Error::InvalidNode(2))
+        );
+        assert_eq!(
+            Macrocell::new("[M2]\n**$\n0 1 0 0 0")?.to_dot(),
+            Err(Error::InvalidNode(2))
+        );
+        assert_eq!(Macrocell::new("[M2]")?.read_into(&mut nodes), Ok(None));
         Ok(())
     }
 }
@@ -0,0 +1,54 @@
+//! Common names of small objects in Conway's Game of Life, as on LifeWiki.
+
+/// Apgcodes and common names of the objects, sorted by the apgcode.
+const NAMES: &[(&str, &str)] = &[
+    ("xp15_4r4z4r4", "Pentadecathlon"),
+    ("xp2_2a54", "Clock"),
+    ("xp2_318c", "Beacon"),
+    ("xp2_7", "Blinker"),
+    ("xp2_7e", "Toad"),
+    ("xp3_co9nas0san9oczgoldlo0oldlogz1047210127401", "Pulsar"),
+    ("xp8_gk2gb3z11", "Figure eight"),
+    ("xq4_153", "Glider"),
+    ("xq4_27dee6", "Middleweight spaceship"),
+    ("xq4_27deee6", "Heavyweight spaceship"),
+    ("xq4_6frc", "Lightweight spaceship"),
+    ("xs4_252", "Tub"),
+    ("xs4_33", "Block"),
+    ("xs5_253", "Boat"),
+    ("xs6_25a4", "Barge"),
+    ("xs6_356", "Ship"),
+    ("xs6_39c", "Aircraft carrier"),
+    ("xs6_696", "Beehive"),
+    ("xs6_bd", "Snake"),
+    ("xs7_178c", "Eater 1"),
+    ("xs7_2596", "Loaf"),
+    ("xs7_25ac", "Long boat"),
+    ("xs8_25ak8", "Long barge"),
+    ("xs8_35ac", "Long ship"),
+    ("xs8_6996", "Pond"),
+    ("xs8_69ic", "Mango"),
+    ("xs9_4aar", "Hat"),
+];
+
+/// The common name of an object in Conway's Game of Life, given its apgcode.
+pub(crate) fn common_name(code: &str) -> Option<&'static str> {
+    NAMES
+        .binary_search_by_key(&code, |&(code, _)| code)
+        .ok()
+        .map(|index| NAMES[index].1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_sorted() {
+        assert!(NAMES.windows(2).all(|pair| pair[0].0 < pair[1].0));
+        assert_eq!(common_name("xq4_153"), Some("Glider"));
+        assert_eq!(common_name("xp2_7"), Some("Blinker"));
+        assert_eq!(common_name("xs4_33"), Some("Block"));
+        assert_eq!(common_name("xs4_3"), None);
+    }
+}
@@ -0,0 +1,172 @@
+//! Orders in which the parsers return the cells.
+//!
+//! Each parser describes its order with an [`Ordering`], given by
+//! [`CaParser::ordering`](crate::parser::CaParser::ordering), and [`reorder`] sorts
+//! the cells of any parser into the order a consumer needs.
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::{Coordinates, Positioned};
+
+/// Height of the strips in [`Ordering::ColumnStrips`].
+pub const STRIP_HEIGHT: i64 = 5;
+
+/// An order of the cells.
+///
+/// The orders are described in the default [`Convention`](crate::Convention).
+/// Another convention converts the coordinates, but doesn't change the order of the cells.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Ordering {
+    /// Sorted by the y coordinate, and then by the x coordinate, as in RLE and Plaintext.
+    RowMajor,
+    /// Strip by strip, where each strip consists of [`STRIP_HEIGHT`] rows starting from
+    /// `y = 0`, and column by column in each strip, from top to bottom, as in apgcode.
+    ColumnStrips,
+    /// Quadrant by quadrant, in the order `nw`, `ne`, `sw`, `se`, down to single cells,
+    /// as in the quadtree of a Macrocell file whose root is centered at `(0, 0)`.
+    ///
+    /// This is the [Z-order](https://en.wikipedia.org/wiki/Z-order_curve) of the cells.
+    Quadtree,
+    /// No particular order, e.g., Life 1.06, where the cells are in the order of the lines.
+    Unordered,
+}
+
+impl Ordering {
+    /// A key of the coordinates, such that sorting the cells by the key gives this order.
+    ///
+    /// The key of [`Ordering::Unordered`] is always the same.
+    fn key(self, (x, y): Coordinates) -> (u128, i64) {
+        let (ux, uy) = ((x as u64) ^ 1 << 63, (y as u64) ^ 1 << 63);
+        match self {
+            Ordering::RowMajor => (u128::from(uy) << 64 | u128::from(ux), 0),
+            Ordering::ColumnStrips => {
+                let strip = (y.div_euclid(STRIP_HEIGHT) as u64) ^ 1 << 63;
+                (
+                    u128::from(strip) << 64 | u128::from(ux),
+                    y.rem_euclid(STRIP_HEIGHT),
+                )
+            }
+            Ordering::Quadtree => {
+                let key = (0..64).fold(0, |key, i| {
+                    key | u128::from(ux >> i & 1) << (2 * i)
+                        | u128::from(uy >> i & 1) << (2 * i + 1)
+                });
+                (key, 0)
+            }
+            Ordering::Unordered => (0, 0),
+        }
+    }
+}
+
+/// Collects the cells returned by a parser, and sorts them into the given order.
+///
+/// Cells at the same position keep their order, and [`Ordering::Unordered`]
+/// keeps the order of all the cells. [`sorted`](crate::sorted) is the same as
+/// sorting into [`Ordering::RowMajor`].
+///
+/// # Example
+///
+/// ```rust
+/// use ca_formats::{apgcode::ApgCode, order::Ordering, parser::CaParser, reorder, rle::Rle};
+///
+/// let mut apgcode = ApgCode::new("xq4_153").unwrap();
+/// assert_eq!(apgcode.ordering(), Ordering::ColumnStrips);
+///
+/// let rle = reorder(Rle::new("3o$2bo$bo!").unwrap(), Ordering::ColumnStrips).unwrap();
+/// let apgcode = apgcode.cells().collect::<Result<Vec<_>, _>>().unwrap();
+/// assert_eq!(rle, apgcode);
+/// ```
+pub fn reorder<T, E, C>(cells: C, to: Ordering) -> Result<Vec<T>, E>
+where
+    T: Positioned,
+    C: IntoIterator<Item = Result<T, E>>,
+{
+    let mut cells = cells.into_iter().collect::<Result<Vec<_>, _>>()?;
+    if to != Ordering::Unordered {
+        cells.sort_by_key(|cell| to.key(cell.position()));
+    }
+    Ok(cells)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        apgcode::{encode_wechsler, ApgCode},
+        life::{Life105, Life106},
+        macrocell::{write_macrocell, Macrocell},
+        parser::CaParser,
+        plaintext::Plaintext,
+        rle::Rle,
+        CellData, ParseOptions,
+    };
+    use std::{convert::Infallible, error::Error as StdError};
+
+    /// Checks that a parser returns the cells in the order it describes.
+    fn check<P: CaParser>(mut parser: P, ordering: Ordering) -> Result<(), P::Error> {
+        assert_eq!(parser.ordering(), ordering);
+        let cells = parser.cells().collect::<Result<Vec<_>, _>>()?;
+        let reordered = reorder(cells.iter().copied().map(Ok::<_, Infallible>), ordering);
+        assert_eq!(reordered, Ok(cells));
+        Ok(())
+    }
+
+    #[test]
+    fn order_parsers() -> Result<(), Box<dyn StdError>> {
+        const RLE: &str = "x = 12, y = 9\n2o$obo$bobo2$3bo$o3b2o2bobo$5bobo$12o$bo3bo!";
+        let cells = Rle::new(RLE)?.collect::<Result<Vec<_>, _>>()?;
+
+        check(Rle::new(RLE)?, Ordering::RowMajor)?;
+        check(
+            Rle::new_with_options(RLE, ParseOptions::new().include_dead(true))?,
+            Ordering::RowMajor,
+        )?;
+        check(Rle::new("#P 3 0\n2o\n#P 0 1\no!")?, Ordering::RowMajor)?;
+        check(
+            Rle::new("#P 3 0\n2o\n#P 0 1\no!")?.with_offset_lines(),
+            Ordering::Unordered,
+        )?;
+        check(Plaintext::new("OO.\nO.O\n.O.O")?, Ordering::RowMajor)?;
+        check(Life105::new("#Life 1.05\n#P 0 0\n.*")?, Ordering::Unordered)?;
+        check(Life106::new("#Life 1.06\n1 0\n0 0"), Ordering::Unordered)?;
+
+        let wechsler = encode_wechsler(cells.iter().map(|cell| cell.position))?;
+        let apgcode = format!("xs{}_{}", cells.len(), wechsler);
+        check(ApgCode::new(&apgcode)?, Ordering::ColumnStrips)?;
+
+        let mut macrocell = Vec::new();
+        write_macrocell(&mut macrocell, None, cells.iter().copied())?;
+        let macrocell = String::from_utf8(macrocell)?;
+        check(Macrocell::new(macrocell.as_str())?, Ordering::Quadtree)?;
+        let multistate =
+            [(-3, 2, 1_u8), (5, -1, 2), (0, 0, 3), (-1, -1, 1)].map(|(x, y, state)| CellData {
+                position: (x, y),
+                state,
+            });
+        let mut macrocell = Vec::new();
+        write_macrocell(&mut macrocell, None, multistate)?;
+        check(Macrocell::new(macrocell.as_slice())?, Ordering::Quadtree)?;
+        Ok(())
+    }
+
+    #[test]
+    fn order_reorder() {
+        let cells = [(1, 0), (0, 1), (0, 0), (1, 1), (-1, -1), (0, 6)];
+        let reordered = |to| reorder(cells.map(Ok::<_, Infallible>), to);
+        assert_eq!(
+            reordered(Ordering::RowMajor),
+            Ok(vec![(-1, -1), (0, 0), (1, 0), (0, 1), (1, 1), (0, 6)])
+        );
+        assert_eq!(
+            reordered(Ordering::ColumnStrips),
+            Ok(vec![(-1, -1), (0, 0), (0, 1), (1, 0), (1, 1), (0, 6)])
+        );
+        assert_eq!(
+            reordered(Ordering::Quadtree),
+            Ok(vec![(-1, -1), (0, 0), (1, 0), (0, 1), (1, 1), (0, 6)])
+        );
+        assert_eq!(reordered(Ordering::Unordered), Ok(cells.to_vec()));
+    }
+}
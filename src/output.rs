@@ -0,0 +1,65 @@
+//! A sink for the writers, optionally compressed with gzip.
+
+#[cfg(feature = "flate2")]
+use flate2::{write::GzEncoder, Compression};
+use std::io::{Error as IoError, Write};
+
+/// A sink that writes to a writer, or to a gzip encoder around it when the `flate2`
+/// feature is enabled, so that the writers can target `.rle.gz` or `.mc.gz` files as Golly does.
+#[derive(Debug)]
+pub(crate) struct Sink<W: Write> {
+    /// The writer, if the output is not compressed.
+    plain: Option<W>,
+    /// The gzip encoder around the writer, if the output is compressed.
+    #[cfg(feature = "flate2")]
+    gzip: Option<GzEncoder<W>>,
+}
+
+impl<W: Write> Sink<W> {
+    /// Creates an uncompressed sink.
+    pub(crate) const fn new(writer: W) -> Self {
+        Sink {
+            plain: Some(writer),
+            #[cfg(feature = "flate2")]
+            gzip: None,
+        }
+    }
+
+    /// Compresses the rest of the output with gzip.
+    ///
+    /// It should be called before anything is written.
+    #[cfg(feature = "flate2")]
+    pub(crate) fn compress(&mut self) {
+        if let Some(writer) = self.plain.take() {
+            self.gzip = Some(GzEncoder::new(writer, Compression::default()));
+        }
+    }
+
+    /// The writer or the encoder.
+    fn inner(&mut self) -> &mut dyn Write {
+        #[cfg(feature = "flate2")]
+        if let Some(encoder) = self.gzip.as_mut() {
+            return encoder;
+        }
+        self.plain.as_mut().expect("a sink always has a writer")
+    }
+
+    /// Finishes the compressed stream, if any, and returns the writer.
+    pub(crate) fn finish(self) -> Result<W, IoError> {
+        #[cfg(feature = "flate2")]
+        if let Some(encoder) = self.gzip {
+            return encoder.finish();
+        }
+        Ok(self.plain.expect("a sink always has a writer"))
+    }
+}
+
+impl<W: Write> Write for Sink<W> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, IoError> {
+        self.inner().write(buf)
+    }
+
+    fn flush(&mut self) -> Result<(), IoError> {
+        self.inner().flush()
+    }
+}
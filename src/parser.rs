@@ -0,0 +1,305 @@
+//! A common interface of the parsers, for functions that accept any format.
+//!
+//! Each parser has its own item type, e.g., [`Node`](crate::macrocell::Node) for Macrocell,
+//! and its own error type. [`CaParser`] returns the living cells of every format as
+//! [`CellData`], so that a generic function only needs one bound.
+
+use crate::{
+    apgcode::{self, ApgCode, PatternType},
+    life::{self, Life105, Life106},
+    macrocell::{self, Macrocell},
+    order::Ordering,
+    plaintext::{self, Plaintext},
+    rle::{self, HeaderData, Rle},
+    CellData, Input, RuleInfo,
+};
+use std::error::Error as StdError;
+
+/// A parser of any format.
+///
+/// The metadata and the comments are read when the parser is created,
+/// and [`cells`](CaParser::cells) reads the remaining cells.
+///
+/// # Example
+///
+/// ```rust
+/// use ca_formats::{
+///     apgcode::ApgCode, macrocell::Macrocell, parser::CaParser, plaintext::Plaintext,
+///     rle::Rle,
+/// };
+///
+/// fn population<P: CaParser>(mut parser: P) -> Result<usize, P::Error> {
+///     let mut population = 0;
+///     for cell in parser.cells() {
+///         population += usize::from(cell?.state != 0);
+///     }
+///     Ok(population)
+/// }
+///
+/// assert_eq!(population(Rle::new("bo$2bo$3o!").unwrap()), Ok(5));
+/// assert_eq!(population(Plaintext::new(".O\n..O\nOOO").unwrap()), Ok(5));
+/// assert_eq!(population(ApgCode::new("xq4_153").unwrap()), Ok(5));
+/// let glider = "[M2]\n$$$$$$*$.*$\n.......*$\n**$\n4 0 1 2 3";
+/// assert_eq!(population(Macrocell::new(glider).unwrap()), Ok(5));
+/// ```
+pub trait CaParser {
+    /// Metadata specific to the format, e.g., the header line of an RLE.
+    type Metadata;
+
+    /// Error returned by the parser.
+    type Error: StdError + 'static;
+
+    /// Metadata specific to the format.
+    fn metadata(&self) -> Self::Metadata;
+
+    /// Comment lines before the cells.
+    ///
+    /// Empty for formats without comments.
+    fn comments(&self) -> &[String] {
+        &[]
+    }
+
+    /// The rulestring, or the rule slug of an apgcode.
+    ///
+    /// `None` if the rule is not specified, or the format has no rule.
+    fn rule(&self) -> Option<&str> {
+        None
+    }
+
+    /// The rule, and where the format writes it. See [`RuleInfo`].
+    ///
+    /// `None` if the rule is not specified, is not a valid [`RuleString`](crate::RuleString),
+    /// or the format has no rule.
+    fn rule_info(&self) -> Option<RuleInfo> {
+        None
+    }
+
+    /// The order of the cells returned by [`cells`](CaParser::cells).
+    ///
+    /// Use [`reorder`](crate::reorder) to sort them into another order.
+    fn ordering(&self) -> Ordering;
+
+    /// Reads the remaining cells.
+    ///
+    /// Dead cells may be returned if the parser is set to include them,
+    /// e.g., by [`ParseOptions::include_dead`](crate::ParseOptions::include_dead).
+    fn cells(&mut self) -> impl Iterator<Item = Result<CellData, Self::Error>> + '_;
+}
+
+impl<I: Input> CaParser for Rle<I> {
+    /// The header line.
+    type Metadata = Option<HeaderData>;
+    type Error = rle::Error;
+
+    fn metadata(&self) -> Self::Metadata {
+        self.header_data().cloned()
+    }
+
+    fn comments(&self) -> &[String] {
+        Rle::comments(self)
+    }
+
+    fn rule(&self) -> Option<&str> {
+        self.header_data()?.rule.as_deref()
+    }
+
+    fn rule_info(&self) -> Option<RuleInfo> {
+        Rle::rule_info(self)
+    }
+
+    /// Row-major, unless `#P` lines [move the pen](Rle::with_offset_lines).
+    fn ordering(&self) -> Ordering {
+        if self.reads_offset_lines() {
+            Ordering::Unordered
+        } else {
+            Ordering::RowMajor
+        }
+    }
+
+    fn cells(&mut self) -> impl Iterator<Item = Result<CellData, Self::Error>> + '_ {
+        self.by_ref()
+    }
+}
+
+impl<I: Input> CaParser for Plaintext<I> {
+    /// Plaintext has no metadata other than the comments.
+    type Metadata = ();
+    type Error = plaintext::Error;
+
+    fn metadata(&self) -> Self::Metadata {}
+
+    fn comments(&self) -> &[String] {
+        Plaintext::comments(self)
+    }
+
+    fn ordering(&self) -> Ordering {
+        Ordering::RowMajor
+    }
+
+    fn cells(&mut self) -> impl Iterator<Item = Result<CellData, Self::Error>> + '_ {
+        self.by_ref().map(|cell| cell.map(CellData::from))
+    }
+}
+
+impl CaParser for ApgCode<'_> {
+    /// The type of the pattern in the prefix.
+    type Metadata = PatternType;
+    type Error = apgcode::Error;
+
+    fn metadata(&self) -> Self::Metadata {
+        self.pattern_type()
+    }
+
+    fn rule(&self) -> Option<&str> {
+        ApgCode::rule(self)
+    }
+
+    fn rule_info(&self) -> Option<RuleInfo> {
+        ApgCode::rule_info(self)
+    }
+
+    fn ordering(&self) -> Ordering {
+        Ordering::ColumnStrips
+    }
+
+    fn cells(&mut self) -> impl Iterator<Item = Result<CellData, Self::Error>> + '_ {
+        self.by_ref().map(|cell| cell.map(CellData::from))
+    }
+}
+
+/// The cells are the expanded quadtree, as in [`Macrocell::cells`].
+/// They are only returned after all the nodes are read.
+impl<I: Input> CaParser for Macrocell<I> {
+    /// The generation in the `#G` line.
+    type Metadata = Option<u64>;
+    type Error = macrocell::Error;
+
+    fn metadata(&self) -> Self::Metadata {
+        self.gen()
+    }
+
+    fn comments(&self) -> &[String] {
+        Macrocell::comments(self)
+    }
+
+    fn rule(&self) -> Option<&str> {
+        Macrocell::rule(self)
+    }
+
+    fn rule_info(&self) -> Option<RuleInfo> {
+        Macrocell::rule_info(self)
+    }
+
+    fn ordering(&self) -> Ordering {
+        Ordering::Quadtree
+    }
+
+    fn cells(&mut self) -> impl Iterator<Item = Result<CellData, Self::Error>> + '_ {
+        match self.expand() {
+            Ok(cells) => cells.into_iter().map(Ok).collect::<Vec<_>>(),
+            Err(e) => vec![Err(e)],
+        }
+        .into_iter()
+    }
+}
+
+impl<I: Input> CaParser for Life105<I> {
+    /// Life 1.05 has no metadata other than the comments and the rule.
+    type Metadata = ();
+    type Error = life::Error;
+
+    fn metadata(&self) -> Self::Metadata {}
+
+    fn comments(&self) -> &[String] {
+        Life105::comments(self)
+    }
+
+    fn rule(&self) -> Option<&str> {
+        Life105::rule(self)
+    }
+
+    fn rule_info(&self) -> Option<RuleInfo> {
+        Life105::rule_info(self)
+    }
+
+    /// Row-major in each block, and in the order of the `#P` lines between the blocks.
+    fn ordering(&self) -> Ordering {
+        Ordering::Unordered
+    }
+
+    fn cells(&mut self) -> impl Iterator<Item = Result<CellData, Self::Error>> + '_ {
+        self.by_ref().map(|cell| cell.map(CellData::from))
+    }
+}
+
+impl<I: Input> CaParser for Life106<I> {
+    /// Life 1.06 has no metadata.
+    type Metadata = ();
+    type Error = life::Error;
+
+    fn metadata(&self) -> Self::Metadata {}
+
+    /// In the order of the lines.
+    fn ordering(&self) -> Ordering {
+        Ordering::Unordered
+    }
+
+    fn cells(&mut self) -> impl Iterator<Item = Result<CellData, Self::Error>> + '_ {
+        self.by_ref().map(|cell| cell.map(CellData::from))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::RuleOrigin;
+
+    /// Collects the sorted living cells and the rule of any parser.
+    fn read<P: CaParser>(mut parser: P) -> Result<(Vec<CellData>, Option<String>), P::Error> {
+        let rule = parser.rule().map(String::from);
+        let mut cells = parser.cells().collect::<Result<Vec<_>, _>>()?;
+        cells.retain(|cell| cell.state != 0);
+        cells.sort_by_key(|cell| (cell.position.1, cell.position.0));
+        Ok((cells, rule))
+    }
+
+    #[test]
+    fn parser_formats() -> Result<(), Box<dyn StdError>> {
+        let glider = [(1, 0), (2, 1), (0, 2), (1, 2), (2, 2)].map(CellData::from);
+
+        let rle = Rle::new("#N Glider\nx = 3, y = 3, rule = B3/S23\nbo$2bo$3o!")?;
+        assert_eq!(CaParser::comments(&rle), ["#N Glider"]);
+        assert_eq!(rle.metadata().map(|data| (data.x, data.y)), Some((3, 3)));
+        let rle_rule = CaParser::rule_info(&rle).ok_or("missing rule")?;
+        assert_eq!(rle_rule.origin, RuleOrigin::Header);
+        assert_eq!(read(rle)?, (glider.to_vec(), Some(String::from("B3/S23"))));
+
+        let plaintext = Plaintext::new("!Name: Glider\n.O\n..O\nOOO")?;
+        assert_eq!(CaParser::comments(&plaintext), ["!Name: Glider"]);
+        assert_eq!(read(plaintext)?, (glider.to_vec(), None));
+
+        let life = Life105::new("#Life 1.05\n#R 23/3\n#P 0 0\n.*\n..*\n***")?;
+        let life_rule = CaParser::rule_info(&life).ok_or("missing rule")?;
+        assert!(life_rule.is_equivalent(&rle_rule));
+        assert_eq!(read(life)?, (glider.to_vec(), Some(String::from("23/3"))));
+
+        let life = Life106::new("#Life 1.06\n1 0\n2 1\n0 2\n1 2\n2 2");
+        assert_eq!(read(life)?, (glider.to_vec(), None));
+
+        let apgcode = ApgCode::new_with_context("b3s23/C1/xq4_153")?;
+        assert_eq!(apgcode.metadata(), PatternType::Spaceship);
+        assert_eq!(CaParser::rule(&apgcode), Some("b3s23"));
+        assert!(CaParser::rule_info(&apgcode).is_some_and(|rule| rule.is_equivalent(&life_rule)));
+        assert_eq!(read(apgcode)?.0.len(), 5);
+
+        let macrocell = Macrocell::new("[M2]\n#G 4\n$$$$$$*$.*$\n.......*$\n**$\n4 0 1 2 3")?;
+        assert_eq!(macrocell.metadata(), Some(4));
+        assert_eq!(read(macrocell)?.0.len(), 5);
+        let mut broken = Macrocell::new("[M2]\n4 0 1 2 3")?;
+        assert!(matches!(
+            CaParser::cells(&mut broken).collect::<Vec<_>>()[..],
+            [Err(_)]
+        ));
+        Ok(())
+    }
+}
@@ -0,0 +1,1442 @@
+//! An in-memory model of a pattern, with its metadata.
+
+use crate::{
+    apgcode::{self, ApgCode, ObjectClass},
+    crop::{contains, trim_cells},
+    evolve::{canonical, displacement, Evolver, DEFAULT_RULE},
+    extend_bounding_box,
+    life::{self, write_life105_with_comments, write_life106, Life105, Life106},
+    macrocell::{self, write_macrocell_with_comments, Macrocell},
+    plaintext::{self, write_plaintext, Plaintext},
+    rle::{self, write_rle_with_gen, CxrlePolicy, Rle},
+    svg::{write_svg, Style},
+    BoundingBox, CellData, Coordinates, Input, RuleString, State,
+};
+use displaydoc::Display;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashSet,
+    error::Error as StdError,
+    io::{Error as IoError, ErrorKind, Write},
+};
+use thiserror::Error;
+
+/// Maximum number of cells in a rectangle drawn by [`Pattern::to_ascii`]
+/// and [`Pattern::to_blocks`], so that a few distant cells don't produce a huge string.
+pub const MAX_DRAWN_CELLS: u64 = 1 << 24;
+
+/// Errors that can be returned by [`Pattern::advance`].
+#[derive(Clone, Debug, Eq, Error, Display, PartialEq)]
+pub enum AdvanceError<E: StdError + 'static> {
+    /// Error when evolving the pattern: {0}.
+    Evolver(#[source] E),
+    /// The generation overflows after {0} more generations.
+    GenerationOverflow(u64),
+}
+
+/// A pattern, i.e., its cells and metadata.
+///
+/// Unlike the parsers, which are lazy iterators, a `Pattern` holds all the cells in memory.
+/// It can be built from any parser, and written in any format.
+///
+/// # Example
+///
+/// ```rust
+/// use ca_formats::{plaintext::Plaintext, rle::Rle, Pattern};
+///
+/// const GLIDER: &str = r"#N Glider
+/// #O Richard K. Guy
+/// #C The smallest, most common, and first discovered spaceship.
+/// x = 3, y = 3, rule = B3/S23
+/// bo$2bo$3o!";
+///
+/// let glider = Pattern::from_rle(Rle::new(GLIDER).unwrap()).unwrap();
+/// assert_eq!(glider.name.as_deref(), Some("Glider"));
+/// assert_eq!(glider.author.as_deref(), Some("Richard K. Guy"));
+/// assert_eq!(glider.rule.as_deref(), Some("B3/S23"));
+/// assert_eq!(glider.bounding_box(), Some((0, 0, 2, 2)));
+///
+/// let mut plaintext = Vec::new();
+/// glider.write_plaintext(&mut plaintext).unwrap();
+///
+/// let parsed = Pattern::from_plaintext(Plaintext::new(plaintext.as_slice()).unwrap()).unwrap();
+/// assert_eq!(parsed.name.as_deref(), Some("Glider"));
+/// assert_eq!(parsed.cells, glider.cells);
+/// ```
+#[derive(Clone, Debug, Eq, PartialEq, Default, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Pattern<S = u8> {
+    /// Cells of the pattern.
+    ///
+    /// The parsers only return living cells, unless dead cells are explicitly included.
+    pub cells: Vec<CellData<S>>,
+    /// Rulestring, kept exactly as it is written in the source file.
+    pub rule: Option<RuleString>,
+    /// Name of the pattern.
+    pub name: Option<String>,
+    /// Author of the pattern.
+    pub author: Option<String>,
+    /// Comments, without the comment markers like `#C` or `!`.
+    pub comments: Vec<String>,
+    /// Current generation.
+    pub gen: Option<u64>,
+    /// Comment lines of the source file, with the comment markers, e.g., `#N Glider`.
+    ///
+    /// Lines that are read into other fields, e.g., the `#CXRLE` line, are not included.
+    /// They are written by the writers under [`CommentPolicy::Verbatim`].
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub source_comments: Vec<String>,
+    /// How the writers write the comment lines.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub comment_policy: CommentPolicy,
+    /// When [`write_rle`](Pattern::write_rle) writes the `#CXRLE` line.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub cxrle_policy: CxrlePolicy,
+}
+
+/// How the writers of [`Pattern`] write the comment lines.
+///
+/// # Example
+///
+/// ```rust
+/// use ca_formats::{rle::Rle, CommentPolicy, Pattern};
+///
+/// const GLIDER: &str = "#N Glider\n#r 23/3\nx = 3, y = 3\nbo$2bo$3o!";
+///
+/// let mut glider = Pattern::from_rle(Rle::new(GLIDER).unwrap()).unwrap();
+/// let mut plaintext = Vec::new();
+/// glider.write_plaintext(&mut plaintext).unwrap();
+/// assert_eq!(plaintext, b"!Name: Glider\n.O\n..O\nOOO\n");
+///
+/// glider.comment_policy = CommentPolicy::Verbatim;
+/// let mut plaintext = Vec::new();
+/// glider.write_plaintext(&mut plaintext).unwrap();
+/// assert_eq!(plaintext, b"!#N Glider\n!#r 23/3\n.O\n..O\nOOO\n");
+/// ```
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Default, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum CommentPolicy {
+    /// Write no comment lines, i.e., drop the name, the author and the comments.
+    Drop,
+    /// Write the [`source_comments`](Pattern::source_comments) as they are.
+    ///
+    /// Lines that are not comments in the target format, or that would be read
+    /// as something else, e.g., a `#R` line in Macrocell, are put after a comment marker,
+    /// e.g., `#C #R 0 0`.
+    Verbatim,
+    /// Write the name, the author and the comments in the comment syntax of the target format,
+    /// e.g., `#N` in RLE and `!Name:` in Plaintext.
+    ///
+    /// Comment lines that are not read into these fields are lost.
+    #[default]
+    Rewrap,
+}
+
+/// The comment syntax of a format.
+struct CommentSyntax {
+    /// Marker of the name.
+    name: &'static str,
+    /// Marker of the author.
+    author: &'static str,
+    /// Marker of the other comments.
+    comment: &'static str,
+    /// Prefix of all comment lines.
+    prefix: &'static str,
+    /// Prefixes of the lines that start like comments, but are read as something else.
+    reserved: &'static [&'static str],
+}
+
+impl CommentSyntax {
+    const RLE: Self = CommentSyntax {
+        name: "#N ",
+        author: "#O ",
+        comment: "#C ",
+        prefix: "#",
+        reserved: &["#CXRLE"],
+    };
+
+    const PLAINTEXT: Self = CommentSyntax {
+        name: "!Name: ",
+        author: "!Author: ",
+        comment: "!",
+        prefix: "!",
+        reserved: &[],
+    };
+
+    const MACROCELL: Self = CommentSyntax {
+        name: "#N ",
+        author: "#O ",
+        comment: "#C ",
+        prefix: "#",
+        reserved: &["#R", "#G"],
+    };
+
+    const LIFE105: Self = CommentSyntax {
+        name: "#D Name: ",
+        author: "#D Author: ",
+        comment: "#D ",
+        prefix: "#D",
+        reserved: &[],
+    };
+
+    /// Whether a line can be written as it is.
+    fn accepts(&self, line: &str) -> bool {
+        line.starts_with(self.prefix)
+            && !self
+                .reserved
+                .iter()
+                .any(|reserved| line.starts_with(reserved))
+    }
+}
+
+impl<S: State> Pattern<S> {
+    /// Reads all the cells in an RLE, and the metadata in the `#N`, `#O`, `#C` (or `#c`),
+    /// `#CXRLE` and header lines.
+    ///
+    /// Other comment lines are ignored.
+    pub fn from_rle<I: Input>(rle: Rle<I, S>) -> Result<Self, rle::Error> {
+        let mut pattern = Self {
+            rule: rle.rule_info().map(|info| info.rule),
+            gen: rle.generation(),
+            ..Self::default()
+        };
+        for comment in rle.comments() {
+            pattern.add_comment(comment, "#N", "#O", &["#C", "#c"]);
+        }
+        pattern.source_comments = rle.comments().to_vec();
+        pattern.cells = rle.collect::<Result<_, _>>()?;
+        Ok(pattern)
+    }
+
+    /// Builds a pattern from a dense grid of states, where `grid[y][x]` is the state
+    /// of the cell at `(x, y)`.
+    ///
+    /// The rows may have different lengths. Cells with state `0` are skipped,
+    /// and the other cells are in row-major order.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use ca_formats::Pattern;
+    ///
+    /// let glider = Pattern::from_grid(&[vec![0_u8, 1], vec![0, 0, 1], vec![1, 1, 1]]);
+    /// let mut rle = Vec::new();
+    /// glider.write_rle(&mut rle).unwrap();
+    /// assert_eq!(rle, b"x = 3, y = 3\nbo$2bo$3o!\n");
+    /// ```
+    pub fn from_grid(grid: &[Vec<S>]) -> Self {
+        let cells = grid
+            .iter()
+            .zip(0..)
+            .flat_map(|(row, y)| {
+                row.iter().zip(0..).filter_map(move |(&state, x)| {
+                    (state != S::default()).then_some(CellData {
+                        position: (x, y),
+                        state,
+                    })
+                })
+            })
+            .collect();
+        Pattern {
+            cells,
+            ..Pattern::default()
+        }
+    }
+
+    /// Builds a pattern from a function that gives the state of the cell at `(x, y)`,
+    /// for `0 <= x < width` and `0 <= y < height`.
+    ///
+    /// The function is called in row-major order. Cells with state `0` are skipped.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use ca_formats::Pattern;
+    ///
+    /// let checkerboard = Pattern::from_fn(4, 2, |x, y| ((x + y) % 2) as u8);
+    /// assert_eq!(checkerboard.cells.len(), 4);
+    /// assert_eq!(checkerboard.bounding_box(), Some((0, 0, 3, 1)));
+    /// ```
+    pub fn from_fn<F: FnMut(i64, i64) -> S>(width: u64, height: u64, mut f: F) -> Self {
+        let mut cells = Vec::new();
+        for y in 0..height as i64 {
+            for x in 0..width as i64 {
+                let state = f(x, y);
+                if state != S::default() {
+                    cells.push(CellData {
+                        position: (x, y),
+                        state,
+                    });
+                }
+            }
+        }
+        Pattern {
+            cells,
+            ..Pattern::default()
+        }
+    }
+
+    /// The bounding box of the living cells, i.e., `(min_x, min_y, max_x, max_y)`,
+    /// as the `bounding_box` methods of the parsers return.
+    ///
+    /// `None` if there are no living cells.
+    pub fn bounding_box(&self) -> Option<BoundingBox> {
+        let mut bounding_box = None;
+        for cell in self.cells.iter().filter(|cell| cell.state != S::default()) {
+            extend_bounding_box(&mut bounding_box, cell.position);
+        }
+        bounding_box
+    }
+
+    /// Translates the living cells so that the upper left corner of their bounding box
+    /// is at `(0, 0)`, and returns the corner before trimming.
+    ///
+    /// Dead cells outside the bounding box are removed. Returns `None`, and removes
+    /// all the cells, if there is no living cell. See [`Trim`](crate::crop::Trim).
+    ///
+    /// Returns an error of kind [`InvalidInput`](ErrorKind::InvalidInput), and leaves
+    /// the pattern unchanged, if the bounding box is wider or taller than `i64::MAX`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use ca_formats::{rle::Rle, Pattern};
+    ///
+    /// let mut blinker = Pattern::from_rle(Rle::new("#CXRLE Pos=-1,0\n3o!").unwrap()).unwrap();
+    /// assert_eq!(blinker.trim().unwrap(), Some((-1, 0)));
+    /// assert_eq!(blinker.bounding_box(), Some((0, 0, 2, 0)));
+    /// ```
+    pub fn trim(&mut self) -> Result<Option<Coordinates>, IoError> {
+        trim_cells(&mut self.cells)
+    }
+
+    /// Keeps the cells in a rectangle, given as a [`BoundingBox`], including its border.
+    ///
+    /// The coordinates of the cells are not changed. See [`Crop`](crate::crop::Crop).
+    pub fn crop(&mut self, rect: BoundingBox) {
+        self.cells.retain(|cell| contains(rect, cell.position));
+    }
+
+    /// Removes the cells in a rectangle, given as a [`BoundingBox`], including its border.
+    ///
+    /// This is the opposite of [`crop`](Pattern::crop).
+    pub fn erase_rect(&mut self, rect: BoundingBox) {
+        self.cells.retain(|cell| !contains(rect, cell.position));
+    }
+
+    /// Sets the state of a cell, replacing any cell at the same position.
+    ///
+    /// A cell with state `0` is removed instead. The new cell is added at the end,
+    /// so the cells may no longer be in row-major order; the writers don't need them to be,
+    /// and [`sort`](Pattern::sort) sorts them again.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use ca_formats::{rle::Rle, Pattern};
+    ///
+    /// let mut blinker = Pattern::from_rle(Rle::new("3o!").unwrap()).unwrap();
+    /// blinker.clear_cell((0, 0));
+    /// blinker.set_cell((1, 1), 1);
+    /// assert_eq!(blinker.to_ascii('o', '.').unwrap(), "oo\no.\n");
+    /// ```
+    pub fn set_cell(&mut self, position: Coordinates, state: S) {
+        self.cells.retain(|cell| cell.position != position);
+        if state != S::default() {
+            self.cells.push(CellData { position, state });
+        }
+    }
+
+    /// Removes the cell at a position, i.e., sets its state to `0`.
+    /// See [`set_cell`](Pattern::set_cell).
+    pub fn clear_cell(&mut self, position: Coordinates) {
+        self.set_cell(position, S::default());
+    }
+
+    /// Pastes the living cells of another pattern, translated by `(dx, dy)`,
+    /// replacing the cells at the same positions, e.g., to add a glider at an offset.
+    ///
+    /// The dead cells of the other pattern are ignored, so that the pasted pattern
+    /// is combined with the cells around it. The metadata of the other pattern is ignored.
+    ///
+    /// Returns an error of kind [`InvalidInput`](ErrorKind::InvalidInput), and leaves
+    /// the pattern unchanged, if the translated coordinates overflow `i64`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use ca_formats::{rle::Rle, Pattern};
+    ///
+    /// let mut pattern = Pattern::from_rle(Rle::new("2o$2o!").unwrap()).unwrap();
+    /// let glider = Pattern::from_rle(Rle::new("bo$2bo$3o!").unwrap()).unwrap();
+    /// pattern.paste(&glider, 3, 2).unwrap();
+    /// assert_eq!(pattern.to_clipboard_string().unwrap(), "x = 6, y = 5\n2o$2o$4bo$5bo$3b3o!\n");
+    /// ```
+    pub fn paste(&mut self, other: &Pattern<S>, dx: i64, dy: i64) -> Result<(), IoError> {
+        let pasted = other
+            .cells
+            .iter()
+            .filter(|cell| cell.state != S::default())
+            .map(|&cell| translate(cell, (dx, dy)))
+            .collect::<Result<Vec<_>, _>>()?;
+        let positions = pasted
+            .iter()
+            .map(|cell| cell.position)
+            .collect::<HashSet<_>>();
+        self.cells
+            .retain(|cell| !positions.contains(&cell.position));
+        self.cells.extend(pasted);
+        Ok(())
+    }
+
+    /// Maps the state of each cell with a function, and removes the cells
+    /// that are mapped to state `0`.
+    ///
+    /// The writers of 2-state formats, e.g., [`write_plaintext`](Pattern::write_plaintext),
+    /// return an error for states other than `0` and `1`. This method converts a multi-state
+    /// pattern to a 2-state pattern before writing it.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use ca_formats::{rle::Rle, Pattern};
+    ///
+    /// let pattern = Pattern::from_rle(Rle::new("ABC!").unwrap()).unwrap();
+    /// let mut plaintext = Vec::new();
+    /// assert!(pattern.write_plaintext(&mut plaintext).is_err());
+    /// let pattern = pattern.map_states(|state| state % 2);
+    /// pattern.write_plaintext(&mut plaintext).unwrap();
+    /// assert_eq!(plaintext, b"O.O\n");
+    /// ```
+    pub fn map_states<F: FnMut(S) -> S>(mut self, mut f: F) -> Self {
+        for cell in &mut self.cells {
+            cell.state = f(cell.state);
+        }
+        self.cells.retain(|cell| cell.state != S::default());
+        self
+    }
+
+    /// Draws the bounding box of the living cells as text, one line for each row,
+    /// with a character for living cells and another for dead cells.
+    ///
+    /// Each line ends with a newline. The string is empty if there is no living cell.
+    /// Meant for small patterns, e.g., in a terminal or in the output of a failed test.
+    ///
+    /// Returns an error of kind [`InvalidInput`](ErrorKind::InvalidInput) if the bounding box
+    /// has more than [`MAX_DRAWN_CELLS`] cells.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use ca_formats::{rle::Rle, Pattern};
+    ///
+    /// let glider = Pattern::from_rle(Rle::new("bo$2bo$3o!").unwrap()).unwrap();
+    /// assert_eq!(glider.to_ascii('#', '.').unwrap(), ".#.\n..#\n###\n");
+    /// ```
+    pub fn to_ascii(&self, alive: char, dead: char) -> Result<String, IoError> {
+        let Some(rect) = self.bounding_box() else {
+            return Ok(String::new());
+        };
+        let (x0, y0, x1, y1) = rect;
+        let living = self.living_in(rect)?;
+        let mut text = String::new();
+        for y in y0..=y1 {
+            text.extend((x0..=x1).map(|x| {
+                if living.contains(&(x, y)) {
+                    alive
+                } else {
+                    dead
+                }
+            }));
+            text.push('\n');
+        }
+        Ok(text)
+    }
+
+    /// Draws the bounding box of the living cells with block characters, two rows in each
+    /// line, so that the cells are roughly square in a terminal.
+    ///
+    /// The upper half block `▀` is a living cell above a dead cell, the lower half block `▄`
+    /// is a dead cell above a living cell, the full block `█` is two living cells, and
+    /// a space is two dead cells. Each line ends with a newline.
+    ///
+    /// Returns an error of kind [`InvalidInput`](ErrorKind::InvalidInput) if the bounding box
+    /// has more than [`MAX_DRAWN_CELLS`] cells.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use ca_formats::{rle::Rle, Pattern};
+    ///
+    /// let glider = Pattern::from_rle(Rle::new("bo$2bo$3o!").unwrap()).unwrap();
+    /// assert_eq!(glider.to_blocks().unwrap(), " ▀▄\n▀▀▀\n");
+    /// ```
+    pub fn to_blocks(&self) -> Result<String, IoError> {
+        self.bounding_box()
+            .map_or_else(|| Ok(String::new()), |rect| self.to_blocks_in(rect))
+    }
+
+    /// Draws a rectangle, given as a [`BoundingBox`], with block characters.
+    /// See [`to_blocks`](Pattern::to_blocks).
+    ///
+    /// Cells outside the rectangle are not drawn, so that a viewer can draw
+    /// a part of a large pattern.
+    ///
+    /// Returns an error of kind [`InvalidInput`](ErrorKind::InvalidInput) if the rectangle
+    /// has more than [`MAX_DRAWN_CELLS`] cells.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use ca_formats::{rle::Rle, Pattern};
+    ///
+    /// let glider = Pattern::from_rle(Rle::new("bo$2bo$3o!").unwrap()).unwrap();
+    /// assert_eq!(glider.to_blocks_in((1, 1, 3, 3)).unwrap(), "▄█ \n   \n");
+    /// ```
+    pub fn to_blocks_in(&self, rect: BoundingBox) -> Result<String, IoError> {
+        let (x0, y0, x1, y1) = rect;
+        let living = self.living_in(rect)?;
+        let mut text = String::new();
+        if x1 < x0 {
+            return Ok(text);
+        }
+        for y in (y0..=y1).step_by(2) {
+            text.extend((x0..=x1).map(|x| {
+                let lower = y.checked_add(1).map(|y| (x, y));
+                let cells = (
+                    living.contains(&(x, y)),
+                    lower.is_some_and(|lower| living.contains(&lower)),
+                );
+                match cells {
+                    (true, true) => '█',
+                    (true, false) => '▀',
+                    (false, true) => '▄',
+                    (false, false) => ' ',
+                }
+            }));
+            text.push('\n');
+        }
+        Ok(text)
+    }
+
+    /// Positions of the living cells in a rectangle to be drawn.
+    ///
+    /// Returns an error if the rectangle has more than [`MAX_DRAWN_CELLS`] cells.
+    fn living_in(&self, rect: BoundingBox) -> Result<HashSet<Coordinates>, IoError> {
+        let (x0, y0, x1, y1) = rect;
+        let span = |min: i64, max: i64| (i128::from(max) - i128::from(min) + 1).max(0) as u128;
+        let area = span(x0, x1).checked_mul(span(y0, y1));
+        if area.is_none_or(|area| area > u128::from(MAX_DRAWN_CELLS)) {
+            return Err(IoError::new(
+                ErrorKind::InvalidInput,
+                format!("the rectangle {:?} is too large to draw", rect),
+            ));
+        }
+        Ok(self
+            .positions()
+            .filter(|&position| contains(rect, position))
+            .collect())
+    }
+
+    /// Sorts the cells in row-major order. See [`sorted`](crate::sorted).
+    pub fn sort(&mut self) {
+        self.cells
+            .sort_by_key(|cell| (cell.position.1, cell.position.0));
+    }
+
+    /// Coordinates of the living cells.
+    fn positions(&self) -> impl Iterator<Item = Coordinates> + '_ {
+        self.cells
+            .iter()
+            .filter(|cell| cell.state != S::default())
+            .map(|cell| cell.position)
+    }
+
+    /// Checks that the pattern can be written in a 2-state format,
+    /// i.e., that all the states are `0` or `1`.
+    fn check_two_state(&self, format: &str) -> Result<(), IoError> {
+        match self.cells.iter().find(|cell| cell.state.into() > 1) {
+            Some(cell) => Err(IoError::new(
+                ErrorKind::InvalidInput,
+                format!("state {} can't be encoded in {}", cell.state.into(), format),
+            )),
+            None => Ok(()),
+        }
+    }
+
+    /// Comment lines in the given syntax, according to the [`CommentPolicy`].
+    fn comment_lines(&self, syntax: &CommentSyntax) -> Vec<String> {
+        let line = |marker: &str, text: &str| {
+            if text.is_empty() {
+                marker.trim_end().to_string()
+            } else {
+                format!("{}{}", marker, text)
+            }
+        };
+        let mut lines = Vec::new();
+        match self.comment_policy {
+            CommentPolicy::Drop => (),
+            CommentPolicy::Verbatim => {
+                lines.extend(self.source_comments.iter().map(|text| {
+                    if syntax.accepts(text) {
+                        text.clone()
+                    } else {
+                        line(syntax.comment, text)
+                    }
+                }));
+            }
+            CommentPolicy::Rewrap => {
+                lines.extend(self.name.iter().map(|text| line(syntax.name, text)));
+                lines.extend(self.author.iter().map(|text| line(syntax.author, text)));
+                lines.extend(self.comments.iter().map(|text| line(syntax.comment, text)));
+            }
+        }
+        lines
+    }
+
+    /// Parse a comment line, e.g., `#N Glider`, into the metadata.
+    ///
+    /// Lines that do not start with any of the markers are ignored.
+    fn add_comment(&mut self, line: &str, name: &str, author: &str, comment: &[&str]) {
+        if let Some(text) = line.strip_prefix(name) {
+            self.name = Some(text.trim().to_string());
+        } else if let Some(text) = line.strip_prefix(author) {
+            self.author = Some(text.trim().to_string());
+        } else if let Some(text) = comment.iter().find_map(|marker| line.strip_prefix(marker)) {
+            self.comments.push(text.trim().to_string());
+        }
+    }
+
+    /// Writes the pattern in [RLE](crate::rle) format, with `#N`, `#O` and `#C` lines.
+    ///
+    /// The comment lines depend on the [`comment_policy`](Pattern::comment_policy).
+    /// The position and the generation are written in the `#CXRLE` line,
+    /// depending on the [`cxrle_policy`](Pattern::cxrle_policy).
+    /// See [`rle::write_rle`] for details.
+    pub fn write_rle<W: Write>(&self, mut writer: W) -> Result<(), IoError> {
+        for line in self.comment_lines(&CommentSyntax::RLE) {
+            writeln!(writer, "{}", line)?;
+        }
+        write_rle_with_gen(
+            writer,
+            self.rule.as_deref(),
+            self.gen,
+            self.cxrle_policy,
+            self.cells.iter().copied(),
+        )
+    }
+
+    /// The pattern in RLE format as a string to be copied to the clipboard,
+    /// with only the rule and the living cells. See [`rle::to_clipboard_string`].
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use ca_formats::{rle::Rle, Pattern};
+    ///
+    /// let glider = Pattern::from_rle(Rle::new("#N Glider\nbo$2bo$3o!").unwrap()).unwrap();
+    /// assert_eq!(glider.to_clipboard_string().unwrap(), "x = 3, y = 3\nbo$2bo$3o!\n");
+    /// ```
+    pub fn to_clipboard_string(&self) -> Result<String, IoError> {
+        rle::to_clipboard_string(self.rule.as_deref(), self.cells.iter().copied())
+    }
+
+    /// Writes the living cells in [Plaintext](crate::plaintext) format,
+    /// with `!Name:`, `!Author:` and `!` lines.
+    ///
+    /// The comment lines depend on the [`comment_policy`](Pattern::comment_policy).
+    /// The format has no rule or generation.
+    /// See [`plaintext::write_plaintext`] for details.
+    ///
+    /// Returns an error of kind [`InvalidInput`](ErrorKind::InvalidInput) if a state
+    /// is larger than `1`. See [`map_states`](Pattern::map_states).
+    pub fn write_plaintext<W: Write>(&self, mut writer: W) -> Result<(), IoError> {
+        self.check_two_state("Plaintext")?;
+        for line in self.comment_lines(&CommentSyntax::PLAINTEXT) {
+            writeln!(writer, "{}", line)?;
+        }
+        write_plaintext(writer, self.positions())
+    }
+
+    /// Writes the pattern in [Macrocell](crate::macrocell) format, with `#N`, `#O` and `#C` lines.
+    ///
+    /// The comment lines depend on the [`comment_policy`](Pattern::comment_policy).
+    /// The generation is written in the `#G` line.
+    /// See [`macrocell::write_macrocell`] for details.
+    pub fn write_macrocell<W: Write>(&self, writer: W) -> Result<(), IoError> {
+        let comments = self.comment_lines(&CommentSyntax::MACROCELL);
+        write_macrocell_with_comments(
+            writer,
+            &comments,
+            self.rule.as_deref(),
+            self.gen,
+            self.cells.iter().copied(),
+        )
+    }
+
+    /// Writes the living cells in [Life 1.05](crate::life) format, with `#D` lines.
+    ///
+    /// The comment lines depend on the [`comment_policy`](Pattern::comment_policy).
+    /// The format has no generation.
+    /// See [`life::write_life105`] for details.
+    ///
+    /// Returns an error of kind [`InvalidInput`](ErrorKind::InvalidInput) if a state
+    /// is larger than `1`. See [`map_states`](Pattern::map_states).
+    pub fn write_life105<W: Write>(&self, writer: W) -> Result<(), IoError> {
+        self.check_two_state("Life 1.05")?;
+        let comments = self.comment_lines(&CommentSyntax::LIFE105);
+        write_life105_with_comments(writer, &comments, self.rule.as_deref(), self.positions())
+    }
+
+    /// Writes the living cells in [Life 1.06](crate::life) format.
+    ///
+    /// The format has no metadata. See [`life::write_life106`] for details.
+    ///
+    /// Returns an error of kind [`InvalidInput`](ErrorKind::InvalidInput) if a state
+    /// is larger than `1`. See [`map_states`](Pattern::map_states).
+    pub fn write_life106<W: Write>(&self, writer: W) -> Result<(), IoError> {
+        self.check_two_state("Life 1.06")?;
+        write_life106(writer, self.positions())
+    }
+
+    /// Writes the living cells as an SVG image in a [`Style`]. See [`write_svg`].
+    ///
+    /// The metadata are not written.
+    pub fn write_svg<W: Write>(&self, writer: W, style: &Style) -> Result<(), IoError> {
+        write_svg(writer, style, self.cells.iter().copied())
+    }
+
+    /// The living cells in [Extended Wechsler format](crate::apgcode::encode_wechsler).
+    ///
+    /// Returns [`apgcode::Error::TooLarge`] if the pattern is too large to be encoded.
+    pub fn to_wechsler(&self) -> Result<String, apgcode::Error> {
+        apgcode::encode_wechsler(self.positions())
+    }
+}
+
+impl Pattern {
+    /// Collect cells in a 2-state format.
+    fn from_coordinates<C, E>(cells: C) -> Result<Self, E>
+    where
+        C: IntoIterator<Item = Result<Coordinates, E>>,
+    {
+        let cells = cells
+            .into_iter()
+            .map(|cell| cell.map(CellData::from))
+            .collect::<Result<_, _>>()?;
+        Ok(Pattern {
+            cells,
+            ..Pattern::default()
+        })
+    }
+
+    /// Builds a 2-state pattern from a dense grid, where `grid[y][x]` is whether the cell
+    /// at `(x, y)` is alive. See [`from_grid`](Pattern::from_grid).
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use ca_formats::Pattern;
+    ///
+    /// let blinker = Pattern::from_bools(&[vec![true, true, true]]);
+    /// let mut plaintext = Vec::new();
+    /// blinker.write_plaintext(&mut plaintext).unwrap();
+    /// assert_eq!(plaintext, b"OOO\n");
+    /// ```
+    pub fn from_bools(grid: &[Vec<bool>]) -> Self {
+        let cells = grid
+            .iter()
+            .zip(0..)
+            .flat_map(|(row, y)| {
+                row.iter()
+                    .zip(0..)
+                    .filter(|&(&alive, _)| alive)
+                    .map(move |(_, x)| CellData::from((x, y)))
+            })
+            .collect();
+        Pattern {
+            cells,
+            ..Pattern::default()
+        }
+    }
+
+    /// Reads all the living cells in a Plaintext, and the metadata in the comment lines.
+    ///
+    /// `!Name:` and `!Author:` lines are the name and the author,
+    /// and other comment lines are comments.
+    pub fn from_plaintext<I: Input>(plaintext: Plaintext<I>) -> Result<Self, plaintext::Error> {
+        let comments = plaintext.comments().to_vec();
+        let mut pattern = Self::from_coordinates(plaintext)?;
+        for comment in &comments {
+            pattern.add_comment(comment, "!Name:", "!Author:", &["!"]);
+        }
+        pattern.source_comments = comments;
+        Ok(pattern)
+    }
+
+    /// Reads all the living cells in an apgcode, and the rule slug in its prefix, if any.
+    pub fn from_apgcode(apgcode: ApgCode<'_>) -> Result<Self, apgcode::Error> {
+        let rule = apgcode.rule_info().map(|info| info.rule);
+        Ok(Pattern {
+            rule,
+            ..Self::from_coordinates(apgcode)?
+        })
+    }
+
+    /// Reads all the cells in a Macrocell, and the metadata in the `#N`, `#O`, `#C`,
+    /// `#R` and `#G` lines.
+    ///
+    /// Other comment lines are ignored. See [`Macrocell::cells`] for the coordinates.
+    pub fn from_macrocell<I: Input>(macrocell: Macrocell<I>) -> Result<Self, macrocell::Error> {
+        let mut pattern = Self {
+            rule: macrocell.rule_info().map(|info| info.rule),
+            gen: macrocell.gen(),
+            ..Self::default()
+        };
+        for comment in macrocell.comments() {
+            pattern.add_comment(comment, "#N", "#O", &["#C"]);
+        }
+        pattern.source_comments = macrocell.comments().to_vec();
+        pattern.cells = macrocell.cells()?;
+        Ok(pattern)
+    }
+
+    /// Reads all the living cells in a Life 1.05 file, the rule in the `#R` line,
+    /// and the metadata in the `#D` lines.
+    ///
+    /// `#D Name:` and `#D Author:` lines are the name and the author,
+    /// and other `#D` lines are comments.
+    pub fn from_life105<I: Input>(life: Life105<I>) -> Result<Self, life::Error> {
+        let rule = life.rule_info().map(|info| info.rule);
+        let comments = life.comments().to_vec();
+        let mut pattern = Pattern {
+            rule,
+            ..Self::from_coordinates(life)?
+        };
+        for comment in &comments {
+            pattern.add_comment(comment, "#D Name:", "#D Author:", &["#D"]);
+        }
+        pattern.source_comments = comments;
+        Ok(pattern)
+    }
+
+    /// Reads all the living cells in a Life 1.06 file.
+    pub fn from_life106<I: Input>(life: Life106<I>) -> Result<Self, life::Error> {
+        Self::from_coordinates(life)
+    }
+
+    /// Evolves the cells with an [`Evolver`] for some generations in the
+    /// [`rule`](Pattern::rule), or [`DEFAULT_RULE`] if there is no rule,
+    /// and adds the generations to the [`gen`](Pattern::gen), so that the
+    /// `#CXRLE` line written by [`write_rle`](Pattern::write_rle) stays correct.
+    ///
+    /// The pattern is unchanged if the evolver returns an error, and
+    /// [`AdvanceError::GenerationOverflow`] is returned, before evolving the cells,
+    /// if the generation would overflow `u64`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use ca_formats::{rle::Rle, CellData, Pattern};
+    /// use std::convert::Infallible;
+    ///
+    /// // Not a real simulator: it moves the cells to the right.
+    /// let mut shift = |cells: &[CellData], _: &str| {
+    ///     let moved = cells
+    ///         .iter()
+    ///         .map(|cell| CellData::from((cell.position.0 + 1, cell.position.1)))
+    ///         .collect();
+    ///     Ok::<_, Infallible>(moved)
+    /// };
+    /// let mut blinker = Pattern::from_rle(Rle::new("3o!").unwrap()).unwrap();
+    /// blinker.advance(&mut shift, 2).unwrap();
+    /// assert_eq!(blinker.gen, Some(2));
+    /// assert_eq!(blinker.bounding_box(), Some((2, 0, 4, 0)));
+    /// ```
+    pub fn advance<E: Evolver>(
+        &mut self,
+        evolver: &mut E,
+        generations: u64,
+    ) -> Result<(), AdvanceError<E::Error>> {
+        let gen = self
+            .gen
+            .unwrap_or_default()
+            .checked_add(generations)
+            .ok_or(AdvanceError::GenerationOverflow(generations))?;
+        let rule = self.rule.as_deref().unwrap_or(DEFAULT_RULE);
+        let mut cells = self.cells.clone();
+        for _ in 0..generations {
+            cells = evolver.step(&cells, rule).map_err(AdvanceError::Evolver)?;
+        }
+        self.cells = cells;
+        self.gen = Some(gen);
+        Ok(())
+    }
+
+    /// Finds the [`ObjectClass`] of the pattern by evolving it with an [`Evolver`]
+    /// in the [`rule`](Pattern::rule), or [`DEFAULT_RULE`] if there is no rule,
+    /// until it returns to a translation of itself.
+    ///
+    /// The population and the bounding box are the ones of the current cells.
+    /// Returns `Ok(None)` if there is no living cell, or if the pattern doesn't return
+    /// within `max_period` generations.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use ca_formats::{apgcode::PatternType, rle::Rle, CellData, Pattern};
+    /// use std::convert::Infallible;
+    ///
+    /// // Not a real simulator: it moves the cells to the right.
+    /// let mut shift = |cells: &[CellData], _: &str| {
+    ///     let moved = cells
+    ///         .iter()
+    ///         .map(|cell| CellData::from((cell.position.0 + 1, cell.position.1)))
+    ///         .collect();
+    ///     Ok::<_, Infallible>(moved)
+    /// };
+    /// let pattern = Pattern::from_rle(Rle::new("3o!").unwrap()).unwrap();
+    /// let object_class = pattern.object_class(&mut shift, 10).unwrap().unwrap();
+    /// assert_eq!(object_class.pattern_type, PatternType::Spaceship);
+    /// assert_eq!(object_class.velocity.unwrap().to_string(), "(1,0)c/1");
+    /// ```
+    pub fn object_class<E: Evolver>(
+        &self,
+        evolver: &mut E,
+        max_period: u64,
+    ) -> Result<Option<ObjectClass>, E::Error> {
+        let rule = self.rule.as_deref().unwrap_or(DEFAULT_RULE);
+        let start = canonical(self.cells.clone());
+        if start.is_empty() {
+            return Ok(None);
+        }
+        let mut cells = self.cells.clone();
+        for period in 1..=max_period {
+            cells = evolver.step(&cells, rule)?;
+            if let Some(shift) = displacement(&start, &canonical(cells.clone())) {
+                let positions = start.iter().map(|cell| cell.position).collect::<Vec<_>>();
+                return Ok(Some(ObjectClass::new(&positions, period, shift)));
+            }
+        }
+        Ok(None)
+    }
+}
+
+/// A builder of [`Pattern`]s, which sets the metadata once for any number of patterns.
+///
+/// Each writer of [`Pattern`] maps the metadata onto the comment syntax of its format,
+/// e.g., `#N` in RLE and `!Name:` in Plaintext.
+///
+/// # Example
+///
+/// ```rust
+/// use ca_formats::{CellData, PatternBuilder, RuleString};
+///
+/// let glider = PatternBuilder::new()
+///     .name("Glider")
+///     .author("Richard K. Guy")
+///     .rule(RuleString::new("B3/S23").unwrap())
+///     .gen(4)
+///     .offset(-1, -1)
+///     .build([(1, 0), (2, 1), (0, 2), (1, 2), (2, 2)].map(CellData::from))
+///     .unwrap();
+///
+/// let mut rle = Vec::new();
+/// glider.write_rle(&mut rle).unwrap();
+/// assert_eq!(
+///     String::from_utf8(rle).unwrap(),
+///     "#N Glider\n#O Richard K. Guy\n#CXRLE Pos=-1,-1 Gen=4\nx = 3, y = 3, rule = B3/S23\nbo$2bo$3o!\n"
+/// );
+///
+/// let mut plaintext = Vec::new();
+/// glider.write_plaintext(&mut plaintext).unwrap();
+/// assert_eq!(
+///     String::from_utf8(plaintext).unwrap(),
+///     "!Name: Glider\n!Author: Richard K. Guy\n.O\n..O\nOOO\n"
+/// );
+/// ```
+#[must_use]
+#[derive(Clone, Debug, Eq, PartialEq, Default, Hash)]
+pub struct PatternBuilder {
+    /// Rulestring.
+    rule: Option<RuleString>,
+    /// Name of the pattern.
+    name: Option<String>,
+    /// Author of the pattern.
+    author: Option<String>,
+    /// Comments.
+    comments: Vec<String>,
+    /// Current generation.
+    gen: Option<u64>,
+    /// Offset added to the coordinates of the cells.
+    offset: Coordinates,
+    /// How the writers write the comment lines.
+    comment_policy: CommentPolicy,
+    /// When the RLE writer writes the `#CXRLE` line.
+    cxrle_policy: CxrlePolicy,
+}
+
+impl PatternBuilder {
+    /// Creates a new builder without any metadata.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the rulestring.
+    pub fn rule(mut self, rule: RuleString) -> Self {
+        self.rule = Some(rule);
+        self
+    }
+
+    /// Sets the name of the pattern.
+    pub fn name<T: Into<String>>(mut self, name: T) -> Self {
+        self.name = Some(name.into());
+        self
+    }
+
+    /// Sets the author of the pattern.
+    pub fn author<T: Into<String>>(mut self, author: T) -> Self {
+        self.author = Some(author.into());
+        self
+    }
+
+    /// Adds a comment. Multi-line comments are split into lines.
+    pub fn comment<T: Into<String>>(mut self, comment: T) -> Self {
+        let comment = comment.into();
+        self.comments.extend(str::lines(&comment).map(String::from));
+        self
+    }
+
+    /// Sets the current generation.
+    pub fn gen(mut self, gen: u64) -> Self {
+        self.gen = Some(gen);
+        self
+    }
+
+    /// Sets how the writers write the comment lines.
+    pub fn comment_policy(mut self, policy: CommentPolicy) -> Self {
+        self.comment_policy = policy;
+        self
+    }
+
+    /// Sets when the RLE writer writes the `#CXRLE` line.
+    pub fn cxrle_policy(mut self, policy: CxrlePolicy) -> Self {
+        self.cxrle_policy = policy;
+        self
+    }
+
+    /// Translates the cells by `(dx, dy)` when building a pattern.
+    pub fn offset(mut self, dx: i64, dy: i64) -> Self {
+        self.offset = (dx, dy);
+        self
+    }
+
+    /// Builds a pattern with the given cells and the metadata.
+    ///
+    /// Returns an error of kind [`InvalidInput`](ErrorKind::InvalidInput) if the translated
+    /// coordinates overflow `i64`, as [`Pattern::paste`] does.
+    pub fn build<S, C>(&self, cells: C) -> Result<Pattern<S>, IoError>
+    where
+        S: State,
+        C: IntoIterator<Item = CellData<S>>,
+    {
+        Ok(Pattern {
+            cells: cells
+                .into_iter()
+                .map(|cell| translate(cell, self.offset))
+                .collect::<Result<_, _>>()?,
+            rule: self.rule.clone(),
+            name: self.name.clone(),
+            author: self.author.clone(),
+            comments: self.comments.clone(),
+            gen: self.gen,
+            source_comments: Vec::new(),
+            comment_policy: self.comment_policy,
+            cxrle_policy: self.cxrle_policy,
+        })
+    }
+}
+
+/// Translates a cell by `(dx, dy)`, or returns an error of kind
+/// [`InvalidInput`](ErrorKind::InvalidInput) if the coordinates overflow `i64`.
+fn translate<S>(cell: CellData<S>, (dx, dy): Coordinates) -> Result<CellData<S>, IoError> {
+    let (x, y) = cell.position;
+    let position = x.checked_add(dx).zip(y.checked_add(dy)).ok_or_else(|| {
+        IoError::new(
+            ErrorKind::InvalidInput,
+            format!(
+                "cell ({}, {}) is out of range after translating by ({}, {})",
+                x, y, dx, dy
+            ),
+        )
+    })?;
+    Ok(CellData {
+        position,
+        state: cell.state,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ParseOptions;
+    use std::{convert::Infallible, error::Error};
+
+    #[test]
+    fn pattern_roundtrip() -> Result<(), Box<dyn Error>> {
+        let pattern = Pattern {
+            cells: [(1, -1), (-1, 0), (1, 0), (0, 1), (1, 1)]
+                .map(CellData::from)
+                .to_vec(),
+            rule: Some(RuleString::new("B3/S23")?),
+            name: Some(String::from("Glider")),
+            author: Some(String::from("Richard K. Guy")),
+            comments: vec![String::from("A spaceship."), String::new()],
+            gen: None,
+            ..Pattern::default()
+        };
+        assert_eq!(pattern.bounding_box(), Some((-1, -1, 1, 1)));
+
+        let mut rle = Vec::new();
+        pattern.write_rle(&mut rle)?;
+        let mut parsed = Pattern::from_rle(Rle::new(rle.as_slice())?)?;
+        assert_eq!(
+            parsed.source_comments,
+            ["#N Glider", "#O Richard K. Guy", "#C A spaceship.", "#C"]
+        );
+        parsed.source_comments.clear();
+        assert_eq!(parsed, pattern);
+
+        let mut macrocell = Vec::new();
+        pattern.write_macrocell(&mut macrocell)?;
+        let mut parsed = Pattern::from_macrocell(Macrocell::new(macrocell.as_slice())?)?;
+        parsed.source_comments.clear();
+        parsed
+            .cells
+            .sort_by_key(|cell| (cell.position.1, cell.position.0));
+        let mut expected = pattern.clone();
+        expected
+            .cells
+            .sort_by_key(|cell| (cell.position.1, cell.position.0));
+        assert_eq!(parsed, expected);
+
+        let mut plaintext = Vec::new();
+        pattern.write_plaintext(&mut plaintext)?;
+        let parsed = Pattern::from_plaintext(Plaintext::new(plaintext.as_slice())?)?;
+        assert_eq!(parsed.name, pattern.name);
+        assert_eq!(parsed.author, pattern.author);
+        assert_eq!(parsed.comments, pattern.comments);
+        assert_eq!(parsed.bounding_box(), Some((0, 0, 2, 2)));
+
+        let mut life = Vec::new();
+        pattern.write_life105(&mut life)?;
+        let parsed = Pattern::from_life105(Life105::new(life.as_slice())?)?;
+        assert_eq!(parsed.rule, pattern.rule);
+        assert_eq!(parsed.name, pattern.name);
+        assert_eq!(parsed.author, pattern.author);
+        assert_eq!(parsed.comments, pattern.comments);
+        assert_eq!(parsed.cells.len(), 5);
+
+        let apgcode = format!("xq4_{}", pattern.to_wechsler()?);
+        let parsed = Pattern::from_apgcode(ApgCode::new(&apgcode)?)?;
+        assert_eq!(parsed.cells.len(), 5);
+        Ok(())
+    }
+
+    #[test]
+    fn pattern_cxrle_policy() -> Result<(), Box<dyn Error>> {
+        let glider = PatternBuilder::new()
+            .gen(4)
+            .offset(-1, -1)
+            .build([(1, 0), (2, 1), (0, 2), (1, 2), (2, 2)].map(CellData::<u8>::from))?;
+        let mut rle = Vec::new();
+        glider.write_rle(&mut rle)?;
+        let parsed = Pattern::from_rle(Rle::new(rle.as_slice())?)?;
+        assert_eq!(parsed.cells, glider.cells);
+        assert_eq!(parsed.gen, Some(4));
+
+        let mut rebased = glider.clone();
+        rebased.cxrle_policy = CxrlePolicy::Never;
+        let mut rle = Vec::new();
+        rebased.write_rle(&mut rle)?;
+        assert_eq!(rle, b"x = 3, y = 3\nbo$2bo$3o!\n");
+        let parsed = Pattern::from_rle(Rle::new(rle.as_slice())?)?;
+        assert_eq!(parsed.bounding_box(), Some((0, 0, 2, 2)));
+        assert_eq!(parsed.gen, None);
+
+        let origin = PatternBuilder::new()
+            .cxrle_policy(CxrlePolicy::Always)
+            .build([CellData::<u8>::from((0, 0))])?;
+        let mut rle = Vec::new();
+        origin.write_rle(&mut rle)?;
+        assert_eq!(rle, b"#CXRLE Pos=0,0\nx = 1, y = 1\no!\n");
+        Ok(())
+    }
+
+    #[test]
+    fn pattern_comment_policy() -> Result<(), Box<dyn Error>> {
+        const RLE: &str = "#N Glider\n#C A spaceship.\n#r 23/3\n#R 0 0\nx = 3, y = 3\nbo$2bo$3o!";
+        let mut pattern = Pattern::from_rle(Rle::new(RLE)?)?;
+
+        pattern.comment_policy = CommentPolicy::Drop;
+        let mut life = Vec::new();
+        pattern.write_life105(&mut life)?;
+        assert!(Life105::new(life.as_slice())?.comments().is_empty());
+
+        pattern.comment_policy = CommentPolicy::Verbatim;
+        let mut macrocell = Vec::new();
+        pattern.write_macrocell(&mut macrocell)?;
+        let parsed = Pattern::from_macrocell(Macrocell::new(macrocell.as_slice())?)?;
+        assert_eq!(
+            parsed.source_comments,
+            ["#N Glider", "#C A spaceship.", "#r 23/3", "#C #R 0 0"]
+        );
+        assert_eq!(parsed.name, pattern.name);
+
+        let mut life = Vec::new();
+        pattern.write_life105(&mut life)?;
+        let parsed = Pattern::from_life105(Life105::new(life.as_slice())?)?;
+        assert_eq!(
+            parsed.comments,
+            ["#N Glider", "#C A spaceship.", "#r 23/3", "#R 0 0"]
+        );
+
+        let mut plaintext = Vec::new();
+        pattern.write_plaintext(&mut plaintext)?;
+        let mut parsed = Pattern::from_plaintext(Plaintext::new(plaintext.as_slice())?)?;
+        parsed.comment_policy = CommentPolicy::Verbatim;
+        let mut rle = Vec::new();
+        parsed.write_rle(&mut rle)?;
+        assert!(String::from_utf8(rle)?.starts_with("#C !#N Glider\n#C !#C A spaceship.\n"));
+        Ok(())
+    }
+
+    #[test]
+    fn pattern_builder() -> Result<(), Box<dyn Error>> {
+        let builder = PatternBuilder::new()
+            .name("Blinker")
+            .comment("Period 2.\nThe smallest oscillator.")
+            .rule(RuleString::new("B3/S23")?)
+            .gen(1)
+            .offset(10, -10);
+        let blinker = builder.build([(0, 0), (1, 0), (2, 0)].map(CellData::from))?;
+        assert_eq!(blinker.bounding_box(), Some((10, -10, 12, -10)));
+        assert_eq!(blinker.comments.len(), 2);
+
+        let mut rle = Vec::new();
+        blinker.write_rle(&mut rle)?;
+        let mut parsed = Pattern::from_rle(Rle::new(rle.as_slice())?)?;
+        assert_eq!(parsed.source_comments.len(), 3);
+        parsed.source_comments.clear();
+        assert_eq!(parsed, blinker);
+
+        let mut macrocell = Vec::new();
+        blinker.write_macrocell(&mut macrocell)?;
+        let parsed = Pattern::from_macrocell(Macrocell::new(macrocell.as_slice())?)?;
+        assert_eq!(parsed.gen, Some(1));
+        assert_eq!(parsed.comments, blinker.comments);
+
+        let error = builder
+            .offset(i64::MAX, 0)
+            .build([CellData::<u8>::from((1, 0))])
+            .unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidInput);
+        Ok(())
+    }
+
+    #[test]
+    fn pattern_generation() -> Result<(), Box<dyn Error>> {
+        for gen in [0, 4, 3480106827776] {
+            let rle = format!("#CXRLE Pos=0,0 Gen={}\nx = 3, y = 1\n3o!\n", gen);
+            let blinker = Pattern::<u8>::from_rle(Rle::new(rle.as_str())?)?;
+            assert_eq!(blinker.gen, Some(gen));
+
+            let mut macrocell = Vec::new();
+            blinker.write_macrocell(&mut macrocell)?;
+            let macrocell = Macrocell::new(macrocell.as_slice())?;
+            assert_eq!(macrocell.gen(), Some(gen));
+            let parsed = Pattern::from_macrocell(macrocell)?;
+
+            let mut written = Vec::new();
+            parsed.write_rle(&mut written)?;
+            assert_eq!(Rle::new(written.as_slice())?.generation(), Some(gen));
+            assert_eq!(String::from_utf8(written)?, rle);
+        }
+
+        let mut stay = |cells: &[CellData], _: &str| Ok::<_, Infallible>(cells.to_vec());
+        let mut blinker = Pattern::from_rle(Rle::new("#CXRLE Gen=4\n3o!")?)?;
+        assert_eq!(
+            blinker.advance(&mut stay, u64::MAX),
+            Err(AdvanceError::GenerationOverflow(u64::MAX))
+        );
+        assert_eq!(blinker.gen, Some(4));
+        Ok(())
+    }
+
+    #[test]
+    fn pattern_object_class() -> Result<(), Box<dyn Error>> {
+        // A blinker rotates by 90 degrees around its center in each generation.
+        let mut rotate = |cells: &[CellData], _: &str| {
+            let rotated = cells
+                .iter()
+                .map(|cell| CellData::from((2 - cell.position.1, cell.position.0)))
+                .collect();
+            Ok::<_, Infallible>(rotated)
+        };
+        let blinker = Pattern::from_rle(Rle::new("#CXRLE Pos=0,1\n3o!")?)?;
+        let object_class = blinker.object_class(&mut rotate, 10)?;
+        let expected = ObjectClass {
+            bounding_box: Some((0, 1, 2, 1)),
+            ..ApgCode::new("xp2_7")?.object_class()?
+        };
+        assert_eq!(object_class, Some(expected));
+        assert_eq!(blinker.object_class(&mut rotate, 1)?, None);
+
+        let mut stay = |cells: &[CellData], _: &str| Ok::<_, Infallible>(cells.to_vec());
+        let block = Pattern::from_rle(Rle::new("2o$2o!")?)?;
+        let object_class = block.object_class(&mut stay, 10)?;
+        assert_eq!(object_class, Some(ApgCode::new("xs4_33")?.object_class()?));
+        assert_eq!(Pattern::default().object_class(&mut stay, 10)?, None);
+        Ok(())
+    }
+
+    #[test]
+    fn pattern_from_grid() -> Result<(), Box<dyn Error>> {
+        let glider = Pattern::from_rle(Rle::new("bo$2bo$3o!")?)?;
+        let grid = [vec![0, 1, 0], vec![0, 0, 1], vec![1, 1, 1]];
+        assert_eq!(Pattern::from_grid(&grid), glider);
+        let bools = grid
+            .clone()
+            .map(|row| row.into_iter().map(|state| state == 1).collect());
+        assert_eq!(Pattern::from_bools(&bools), glider);
+        assert_eq!(
+            Pattern::from_fn(3, 3, |x, y| grid[y as usize][x as usize]),
+            glider
+        );
+
+        let multistate = Pattern::<u16>::from_grid(&[vec![], vec![300, 0, 2]]);
+        assert_eq!(
+            multistate.cells,
+            [((0, 1), 300), ((2, 1), 2)].map(|(position, state)| CellData { position, state })
+        );
+        assert!(Pattern::from_fn(0, 5, |_, _| 1_u8).cells.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn pattern_to_text() -> Result<(), Box<dyn Error>> {
+        let pattern = Pattern::from_rle(Rle::new("#CXRLE Pos=-2,5\n2o$obo$2bo$o!")?)?;
+        assert_eq!(pattern.to_ascii('O', '.')?, "OO.\nO.O\n..O\nO..\n");
+        assert_eq!(pattern.to_blocks()?, "█▀▄\n▄ ▀\n");
+        let pattern = Pattern::from_rle(Rle::new_with_options(
+            "3o!",
+            ParseOptions::new().include_dead(true),
+        )?)?;
+        assert_eq!(pattern.to_blocks()?, "▀▀▀\n");
+        assert_eq!(Pattern::<u8>::default().to_ascii('O', '.')?, "");
+        assert_eq!(Pattern::<u8>::default().to_blocks()?, "");
+        assert_eq!(
+            Pattern::<u8>::default().to_blocks_in((0, 0, 1, 2))?,
+            "  \n  \n"
+        );
+        assert_eq!(pattern.to_blocks_in((1, 0, 0, 0))?, "");
+
+        let corner = PatternBuilder::new().build([CellData::from((i64::MAX, i64::MAX))])?;
+        assert_eq!(corner.to_ascii('O', '.')?, "O\n");
+        assert_eq!(corner.to_blocks()?, "▀\n");
+        assert_eq!(corner.to_wechsler()?, "1");
+        let far = PatternBuilder::new()
+            .build([(i64::MIN, i64::MIN), (i64::MAX, i64::MAX)].map(CellData::from))?;
+        assert!(far.to_ascii('O', '.').is_err());
+        assert!(far.to_blocks().is_err());
+        assert!(far.to_wechsler().is_err());
+        assert!(corner.to_blocks_in((0, 0, 1 << 12, 1 << 12)).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn pattern_trim_crop() -> Result<(), Box<dyn Error>> {
+        let mut pattern = Pattern::from_rle(Rle::new("#CXRLE Pos=-3,-2\nbo$2bo$3o!")?)?;
+        let original = pattern.clone();
+        pattern.crop((-3, -1, -2, 0));
+        assert_eq!(pattern.cells, [(-3, 0), (-2, 0)].map(CellData::from));
+        pattern.crop((5, 5, 4, 4));
+        assert!(pattern.cells.is_empty());
+        assert_eq!(pattern.trim()?, None);
+
+        let mut pattern = original.clone();
+        assert_eq!(pattern.trim()?, Some((-3, -2)));
+        assert_eq!(pattern.trim()?, Some((0, 0)));
+        assert_eq!(pattern.bounding_box(), Some((0, 0, 2, 2)));
+        assert_eq!(
+            pattern.cells,
+            Pattern::from_rle(Rle::new("bo$2bo$3o!")?)?.cells
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn pattern_edit() -> Result<(), Box<dyn Error>> {
+        let mut pattern = Pattern::from_rle(Rle::new_with_options(
+            "#N Block\nx = 3, y = 2, rule = 23/3/3\nAB$2A!",
+            ParseOptions::new().include_dead(true),
+        )?)?;
+        pattern.set_cell((1, 0), 2);
+        pattern.set_cell((5, 5), 1);
+        pattern.clear_cell((5, 5));
+        pattern.clear_cell((9, 9));
+        assert_eq!(pattern.cells.len(), 6);
+
+        let glider = Pattern::from_rle(Rle::new("bo$2bo$3o!")?)?;
+        pattern.paste(&glider, -1, 0)?;
+        pattern.paste(
+            &Pattern::from_rle(Rle::new_with_options(
+                "3b!",
+                ParseOptions::new().include_dead(true),
+            )?)?,
+            0,
+            0,
+        )?;
+        let original = pattern.clone();
+        let error = pattern.paste(&glider, i64::MAX, 0).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidInput);
+        assert_eq!(pattern, original);
+        pattern.erase_rect((1, 2, 1, 2));
+        pattern.sort();
+        let mut rle = Vec::new();
+        pattern.write_rle(&mut rle)?;
+        let parsed = Pattern::from_rle(Rle::new(rle.as_slice())?)?;
+        assert_eq!(parsed.name.as_deref(), Some("Block"));
+        assert_eq!(parsed.to_ascii('o', '.')?, ".oo\n.oo\noo.\n");
+        let states = parsed.cells.iter().map(|cell| cell.state);
+        assert_eq!(states.collect::<Vec<_>>(), [1, 2, 1, 1, 1, 1]);
+        Ok(())
+    }
+
+    #[test]
+    fn pattern_two_state() -> Result<(), Box<dyn Error>> {
+        let pattern = Pattern::from_rle(Rle::new_with_options(
+            "x = 3, y = 2, rule = 23/3/3\n.A$BA!",
+            ParseOptions::new().include_dead(true),
+        )?)?;
+        let mut output = Vec::new();
+        let errors = [
+            pattern.write_plaintext(&mut output),
+            pattern.write_life105(&mut output),
+            pattern.write_life106(&mut output),
+        ];
+        for error in errors {
+            let error = error.unwrap_err();
+            assert_eq!(error.kind(), ErrorKind::InvalidInput);
+            assert!(error.to_string().starts_with("state 2"));
+        }
+        assert!(output.is_empty());
+
+        let pattern = pattern.map_states(|state| u8::from(state == 1));
+        assert_eq!(pattern.cells.len(), 2);
+        pattern.write_life106(&mut output)?;
+        assert_eq!(output, b"#Life 1.06\n1 0\n1 1\n");
+        Ok(())
+    }
+}
@@ -1,22 +1,246 @@
 //! A parser for [Plaintext](https://www.conwaylife.com/wiki/Plaintext) format.
 
-use crate::{Coordinates, Input};
+use crate::{
+    extend_bounding_box, input::SharedIoError, BoundingBox, CellData, Convention, Coordinates,
+    Input, ParseOptions,
+};
 use displaydoc::Display;
-use std::io::{BufReader, Error as IoError, Read};
+use std::{
+    fmt::{self, Debug, Formatter},
+    io::{BufReader, Error as IoError, Read, Write},
+    vec::IntoIter,
+};
 use thiserror::Error;
 
 /// Errors that can be returned when parsing a Plaintext file.
-#[derive(Debug, Error, Display)]
+#[derive(Clone, Debug, Eq, Error, Display, PartialEq)]
 pub enum Error {
     /// Unexpected character: {0}.
     UnexpectedChar(char),
+    /// Coordinates out of range in the convention: {0:?}.
+    CoordinateOverflow(Coordinates),
     /// Error when reading from input: {0}.
-    IoError(#[from] IoError),
+    IoError(#[from] SharedIoError),
+}
+
+impl From<IoError> for Error {
+    fn from(error: IoError) -> Self {
+        Error::IoError(error.into())
+    }
+}
+
+/// The characters that represent living and dead cells in a Plaintext body,
+/// set by [`Plaintext::with_glyphs`].
+///
+/// Only ASCII characters can be glyphs; other bytes are ignored. A character in both sets
+/// is a living cell. Whitespaces that are not dead glyphs are skipped,
+/// and other characters are [`Error::UnexpectedChar`].
+///
+/// # Example
+///
+/// ```rust
+/// use ca_formats::plaintext::{Glyphs, Plaintext};
+///
+/// let glyphs = Glyphs::new(b"o#", b"- ");
+/// let glider = Plaintext::new(" o\n--#\n###").unwrap().with_glyphs(glyphs);
+/// let cells = glider.map(|cell| cell.unwrap()).collect::<Vec<_>>();
+/// assert_eq!(cells, vec![(1, 0), (2, 1), (0, 2), (1, 2), (2, 2)]);
+/// ```
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub struct Glyphs {
+    /// Bit set of the ASCII characters for living cells.
+    alive: u128,
+    /// Bit set of the ASCII characters for dead cells.
+    dead: u128,
+}
+
+impl Glyphs {
+    /// The glyphs of the Plaintext format: `O` and `*` for living cells, and `.` for dead cells.
+    pub const STANDARD: Self = Self::new(b"O*", b".");
+
+    /// Glyphs often seen in hand-typed patterns: `O`, `o`, `*`, `#`, `X` and `x`
+    /// for living cells, and `.`, `-`, `_` and space for dead cells.
+    pub const LENIENT: Self = Self::new(b"Oo*#Xx", b".-_ ");
+
+    /// Creates glyph sets from the characters for living cells and dead cells.
+    pub const fn new(alive: &[u8], dead: &[u8]) -> Self {
+        Self {
+            alive: bit_set(alive),
+            dead: bit_set(dead),
+        }
+    }
+
+    /// The kind of a character in a cell line.
+    pub const fn classify(self, c: u8) -> CharKind {
+        match self.state(c) {
+            Some(1) => CharKind::Alive,
+            Some(_) => CharKind::Dead,
+            None if c.is_ascii_whitespace() => CharKind::Whitespace,
+            None => CharKind::Unexpected,
+        }
+    }
+
+    /// The state of the cell represented by a character, if it is a glyph.
+    const fn state(self, c: u8) -> Option<u8> {
+        if c >= 128 {
+            None
+        } else if self.alive & (1 << c) != 0 {
+            Some(1)
+        } else if self.dead & (1 << c) != 0 {
+            Some(0)
+        } else {
+            None
+        }
+    }
+}
+
+impl Default for Glyphs {
+    fn default() -> Self {
+        Self::STANDARD
+    }
+}
+
+/// The bit set of the ASCII characters in a string.
+const fn bit_set(chars: &[u8]) -> u128 {
+    let mut set = 0;
+    let mut i = 0;
+    while i < chars.len() {
+        if chars[i] < 128 {
+            set |= 1 << chars[i];
+        }
+        i += 1;
+    }
+    set
+}
+
+/// The kind of a character in a Plaintext file, returned by [`Glyphs::classify`]
+/// and [`Line::chars`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub enum CharKind {
+    /// A character in a comment line.
+    Comment,
+    /// A living cell.
+    Alive,
+    /// A dead cell.
+    Dead,
+    /// A whitespace that is skipped.
+    Whitespace,
+    /// A character that is neither a glyph nor a whitespace,
+    /// which is an [`Error::UnexpectedChar`] for the parser.
+    Unexpected,
+}
+
+/// The kind of a line in a Plaintext file.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub enum LineKind {
+    /// A comment line, which starts with `!`.
+    Comment,
+    /// A line of cells.
+    Cells,
+}
+
+/// A line of a Plaintext file, returned by [`Lines`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub struct Line<'a> {
+    /// Line number, starting from `1`.
+    pub number: usize,
+    /// Whether it is a comment line or a line of cells.
+    pub kind: LineKind,
+    /// The content of the line, without the line break.
+    pub text: &'a [u8],
+}
+
+impl<'a> Line<'a> {
+    /// The kind of each byte in the line, together with its column in bytes, starting from `0`.
+    ///
+    /// All the bytes of a comment line are [`CharKind::Comment`].
+    pub fn chars(&self, glyphs: Glyphs) -> impl Iterator<Item = (usize, CharKind)> + 'a {
+        let kind = self.kind;
+        self.text.iter().enumerate().map(move |(column, &c)| {
+            let char_kind = match kind {
+                LineKind::Comment => CharKind::Comment,
+                LineKind::Cells => glyphs.classify(c),
+            };
+            (column, char_kind)
+        })
+    }
+}
+
+/// A low-level iterator over the [`Line`]s of a Plaintext file.
+///
+/// It only tells comment lines from lines of cells, as the parser does,
+/// so that editors can implement highlighting and folding.
+/// The characters in a line are classified by [`Line::chars`].
+///
+/// # Example
+///
+/// ```rust
+/// use ca_formats::plaintext::{CharKind, Glyphs, LineKind, Lines};
+///
+/// let mut lines = Lines::new("!Name: Blinker\r\nOxO\n");
+/// let comment = lines.next().unwrap();
+/// assert_eq!((comment.kind, comment.text), (LineKind::Comment, &b"!Name: Blinker"[..]));
+///
+/// let cells = lines.next().unwrap();
+/// let kinds = cells.chars(Glyphs::STANDARD).map(|(_, kind)| kind);
+/// assert_eq!(
+///     kinds.collect::<Vec<_>>(),
+///     [CharKind::Alive, CharKind::Unexpected, CharKind::Alive]
+/// );
+/// assert_eq!(lines.next(), None);
+/// ```
+#[must_use]
+#[derive(Clone, Debug)]
+pub struct Lines<'a> {
+    /// The rest of the file.
+    rest: Option<&'a [u8]>,
+    /// Number of the last line returned.
+    number: usize,
+}
+
+impl<'a> Lines<'a> {
+    /// Creates a new iterator from the content of a Plaintext file.
+    pub fn new<B: AsRef<[u8]> + ?Sized>(text: &'a B) -> Self {
+        let text = text.as_ref();
+        Lines {
+            rest: (!text.is_empty()).then_some(text),
+            number: 0,
+        }
+    }
+}
+
+impl<'a> Iterator for Lines<'a> {
+    type Item = Line<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let rest = self.rest?;
+        let (mut text, rest) = match rest.iter().position(|&c| c == b'\n') {
+            Some(end) => (&rest[..end], &rest[end + 1..]),
+            None => (rest, &rest[rest.len()..]),
+        };
+        self.rest = (!rest.is_empty()).then_some(rest);
+        if let [line @ .., b'\r'] = text {
+            text = line;
+        }
+        self.number += 1;
+        let kind = if text.starts_with(b"!") {
+            LineKind::Comment
+        } else {
+            LineKind::Cells
+        };
+        Some(Line {
+            number: self.number,
+            kind,
+            text,
+        })
+    }
 }
 
 /// A parser for [Plaintext](https://www.conwaylife.com/wiki/Plaintext) format.
 ///
 /// As an iterator, it iterates over the living cells.
+/// The cells are returned in row-major order, i.e., sorted by the y coordinate,
+/// and then by the x coordinate.
 ///
 /// # Examples
 ///
@@ -51,6 +275,9 @@ pub enum Error {
 #[must_use]
 #[derive(Debug)]
 pub struct Plaintext<I: Input> {
+    /// Comment lines before the body.
+    comments: Vec<String>,
+
     /// An iterator over lines of a Plaintext file.
     lines: I::Lines,
 
@@ -59,26 +286,234 @@ pub struct Plaintext<I: Input> {
 
     /// Coordinates of the current cell.
     position: Coordinates,
+
+    /// Convention of the returned coordinates.
+    convention: Convention,
+
+    /// Characters for living and dead cells.
+    glyphs: Glyphs,
+
+    /// Whether [`cells`](Plaintext::cells) also returns the dead cells.
+    include_dead: bool,
 }
 
 impl<I: Input> Plaintext<I> {
     /// Creates a new parser instance from input.
     pub fn new(input: I) -> Result<Self, Error> {
         let mut lines = input.lines();
+        let mut comments = Vec::new();
         let mut current_line = None;
         for item in &mut lines {
             let line = I::line(item)?;
-            if !line.as_ref().starts_with('!') {
+            if line.as_ref().starts_with(b"!") {
+                comments.push(String::from_utf8_lossy(line.as_ref()).into_owned());
+            } else {
                 current_line = Some(I::bytes(line));
                 break;
             }
         }
         Ok(Self {
+            comments,
             lines,
             current_line,
             position: (0, 0),
+            convention: Convention::default(),
+            glyphs: Glyphs::default(),
+            include_dead: false,
         })
     }
+
+    /// Creates a new parser instance from input with the [`ParseOptions`]
+    /// that make sense for this format: the convention, [`Glyphs::LENIENT`]
+    /// if the options are lenient, and whether [`cells`](Plaintext::cells)
+    /// includes the dead cells.
+    pub fn new_with_options(input: I, options: ParseOptions) -> Result<Self, Error> {
+        let mut plaintext = Self::new(input)?;
+        plaintext.convention = options.convention;
+        plaintext.include_dead = options.include_dead;
+        if options.lenient {
+            plaintext.glyphs = Glyphs::LENIENT;
+        }
+        Ok(plaintext)
+    }
+
+    /// Comment lines before the body, e.g., `!Name: Glider`.
+    pub fn comments(&self) -> &[String] {
+        &self.comments
+    }
+
+    /// Use other characters for living and dead cells, e.g., [`Glyphs::LENIENT`].
+    ///
+    /// Comment lines still start with `!`.
+    pub fn with_glyphs(mut self, glyphs: Glyphs) -> Self {
+        self.glyphs = glyphs;
+        self
+    }
+
+    /// Turns the parser into an iterator over [`CellData`], which also returns
+    /// the dead cells, i.e., cells with state `0`, if `include` is `true`.
+    ///
+    /// Since Plaintext files have no header line, only the dead cells written
+    /// explicitly, e.g., as `.`, are returned. Dead cells omitted at the end of a line are not.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use ca_formats::plaintext::Plaintext;
+    ///
+    /// let blinker = Plaintext::new("...\nOOO\n...").unwrap().include_dead(true);
+    /// let states = blinker.map(|cell| cell.unwrap().state).collect::<Vec<_>>();
+    /// assert_eq!(states, vec![0, 0, 0, 1, 1, 1, 0, 0, 0]);
+    /// ```
+    pub fn include_dead(self, include: bool) -> Cells<I> {
+        Cells {
+            plaintext: self,
+            include_dead: include,
+        }
+    }
+
+    /// Turns the parser into an iterator over [`CellData`], which also returns
+    /// the dead cells if [`ParseOptions::include_dead`] was set in
+    /// [`new_with_options`](Plaintext::new_with_options). See [`include_dead`](Plaintext::include_dead).
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use ca_formats::{plaintext::Plaintext, ParseOptions};
+    ///
+    /// let options = ParseOptions::new().include_dead(true);
+    /// let blinker = Plaintext::new_with_options(".O\n.O", options).unwrap();
+    /// let states = blinker.cells().map(|cell| cell.unwrap().state).collect::<Vec<_>>();
+    /// assert_eq!(states, vec![0, 1, 0, 1]);
+    /// ```
+    pub fn cells(self) -> Cells<I> {
+        let include = self.include_dead;
+        self.include_dead(include)
+    }
+
+    /// The next cell, skipping the dead cells unless `include_dead` is `true`.
+    fn next_cell(&mut self, include_dead: bool) -> Option<Result<CellData, Error>> {
+        loop {
+            if let Some(c) = self.current_line.as_mut().and_then(Iterator::next) {
+                let state = match self.glyphs.state(c) {
+                    Some(1) => 1,
+                    Some(_) if include_dead => 0,
+                    Some(_) => {
+                        self.position.0 += 1;
+                        continue;
+                    }
+                    None if c.is_ascii_whitespace() => continue,
+                    None => return Some(Err(Error::UnexpectedChar(char::from(c)))),
+                };
+                let cell = self.position;
+                self.position.0 += 1;
+                return Some(match self.convention.apply(cell) {
+                    Some(position) => Ok(CellData { position, state }),
+                    None => Err(Error::CoordinateOverflow(cell)),
+                });
+            } else if let Err(e) = self.next_line()? {
+                return Some(Err(e));
+            }
+        }
+    }
+
+    /// Move to the next line, skipping comment lines.
+    ///
+    /// Returns `None` at the end of the input.
+    fn next_line(&mut self) -> Option<Result<(), Error>> {
+        if let Some(bytes) = self.current_line.take() {
+            I::recycle(&mut self.lines, bytes);
+        }
+        let item = self.lines.next()?;
+        Some(I::line(item).map_err(Error::from).map(|line| {
+            if line.as_ref().starts_with(b"!") {
+                I::recycle(&mut self.lines, I::bytes(line));
+            } else {
+                self.position.0 = 0;
+                self.position.1 += 1;
+                self.current_line = Some(I::bytes(line));
+            }
+        }))
+    }
+
+    /// Compute the bounding box of the remaining living cells line by line,
+    /// without constructing the [`CellData`] of each cell.
+    ///
+    /// The convention is applied. Returns `Ok(None)` if there is no living cell.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use ca_formats::plaintext::Plaintext;
+    ///
+    /// let glider = Plaintext::new("...\n.O.\n..O\nOOO").unwrap();
+    /// assert_eq!(glider.bounding_box().unwrap(), Some((0, 1, 2, 3)));
+    /// ```
+    pub fn bounding_box(mut self) -> Result<Option<BoundingBox>, Error> {
+        let mut bounding_box = None;
+        loop {
+            if let Some(bytes) = self.current_line.as_mut() {
+                let (mut x, y) = self.position;
+                let mut row = None;
+                for c in bytes {
+                    match self.glyphs.state(c) {
+                        Some(1) => {
+                            row = Some((row.map_or(x, |(start, _)| start), x));
+                            x += 1;
+                        }
+                        Some(_) => x += 1,
+                        None if c.is_ascii_whitespace() => (),
+                        None => return Err(Error::UnexpectedChar(char::from(c))),
+                    }
+                }
+                if let Some((start, end)) = row {
+                    for cell in [(start, y), (end, y)] {
+                        let position = self
+                            .convention
+                            .apply(cell)
+                            .ok_or(Error::CoordinateOverflow(cell))?;
+                        extend_bounding_box(&mut bounding_box, position);
+                    }
+                }
+            }
+            match self.next_line() {
+                Some(result) => result?,
+                None => return Ok(bounding_box),
+            }
+        }
+    }
+
+    /// Count the remaining living cells, without constructing the [`CellData`] of each cell.
+    ///
+    /// This is faster than [`count`](Iterator::count) when only the population is needed.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use ca_formats::plaintext::Plaintext;
+    ///
+    /// let glider = Plaintext::new(".O.\n..O\nOOO").unwrap();
+    /// assert_eq!(glider.population().unwrap(), 5);
+    /// ```
+    pub fn population(mut self) -> Result<usize, Error> {
+        let mut population = 0;
+        loop {
+            if let Some(bytes) = self.current_line.as_mut() {
+                for c in bytes {
+                    match self.glyphs.state(c) {
+                        Some(1) => population += 1,
+                        Some(_) => (),
+                        None if c.is_ascii_whitespace() => (),
+                        None => return Err(Error::UnexpectedChar(char::from(c))),
+                    }
+                }
+            }
+            match self.next_line() {
+                Some(result) => result?,
+                None => return Ok(population),
+            }
+        }
+    }
 }
 
 impl<I, L> Plaintext<I>
@@ -88,7 +523,9 @@ where
 {
     /// Parse the remaining unparsed lines as a new Plaintext.
     pub fn remains(self) -> Result<Plaintext<L>, Error> {
-        Plaintext::new(self.lines)
+        let mut plaintext = Plaintext::new(self.lines)?.with_glyphs(self.glyphs);
+        plaintext.convention = self.convention;
+        Ok(plaintext)
     }
 }
 
@@ -106,9 +543,13 @@ where
 {
     fn clone(&self) -> Self {
         Self {
+            comments: self.comments.clone(),
             lines: self.lines.clone(),
             current_line: self.current_line.clone(),
             position: self.position,
+            convention: self.convention,
+            glyphs: self.glyphs,
+            include_dead: self.include_dead,
         }
     }
 }
@@ -118,40 +559,202 @@ impl<I: Input> Iterator for Plaintext<I> {
     type Item = Result<Coordinates, Error>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        loop {
-            if let Some(c) = self.current_line.as_mut().and_then(Iterator::next) {
-                match c {
-                    b'O' | b'*' => {
-                        let cell = self.position;
-                        self.position.0 += 1;
-                        return Some(Ok(cell));
-                    }
-                    b'.' => self.position.0 += 1,
-                    _ if c.is_ascii_whitespace() => continue,
-                    _ => return Some(Err(Error::UnexpectedChar(char::from(c)))),
-                }
-            } else if let Some(item) = self.lines.next() {
-                match I::line(item) {
-                    Ok(line) => {
-                        if line.as_ref().starts_with('!') {
-                            continue;
-                        } else {
-                            self.position.0 = 0;
-                            self.position.1 += 1;
-                            self.current_line = Some(I::bytes(line));
-                        }
-                    }
-                    Err(e) => {
-                        return Some(Err(Error::IoError(e)));
-                    }
-                }
-            } else {
-                return None;
+        self.next_cell(false)
+            .map(|cell| cell.map(|cell| cell.position))
+    }
+}
+
+/// An iterator over cells in a Plaintext file, returned by [`Plaintext::include_dead`].
+#[must_use]
+pub struct Cells<I: Input> {
+    /// The parser.
+    plaintext: Plaintext<I>,
+
+    /// Whether to return the dead cells.
+    include_dead: bool,
+}
+
+impl<I: Input> Debug for Cells<I>
+where
+    Plaintext<I>: Debug,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Cells")
+            .field("plaintext", &self.plaintext)
+            .field("include_dead", &self.include_dead)
+            .finish()
+    }
+}
+
+impl<I: Input> Clone for Cells<I>
+where
+    I::Lines: Clone,
+    I::Bytes: Clone,
+{
+    fn clone(&self) -> Self {
+        Self {
+            plaintext: self.plaintext.clone(),
+            include_dead: self.include_dead,
+        }
+    }
+}
+
+/// An iterator over living cells, and dead cells if they are included, in a Plaintext file.
+impl<I: Input> Iterator for Cells<I> {
+    type Item = Result<CellData, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.plaintext.next_cell(self.include_dead)
+    }
+}
+
+/// An iterator over the patterns in a file containing many Plaintext patterns,
+/// e.g., a `.cells` collection.
+///
+/// A pattern ends when a comment line, i.e., a line starting with `!`, appears after its body.
+/// Blank lines at the start and the end of each pattern are skipped, but blank lines
+/// inside a body are kept as empty rows. Thus patterns that are only separated by blank lines
+/// are read as a single pattern.
+///
+/// The lines of each pattern are read and buffered before the pattern is returned.
+/// Lines that are not valid UTF-8 are decoded lossily.
+///
+/// # Example
+///
+/// ```rust
+/// use ca_formats::plaintext::PlaintextStream;
+///
+/// const COLLECTION: &str = "!Name: Glider\n.O.\n..O\nOOO\n\n!Name: Blinker\nOOO\n";
+///
+/// let mut stream = PlaintextStream::new(COLLECTION);
+/// let glider = stream.next().unwrap().unwrap();
+/// assert_eq!(glider.comments(), ["!Name: Glider"]);
+/// assert_eq!(glider.count(), 5);
+///
+/// let blinker = stream.next().unwrap().unwrap();
+/// assert_eq!(blinker.comments(), ["!Name: Blinker"]);
+/// assert_eq!(blinker.count(), 3);
+/// assert!(stream.next().is_none());
+/// ```
+#[must_use]
+#[derive(Debug)]
+pub struct PlaintextStream<I: Input> {
+    /// An iterator over lines of the file.
+    lines: I::Lines,
+
+    /// The first comment line of the next pattern, which has been read.
+    next_comment: Option<String>,
+}
+
+impl<I: Input> PlaintextStream<I> {
+    /// Creates a new stream from input.
+    pub fn new(input: I) -> Self {
+        Self {
+            lines: input.lines(),
+            next_comment: None,
+        }
+    }
+}
+
+impl<R: Read> PlaintextStream<BufReader<R>> {
+    /// Creates a new stream from something that implements [`Read`] trait, e.g., a [`File`](std::fs::File).
+    pub fn new_from_file(file: R) -> Self {
+        Self::new(BufReader::new(file))
+    }
+}
+
+impl<I: Input> Clone for PlaintextStream<I>
+where
+    I::Lines: Clone,
+{
+    fn clone(&self) -> Self {
+        Self {
+            lines: self.lines.clone(),
+            next_comment: self.next_comment.clone(),
+        }
+    }
+}
+
+/// An iterator over the patterns in a Plaintext file.
+impl<I: Input> Iterator for PlaintextStream<I> {
+    type Item = Result<Plaintext<IntoIter<String>>, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut lines = self.next_comment.take().into_iter().collect::<Vec<_>>();
+        let mut in_body = false;
+        while let Some(item) = self.lines.next() {
+            let line = match I::line(item) {
+                Ok(line) => line,
+                Err(e) => return Some(Err(Error::from(e))),
+            };
+            let bytes = line.as_ref();
+            let text = String::from_utf8_lossy(bytes).into_owned();
+            let is_comment = bytes.starts_with(b"!");
+            let is_blank = bytes.iter().all(u8::is_ascii_whitespace);
+            I::recycle(&mut self.lines, I::bytes(line));
+            if is_comment && in_body {
+                self.next_comment = Some(text);
+                break;
+            } else if in_body || !is_blank {
+                in_body |= !is_comment;
+                lines.push(text);
             }
         }
+        if !in_body {
+            return None;
+        }
+        while lines.last().is_some_and(|line| line.trim().is_empty()) {
+            lines.pop();
+        }
+        Some(Plaintext::new(lines.into_iter()))
     }
 }
 
+/// Writes living cells in Plaintext format.
+///
+/// The cells can be given in any order, and duplicated cells are ignored.
+/// Since the format does not record the position of the pattern, the cells are
+/// translated so that the upper left corner of the bounding box is `(0, 0)`.
+///
+/// Dead cells at the end of each line are omitted.
+///
+/// # Example
+///
+/// ```rust
+/// use ca_formats::plaintext::write_plaintext;
+///
+/// let mut plaintext = Vec::new();
+/// write_plaintext(&mut plaintext, [(2, 1), (3, 2), (1, 3), (2, 3), (3, 3)]).unwrap();
+/// assert_eq!(plaintext, b".O\n..O\nOOO\n");
+/// ```
+pub fn write_plaintext<W, C>(mut writer: W, cells: C) -> Result<(), IoError>
+where
+    W: Write,
+    C: IntoIterator<Item = Coordinates>,
+{
+    let mut cells = cells.into_iter().collect::<Vec<_>>();
+    cells.sort_by_key(|&(x, y)| (y, x));
+    cells.dedup();
+    let x_min = cells.iter().map(|&(x, _)| x).min().unwrap_or_default();
+    let mut y = cells.first().map_or(0, |&(_, y)| y);
+    let mut line = Vec::new();
+    for (cell_x, cell_y) in cells {
+        while y < cell_y {
+            line.push(b'\n');
+            writer.write_all(&line)?;
+            line.clear();
+            y += 1;
+        }
+        line.resize((cell_x - x_min) as usize, b'.');
+        line.push(b'O');
+    }
+    if !line.is_empty() {
+        line.push(b'\n');
+        writer.write_all(&line)?;
+    }
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -172,4 +775,181 @@ OOO";
         assert_eq!(cells, vec![(1, 0), (2, 1), (0, 2), (1, 2), (2, 2)]);
         Ok(())
     }
+
+    #[test]
+    fn plaintext_lines() -> Result<(), Error> {
+        let lines = vec!["!Name: Glider", ".O.", "..O", "OOO"];
+
+        let glider = Plaintext::new(lines.iter())?;
+        let cells = glider.collect::<Result<Vec<_>, _>>()?;
+        assert_eq!(cells, vec![(1, 0), (2, 1), (0, 2), (1, 2), (2, 2)]);
+
+        let glider = Plaintext::new(lines.into_iter())?;
+        assert_eq!(glider.count(), 5);
+
+        let lines = vec![
+            String::from(".O."),
+            String::from("..O"),
+            String::from("OOO"),
+        ];
+        let glider = Plaintext::new(lines.iter())?;
+        assert_eq!(glider.count(), 5);
+        let glider = Plaintext::new(lines.into_iter())?;
+        assert_eq!(glider.count(), 5);
+        Ok(())
+    }
+
+    #[test]
+    fn plaintext_line_model() {
+        let lines = Lines::new("!Name: Glider\n!\n.O.\n\n#O*\r\n!end").collect::<Vec<_>>();
+        let kinds = lines.iter().map(|line| (line.number, line.kind));
+        assert_eq!(
+            kinds.collect::<Vec<_>>(),
+            [
+                (1, LineKind::Comment),
+                (2, LineKind::Comment),
+                (3, LineKind::Cells),
+                (4, LineKind::Cells),
+                (5, LineKind::Cells),
+                (6, LineKind::Comment),
+            ]
+        );
+        assert_eq!(lines[4].text, b"#O*");
+        let chars = lines[4].chars(Glyphs::LENIENT).collect::<Vec<_>>();
+        assert_eq!(
+            chars,
+            [
+                (0, CharKind::Alive),
+                (1, CharKind::Alive),
+                (2, CharKind::Alive)
+            ]
+        );
+        assert!(lines[0]
+            .chars(Glyphs::STANDARD)
+            .all(|(_, kind)| kind == CharKind::Comment));
+        assert_eq!(Glyphs::STANDARD.classify(b'\t'), CharKind::Whitespace);
+        assert_eq!(Glyphs::STANDARD.classify(b'.'), CharKind::Dead);
+        assert_eq!(Lines::new("").next(), None);
+    }
+
+    #[test]
+    fn plaintext_stream() -> Result<(), Error> {
+        const COLLECTION: &str = r"!Name: Glider
+!
+.O.
+..O
+OOO
+
+!Name: Beehive with tail
+
+.OO
+O..O
+.OO
+
+..O
+!Name: Blinker
+OOO
+";
+
+        let patterns = PlaintextStream::new(COLLECTION).collect::<Result<Vec<_>, _>>()?;
+        assert_eq!(patterns.len(), 3);
+        assert_eq!(patterns[0].comments(), ["!Name: Glider", "!"]);
+        assert_eq!(patterns[1].comments(), ["!Name: Beehive with tail"]);
+
+        let tail = patterns[1].clone().last().unwrap()?;
+        assert_eq!(tail, (2, 4));
+
+        let counts = patterns
+            .into_iter()
+            .map(Iterator::count)
+            .collect::<Vec<_>>();
+        assert_eq!(counts, vec![5, 7, 3]);
+
+        assert!(PlaintextStream::new("!Only comments\n\n").next().is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn plaintext_population() -> Result<(), Error> {
+        const GLIDER: &str = "!Name: Glider\n.O.\n!\n..*\nOOO";
+        assert_eq!(Plaintext::new(GLIDER)?.population()?, 5);
+
+        let mut partial = Plaintext::new(GLIDER)?;
+        partial.nth(1);
+        assert_eq!(partial.population()?, 3);
+
+        assert!(Plaintext::new("O?")?.population().is_err());
+
+        assert_eq!(Plaintext::new(GLIDER)?.bounding_box()?, Some((0, 0, 2, 2)));
+        assert_eq!(Plaintext::new("..\n..")?.bounding_box()?, None);
+        Ok(())
+    }
+
+    #[test]
+    fn plaintext_include_dead() -> Result<(), Error> {
+        const GLIDER: &str = "!Name: Glider\n.O\n..O\nOOO";
+
+        let cells = Plaintext::new(GLIDER)?
+            .include_dead(true)
+            .collect::<Result<Vec<_>, _>>()?;
+        let states = cells.iter().map(|c| c.state).collect::<Vec<_>>();
+        assert_eq!(states, vec![0, 1, 0, 0, 1, 1, 1, 1]);
+        assert_eq!(cells[3].position, (1, 1));
+
+        let cells = Plaintext::new(GLIDER)?
+            .include_dead(false)
+            .map(|c| c.map(|c| c.position))
+            .collect::<Result<Vec<_>, _>>()?;
+        assert_eq!(cells, vec![(1, 0), (2, 1), (0, 2), (1, 2), (2, 2)]);
+
+        let options = ParseOptions::new().include_dead(true);
+        let plaintext = Plaintext::new_with_options(GLIDER, options)?;
+        assert_eq!(plaintext.clone().count(), 5);
+        assert_eq!(plaintext.cells().count(), 8);
+        assert_eq!(Plaintext::new(GLIDER)?.cells().count(), 5);
+        Ok(())
+    }
+
+    #[test]
+    fn plaintext_glyphs() -> Result<(), Error> {
+        const GLIDER: &str = "!Name: Glider\n_o\n--x\n#O*\n\n- \u{e9}";
+
+        let mut glider = Plaintext::new(GLIDER)?.with_glyphs(Glyphs::LENIENT);
+        let cells = glider.by_ref().take(5).collect::<Result<Vec<_>, _>>()?;
+        assert_eq!(cells, vec![(1, 0), (2, 1), (0, 2), (1, 2), (2, 2)]);
+        assert!(matches!(glider.next(), Some(Err(Error::UnexpectedChar(_)))));
+
+        let glider = Plaintext::new(GLIDER)?.with_glyphs(Glyphs::LENIENT);
+        assert!(glider.population().is_err());
+        let glider = Plaintext::new("_o\n--x\n#O*")?.with_glyphs(Glyphs::LENIENT);
+        assert_eq!(glider.bounding_box()?, Some((0, 0, 2, 2)));
+
+        let cells = Plaintext::new("x x")?
+            .with_glyphs(Glyphs::new(b"x\xe9", b" "))
+            .include_dead(true)
+            .map(|c| c.map(|c| (c.position, c.state)))
+            .collect::<Result<Vec<_>, _>>()?;
+        assert_eq!(cells, vec![((0, 0), 1), ((1, 0), 0), ((2, 0), 1)]);
+        assert_eq!(Glyphs::default(), Glyphs::STANDARD);
+        assert!(matches!(
+            Plaintext::new("x")?.next(),
+            Some(Err(Error::UnexpectedChar('x')))
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn plaintext_write() -> Result<(), Error> {
+        let mut plaintext = Vec::new();
+        write_plaintext(&mut plaintext, [(0, 2), (-1, 0), (2, 2), (0, 2)])?;
+        assert_eq!(String::from_utf8(plaintext.clone()).unwrap(), "O\n\n.O.O\n");
+
+        let cells = Plaintext::new(plaintext.as_slice())?.collect::<Result<Vec<_>, _>>()?;
+        assert_eq!(cells, vec![(0, 0), (1, 2), (3, 2)]);
+
+        let mut plaintext = Vec::new();
+        write_plaintext(&mut plaintext, [])?;
+        assert!(plaintext.is_empty());
+        Ok(())
+    }
 }
@@ -0,0 +1,135 @@
+//! Multistate patterns split into bit-planes.
+//!
+//! Some multistate dumps write a pattern as several 2-state patterns, e.g., several RLEs
+//! in one file, where the `i`-th pattern is the `i`-th bit of the states.
+//! [`overlay`] combines such planes into one multistate pattern.
+
+use crate::{CellData, State};
+use displaydoc::Display;
+use std::{collections::BTreeMap, error::Error as StdError};
+use thiserror::Error;
+
+/// Errors that can be returned by [`overlay`].
+#[derive(Clone, Debug, Eq, Error, Display, PartialEq)]
+pub enum Error<E: StdError + 'static> {
+    /// Too many planes for the state type: {0}.
+    TooManyPlanes(usize),
+    /// {0}
+    Parser(#[source] E),
+}
+
+/// Overlays bit-planes into one multistate pattern.
+///
+/// A cell that is alive in the `i`-th plane, i.e., whose state is not `0`, sets the `i`-th bit
+/// of the state of the cell at the same position, starting from the lowest bit.
+/// Cells with state `0` in every plane are not returned.
+///
+/// The cells are returned in row-major order. Returns [`Error::TooManyPlanes`]
+/// if the states of the planes don't fit in the state type, e.g., more than 8 planes for `u8`.
+///
+/// # Example
+///
+/// ```rust
+/// use ca_formats::{planes::overlay, rle::RleStream, CellData};
+///
+/// const PLANES: &str = "#C bit 0\nx = 3, y = 1\nobo!\n#C bit 1\nx = 3, y = 1\n2o!";
+///
+/// let planes = RleStream::new(PLANES).collect::<Result<Vec<_>, _>>().unwrap();
+/// let cells: Vec<CellData> = overlay(planes).unwrap();
+/// assert_eq!(
+///     cells,
+///     [
+///         CellData { position: (0, 0), state: 3 },
+///         CellData { position: (1, 0), state: 2 },
+///         CellData { position: (2, 0), state: 1 },
+///     ]
+/// );
+/// ```
+pub fn overlay<S, T, E, P, C>(planes: C) -> Result<Vec<CellData<S>>, Error<E>>
+where
+    S: State,
+    T: State,
+    E: StdError + 'static,
+    P: IntoIterator<Item = Result<CellData<T>, E>>,
+    C: IntoIterator<Item = P>,
+{
+    let mut states = BTreeMap::new();
+    let mut count = 0;
+    for (i, plane) in planes.into_iter().enumerate() {
+        count = i + 1;
+        let bit = u32::try_from(i)
+            .ok()
+            .and_then(|i| 1_u32.checked_shl(i))
+            .ok_or(Error::TooManyPlanes(count))?;
+        for cell in plane {
+            let cell = cell.map_err(Error::Parser)?;
+            if cell.state.into() != 0 {
+                let (x, y) = cell.position;
+                *states.entry((y, x)).or_insert(0) |= bit;
+            }
+        }
+    }
+    let max = u32::try_from(count)
+        .ok()
+        .and_then(|count| 1_u32.checked_shl(count))
+        .map_or(u32::MAX, |shifted| shifted - 1);
+    if S::try_from(max).is_err() {
+        return Err(Error::TooManyPlanes(count));
+    }
+    Ok(states
+        .into_iter()
+        .map(|((y, x), state)| CellData {
+            position: (x, y),
+            state: S::try_from(state).unwrap_or_default(),
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{rle::Rle, ParseOptions};
+
+    #[test]
+    fn planes_overlay() -> Result<(), Box<dyn StdError>> {
+        let planes = [
+            Rle::new("o$bo!")?,
+            Rle::new("2o!")?,
+            Rle::new_with_options("b.A!", ParseOptions::new().include_dead(true))?,
+        ];
+        let cells = overlay::<u8, _, _, _, _>(planes)?;
+        assert_eq!(
+            cells,
+            [
+                CellData {
+                    position: (0, 0),
+                    state: 3
+                },
+                CellData {
+                    position: (1, 0),
+                    state: 2
+                },
+                CellData {
+                    position: (2, 0),
+                    state: 4
+                },
+                CellData {
+                    position: (1, 1),
+                    state: 1
+                },
+            ]
+        );
+
+        let planes = (0..9).map(|_| Rle::new("o!").unwrap());
+        assert_eq!(
+            overlay::<u8, _, _, _, _>(planes.clone()),
+            Err(Error::TooManyPlanes(9))
+        );
+        assert_eq!(overlay::<u16, _, _, _, _>(planes)?[0].state, 511);
+        assert!(matches!(
+            overlay::<u8, _, _, _, _>([Rle::new("2z!")?]),
+            Err(Error::Parser(_))
+        ));
+        Ok(())
+    }
+}
@@ -0,0 +1,195 @@
+//! Rendering patterns as images of the [`image`](https://crates.io/crates/image) crate,
+//! e.g., thumbnails for a pattern gallery.
+
+use crate::{crop::contains, rulefile::RuleFile, Pattern, State};
+use image::{Rgba, RgbaImage};
+
+/// Colors of the states of a rule.
+///
+/// The default palette is the palette of Golly for 2-state rules.
+/// See [`Palette::golly`].
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub struct Palette {
+    /// Color of the dead cells, i.e., the background.
+    pub background: Rgba<u8>,
+    /// Colors of the states `1`, `2`, and so on.
+    ///
+    /// States without a color use the last color, or white if there is none.
+    pub colors: Vec<Rgba<u8>>,
+}
+
+impl Palette {
+    /// A palette like the default colors of Golly, for rules with `state_count` states.
+    ///
+    /// The background is dark gray. Living cells are white in 2-state rules, and in rules
+    /// with more states, the colors are a gradient from red for state `1` to yellow for
+    /// the last state.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use ca_formats::render::Palette;
+    /// use image::Rgba;
+    ///
+    /// let palette = Palette::golly(4);
+    /// assert_eq!(
+    ///     palette.colors,
+    ///     [Rgba([255, 0, 0, 255]), Rgba([255, 128, 0, 255]), Rgba([255, 255, 0, 255])]
+    /// );
+    /// ```
+    pub fn golly(state_count: usize) -> Self {
+        let living = state_count.max(2) - 1;
+        let colors = if living == 1 {
+            vec![Rgba([255, 255, 255, 255])]
+        } else {
+            (0..living)
+                .map(|i| {
+                    let green = (i * 255 + (living - 1) / 2) / (living - 1);
+                    Rgba([255, green as u8, 0, 255])
+                })
+                .collect()
+        };
+        Palette {
+            background: Rgba([48, 48, 48, 255]),
+            colors,
+        }
+    }
+
+    /// The colors of a [`RuleFile`].
+    ///
+    /// States without a color in the rule file use the colors of [`Palette::golly`].
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use ca_formats::{render::Palette, rulefile::RuleFile};
+    /// use image::Rgba;
+    ///
+    /// let rule_file = RuleFile::parse("@RULE Wire\n@TABLE\nn_states:3\n@COLORS\n2 0 0 255").unwrap();
+    /// let palette = Palette::from_rule_file(&rule_file);
+    /// assert_eq!(palette.colors, [Rgba([255, 0, 0, 255]), Rgba([0, 0, 255, 255])]);
+    /// ```
+    pub fn from_rule_file(rule_file: &RuleFile) -> Self {
+        let last = rule_file.colors.keys().next_back().copied().unwrap_or(0);
+        let state_count = rule_file.states.unwrap_or(0).max(last + 1);
+        let mut palette = Palette::golly(state_count as usize);
+        for (&state, &[r, g, b]) in &rule_file.colors {
+            let color = Rgba([r, g, b, 255]);
+            match state {
+                0 => palette.background = color,
+                _ => palette.colors[state as usize - 1] = color,
+            }
+        }
+        palette
+    }
+
+    /// The color of a state.
+    pub fn color(&self, state: u32) -> Rgba<u8> {
+        match state {
+            0 => self.background,
+            _ => self
+                .colors
+                .get(state as usize - 1)
+                .or(self.colors.last())
+                .copied()
+                .unwrap_or(Rgba([255, 255, 255, 255])),
+        }
+    }
+}
+
+impl Default for Palette {
+    fn default() -> Self {
+        Palette::golly(2)
+    }
+}
+
+impl<S: State> Pattern<S> {
+    /// Renders the bounding box of the living cells as an image, where each cell is
+    /// a square of `scale` × `scale` pixels.
+    ///
+    /// The image is empty if there is no living cell, or if `scale` is `0`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the size of the image doesn't fit in `u32`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use ca_formats::{render::Palette, rle::Rle, Pattern};
+    /// use image::Rgba;
+    ///
+    /// let glider = Pattern::from_rle(Rle::new("bo$2bo$3o!").unwrap()).unwrap();
+    /// let image = glider.to_image(4, &Palette::default());
+    /// assert_eq!(image.dimensions(), (12, 12));
+    /// assert_eq!(image.get_pixel(0, 0), &Rgba([48, 48, 48, 255]));
+    /// assert_eq!(image.get_pixel(11, 11), &Rgba([255, 255, 255, 255]));
+    /// ```
+    #[cfg_attr(docs_rs, doc(cfg(feature = "image")))]
+    pub fn to_image(&self, scale: u32, palette: &Palette) -> RgbaImage {
+        let Some((x0, y0, x1, y1)) = self.bounding_box() else {
+            return RgbaImage::new(0, 0);
+        };
+        let size = |length: i64| {
+            u32::try_from(length)
+                .ok()
+                .and_then(|length| length.checked_mul(scale))
+                .expect("the image is too large")
+        };
+        let mut image =
+            RgbaImage::from_pixel(size(x1 - x0 + 1), size(y1 - y0 + 1), palette.background);
+        for cell in &self.cells {
+            let (x, y) = cell.position;
+            if !contains((x0, y0, x1, y1), (x, y)) {
+                continue;
+            }
+            let color = palette.color(cell.state.into());
+            let (left, top) = ((x - x0) as u32 * scale, (y - y0) as u32 * scale);
+            for dy in 0..scale {
+                for dx in 0..scale {
+                    image.put_pixel(left + dx, top + dy, color);
+                }
+            }
+        }
+        image
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rle::Rle;
+    use std::error::Error as StdError;
+
+    #[test]
+    fn render_palette() -> Result<(), Box<dyn StdError>> {
+        assert_eq!(Palette::golly(0), Palette::default());
+        assert_eq!(Palette::golly(3).colors.len(), 2);
+        assert_eq!(Palette::golly(3).color(2), Rgba([255, 255, 0, 255]));
+        assert_eq!(Palette::golly(3).color(7), Rgba([255, 255, 0, 255]));
+        let empty = Palette {
+            background: Rgba([0, 0, 0, 0]),
+            colors: Vec::new(),
+        };
+        assert_eq!(empty.color(1), Rgba([255, 255, 255, 255]));
+        let rule_file = RuleFile::parse("@RULE A\n@COLORS\n0 1 2 3\n2 4 5 6")?;
+        let palette = Palette::from_rule_file(&rule_file);
+        assert_eq!(palette.background, Rgba([1, 2, 3, 255]));
+        assert_eq!(palette.color(1), Rgba([255, 0, 0, 255]));
+        assert_eq!(palette.color(2), Rgba([4, 5, 6, 255]));
+
+        let pattern = Pattern::from_rle(Rle::new("x = 3, y = 2, rule = 23/3/3\nA.B$2.A!")?)?;
+        let image = pattern.to_image(1, &Palette::golly(3));
+        assert_eq!(image.dimensions(), (3, 2));
+        assert_eq!(image.get_pixel(0, 0), &Rgba([255, 0, 0, 255]));
+        assert_eq!(image.get_pixel(1, 0), &Rgba([48, 48, 48, 255]));
+        assert_eq!(image.get_pixel(2, 0), &Rgba([255, 255, 0, 255]));
+        assert_eq!(image.get_pixel(2, 1), &Rgba([255, 0, 0, 255]));
+        assert_eq!(pattern.to_image(0, &empty).dimensions(), (0, 0));
+        assert_eq!(
+            Pattern::<u8>::default().to_image(5, &empty).dimensions(),
+            (0, 0)
+        );
+        Ok(())
+    }
+}
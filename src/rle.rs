@@ -3,51 +3,261 @@
 //! It is basically the same as the original [RLE](https://www.conwaylife.com/wiki/Run_Length_Encoded)
 //! format, except that it supports up to 256 states, and a `#CXRLE` line.
 
-use crate::{CellData, Coordinates, Input};
-use lazy_regex::regex;
-use std::io::{BufReader, Error as IoError, Read};
+#[cfg(feature = "ca-rules")]
+use crate::rules::{ParseRuleError, Rule};
+use crate::{
+    extend_bounding_box,
+    input::{Normalized, ReaderLines, SharedIoError},
+    output::Sink,
+    scan::{parse_number, Scanner},
+    BoundingBox, CellData, Convention, Coordinates, Input, ParseOptions, RuleInfo, RuleOrigin,
+    State,
+};
+use displaydoc::Display;
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{btree_map, BTreeMap, VecDeque},
+    fmt,
+    io::{BufRead, BufReader, Error as IoError, ErrorKind, Read, Seek, Write},
+    mem,
+    ops::{Bound, RangeBounds},
+    vec::IntoIter,
+};
 use thiserror::Error;
 
 /// Errors that can be returned when parsing a RLE file.
-#[derive(Debug, Error)]
+#[derive(Clone, Debug, Eq, Error, PartialEq)]
 pub enum Error {
     #[error("Invalid state: {0}.")]
     InvalidState(String),
-    #[error("Invalid \"#CXRLE\" line: {0}.")]
-    InvalidCxrleLine(String),
-    #[error("Invalid header line: {0}.")]
-    InvalidHeaderLine(String),
+    #[error("Invalid \"#CXRLE\" line at column {1}: {0}.")]
+    InvalidCxrleLine(String, usize),
+    #[error("Invalid header line at column {1}: {0}.")]
+    InvalidHeaderLine(String, usize),
+    #[error("Invalid \"#P\" line at column {1}: {0}.")]
+    InvalidOffsetLine(String, usize),
+    #[error("Coordinates out of range after {0:?}.")]
+    CoordinateOverflow(Coordinates),
+    #[error("Run count too large at byte {0}.")]
+    RunCountOverflow(usize),
+    #[error("Rejected in strict mode: {0}")]
+    Strict(Warning),
+    #[error("More than {0} cells.")]
+    TooManyCells(u64),
     #[error("Error when reading from input: {0}.")]
-    IoError(#[from] IoError),
+    IoError(#[from] SharedIoError),
+}
+
+impl From<IoError> for Error {
+    fn from(error: IoError) -> Self {
+        Error::IoError(error.into())
+    }
+}
+
+/// Problems in an RLE file that are not errors, collected by [`Rle::warnings`].
+///
+/// They don't stop the parsing, but may be worth fixing, e.g., when checking
+/// patterns submitted to a database.
+#[derive(Clone, Debug, Eq, PartialEq, Hash, Display)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Warning {
+    /// Unknown comment directive: {0}.
+    UnknownDirective(String),
+    /// Duplicate "#CXRLE" line, only the last one is used: {0}.
+    DuplicateCxrle(String),
+    /// The header line declares a size of {declared:?}, but the size of the pattern is {actual:?}.
+    StaleHeader {
+        /// Width and height declared by the header line.
+        declared: (u64, u64),
+        /// Width and height of the pattern.
+        actual: (u64, u64),
+    },
+    /// Trailing characters after "!": {0}.
+    TrailingGarbage(String),
+    /// The body ends without a "!".
+    MissingTerminator,
+}
+
+/// Whether a line starts with one of the prefixes of comment lines.
+fn is_comment(line: &[u8], prefixes: &[String]) -> bool {
+    prefixes
+        .iter()
+        .any(|prefix| line.starts_with(prefix.as_bytes()))
+}
+
+/// Whether a comment line starts with a directive in the RLE format,
+/// i.e., `#C`, `#c`, `#N`, `#O`, `#P`, `#R` or `#r`.
+fn is_known_directive(line: &[u8]) -> bool {
+    line.get(1).is_some_and(|c| b"CcNOPRr".contains(c))
 }
 
 /// Data from the `#CXRLE` line, e.g., `#CXRLE Pos=0,-1377 Gen=3480106827776`.
 #[derive(Clone, Debug, Eq, PartialEq, Default, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct CxrleData {
     /// Coordinates of the upper left corner of the pattern.
     pub pos: Option<Coordinates>,
     /// Current generation.
     pub gen: Option<u64>,
+    /// Other `key=value` pairs, e.g., `Layer=2`.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "BTreeMap::is_empty")
+    )]
+    pub extra: BTreeMap<String, String>,
+}
+
+/// When the RLE writers write a `#CXRLE` line.
+///
+/// The cells are written relative to the upper left corner of the pattern.
+/// The `Pos` in the `#CXRLE` line keeps their coordinates, so that parsing the
+/// written RLE gives the same coordinates. Without it, the pattern is re-based so that
+/// the upper left corner is at `(0, 0)`.
+///
+/// # Example
+///
+/// ```rust
+/// use ca_formats::{
+///     rle::{CxrlePolicy, RleWriter},
+///     CellData,
+/// };
+///
+/// for (policy, expected) in [
+///     (CxrlePolicy::Auto, "#CXRLE Pos=2,3\nx = 1, y = 1\no!\n"),
+///     (CxrlePolicy::Never, "x = 1, y = 1\no!\n"),
+/// ] {
+///     let mut writer = RleWriter::new(Vec::new())
+///         .with_size(1, 1)
+///         .with_origin(2, 3)
+///         .with_cxrle_policy(policy);
+///     writer.write_cell(CellData::from((2, 3))).unwrap();
+///     assert_eq!(writer.finish().unwrap(), expected.as_bytes());
+/// }
+/// ```
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Default, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum CxrlePolicy {
+    /// Write a `#CXRLE` line if the upper left corner is not `(0, 0)`,
+    /// or if there is a generation.
+    #[default]
+    Auto,
+    /// Always write a `#CXRLE` line with the position, even at `(0, 0)`.
+    Always,
+    /// Never write a `#CXRLE` line. The pattern is re-based so that its upper left corner
+    /// is at `(0, 0)`, and the generation is dropped.
+    Never,
+}
+
+/// Writes the line in Golly's syntax, e.g., `#CXRLE Pos=0,-1377 Gen=3480106827776`,
+/// which can be parsed back to the same data.
+///
+/// # Example
+///
+/// ```rust
+/// use ca_formats::rle::CxrleData;
+///
+/// let cxrle_data = CxrleData {
+///     pos: Some((-1, -1)),
+///     gen: Some(4),
+///     ..Default::default()
+/// };
+/// assert_eq!(cxrle_data.to_string(), "#CXRLE Pos=-1,-1 Gen=4");
+/// ```
+impl fmt::Display for CxrleData {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("#CXRLE")?;
+        if let Some((x, y)) = self.pos {
+            write!(f, " Pos={},{}", x, y)?;
+        }
+        if let Some(gen) = self.gen {
+            write!(f, " Gen={}", gen)?;
+        }
+        for (key, value) in &self.extra {
+            write!(f, " {}={}", key, value)?;
+        }
+        Ok(())
+    }
 }
 
 /// Parse the `#CXRLE` line.
-fn parse_cxrle(line: &str) -> Option<CxrleData> {
-    let re = regex!(r"(?:Pos\s*=\s*(?P<x>-?\d+),\s*(?P<y>-?\d+))|(?:Gen\s*=\s*(?P<gen>\d+))");
+///
+/// `Pos=x,y` and `Gen=g` are searched anywhere in the line. Other `key=value` pairs
+/// that start a word are collected into [`CxrleData::extra`], and other text is ignored.
+/// Returns the column of a number that is too large.
+fn parse_cxrle(line: &[u8]) -> Result<CxrleData, usize> {
     let mut data = CxrleData::default();
-    for cap in re.captures_iter(line) {
-        if let Some(gen) = cap.name("gen") {
-            data.gen = Some(gen.as_str().parse().ok())?;
+    let mut scanner = Scanner::new(line);
+    scanner.eat(b"#CXRLE");
+    let mut word_start = true;
+    loop {
+        let mut attempt = scanner.clone();
+        if let Some([x, y]) = match_pos(&mut attempt) {
+            data.pos = Some((parse_number(x)?, parse_number(y)?));
+            scanner = attempt;
+            word_start = false;
+        } else if let Some(gen) = match_gen(&mut attempt) {
+            data.gen = Some(parse_number(gen)?);
+            scanner = attempt;
+            word_start = false;
+        } else if let Some((key, value)) = word_start.then(|| match_pair(&mut attempt)).flatten() {
+            data.extra.insert(key.to_owned(), value.to_owned());
+            scanner = attempt;
+            word_start = false;
         } else {
-            let x = cap["x"].parse().ok()?;
-            let y = cap["y"].parse().ok()?;
-            data.pos = Some((x, y));
+            word_start = scanner.peek().is_some_and(|c| c.is_ascii_whitespace());
+            if !scanner.advance() {
+                return Ok(data);
+            }
         }
     }
-    Some(data)
+}
+
+/// Match `([A-Za-z_]\w*)\s*=\s*([^\s=]+)` at the current position,
+/// where the key is neither `Pos` nor `Gen`.
+fn match_pair<'a>(scanner: &mut Scanner<'a>) -> Option<(&'a str, &'a str)> {
+    let key = scanner.identifier()?;
+    if key == "Pos" || key == "Gen" || !match_equal_sign(scanner) {
+        return None;
+    }
+    let value = scanner.word()?;
+    (!value.contains('=')).then_some((key, value))
+}
+
+/// Match `Pos\s*=\s*(-?\d+),\s*(-?\d+)` at the current position.
+fn match_pos<'a>(scanner: &mut Scanner<'a>) -> Option<[(&'a str, usize); 2]> {
+    if !(scanner.eat(b"Pos") && match_equal_sign(scanner)) {
+        return None;
+    }
+    let x = scanner.digits(true)?;
+    if !scanner.eat(b",") {
+        return None;
+    }
+    scanner.skip_whitespace();
+    Some([x, scanner.digits(true)?])
+}
+
+/// Match `Gen\s*=\s*(\d+)` at the current position.
+fn match_gen<'a>(scanner: &mut Scanner<'a>) -> Option<(&'a str, usize)> {
+    if !(scanner.eat(b"Gen") && match_equal_sign(scanner)) {
+        return None;
+    }
+    scanner.digits(false)
+}
+
+/// Match `\s*=\s*` at the current position.
+fn match_equal_sign(scanner: &mut Scanner) -> bool {
+    scanner.skip_whitespace();
+    let matched = scanner.eat(b"=");
+    scanner.skip_whitespace();
+    matched
 }
 
 /// Data from the header line, e.g., `x = 3, y = 3, rule = B3/S23`.
 #[derive(Clone, Debug, Eq, PartialEq, Default, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct HeaderData {
     /// Width of the pattern.
     pub x: u64,
@@ -55,20 +265,437 @@ pub struct HeaderData {
     pub y: u64,
     /// Rulestring.
     pub rule: Option<String>,
+    /// Other `key = value` pairs, e.g., `color = red` or `generation = 10`.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "BTreeMap::is_empty")
+    )]
+    pub extra: BTreeMap<String, String>,
+}
+
+/// Writes the line in Golly's syntax, e.g., `x = 3, y = 3, rule = B3/S23`,
+/// which can be parsed back to the same data.
+///
+/// # Example
+///
+/// ```rust
+/// use ca_formats::rle::HeaderData;
+///
+/// let header_data = HeaderData {
+///     x: 3,
+///     y: 3,
+///     rule: Some(String::from("B3/S23")),
+///     ..Default::default()
+/// };
+/// assert_eq!(header_data.to_string(), "x = 3, y = 3, rule = B3/S23");
+/// ```
+impl fmt::Display for HeaderData {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "x = {}, y = {}", self.x, self.y)?;
+        if let Some(rule) = &self.rule {
+            write!(f, ", rule = {}", rule)?;
+        }
+        for (key, value) in &self.extra {
+            write!(f, ", {} = {}", key, value)?;
+        }
+        Ok(())
+    }
+}
+
+impl HeaderData {
+    /// The rulestring in a canonical form, so that the same rule written
+    /// in different ways can be compared, e.g., when deduplicating patterns.
+    ///
+    /// Totalistic rules, including Generations rules and rules with the hexagonal (`H`)
+    /// or von Neumann (`V`) neighborhood, are written in the `B/S` notation,
+    /// with the conditions sorted, e.g., `b3/s23`, `23/3` and `b3s32` all become `B3/S23`,
+    /// and `345/2/4` becomes `B2/S345/C4`.
+    /// Other rules, e.g., non-totalistic rules, are only trimmed.
+    ///
+    /// `None` if the rule is not specified.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use ca_formats::rle::Rle;
+    ///
+    /// let glider = Rle::new("x = 3, y = 3, rule = 23/3\nbo$2bo$3o!").unwrap();
+    /// let header_data = glider.header_data().unwrap();
+    /// assert_eq!(header_data.normalized_rule().as_deref(), Some("B3/S23"));
+    /// ```
+    pub fn normalized_rule(&self) -> Option<String> {
+        let rule = self.rule.as_deref()?.trim();
+        Some(normalize_rule(rule).unwrap_or_else(|| rule.to_string()))
+    }
+}
+
+/// Write a totalistic rulestring in the canonical `B/S` notation.
+///
+/// Returns `None` if the rule is not totalistic.
+pub(crate) fn normalize_rule(rule: &str) -> Option<String> {
+    if !rule.is_ascii() {
+        return None;
+    }
+    let rule = rule.to_ascii_lowercase();
+    let (body, suffix, max) = match rule.as_bytes().last()? {
+        b'h' => (&rule[..rule.len() - 1], "H", 6),
+        b'v' => (&rule[..rule.len() - 1], "V", 4),
+        _ => (rule.as_str(), "", 8),
+    };
+    let (mut birth, mut survival, mut states) = (None, None, None);
+    if body.bytes().all(|c| c.is_ascii_digit() || c == b'/') {
+        let mut parts = body.split('/');
+        survival = parts.next();
+        birth = parts.next();
+        states = parts.next();
+        if parts.next().is_some() {
+            return None;
+        }
+    } else {
+        let mut rest = body;
+        while let Some(letter) = rest.chars().next() {
+            let end = rest[1..]
+                .find(|c: char| !c.is_ascii_digit())
+                .map_or(rest.len(), |index| index + 1);
+            let section = match letter {
+                'b' => &mut birth,
+                's' => &mut survival,
+                'c' | 'g' => &mut states,
+                _ => return None,
+            };
+            if section.replace(&rest[1..end]).is_some() {
+                return None;
+            }
+            rest = rest[end..].strip_prefix('/').unwrap_or(&rest[end..]);
+        }
+    }
+    let conditions = |digits: &str| {
+        let mut conditions = digits.bytes().map(|c| c - b'0').collect::<Vec<_>>();
+        conditions.sort_unstable();
+        conditions.dedup();
+        if conditions.iter().any(|&n| n > max) {
+            return None;
+        }
+        Some(
+            conditions
+                .into_iter()
+                .map(|n| char::from(n + b'0'))
+                .collect::<String>(),
+        )
+    };
+    let birth = conditions(birth?)?;
+    let survival = conditions(survival?)?;
+    let states = match states {
+        Some(states) => states.parse::<usize>().ok().filter(|&n| n >= 2)?,
+        None => 2,
+    };
+    if states == 2 {
+        Some(format!("B{}/S{}{}", birth, survival, suffix))
+    } else {
+        Some(format!("B{}/S{}/C{}{}", birth, survival, states, suffix))
+    }
+}
+
+/// The lines before the body of an RLE, returned by [`read_header`].
+#[derive(Clone, Debug, Eq, PartialEq, Default, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Metadata {
+    /// Comment lines, e.g., `#N Glider`, except the `#CXRLE` line.
+    pub comments: Vec<String>,
+    /// Data from the `#CXRLE` line.
+    pub cxrle_data: Option<CxrleData>,
+    /// Data from the header line.
+    pub header_data: Option<HeaderData>,
+    /// Population declared in a `#C population = N` comment.
+    pub declared_population: Option<u64>,
+    /// Cost of a glider synthesis in a comment like `#C costs N gliders`.
+    /// See [`Rle::synthesis_cost`].
+    pub synthesis_cost: Option<u64>,
+}
+
+/// Read the lines before the body, where comment lines start with any of the prefixes.
+///
+/// Returns the metadata, the first line of the body, if any, and the number of lines
+/// before it.
+fn read_metadata<I: Input>(
+    lines: &mut I::Lines,
+    comment_prefixes: &[String],
+    warnings: &mut Vec<Warning>,
+) -> Result<(Metadata, Option<I::Line>, usize), Error> {
+    let mut metadata = Metadata::default();
+    for (line_number, item) in lines.enumerate() {
+        let line = I::line(item)?;
+        if line.as_ref().starts_with(b"#CXRLE") {
+            let replaced = metadata
+                .cxrle_data
+                .replace(parse_cxrle(line.as_ref()).map_err(|column| {
+                    Error::InvalidCxrleLine(
+                        String::from_utf8_lossy(line.as_ref()).into_owned(),
+                        column,
+                    )
+                })?);
+            if replaced.is_some() {
+                warnings.push(Warning::DuplicateCxrle(
+                    String::from_utf8_lossy(line.as_ref()).into_owned(),
+                ));
+            }
+        } else if is_header_line(line.as_ref()) {
+            metadata
+                .header_data
+                .replace(parse_header(line.as_ref()).map_err(|column| {
+                    Error::InvalidHeaderLine(
+                        String::from_utf8_lossy(line.as_ref()).into_owned(),
+                        column,
+                    )
+                })?);
+        } else if is_comment(line.as_ref(), comment_prefixes) {
+            let comment = String::from_utf8_lossy(line.as_ref()).into_owned();
+            if line.as_ref().starts_with(b"#") && !is_known_directive(line.as_ref()) {
+                warnings.push(Warning::UnknownDirective(comment.clone()));
+            }
+            if let Some(population) = parse_population_comment(line.as_ref()) {
+                metadata.declared_population = Some(population);
+            }
+            if let Some(cost) = parse_cost_comment(line.as_ref()) {
+                metadata.synthesis_cost = Some(cost);
+            }
+            metadata.comments.push(comment);
+        } else {
+            return Ok((metadata, Some(line), line_number));
+        }
+    }
+    Ok((metadata, None, 0))
+}
+
+/// Read only the comments, the `#CXRLE` line and the header line of an RLE,
+/// and stop at the first line of the body.
+///
+/// Unlike [`Rle::new`], no parser is created, so this is a cheap way to scan
+/// the metadata of many files, e.g., for an index of a pattern collection.
+/// A reader is left at the line after the first line of the body.
+///
+/// # Example
+///
+/// ```rust
+/// use ca_formats::rle::read_header;
+///
+/// let metadata = read_header("#N Glider\nx = 3, y = 3, rule = B3/S23\nbo$2bo$3o!").unwrap();
+/// assert_eq!(metadata.comments, ["#N Glider"]);
+/// assert_eq!(metadata.header_data.unwrap().rule.as_deref(), Some("B3/S23"));
+/// assert_eq!(metadata.cxrle_data, None);
+/// ```
+pub fn read_header<I: Input>(input: I) -> Result<Metadata, Error> {
+    let mut lines = input.lines();
+    let (metadata, _, _) = read_metadata::<I>(&mut lines, &[String::from("#")], &mut Vec::new())?;
+    Ok(metadata)
+}
+
+/// Exact population and bounding box of an RLE, returned by [`Rle::count_pass`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Default, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Counts {
+    /// Number of living cells.
+    pub population: usize,
+    /// Bounding box of the living cells, or `None` if there is no living cell.
+    pub bounding_box: Option<BoundingBox>,
+}
+
+/// Header data inferred from the body of an RLE, returned by [`Rle::infer_header`].
+#[derive(Clone, Debug, Eq, PartialEq, Default, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct InferredHeader {
+    /// Data from the header line, if any.
+    pub declared: Option<HeaderData>,
+    /// Header data with the width and the height computed from the body,
+    /// and the rule and the other pairs from the header line.
+    pub inferred: HeaderData,
+}
+
+impl InferredHeader {
+    /// Whether the header line is missing, or declares a width or a height
+    /// different from the inferred ones.
+    pub fn is_mismatched(&self) -> bool {
+        self.declared
+            .as_ref()
+            .is_none_or(|declared| (declared.x, declared.y) != (self.inferred.x, self.inferred.y))
+    }
+
+    /// Whether the header line is missing, or declares a bounding box
+    /// that is too small to contain the body.
+    ///
+    /// A grid allocated from such a header line can't hold all the cells.
+    pub fn is_too_small(&self) -> bool {
+        self.declared
+            .as_ref()
+            .is_none_or(|declared| declared.x < self.inferred.x || declared.y < self.inferred.y)
+    }
+}
+
+#[cfg(feature = "ca-rules")]
+#[cfg_attr(docs_rs, doc(cfg(feature = "ca-rules")))]
+impl HeaderData {
+    /// The rulestring parsed by [`ca-rules`](https://crates.io/crates/ca-rules).
+    ///
+    /// `None` if the rule is not specified.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use ca_formats::rle::Rle;
+    ///
+    /// let glider = Rle::new("x = 3, y = 3, rule = B3/S23\nbo$2bo$3o!").unwrap();
+    /// let header_data = glider.header_data().unwrap();
+    /// assert_eq!(header_data.parsed_rule().unwrap().unwrap().b, vec![3]);
+    /// assert_eq!(header_data.state_count(), Some(2));
+    /// ```
+    pub fn parsed_rule(&self) -> Option<Result<Rule, ParseRuleError>> {
+        self.rule.as_deref().map(Rule::parse)
+    }
+
+    /// Number of states of the rule.
+    ///
+    /// `None` if the rule is not specified, or can't be parsed.
+    pub fn state_count(&self) -> Option<usize> {
+        self.parsed_rule()?.ok().map(|rule| rule.state_count())
+    }
+}
+
+/// Whether a line is a header line, i.e., starts with `x =`, ignoring whitespaces and case.
+fn is_header_line(line: &[u8]) -> bool {
+    let mut scanner = Scanner::new(line);
+    scanner.skip_whitespace();
+    (scanner.eat(b"x") || scanner.eat(b"X")) && {
+        scanner.skip_whitespace();
+        scanner.eat(b"=")
+    }
+}
+
+/// Whether the line may become a header line when more bytes are appended, i.e., `\s*[xX]?\s*`.
+fn is_header_prefix(line: &[u8]) -> bool {
+    let mut scanner = Scanner::new(line);
+    scanner.skip_whitespace();
+    let _ = scanner.eat(b"x") || scanner.eat(b"X");
+    scanner.skip_whitespace();
+    scanner.peek().is_none()
+}
+
+/// Parse the header line, e.g., `x = 3, y = 3, rule = B3/S23`.
+///
+/// The keys are case-insensitive, and whitespaces around the separators are optional.
+/// `x` and `y` must come first; other pairs than `rule` are collected in [`HeaderData::extra`].
+///
+/// Returns the column where the line stops matching.
+fn parse_header(line: &[u8]) -> Result<HeaderData, usize> {
+    let mut scanner = Scanner::new(line);
+    scanner.skip_whitespace();
+    if !scanner.eat(b"x") {
+        scanner.expect(b"X")?;
+    }
+    expect_equal_sign(&mut scanner)?;
+    let x = scanner.number(false)?;
+    scanner.skip_whitespace();
+    scanner.expect(b",")?;
+    scanner.skip_whitespace();
+    if !scanner.eat(b"y") {
+        scanner.expect(b"Y")?;
+    }
+    expect_equal_sign(&mut scanner)?;
+    let y = scanner.number(false)?;
+    let mut rule = None;
+    let mut extra = BTreeMap::new();
+    scanner.skip_whitespace();
+    while scanner.eat(b",") {
+        scanner.skip_whitespace();
+        let key = scanner.identifier().ok_or(scanner.column())?;
+        expect_equal_sign(&mut scanner)?;
+        let value = scanner.value()?.to_owned();
+        if key.eq_ignore_ascii_case("rule") {
+            rule = Some(value);
+        } else {
+            extra.insert(key.to_owned(), value);
+        }
+    }
+    scanner.end()?;
+    Ok(HeaderData { x, y, rule, extra })
+}
+
+/// Parse a `#P x y` line, which moves the pen to `(x, y)`.
+///
+/// Returns the column where the line stops matching.
+fn parse_offset_line(line: &[u8]) -> Result<Coordinates, usize> {
+    let mut scanner = Scanner::new(line);
+    scanner.expect(b"#P")?;
+    scanner.expect_whitespace()?;
+    let x = scanner.number(true)?;
+    scanner.expect_whitespace()?;
+    let y = scanner.number(true)?;
+    scanner.end()?;
+    Ok((x, y))
 }
 
-/// Parse the header line.
-fn parse_header(line: &str) -> Option<HeaderData> {
-    let re =
-        regex!(r"^x\s*=\s*(?P<x>\d+),\s*y\s*=\s*(?P<y>\d+)(?:,\s*rule\s*=\s*(?P<rule>.*\S)\s*)?$");
-    let mut data = HeaderData::default();
-    let cap = re.captures(line)?;
-    data.x = cap["x"].parse().ok()?;
-    data.y = cap["y"].parse().ok()?;
-    if let Some(rule) = cap.name("rule") {
-        data.rule = Some(rule.as_str().to_owned());
+/// Parse a `#C population = N` comment, which apgsearch adds to declare the population.
+///
+/// Returns `None` if the line is not such a comment.
+fn parse_population_comment(line: &[u8]) -> Option<u64> {
+    let mut scanner = Scanner::new(line);
+    if !(scanner.eat(b"#C") || scanner.eat(b"#c")) || !scanner.skip_whitespace() {
+        return None;
+    }
+    if !scanner.identifier()?.eq_ignore_ascii_case("population") {
+        return None;
+    }
+    if !match_equal_sign(&mut scanner) {
+        return None;
+    }
+    let population = scanner.number(false).ok()?;
+    scanner.end().ok()?;
+    Some(population)
+}
+
+/// Parse a comment that gives the cost of a glider synthesis, e.g., `#C costs 12 gliders`.
+/// See [`Rle::synthesis_cost`] for the forms, which may end with `synthesis` and a period.
+///
+/// Returns `None` if the line is not such a comment.
+fn parse_cost_comment(line: &[u8]) -> Option<u64> {
+    let mut scanner = Scanner::new(line);
+    if !(scanner.eat(b"#C") || scanner.eat(b"#c")) || !scanner.skip_whitespace() {
+        return None;
+    }
+    if !scanner.peek().is_some_and(|c| c.is_ascii_digit()) {
+        let keyword = scanner.identifier()?;
+        if !(keyword.eq_ignore_ascii_case("cost") || keyword.eq_ignore_ascii_case("costs")) {
+            return None;
+        }
+        scanner.skip_whitespace();
+        if scanner.eat(b":") || scanner.eat(b"=") {
+            scanner.skip_whitespace();
+        }
+    }
+    let cost = scanner.number(false).ok()?;
+    if !(scanner.eat(b"-") || scanner.skip_whitespace()) {
+        return None;
+    }
+    let unit = scanner.identifier()?;
+    if !(unit.eq_ignore_ascii_case("glider") || unit.eq_ignore_ascii_case("gliders")) {
+        return None;
     }
-    Some(data)
+    scanner.skip_whitespace();
+    if let Some(word) = scanner.identifier() {
+        if !word.eq_ignore_ascii_case("synthesis") {
+            return None;
+        }
+    }
+    scanner.eat(b".");
+    scanner.end().ok()?;
+    Some(cost)
+}
+
+/// Skip `\s*=\s*`, or return an error.
+fn expect_equal_sign(scanner: &mut Scanner) -> Result<(), usize> {
+    scanner.skip_whitespace();
+    scanner.expect(b"=")?;
+    scanner.skip_whitespace();
+    Ok(())
 }
 
 /// A parser for Golly's [Extended RLE format](http://golly.sourceforge.net/Help/formats.html#rle).
@@ -77,6 +704,13 @@ fn parse_header(line: &str) -> Option<HeaderData> {
 /// format, except that it supports up to 256 states, and a `#CXRLE` line.
 ///
 /// As an iterator, it iterates over the living cells.
+/// The cells are returned in row-major order, i.e., sorted by the y coordinate,
+/// and then by the x coordinate.
+///
+/// The state of the cells is a `u8` by default. The multi-char states of the format
+/// can go slightly beyond `255` (up to `yX`, i.e., `264`), and such states are reported
+/// as [`Error::InvalidState`]. Use [`with_state_type`](Rle::with_state_type)
+/// to parse them into a wider type.
 ///
 /// # Examples
 ///
@@ -114,7 +748,10 @@ fn parse_header(line: &str) -> Option<HeaderData> {
 /// ```
 #[must_use]
 #[derive(Debug)]
-pub struct Rle<I: Input> {
+pub struct Rle<I: Input, S = u8> {
+    /// Comment lines before the body, except the `#CXRLE` line.
+    comments: Vec<String>,
+
     /// Data from the `#CXRLE` line.
     cxrle_data: Option<CxrleData>,
 
@@ -127,27 +764,69 @@ pub struct Rle<I: Input> {
     /// An iterator over bytes of the current line.
     current_line: Option<I::Bytes>,
 
+    /// The state machine that decodes the body.
+    decoder: Decoder,
+
     /// Coordinates of the current cell.
     position: Coordinates,
 
-    /// X coordinates of the upper left corner of the pattern.
-    x_start: i64,
-
-    /// Run count in the RLE string, i.e., the numbers before `b`, `o`, `$` and other tags.
-    run_count: i64,
-
     /// Remaining run count for the current cell when iterating over cells.
     alive_count: i64,
 
     /// State of the current cell.
-    state: u8,
+    state: S,
 
-    /// Prefix in a multi-char state, i.e., `p` in `pA`.
-    state_prefix: Option<u8>,
+    /// Whether to ignore the position in the `#CXRLE` line.
+    ignore_cxrle_pos: bool,
 
-    /// Whether this RLE file allows unknown cells.
-    #[cfg(feature = "unknown")]
-    unknown: bool,
+    /// Offset added to the returned coordinates.
+    offset: Coordinates,
+
+    /// Convention of the returned coordinates.
+    convention: Convention,
+
+    /// Whether to include the dead cells in the bounding box.
+    include_dead: bool,
+
+    /// Dead cells in the bounding box, when they are included.
+    dead_cells: Option<DeadCells>,
+
+    /// Whether the warnings are errors.
+    strict: bool,
+
+    /// The maximum number of cells returned by the iterator.
+    max_cells: Option<u64>,
+
+    /// Number of cells returned by the iterator so far.
+    cell_count: u64,
+
+    /// Whether `#P x y` lines in the body move the pen.
+    offset_lines: bool,
+
+    /// Warnings collected so far.
+    warnings: Vec<Warning>,
+
+    /// The largest x and y coordinates of the runs decoded so far.
+    extent: Option<Coordinates>,
+
+    /// Whether the end of the body has been checked for warnings.
+    body_checked: bool,
+
+    /// Whether some rows were skipped by [`skip_rows`](Rle::skip_rows) without decoding
+    /// their runs, so that the extent and the number of returned cells are unknown.
+    skipped: bool,
+
+    /// Where the parser is in the input, for [`Rle::locations`].
+    cursor: Cursor,
+
+    /// Prefixes of comment lines.
+    comment_prefixes: Vec<String>,
+
+    /// Population declared in a `#C population = N` comment.
+    declared_population: Option<u64>,
+
+    /// Cost of a glider synthesis in a comment like `#C costs N gliders`.
+    synthesis_cost: Option<u64>,
 }
 
 impl<I: Input> Rle<I> {
@@ -155,90 +834,1090 @@ impl<I: Input> Rle<I> {
     ///
     /// If there are multiple header lines / `CXRLE` lines, only the last one will be taken.
     pub fn new(input: I) -> Result<Self, Error> {
-        let mut lines = input.lines();
-        let mut cxrle_data = None;
-        let mut header_data = None;
-        let mut current_line = None;
-        let mut position = (0, 0);
-        let mut x_start = 0;
-        for item in &mut lines {
-            let line = I::line(item)?;
-            if line.as_ref().starts_with("#CXRLE") {
-                cxrle_data.replace(
-                    parse_cxrle(line.as_ref())
-                        .ok_or_else(|| Error::InvalidCxrleLine(line.as_ref().to_string()))?,
-                );
-            } else if line.as_ref().starts_with("x ") || line.as_ref().starts_with("x=") {
-                header_data.replace(
-                    parse_header(line.as_ref())
-                        .ok_or_else(|| Error::InvalidHeaderLine(line.as_ref().to_string()))?,
-                );
-            } else if !line.as_ref().starts_with('#') {
-                current_line = Some(I::bytes(line));
-                break;
-            }
-        }
-        if let Some(CxrleData { pos: Some(pos), .. }) = cxrle_data {
-            position = pos;
-            x_start = pos.0;
-        }
+        Self::new_with_comment_prefixes(input, &["#"])
+    }
+
+    /// Create a new parser instance from input with the [`ParseOptions`] that make sense
+    /// for this format: the convention, whether to include the dead cells, whether to allow
+    /// unknown cells, the strict mode and the maximum number of cells.
+    ///
+    /// With [`include_dead`](ParseOptions::include_dead), the dead cells, i.e., cells with
+    /// state `0`, in the bounding box declared by the header line are also returned.
+    /// They are returned in the order of the rows, so that a dense array can be filled
+    /// in a single pass. Living cells outside the bounding box are still returned,
+    /// but the dead cells around them are not. This has no effect if there is no header line.
+    ///
+    /// With [`unknown`](ParseOptions::unknown), there is another symbol, `?`,
+    /// which represents unknown cells. Now unknown cells are the background.
+    /// Dead cells at the end of each line must not be omitted.
+    /// The iterator will also explicitly output the dead cells, so `include_dead`
+    /// has no further effect.
+    ///
+    /// With [`strict`](ParseOptions::strict), a warning about the lines before the body
+    /// is returned as an [`Error::Strict`] here, and a warning about the body is returned
+    /// as an [`Error::Strict`] by the iterator when it reaches the end of the pattern.
+    ///
+    /// With [`max_cells`](ParseOptions::max_cells), the iterator returns an
+    /// [`Error::TooManyCells`] instead of the next cell after returning that many cells,
+    /// and then stops.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use ca_formats::{
+    ///     rle::{Error, Rle},
+    ///     ParseOptions,
+    /// };
+    ///
+    /// let options = ParseOptions::new().include_dead(true);
+    /// let blinker = Rle::new_with_options("x = 3, y = 2\n3o!", options).unwrap();
+    /// let states = blinker.map(|cell| cell.unwrap().state).collect::<Vec<_>>();
+    /// assert_eq!(states, vec![1, 1, 1, 0, 0, 0]);
+    ///
+    /// let options = ParseOptions::new().with_max_cells(2);
+    /// let blinker = Rle::new_with_options("3o!", options).unwrap();
+    /// let cells = blinker.collect::<Result<Vec<_>, _>>();
+    /// assert_eq!(cells, Err(Error::TooManyCells(2)));
+    ///
+    /// let options = ParseOptions::new().strict(true);
+    /// assert!(Rle::new_with_options("#X Blinker\n3o!", options).is_err());
+    /// ```
+    pub fn new_with_options(input: I, options: ParseOptions) -> Result<Self, Error> {
+        Self::new(input)?.apply_options(options)
+    }
+
+    /// Create a new parser instance from input, where comment lines are the lines
+    /// starting with any of the given prefixes, e.g., `//`, instead of `#`.
+    ///
+    /// Comment lines are skipped both before and in the body. The `#CXRLE` line
+    /// is still recognized, even if `#` is not one of the prefixes. Empty prefixes are ignored.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use ca_formats::rle::Rle;
+    ///
+    /// let glider = Rle::new_with_comment_prefixes(
+    ///     "// Glider\nx = 3, y = 3\nbo$2bo$\n#C bottom row\n3o!",
+    ///     &["#", "//"],
+    /// )
+    /// .unwrap();
+    /// assert_eq!(glider.comments(), ["// Glider"]);
+    /// assert_eq!(glider.count(), 5);
+    /// ```
+    pub fn new_with_comment_prefixes<P: AsRef<str>>(
+        input: I,
+        prefixes: &[P],
+    ) -> Result<Self, Error> {
+        let comment_prefixes = prefixes
+            .iter()
+            .map(|prefix| prefix.as_ref().to_string())
+            .filter(|prefix| !prefix.is_empty())
+            .collect::<Vec<_>>();
+        Self::from_lines(input.lines(), comment_prefixes)
+    }
+
+    /// Read the comments, the `#CXRLE` line and the header line from the lines of an input.
+    fn from_lines(mut lines: I::Lines, comment_prefixes: Vec<String>) -> Result<Self, Error> {
+        let mut warnings = Vec::new();
+        let (metadata, first_line, line_number) =
+            read_metadata::<I>(&mut lines, &comment_prefixes, &mut warnings)?;
+        let Metadata {
+            comments,
+            cxrle_data,
+            header_data,
+            declared_population,
+            synthesis_cost,
+        } = metadata;
+        let cursor = Cursor {
+            line: line_number + usize::from(first_line.is_some()),
+            ..Cursor::default()
+        };
+        let current_line = first_line.map(I::bytes);
+        let decoder = Decoder::new(cxrle_data.as_ref());
         Ok(Self {
+            comments,
             cxrle_data,
             header_data,
             lines,
             current_line,
-            position,
-            x_start,
-            run_count: 0,
+            position: decoder.position,
+            decoder,
             alive_count: 0,
             state: 1,
-            state_prefix: None,
-            #[cfg(feature = "unknown")]
-            unknown: false,
+            ignore_cxrle_pos: false,
+            offset: (0, 0),
+            convention: Convention::default(),
+            include_dead: false,
+            dead_cells: None,
+            strict: false,
+            max_cells: None,
+            cell_count: 0,
+            offset_lines: false,
+            warnings,
+            extent: None,
+            body_checked: false,
+            skipped: false,
+            cursor,
+            comment_prefixes,
+            declared_population,
+            synthesis_cost,
         })
     }
+}
+
+impl<I: Input, S: State> Rle<I, S> {
+    /// Comment lines before the body, e.g., `#N Glider`, except the `#CXRLE` line.
+    pub fn comments(&self) -> &[String] {
+        &self.comments
+    }
+
+    /// Prefixes of comment lines, `#` by default.
+    pub fn comment_prefixes(&self) -> &[String] {
+        &self.comment_prefixes
+    }
+
+    /// The population declared in a `#C population = N` comment before the body,
+    /// as added by apgsearch and some scripts.
+    ///
+    /// The comment is not checked against the pattern, so it is not used as a lower bound
+    /// in [`size_hint`](Iterator::size_hint).
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use ca_formats::rle::Rle;
+    ///
+    /// let glider = Rle::new("#C population = 5\nbo$2bo$3o!").unwrap();
+    /// assert_eq!(glider.declared_population(), Some(5));
+    /// assert_eq!(glider.size_hint(), (0, None));
+    /// ```
+    pub const fn declared_population(&self) -> Option<u64> {
+        self.declared_population
+    }
+
+    /// The cost of a glider synthesis of the pattern, in gliders, from a comment
+    /// before the body like `#C costs 12 gliders` or `#C 3-glider synthesis`,
+    /// as written in the syntheses of Shinjuku and Catagolue.
+    ///
+    /// The forms are, ignoring case, `#C costs N gliders`, `#C cost: N gliders`,
+    /// `#C cost = N gliders`, `#C N gliders` and `#C N-glider synthesis`.
+    /// If there are several such comments, the last one is used.
+    /// The comments are still returned by [`comments`](Rle::comments).
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use ca_formats::rle::Rle;
+    ///
+    /// let rle = Rle::new("#N Glider\n#C costs 2 gliders\nbo$2bo$3o!").unwrap();
+    /// assert_eq!(rle.synthesis_cost(), Some(2));
+    /// assert_eq!(rle.comments().len(), 2);
+    /// ```
+    pub const fn synthesis_cost(&self) -> Option<u64> {
+        self.synthesis_cost
+    }
 
     /// Data from the `#CXRLE` line.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use ca_formats::rle::Rle;
+    ///
+    /// let glider = Rle::new("#CXRLE Pos=-1,-1 Gen=4 Layer=2\nbo$2bo$3o!").unwrap();
+    /// let cxrle_data = glider.cxrle_data().unwrap();
+    /// assert_eq!(cxrle_data.pos, Some((-1, -1)));
+    /// assert_eq!(cxrle_data.gen, Some(4));
+    /// assert_eq!(cxrle_data.extra["Layer"], "2");
+    /// ```
     pub const fn cxrle_data(&self) -> Option<&CxrleData> {
         self.cxrle_data.as_ref()
     }
 
+    /// The generation count, from `Gen=` in the `#CXRLE` line.
+    ///
+    /// Golly writes it as `#G` in a Macrocell file. See
+    /// [`Macrocell::gen`](crate::macrocell::Macrocell::gen).
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use ca_formats::rle::Rle;
+    ///
+    /// let glider = Rle::new("#CXRLE Pos=-1,-1 Gen=4\nbo$2bo$3o!").unwrap();
+    /// assert_eq!(glider.generation(), Some(4));
+    /// assert_eq!(Rle::new("bo$2bo$3o!").unwrap().generation(), None);
+    /// ```
+    pub fn generation(&self) -> Option<u64> {
+        self.cxrle_data.as_ref()?.gen
+    }
+
     /// Data from the header line.
     pub const fn header_data(&self) -> Option<&HeaderData> {
         self.header_data.as_ref()
     }
 
+    /// The rule in the header line. See [`RuleInfo`].
+    pub fn rule_info(&self) -> Option<RuleInfo> {
+        let rule = self.header_data.as_ref()?.rule.as_deref();
+        RuleInfo::from_parser(rule, RuleOrigin::Header)
+    }
+
+    /// Warnings collected so far.
+    ///
+    /// Warnings about the comment lines and the `#CXRLE` lines are collected when the
+    /// parser is created. Warnings about the body, i.e., a header line whose size doesn't
+    /// match the pattern, a missing `!`, and characters after the `!`, are collected by the iterator
+    /// when it reaches the end of the pattern, but not by the other methods that consume
+    /// the parser, e.g., [`population`](Rle::population).
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use ca_formats::rle::{Rle, Warning};
+    ///
+    /// let mut rle = Rle::new("#X Glider\nx = 2, y = 3\nbo$2bo$3o!2o").unwrap();
+    /// assert_eq!(rle.warnings().len(), 1);
+    /// assert_eq!(rle.by_ref().count(), 5);
+    /// assert_eq!(
+    ///     rle.warnings(),
+    ///     [
+    ///         Warning::UnknownDirective(String::from("#X Glider")),
+    ///         Warning::TrailingGarbage(String::from("2o")),
+    ///         Warning::StaleHeader {
+    ///             declared: (2, 3),
+    ///             actual: (3, 3),
+    ///         },
+    ///     ]
+    /// );
+    /// ```
+    pub fn warnings(&self) -> &[Warning] {
+        &self.warnings
+    }
+
     /// Allow unknown cells.
     ///
     /// In this variant of RLE format, there is another symbol, `?`,
     /// which represents unknown cells. Now unknown cells are the background.
     /// Dead cells at the end of each line must not be omitted.
     /// The iterator will also explicitly output the dead cells.
-    #[cfg(feature = "unknown")]
-    #[cfg_attr(docs_rs, doc(cfg(feature = "unknown")))]
+    #[deprecated(note = "use `ParseOptions::unknown` with `Rle::new_with_options` instead")]
     pub fn with_unknown(mut self) -> Self {
-        self.unknown = true;
+        self.decoder.unknown = true;
+        self.update_dead_cells();
         self
     }
-}
 
-impl<I, L> Rle<I>
-where
-    I: Input<Lines = L>,
-    L: Input,
-{
-    /// Parse the remaining unparsed lines as a new RLE.
-    pub fn remains(self) -> Result<Rle<L>, Error> {
-        Rle::new(self.lines)
+    /// Ignore the position in the `#CXRLE` line, so that the returned coordinates
+    /// are relative to the upper left corner of the pattern.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use ca_formats::rle::Rle;
+    ///
+    /// let glider = Rle::new("#CXRLE Pos=-1,-1\nbo$2bo$3o!").unwrap().ignore_cxrle_pos();
+    /// let cells = glider.map(|cell| cell.unwrap().position).collect::<Vec<_>>();
+    /// assert_eq!(cells, vec![(1, 0), (2, 1), (0, 2), (1, 2), (2, 2)]);
+    /// ```
+    pub fn ignore_cxrle_pos(mut self) -> Self {
+        self.ignore_cxrle_pos = true;
+        self.update_decoder();
+        self
     }
 
-    /// Try to parse the remaining unparsed lines as a new RLE.
+    /// Translate the returned coordinates by `(dx, dy)`.
     ///
-    /// Returns `Ok(None)` if the remaining lines is empty or only
-    /// contains header lines and comments.
-    pub fn try_remains(self) -> Result<Option<Rle<L>>, Error> {
-        let rle = Rle::new(self.lines)?;
-        Ok(if rle.current_line.is_some() {
+    /// The offset is added to the position in the `#CXRLE` line,
+    /// unless it is ignored by [`ignore_cxrle_pos`](Rle::ignore_cxrle_pos).
+    /// Calling this method again replaces the previous offset.
+    /// Cells whose translated coordinates don't fit in `i64` are not wrapped around;
+    /// the parser returns an [`Error::CoordinateOverflow`] instead.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use ca_formats::rle::Rle;
+    ///
+    /// let glider = Rle::new("#CXRLE Pos=-1,-1\nbo$2bo$3o!").unwrap().with_offset(10, 20);
+    /// let cells = glider.map(|cell| cell.unwrap().position).collect::<Vec<_>>();
+    /// assert_eq!(cells, vec![(10, 19), (11, 20), (9, 21), (10, 21), (11, 21)]);
+    /// ```
+    pub fn with_offset(mut self, dx: i64, dy: i64) -> Self {
+        self.offset = (dx, dy);
+        self.update_decoder();
+        self
+    }
+
+    /// The [`ParseOptions`] of the parser.
+    fn options(&self) -> ParseOptions {
+        ParseOptions {
+            convention: self.convention,
+            include_dead: self.include_dead,
+            unknown: self.decoder.unknown,
+            lenient: false,
+            strict: self.strict,
+            max_cells: self.max_cells,
+        }
+    }
+
+    /// Apply the [`ParseOptions`] before the iteration starts.
+    ///
+    /// The convention is applied after the offset in the `#CXRLE` line
+    /// and the offset given by [`with_offset`](Rle::with_offset).
+    fn apply_options(mut self, options: ParseOptions) -> Result<Self, Error> {
+        self.convention = options.convention;
+        self.update_decoder();
+        self.decoder.unknown = options.unknown;
+        self.include_dead = options.include_dead;
+        self.update_dead_cells();
+        self.strict = options.strict;
+        self.max_cells = options.max_cells;
+        match self.warnings.first() {
+            Some(warning) if self.strict => Err(Error::Strict(warning.clone())),
+            _ => Ok(self),
+        }
+    }
+
+    /// Set up the dead cells in the bounding box declared by the header line,
+    /// from both [`include_dead`](ParseOptions::include_dead) and
+    /// [`unknown`](ParseOptions::unknown), so that the order in which they are set
+    /// doesn't matter.
+    fn update_dead_cells(&mut self) {
+        self.dead_cells = self
+            .header_data
+            .as_ref()
+            .filter(|_| self.include_dead && !self.decoder.unknown)
+            .map(|data| DeadCells::new(self.decoder.position, data));
+    }
+
+    /// Read `#P x y` lines in the body, as in some old files converted from Life 1.05,
+    /// where a pattern is split into blocks at different offsets.
+    ///
+    /// Each `#P` line moves the pen to `(x, y)`, relative to the upper left corner
+    /// of the pattern, and the following `$` go back to column `x`.
+    /// A `#P` line among the comments before the body moves the pen
+    /// to the start of the first block; a malformed one there, or one whose coordinates
+    /// are out of range, is kept as a comment.
+    /// A malformed `#P` line in the body is an [`Error::InvalidOffsetLine`].
+    ///
+    /// The cells are no longer guaranteed to be in row-major order.
+    /// Without this option, `#P` lines are ignored like other comments.
+    /// It should be called before the iteration starts.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use ca_formats::rle::Rle;
+    ///
+    /// let rle = Rle::new("#P 0 0\n2o$o\n#P 10 -1\nbo$2o!").unwrap().with_offset_lines();
+    /// let cells = rle.map(|cell| cell.unwrap().position).collect::<Vec<_>>();
+    /// assert_eq!(cells, vec![(0, 0), (1, 0), (0, 1), (11, -1), (10, 0), (11, 0)]);
+    /// ```
+    pub fn with_offset_lines(mut self) -> Self {
+        self.offset_lines = true;
+        let start = self
+            .comments
+            .iter()
+            .rev()
+            .filter(|line| line.starts_with("#P"))
+            .filter_map(|line| parse_offset_line(line.as_bytes()).ok())
+            .find_map(|start| self.offset_position(start));
+        if let Some(start) = start {
+            self.decoder.move_to(start);
+        }
+        self
+    }
+
+    /// Only return the cells whose x coordinates are in a range, e.g., for loading
+    /// a window of a wide pattern together with [`skip_rows`](Rle::skip_rows).
+    ///
+    /// The runs are clipped to the range as they are decoded, and the runs outside
+    /// the range are skipped as a whole, instead of filtering the cells one by one.
+    /// This also applies to the other methods that read the runs, e.g.,
+    /// [`population`](Rle::population) and [`bounding_box`](Rle::bounding_box).
+    ///
+    /// The x coordinates are the ones after the offsets are applied, before the
+    /// [convention](ParseOptions::convention) swaps them with the y coordinates.
+    /// The [warning](Warning::StaleHeader) about the size in the header line is not collected.
+    /// It should be called before the iteration starts.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use ca_formats::rle::Rle;
+    ///
+    /// let rle = Rle::new("x = 10, y = 2\n10o$o8bo!").unwrap().columns(8..);
+    /// let cells = rle.map(|cell| cell.unwrap().position).collect::<Vec<_>>();
+    /// assert_eq!(cells, [(8, 0), (9, 0), (9, 1)]);
+    /// ```
+    pub fn columns<R: RangeBounds<i64>>(mut self, range: R) -> Self {
+        let start = match range.start_bound() {
+            Bound::Included(&x) => i128::from(x),
+            Bound::Excluded(&x) => i128::from(x) + 1,
+            Bound::Unbounded => i128::from(i64::MIN),
+        };
+        let end = match range.end_bound() {
+            Bound::Included(&x) => i128::from(x),
+            Bound::Excluded(&x) => i128::from(x) - 1,
+            Bound::Unbounded => i128::from(i64::MAX),
+        };
+        self.decoder.columns = Some((start, end));
+        self
+    }
+
+    /// Whether `#P x y` lines in the body move the pen. See [`with_offset_lines`](Rle::with_offset_lines).
+    pub(crate) const fn reads_offset_lines(&self) -> bool {
+        self.offset_lines
+    }
+
+    /// Coordinates relative to the upper left corner of the pattern, as the pen sees them.
+    ///
+    /// Returns `None` if they are out of range.
+    fn offset_position(&self, (x, y): Coordinates) -> Option<Coordinates> {
+        let (x_origin, y_origin) = self
+            .cxrle_data
+            .as_ref()
+            .and_then(|data| data.pos)
+            .unwrap_or_default();
+        Some((x_origin.checked_add(x)?, y_origin.checked_add(y)?))
+    }
+
+    /// Collect the warnings at the end of the body, once.
+    ///
+    /// In strict mode, the first of them is returned as an error.
+    fn check_body(&mut self) -> Result<(), Error> {
+        if self.body_checked {
+            return Ok(());
+        }
+        self.body_checked = true;
+        let count = self.warnings.len();
+        if !self.decoder.finished {
+            self.warnings.push(Warning::MissingTerminator);
+        }
+        if let Some(bytes) = self.current_line.as_mut() {
+            let rest = bytes.collect::<Vec<_>>();
+            let rest = rest.trim_ascii();
+            if !rest.is_empty() {
+                self.warnings.push(Warning::TrailingGarbage(
+                    String::from_utf8_lossy(rest).into_owned(),
+                ));
+            }
+        }
+        let extent_known = !self.skipped && self.decoder.columns.is_none();
+        if let Some(header_data) = self.header_data.as_ref().filter(|_| extent_known) {
+            let (x_origin, y_origin) = self
+                .cxrle_data
+                .as_ref()
+                .and_then(|data| data.pos)
+                .unwrap_or_default();
+            let span = |start: i64, end: i64| {
+                u64::try_from(i128::from(end) - i128::from(start) + 1).unwrap_or(0)
+            };
+            let actual = self
+                .extent
+                .map_or((0, 0), |(x, y)| (span(x_origin, x), span(y_origin, y)));
+            let declared = (header_data.x, header_data.y);
+            if declared != actual {
+                self.warnings
+                    .push(Warning::StaleHeader { declared, actual });
+            }
+        }
+        match self.warnings.get(count) {
+            Some(warning) if self.strict => Err(Error::Strict(warning.clone())),
+            _ => Ok(()),
+        }
+    }
+
+    /// Coordinates of the current cell, translated by the offsets, in the convention.
+    fn current_position(&self) -> Result<Coordinates, Error> {
+        self.place(self.position)
+    }
+
+    /// The next dead cell before the target of [`DeadCells`].
+    fn next_dead(&mut self) -> Option<Result<CellData<S>, Error>> {
+        let dead_cells = self.dead_cells.as_mut()?;
+        let position = dead_cells.find(|&(x, _)| self.decoder.in_columns(x))?;
+        Some(self.place(position).map(|position| CellData {
+            position,
+            state: S::default(),
+        }))
+    }
+
+    /// The remaining dead cells at the end of the pattern.
+    fn finish_dead(&mut self) -> Option<Result<CellData<S>, Error>> {
+        let dead_cells = self.dead_cells.as_mut()?;
+        dead_cells.target = (dead_cells.x_start, dead_cells.y_end);
+        self.next_dead()
+    }
+
+    /// Translate coordinates by the offsets, and convert them into the convention.
+    fn place(&self, (x, y): Coordinates) -> Result<Coordinates, Error> {
+        let (dx, dy) = self.shift();
+        let cell = (x.wrapping_add(dx), y.wrapping_add(dy));
+        self.convention
+            .apply(cell)
+            .ok_or(Error::CoordinateOverflow(cell))
+    }
+
+    /// The total translation of the offsets, before the convention is applied.
+    ///
+    /// It may wrap around, e.g., when a huge position in the `#CXRLE` line is ignored,
+    /// but the translated coordinates are exact, because the [`Decoder`] rejects
+    /// runs whose translated coordinates are out of range.
+    fn shift(&self) -> Coordinates {
+        let (dx, dy) = self.offset;
+        match self.cxrle_data.as_ref().and_then(|data| data.pos) {
+            Some((pos_x, pos_y)) if self.ignore_cxrle_pos => {
+                (dx.wrapping_sub(pos_x), dy.wrapping_sub(pos_y))
+            }
+            _ => (dx, dy),
+        }
+    }
+
+    /// Tell the decoder the translation and the convention, after they are changed.
+    fn update_decoder(&mut self) {
+        let (dx, dy) = self.offset;
+        let (pos_x, pos_y) = match self.cxrle_data.as_ref().and_then(|data| data.pos) {
+            Some(pos) if self.ignore_cxrle_pos => pos,
+            _ => (0, 0),
+        };
+        self.decoder.shift = (
+            i128::from(dx) - i128::from(pos_x),
+            i128::from(dy) - i128::from(pos_y),
+        );
+        self.decoder.y_up = self.convention.y_up;
+    }
+
+    /// Move to the next line, skipping comment lines and header lines.
+    ///
+    /// `#P` lines move the pen if [`with_offset_lines`](Rle::with_offset_lines) is set.
+    /// Returns `None` at the end of the input.
+    fn next_line(&mut self) -> Option<Result<(), Error>> {
+        if let Some(bytes) = self.current_line.take() {
+            I::recycle(&mut self.lines, bytes);
+            self.decoder.offset += 1;
+        }
+        let item = self.lines.next()?;
+        self.cursor.line += 1;
+        self.cursor.column = 0;
+        Some(I::line(item).map_err(Error::from).and_then(|line| {
+            if self.offset_lines && line.as_ref().starts_with(b"#P") {
+                let start = parse_offset_line(line.as_ref()).map_err(|column| {
+                    Error::InvalidOffsetLine(
+                        String::from_utf8_lossy(line.as_ref()).into_owned(),
+                        column,
+                    )
+                })?;
+                let start = self
+                    .offset_position(start)
+                    .ok_or(Error::CoordinateOverflow(start))?;
+                I::recycle(&mut self.lines, I::bytes(line));
+                self.decoder.move_to(start);
+            } else if is_comment(line.as_ref(), &self.comment_prefixes)
+                | is_header_line(line.as_ref())
+            {
+                I::recycle(&mut self.lines, I::bytes(line));
+            } else {
+                self.current_line = Some(I::bytes(line));
+            }
+            Ok(())
+        }))
+    }
+
+    /// Skip the next `n` rows of the body, so that the parser continues at the beginning
+    /// of the row `n` rows below the current one.
+    ///
+    /// Only the run counts, the `$` and the `!` in the skipped rows are read. The runs of cells
+    /// are not decoded, so that a viewer can quickly jump to the middle of a tall pattern,
+    /// but invalid states in the skipped rows are not reported either. The remaining cells
+    /// in the current row are also skipped.
+    ///
+    /// The rows are counted by the pen in the body, before the offsets and the convention
+    /// are applied. If [`with_offset_lines`](Rle::with_offset_lines) is set, a `#P` line
+    /// that moves the pen below the target row ends the skip.
+    ///
+    /// After skipping, the [warning](Warning::StaleHeader) about the size in the header line
+    /// is not collected, since the extent of the skipped rows is unknown.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use ca_formats::rle::Rle;
+    ///
+    /// let mut glider = Rle::new("x = 3, y = 3\nbo$2bo$3o!").unwrap();
+    /// glider.skip_rows(2).unwrap();
+    /// let cells = glider.map(|cell| cell.unwrap().position).collect::<Vec<_>>();
+    /// assert_eq!(cells, [(0, 2), (1, 2), (2, 2)]);
+    /// ```
+    pub fn skip_rows(&mut self, n: u64) -> Result<(), Error> {
+        if n == 0 {
+            return Ok(());
+        }
+        let target = self.decoder.position.1.saturating_add_unsigned(n);
+        self.alive_count = 0;
+        self.skipped = true;
+        while !self.decoder.finished && self.decoder.position.1 < target {
+            if let Some(c) = self.next_byte() {
+                self.decoder.skip(c)?;
+            } else {
+                match self.next_line() {
+                    Some(result) => result?,
+                    None => break,
+                }
+            }
+        }
+        if let Some(dead_cells) = &mut self.dead_cells {
+            let start = (dead_cells.x_start, target);
+            if (dead_cells.cursor.1, dead_cells.cursor.0) < (start.1, start.0) {
+                dead_cells.cursor = start;
+                dead_cells.target = start;
+            }
+        }
+        Ok(())
+    }
+
+    /// The next byte of the current line.
+    fn next_byte(&mut self) -> Option<u8> {
+        let c = self.current_line.as_mut()?.next()?;
+        self.cursor.column += 1;
+        Some(c)
+    }
+
+    /// Count the remaining living cells by summing the run lengths,
+    /// without constructing the [`CellData`] of each cell.
+    ///
+    /// This is faster than [`count`](Iterator::count) when only the population is needed,
+    /// especially for patterns with long runs. Cells with state `0`, e.g., the dead cells
+    /// included by [`include_dead`](ParseOptions::include_dead), are not counted.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use ca_formats::rle::Rle;
+    ///
+    /// let glider = Rle::new("x = 3, y = 3\nbo$2bo$3o!").unwrap();
+    /// assert_eq!(glider.population().unwrap(), 5);
+    /// ```
+    pub fn population(mut self) -> Result<usize, Error> {
+        let mut population = 0;
+        if self.state != S::default() {
+            population += self.alive_count as usize;
+        }
+        loop {
+            if self.decoder.finished {
+                return Ok(population);
+            } else if let Some(c) = self.next_byte() {
+                if let Some(run) = self.decoder.decode(c)? {
+                    if S::try_from(run.state).is_err() {
+                        return Err(Error::InvalidState(encode_state(run.state)));
+                    }
+                    if run.state != 0 {
+                        population += run.count as usize;
+                    }
+                }
+            } else {
+                match self.next_line() {
+                    Some(result) => result?,
+                    None => return Ok(population),
+                }
+            }
+        }
+    }
+
+    /// Compute the bounding box of the remaining living cells from the runs,
+    /// without constructing the [`CellData`] of each cell.
+    ///
+    /// Unlike the header line, which may be missing or wrong, the bounding box
+    /// is computed from the body. The offsets and the convention are applied.
+    /// Returns `Ok(None)` if there is no living cell.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use ca_formats::rle::Rle;
+    ///
+    /// let glider = Rle::new("#CXRLE Pos=-1,-1\nbo$2bo$3o!").unwrap();
+    /// assert_eq!(glider.bounding_box().unwrap(), Some((-1, -1, 1, 1)));
+    /// ```
+    pub fn bounding_box(mut self) -> Result<Option<BoundingBox>, Error> {
+        let mut bounding_box = None;
+        if self.state != S::default() && self.alive_count > 0 {
+            let (x, y) = self.position;
+            extend_bounding_box(&mut bounding_box, self.place((x, y))?);
+            extend_bounding_box(
+                &mut bounding_box,
+                self.place((x + self.alive_count - 1, y))?,
+            );
+        }
+        loop {
+            if self.decoder.finished {
+                return Ok(bounding_box);
+            } else if let Some(c) = self.next_byte() {
+                if let Some(run) = self.decoder.decode(c)? {
+                    if S::try_from(run.state).is_err() {
+                        return Err(Error::InvalidState(encode_state(run.state)));
+                    }
+                    if run.state != 0 {
+                        let (x, y) = run.position;
+                        extend_bounding_box(&mut bounding_box, self.place((x, y))?);
+                        extend_bounding_box(&mut bounding_box, self.place((x + run.count - 1, y))?);
+                    }
+                }
+            } else {
+                match self.next_line() {
+                    Some(result) => result?,
+                    None => return Ok(bounding_box),
+                }
+            }
+        }
+    }
+
+    /// Compute the width and the height of the pattern from the body,
+    /// and compare them with the header line.
+    ///
+    /// The width and the height are measured from the upper left corner of the body,
+    /// i.e., the position in the `#CXRLE` line, to the last living cell,
+    /// so that the living cells fit in a grid of this size. If some cells are above
+    /// or to the left of the corner, e.g., when they are moved by `#P` lines,
+    /// the grid starts from them instead.
+    /// The offsets and the convention are ignored.
+    ///
+    /// It should be called before the iteration starts.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use ca_formats::rle::Rle;
+    ///
+    /// let glider = Rle::new("x = 2, y = 3, rule = B3/S23\nbo$2bo$3o!").unwrap();
+    /// let header = glider.infer_header().unwrap();
+    /// assert_eq!((header.inferred.x, header.inferred.y), (3, 3));
+    /// assert_eq!(header.inferred.rule.as_deref(), Some("B3/S23"));
+    /// assert!(header.is_mismatched());
+    /// assert!(header.is_too_small());
+    /// ```
+    pub fn infer_header(mut self) -> Result<InferredHeader, Error> {
+        let declared = self.header_data.clone();
+        self.ignore_cxrle_pos = true;
+        self.offset = (0, 0);
+        self.convention = Convention::default();
+        self.update_decoder();
+        let span = |min: i64, max: i64| {
+            let span = i128::from(max) - i128::from(min.min(0)) + 1;
+            u64::try_from(span).unwrap_or(u64::MAX)
+        };
+        let (x, y) = match self.bounding_box()? {
+            Some((min_x, min_y, max_x, max_y)) => (span(min_x, max_x), span(min_y, max_y)),
+            None => (0, 0),
+        };
+        let rule = declared.as_ref().and_then(|data| data.rule.clone());
+        let extra = declared
+            .as_ref()
+            .map(|data| data.extra.clone())
+            .unwrap_or_default();
+        Ok(InferredHeader {
+            declared,
+            inferred: HeaderData { x, y, rule, extra },
+        })
+    }
+
+    /// Decode the remaining cells into a buffer, and pass them to a callback
+    /// in slices of at most `chunk_size` cells.
+    ///
+    /// The cells are the same as the ones returned by the iterator, in the same order,
+    /// but decoding a whole run at a time avoids the overhead of calling
+    /// [`next`](Iterator::next) for each cell, which makes a difference for dense patterns.
+    /// A `chunk_size` of `0` is treated as `1`.
+    ///
+    /// When an error occurs, the cells before the error are passed to the callback
+    /// before the error is returned.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use ca_formats::rle::Rle;
+    ///
+    /// let glider = Rle::new("x = 3, y = 3, rule = B3/S23\nbo$2bo$3o!").unwrap();
+    /// let mut chunks = Vec::new();
+    /// glider
+    ///     .for_each_chunk(2, |cells| chunks.push(cells.len()))
+    ///     .unwrap();
+    /// assert_eq!(chunks, vec![2, 2, 1]);
+    /// ```
+    pub fn for_each_chunk<F>(mut self, chunk_size: usize, mut f: F) -> Result<(), Error>
+    where
+        F: FnMut(&[CellData<S>]),
+    {
+        let chunk_size = chunk_size.max(1);
+        let mut buffer = Vec::with_capacity(chunk_size);
+        loop {
+            let result = self.fill_chunk(&mut buffer, chunk_size);
+            if !buffer.is_empty() {
+                f(&buffer);
+                buffer.clear();
+            }
+            if !result? {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Decode cells into the buffer until it holds `chunk_size` cells.
+    ///
+    /// Returns `false` when the pattern ends before the buffer is full.
+    fn fill_chunk(
+        &mut self,
+        buffer: &mut Vec<CellData<S>>,
+        chunk_size: usize,
+    ) -> Result<bool, Error> {
+        if self.dead_cells.is_some() {
+            while buffer.len() < chunk_size {
+                match self.next() {
+                    Some(cell) => buffer.push(cell?),
+                    None => return Ok(false),
+                }
+            }
+            return Ok(true);
+        }
+        let (dx, dy) = self.shift();
+        let convention = self.convention;
+        loop {
+            let count = self.alive_count.min((chunk_size - buffer.len()) as i64);
+            let (x, y) = self.position;
+            let state = self.state;
+            for x in x..x + count {
+                let cell = (x.wrapping_add(dx), y.wrapping_add(dy));
+                let position = convention
+                    .apply(cell)
+                    .ok_or(Error::CoordinateOverflow(cell))?;
+                buffer.push(CellData { position, state });
+            }
+            self.alive_count -= count;
+            self.position.0 += count;
+            if buffer.len() == chunk_size {
+                return Ok(true);
+            } else if self.decoder.finished {
+                return Ok(false);
+            } else if let Some(c) = self.next_byte() {
+                if let Some(run) = self.decoder.decode(c)? {
+                    self.state = S::try_from(run.state)
+                        .map_err(|_| Error::InvalidState(encode_state(run.state)))?;
+                    self.position = run.position;
+                    self.alive_count = run.count;
+                }
+            } else {
+                match self.next_line() {
+                    Some(result) => result?,
+                    None => return Ok(false),
+                }
+            }
+        }
+    }
+
+    /// Return the location of the run of each cell in the input, e.g., for an editor
+    /// to map the cells back to the text. See [`Locations`].
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use ca_formats::rle::{Location, Rle};
+    ///
+    /// let glider = Rle::new("#N Glider\nx = 3, y = 3\nbo$2bo$\n3o!").unwrap();
+    /// let locations = glider
+    ///     .locations()
+    ///     .map(|cell| cell.unwrap().1.unwrap())
+    ///     .collect::<Vec<_>>();
+    /// assert_eq!(locations[0], Location { line: 3, column: 2 });
+    /// assert_eq!(locations[1], Location { line: 3, column: 6 });
+    /// assert_eq!(locations[4], Location { line: 4, column: 1 });
+    /// ```
+    pub fn locations(self) -> Locations<I, S> {
+        Locations { rle: self }
+    }
+
+    /// Group the remaining living cells into 8×8 tiles, reading whole runs at once
+    /// instead of constructing the [`CellData`] of each cell. See [`Tiles`].
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use ca_formats::rle::Rle;
+    ///
+    /// let rle = Rle::new("#CXRLE Pos=-1,0\n12o$o!").unwrap();
+    /// let tiles = rle.tiles().collect::<Result<Vec<_>, _>>().unwrap();
+    /// assert_eq!(
+    ///     tiles,
+    ///     vec![(-1, 0, 0x01_01 << 48), (0, 0, 0xff_00 << 48), (1, 0, 0xe0_00 << 48)]
+    /// );
+    /// ```
+    pub fn tiles(self) -> Tiles<I, S> {
+        Tiles {
+            rle: self,
+            band: None,
+            row: BTreeMap::new(),
+            ready: BTreeMap::new().into_iter(),
+            ready_y: 0,
+        }
+    }
+
+    /// The next run of cells, with the offsets applied, but not the convention.
+    ///
+    /// The run that is partly returned by the iterator is returned first.
+    fn next_run(&mut self) -> Option<Result<Run, Error>> {
+        if self.alive_count > 0 {
+            let (dx, dy) = self.shift();
+            let run = Run {
+                position: (
+                    self.position.0.wrapping_add(dx),
+                    self.position.1.wrapping_add(dy),
+                ),
+                state: self.state.into(),
+                count: self.alive_count,
+            };
+            self.position.0 += self.alive_count;
+            self.alive_count = 0;
+            return Some(Ok(run));
+        }
+        loop {
+            if self.decoder.finished {
+                return None;
+            } else if let Some(c) = self.next_byte() {
+                match self.decoder.decode(c) {
+                    Ok(Some(run)) => {
+                        if S::try_from(run.state).is_err() {
+                            return Some(Err(Error::InvalidState(encode_state(run.state))));
+                        }
+                        let (dx, dy) = self.shift();
+                        let (x, y) = run.position;
+                        return Some(Ok(Run {
+                            position: (x.wrapping_add(dx), y.wrapping_add(dy)),
+                            ..run
+                        }));
+                    }
+                    Ok(None) => (),
+                    Err(e) => return Some(Err(e)),
+                }
+            } else {
+                match self.next_line()? {
+                    Ok(()) => (),
+                    Err(e) => return Some(Err(e)),
+                }
+            }
+        }
+    }
+
+    /// Use another type for the states of the cells, e.g., `u16`.
+    ///
+    /// ```rust
+    /// use ca_formats::{rle::Rle, CellData};
+    ///
+    /// let rle = Rle::new("yX!").unwrap().with_state_type::<u16>();
+    /// let cells = rle.collect::<Result<Vec<_>, _>>().unwrap();
+    /// assert_eq!(cells, vec![CellData { position: (0, 0), state: 264 }]);
+    /// ```
+    pub fn with_state_type<T: State>(self) -> Rle<I, T> {
+        Rle {
+            comments: self.comments,
+            cxrle_data: self.cxrle_data,
+            header_data: self.header_data,
+            lines: self.lines,
+            current_line: self.current_line,
+            decoder: self.decoder,
+            position: self.position,
+            alive_count: self.alive_count,
+            state: T::try_from(self.state.into()).unwrap_or_default(),
+            ignore_cxrle_pos: self.ignore_cxrle_pos,
+            offset: self.offset,
+            convention: self.convention,
+            include_dead: self.include_dead,
+            dead_cells: self.dead_cells,
+            strict: self.strict,
+            max_cells: self.max_cells,
+            cell_count: self.cell_count,
+            offset_lines: self.offset_lines,
+            warnings: self.warnings,
+            extent: self.extent,
+            body_checked: self.body_checked,
+            skipped: self.skipped,
+            cursor: self.cursor,
+            comment_prefixes: self.comment_prefixes,
+            declared_population: self.declared_population,
+            synthesis_cost: self.synthesis_cost,
+        }
+    }
+}
+
+impl<I, B, S> Rle<I, S>
+where
+    I: Input<Lines = ReaderLines<B>>,
+    B: BufRead + Seek,
+    S: State,
+{
+    /// Rewind the parser to the beginning of its reader, e.g., a [`File`](std::fs::File)
+    /// in a [`BufReader`], to iterate over the cells again without reopening the file.
+    ///
+    /// The comments and the header are read again, so that changes in the file are
+    /// picked up, e.g., for a "reload" action. The options, e.g., the offset and the
+    /// [`ParseOptions`], are kept.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use ca_formats::rle::Rle;
+    /// use std::{fs::File, io::BufReader};
+    ///
+    /// let file = File::open("tests/sirrobin.rle").unwrap();
+    /// let mut sirrobin = Rle::new(BufReader::new(file)).unwrap().with_offset(1, 1);
+    /// let population = sirrobin.by_ref().count();
+    ///
+    /// let sirrobin = sirrobin.reset().unwrap();
+    /// assert_eq!(sirrobin.count(), population);
+    /// ```
+    pub fn reset(self) -> Result<Self, Error> {
+        let options = self.options();
+        let mut lines = self.lines;
+        lines.rewind()?;
+        let mut rle = Rle::from_lines(lines, self.comment_prefixes)?.with_state_type::<S>();
+        rle.ignore_cxrle_pos = self.ignore_cxrle_pos;
+        rle.offset = self.offset;
+        rle.decoder.columns = self.decoder.columns;
+        rle = rle.apply_options(options)?;
+        if self.offset_lines {
+            rle = rle.with_offset_lines();
+        }
+        Ok(rle)
+    }
+}
+
+impl<I, L, S> Rle<I, S>
+where
+    I: Input<Lines = L>,
+    L: Input,
+    S: State,
+{
+    /// Parse the remaining unparsed lines as a new RLE.
+    pub fn remains(self) -> Result<Rle<L, S>, Error> {
+        let options = self.options();
+        let mut rle = Rle::new_with_comment_prefixes(self.lines, &self.comment_prefixes)?
+            .with_state_type()
+            .with_offset(self.offset.0, self.offset.1);
+        rle.ignore_cxrle_pos = self.ignore_cxrle_pos;
+        rle = rle.apply_options(options)?;
+        if self.offset_lines {
+            rle = rle.with_offset_lines();
+        }
+        Ok(rle)
+    }
+
+    /// Try to parse the remaining unparsed lines as a new RLE.
+    ///
+    /// Returns `Ok(None)` if the remaining lines is empty or only
+    /// contains header lines and comments.
+    pub fn try_remains(self) -> Result<Option<Rle<L, S>>, Error> {
+        let rle = self.remains()?;
+        Ok(if rle.current_line.is_some() {
             Some(rle)
         } else {
             None
@@ -246,6 +1925,129 @@ where
     }
 }
 
+#[cfg(feature = "rayon")]
+#[cfg_attr(docs_rs, doc(cfg(feature = "rayon")))]
+impl<I: Input, S: State + Send> Rle<I, S> {
+    /// Decode the remaining cells in parallel, using [`rayon`](https://crates.io/crates/rayon).
+    ///
+    /// The rest of the body is read into memory, and split into chunks at the `$`
+    /// row boundaries after a quick scan. The chunks are decoded in parallel,
+    /// and the cells are returned in the same order as the iterator.
+    ///
+    /// The dead cells included by [`include_dead`](ParseOptions::include_dead) are not returned,
+    /// and [`max_cells`](ParseOptions::max_cells) is not checked.
+    /// If [`with_offset_lines`](Rle::with_offset_lines) is set, the cells are decoded
+    /// sequentially, because a `#P` line can move the pen anywhere.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use ca_formats::rle::Rle;
+    ///
+    /// let glider = Rle::new("x = 3, y = 3, rule = B3/S23\nbo$2bo$3o!").unwrap();
+    /// let cells = glider.par_cells().unwrap();
+    /// let cells = cells.into_iter().map(|cell| cell.position).collect::<Vec<_>>();
+    /// assert_eq!(cells, vec![(1, 0), (2, 1), (0, 2), (1, 2), (2, 2)]);
+    /// ```
+    pub fn par_cells(mut self) -> Result<Vec<CellData<S>>, Error> {
+        if self.offset_lines {
+            self.dead_cells = None;
+            return self.collect();
+        }
+        let mut cells = Vec::new();
+        while self.alive_count > 0 {
+            self.alive_count -= 1;
+            cells.push(CellData {
+                position: self.current_position()?,
+                state: self.state,
+            });
+            self.position.0 += 1;
+        }
+
+        let mut body = Vec::new();
+        let offset = self.decoder.offset;
+        let mut finished = self.decoder.finished;
+        while !finished {
+            if let Some(bytes) = self.current_line.as_mut() {
+                for c in bytes {
+                    if c == b'!' {
+                        finished = true;
+                        break;
+                    }
+                    body.push(c);
+                }
+                body.push(b'\n');
+            }
+            if !finished {
+                match self.next_line() {
+                    Some(result) => result?,
+                    None => break,
+                }
+            }
+        }
+
+        self.decoder.offset = offset;
+        let chunk_len = body.len() / (rayon::current_num_threads() * 4) + 1;
+        let mut starts = vec![(0, self.decoder.clone())];
+        let mut run_count = self.decoder.run_count;
+        let mut y = self.decoder.position.1;
+        for (i, &c) in body.iter().enumerate() {
+            if c.is_ascii_digit() {
+                run_count = run_count
+                    .saturating_mul(10)
+                    .saturating_add((c - b'0') as i64);
+            } else if c == b'$' {
+                y = y.saturating_add(run_count.max(1));
+                run_count = 0;
+                if i + 1 - starts[starts.len() - 1].0 >= chunk_len {
+                    starts.push((i + 1, self.decoder.at_row(y, self.decoder.offset + i + 1)));
+                }
+            } else if !c.is_ascii_whitespace() {
+                run_count = 0;
+            }
+        }
+
+        let ends = starts
+            .iter()
+            .skip(1)
+            .map(|(start, _)| *start)
+            .chain([body.len()])
+            .collect::<Vec<_>>();
+        let chunks = starts
+            .into_iter()
+            .zip(ends)
+            .map(|((start, decoder), end)| (&body[start..end], decoder))
+            .collect::<Vec<_>>();
+        let (dx, dy) = self.shift();
+        let convention = self.convention;
+        let results = chunks
+            .into_par_iter()
+            .map(|(chunk, mut decoder)| {
+                let mut cells = Vec::new();
+                for &c in chunk {
+                    if let Some(run) = decoder.decode(c)? {
+                        let state = S::try_from(run.state)
+                            .map_err(|_| Error::InvalidState(encode_state(run.state)))?;
+                        let (x, y) = run.position;
+                        for x in x..x + run.count {
+                            let cell = (x.wrapping_add(dx), y.wrapping_add(dy));
+                            let position = convention
+                                .apply(cell)
+                                .ok_or(Error::CoordinateOverflow(cell))?;
+                            cells.push(CellData { position, state });
+                        }
+                    }
+                }
+                Ok(cells)
+            })
+            .collect::<Vec<Result<_, Error>>>();
+        for result in results {
+            cells.extend(result?);
+        }
+        Ok(cells)
+    }
+}
+
 impl<R: Read> Rle<BufReader<R>> {
     /// Creates a new parser instance from something that implements [`Read`] trait,
     /// e.g., a [`File`](std::fs::File).
@@ -254,376 +2056,3364 @@ impl<R: Read> Rle<BufReader<R>> {
     }
 }
 
-impl<I: Input> Clone for Rle<I>
+impl<'a> Rle<Normalized<&'a str>> {
+    /// Creates a new parser instance from text pasted from the clipboard,
+    /// e.g., a pattern copied from Golly or from a forum post.
+    ///
+    /// It is more forgiving than [`Rle::new`]: blank lines and a byte order mark
+    /// before the pattern are skipped, and the text is [`Normalized`], so that
+    /// `CRLF` or `CR` line endings and trailing whitespaces are accepted.
+    /// As always, the header line may be missing.
+    /// [`to_clipboard_string`] writes text in the same format.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use ca_formats::rle::Rle;
+    ///
+    /// let glider = Rle::from_clipboard("\r\n  \r\nx = 3, y = 3, rule = B3/S23 \r\nbo$2bo$3o!\r\n").unwrap();
+    /// assert_eq!(glider.header_data().unwrap().rule.as_deref(), Some("B3/S23"));
+    /// assert_eq!(glider.count(), 5);
+    /// ```
+    pub fn from_clipboard(text: &'a str) -> Result<Self, Error> {
+        let text = text.trim_start_matches(|c: char| c.is_whitespace() || c == '\u{feff}');
+        Self::new(Normalized::new(text))
+    }
+}
+
+impl<I: Input, S: Clone> Clone for Rle<I, S>
 where
     I::Lines: Clone,
     I::Bytes: Clone,
 {
     fn clone(&self) -> Self {
         Self {
+            comments: self.comments.clone(),
             cxrle_data: self.cxrle_data.clone(),
             header_data: self.header_data.clone(),
             lines: self.lines.clone(),
             current_line: self.current_line.clone(),
+            decoder: self.decoder.clone(),
             position: self.position,
-            x_start: self.x_start,
-            run_count: self.run_count,
             alive_count: self.alive_count,
-            state: self.state,
-            state_prefix: self.state_prefix,
-            #[cfg(feature = "unknown")]
-            unknown: self.unknown,
+            state: self.state.clone(),
+            ignore_cxrle_pos: self.ignore_cxrle_pos,
+            offset: self.offset,
+            convention: self.convention,
+            include_dead: self.include_dead,
+            dead_cells: self.dead_cells.clone(),
+            strict: self.strict,
+            max_cells: self.max_cells,
+            cell_count: self.cell_count,
+            offset_lines: self.offset_lines,
+            warnings: self.warnings.clone(),
+            extent: self.extent,
+            body_checked: self.body_checked,
+            skipped: self.skipped,
+            cursor: self.cursor,
+            comment_prefixes: self.comment_prefixes.clone(),
+            declared_population: self.declared_population,
+            synthesis_cost: self.synthesis_cost,
         }
     }
 }
 
-/// An iterator over living cells in an RLE file.
-impl<I: Input> Iterator for Rle<I> {
-    type Item = Result<CellData, Error>;
-
-    fn next(&mut self) -> Option<Self::Item> {
-        if self.alive_count > 0 {
-            self.alive_count -= 1;
-            let cell = CellData {
-                position: self.position,
-                state: self.state,
-            };
-            self.position.0 += 1;
-            return Some(Ok(cell));
+impl<I: Input, S: State> Rle<I, S>
+where
+    I::Lines: Clone,
+    I::Bytes: Clone,
+{
+    /// Scan the rest of the body once, summing the run lengths, to find the exact
+    /// population and bounding box of the remaining living cells, without advancing
+    /// the parser.
+    ///
+    /// It is a first pass for inputs that can be read twice, e.g., `&str` and `&[u8]`:
+    /// the result gives the exact capacity of a buffer, or the size of a grid,
+    /// before the cells are read in the second pass. For very large patterns,
+    /// this is faster than letting a [`Vec`] grow by doubling. A parser of a file in a
+    /// [`BufReader`] can't be cloned, but it can be rewound with [`reset`](Rle::reset)
+    /// after the first pass instead.
+    ///
+    /// Like [`population`](Rle::population), cells with state `0` are not counted.
+    /// The offsets and the convention are applied to the bounding box.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use ca_formats::rle::Rle;
+    ///
+    /// let glider = Rle::new("#CXRLE Pos=-1,-1\nbo$2bo$3o!").unwrap();
+    /// let counts = glider.count_pass().unwrap();
+    /// assert_eq!(counts.population, 5);
+    /// assert_eq!(counts.bounding_box, Some((-1, -1, 1, 1)));
+    ///
+    /// let mut cells = Vec::with_capacity(counts.population);
+    /// for cell in glider {
+    ///     cells.push(cell.unwrap());
+    /// }
+    /// assert_eq!(cells.capacity(), 5);
+    /// ```
+    pub fn count_pass(&self) -> Result<Counts, Error> {
+        let mut rle = self.clone();
+        rle.dead_cells = None;
+        let mut counts = Counts::default();
+        while let Some(run) = rle.next_run() {
+            let run = run?;
+            if run.state == 0 || run.count <= 0 {
+                continue;
+            }
+            let (x, y) = run.position;
+            counts.population += run.count as usize;
+            for cell in [(x, y), (x + run.count - 1, y)] {
+                let position = self
+                    .convention
+                    .apply(cell)
+                    .ok_or(Error::CoordinateOverflow(cell))?;
+                extend_bounding_box(&mut counts.bounding_box, position);
+            }
         }
+        Ok(counts)
+    }
+}
+
+impl<I: Input, S: State> Rle<I, S> {
+    /// The next cell, before [`max_cells`](ParseOptions::max_cells) is checked.
+    fn next_cell(&mut self) -> Option<Result<CellData<S>, Error>> {
         loop {
-            if let Some(c) = self.current_line.as_mut().and_then(Iterator::next) {
-                if c.is_ascii_digit() {
-                    self.run_count = 10 * self.run_count + (c - b'0') as i64
-                } else if !c.is_ascii_whitespace() {
-                    if self.run_count == 0 {
-                        self.run_count = 1;
-                    }
-                    if self.state_prefix.is_some() && !(b'A'..=b'X').contains(&c) {
-                        let mut state_string = char::from(self.state_prefix.unwrap()).to_string();
-                        state_string.push(char::from(c));
-                        return Some(Err(Error::InvalidState(state_string)));
-                    }
-                    match c {
-                        #[cfg(feature = "unknown")]
-                        b'?' if self.unknown => {
-                            self.position.0 += self.run_count;
-                            self.run_count = 0;
-                        }
-                        #[cfg(feature = "unknown")]
-                        b'b' | b'.' | b'o' | b'A'..=b'X' if self.unknown => {
-                            match c {
-                                b'b' | b'.' => self.state = 0,
-                                b'o' => self.state = 1,
-                                _ => {
-                                    self.state =
-                                        24 * (self.state_prefix.take().unwrap_or(b'o') - b'o');
-                                    self.state += c + 1 - b'A';
-                                }
-                            }
-                            self.alive_count = self.run_count - 1;
-                            self.run_count = 0;
-                            let cell = CellData {
-                                position: self.position,
-                                state: self.state,
-                            };
-                            self.position.0 += 1;
-                            return Some(Ok(cell));
-                        }
-                        b'b' | b'.' => {
-                            self.position.0 += self.run_count;
-                            self.run_count = 0;
-                        }
-                        b'o' | b'A'..=b'X' => {
-                            if c == b'o' {
-                                self.state = 1;
-                            } else {
-                                self.state = 24 * (self.state_prefix.take().unwrap_or(b'o') - b'o');
-                                self.state += c + 1 - b'A';
+            if let Some(cell) = self.next_dead() {
+                return Some(cell);
+            } else if self.alive_count > 0 {
+                self.alive_count -= 1;
+                let cell = match self.current_position() {
+                    Ok(position) => CellData {
+                        position,
+                        state: self.state,
+                    },
+                    Err(e) => return Some(Err(e)),
+                };
+                if let Some(dead_cells) = &mut self.dead_cells {
+                    dead_cells.skip(self.position);
+                }
+                self.position.0 += 1;
+                return Some(Ok(cell));
+            } else if self.decoder.finished {
+                if let Err(e) = self.check_body() {
+                    return Some(Err(e));
+                }
+                return self.finish_dead();
+            } else if let Some(c) = self.next_byte() {
+                if self.decoder.is_idle() && !c.is_ascii_whitespace() {
+                    self.cursor.token = self.cursor.location();
+                }
+                match self.decoder.decode(c) {
+                    Ok(Some(run)) => match S::try_from(run.state) {
+                        Ok(state) => {
+                            self.cursor.run = self.cursor.token;
+                            let (x, y) = (run.position.0 + run.count - 1, run.position.1);
+                            self.extent = Some(
+                                self.extent
+                                    .map_or((x, y), |(max_x, max_y)| (max_x.max(x), max_y.max(y))),
+                            );
+                            self.state = state;
+                            self.position = run.position;
+                            self.alive_count = run.count;
+                            if let Some(dead_cells) = &mut self.dead_cells {
+                                dead_cells.target = run.position;
                             }
-                            self.alive_count = self.run_count - 1;
-                            self.run_count = 0;
-                            let cell = CellData {
-                                position: self.position,
-                                state: self.state,
-                            };
-                            self.position.0 += 1;
-                            return Some(Ok(cell));
-                        }
-                        b'p'..=b'y' => {
-                            self.state_prefix = Some(c);
                         }
-                        b'$' => {
-                            self.position.0 = self.x_start;
-                            self.position.1 += self.run_count;
-                            self.run_count = 0;
-                        }
-                        b'!' => {
-                            self.current_line = None;
-                            return None;
+                        Err(_) => return Some(Err(Error::InvalidState(encode_state(run.state)))),
+                    },
+                    Ok(None) => (),
+                    Err(e) => return Some(Err(e)),
+                }
+            } else {
+                match self.next_line() {
+                    Some(Ok(())) => (),
+                    Some(Err(e)) => return Some(Err(e)),
+                    None => {
+                        if let Err(e) = self.check_body() {
+                            return Some(Err(e));
                         }
-                        _ => return Some(Err(Error::InvalidState(char::from(c).to_string()))),
+                        return self.finish_dead();
                     }
                 }
-            } else if let Some(item) = self.lines.next() {
-                match I::line(item) {
-                    Ok(line) => {
-                        if line.as_ref().starts_with('#')
-                            | line.as_ref().starts_with("x ")
-                            | line.as_ref().starts_with("x=")
-                        {
-                            continue;
-                        } else {
-                            self.current_line = Some(I::bytes(line));
-                        }
+            }
+        }
+    }
+}
+
+/// An iterator over living cells in an RLE file.
+///
+/// Dead cells are also returned if they are included by [`ParseOptions::include_dead`].
+impl<I: Input, S: State> Iterator for Rle<I, S> {
+    type Item = Result<CellData<S>, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let max_cells = self.max_cells.unwrap_or(u64::MAX);
+        if self.cell_count > max_cells {
+            return None;
+        }
+        let cell = self.next_cell()?;
+        if cell.is_ok() {
+            if self.cell_count == max_cells {
+                self.cell_count += 1;
+                return Some(Err(Error::TooManyCells(max_cells)));
+            }
+            self.cell_count += 1;
+        }
+        Some(cell)
+    }
+}
+
+/// An 8×8 tile of living cells, as `(tile_x, tile_y, bits)`.
+///
+/// The tile covers the cells from `(8 * tile_x, 8 * tile_y)` to `(8 * tile_x + 7, 8 * tile_y + 7)`.
+/// The rows are the bytes of `bits` from the most significant one, and in each row,
+/// the leftmost cell is the most significant bit, as in a level 3 leaf of a Macrocell file,
+/// i.e., [`NodeData::Level3`](crate::macrocell::NodeData::Level3).
+pub type Tile = (i64, i64, u64);
+
+/// An iterator over the 8×8 tiles of the living cells in an RLE, created by [`Rle::tiles`].
+///
+/// This is the leaf representation that HashLife implementations use. The runs in the RLE
+/// are added to the tiles directly, so long runs are cheap. Since the runs are in the order
+/// of the rows, the tiles are returned band by band, where a band is 8 rows, and from left
+/// to right in each band. Only the tiles with living cells are returned.
+///
+/// Any non-zero state counts as a living cell. The offsets are applied,
+/// but the [`Convention`] is ignored. With [`Rle::with_offset_lines`], the runs
+/// may go back to a previous band, so that a tile may be returned more than once,
+/// and the bits of the same tile should be combined with `|`.
+#[must_use]
+pub struct Tiles<I: Input, S = u8> {
+    /// The parser.
+    rle: Rle<I, S>,
+    /// Y coordinate of the tiles in the current band.
+    band: Option<i64>,
+    /// Tiles of the current band, by their x coordinates.
+    row: BTreeMap<i64, u64>,
+    /// Tiles of a finished band that are not returned yet.
+    ready: btree_map::IntoIter<i64, u64>,
+    /// Y coordinate of the tiles in `ready`.
+    ready_y: i64,
+}
+
+impl<I: Input, S> fmt::Debug for Tiles<I, S>
+where
+    Rle<I, S>: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Tiles")
+            .field("rle", &self.rle)
+            .field("band", &self.band)
+            .field("row", &self.row)
+            .field("ready", &self.ready)
+            .field("ready_y", &self.ready_y)
+            .finish()
+    }
+}
+
+impl<I: Input, S: State> Tiles<I, S> {
+    /// Move the tiles of the current band to `ready`.
+    fn finish_band(&mut self) {
+        if let Some(band) = self.band.take() {
+            self.ready = std::mem::take(&mut self.row).into_iter();
+            self.ready_y = band;
+        }
+    }
+
+    /// Set the bits of a run of living cells.
+    fn add_run(&mut self, run: Run) {
+        let (x, y) = run.position;
+        let last = x + run.count - 1;
+        let shift = 8 * (7 - y.rem_euclid(8));
+        for tile_x in x.div_euclid(8)..=last.div_euclid(8) {
+            let start = (x - 8 * tile_x).max(0);
+            let end = (last - 8 * tile_x).min(7);
+            let row = (0xff_u8 >> start) & (0xff_u8 << (7 - end));
+            *self.row.entry(tile_x).or_default() |= u64::from(row) << shift;
+        }
+    }
+}
+
+impl<I: Input, S: State> Iterator for Tiles<I, S> {
+    type Item = Result<Tile, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some((tile_x, bits)) = self.ready.next() {
+                return Some(Ok((tile_x, self.ready_y, bits)));
+            }
+            match self.rle.next_run() {
+                Some(Ok(run)) => {
+                    if run.state == 0 || run.count <= 0 {
+                        continue;
                     }
-                    Err(e) => {
-                        return Some(Err(Error::IoError(e)));
+                    let band = run.position.1.div_euclid(8);
+                    if self.band != Some(band) {
+                        self.finish_band();
+                        self.band = Some(band);
                     }
+                    self.add_run(run);
                 }
-            } else {
-                return None;
+                Some(Err(e)) => return Some(Err(e)),
+                None => {
+                    self.finish_band();
+                    if self.ready.len() == 0 {
+                        return None;
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// An iterator over the RLEs in a file containing many concatenated RLEs,
+/// e.g., a pattern collection.
+///
+/// Each RLE ends with the line that contains its `!`. Its comment lines, `#CXRLE` line
+/// and header line are the ones between the previous `!` and its body.
+/// Blank lines between RLEs are skipped.
+///
+/// The lines of each RLE are read and buffered before the RLE is returned,
+/// so that the RLEs can be kept after the stream moves on.
+/// Lines that are not valid UTF-8 are decoded lossily.
+///
+/// Unlike [`Rle::remains`], the state type and the other options of the RLEs
+/// have to be set on each returned RLE.
+///
+/// # Example
+///
+/// ```rust
+/// use ca_formats::rle::RleStream;
+///
+/// const COLLECTION: &str = "#N Glider\nx = 3, y = 3\nbo$2bo$3o!\n\n#N Blinker\nx = 3, y = 1\n3o!";
+///
+/// let mut stream = RleStream::new(COLLECTION);
+/// let glider = stream.next().unwrap().unwrap();
+/// assert_eq!(glider.comments(), ["#N Glider"]);
+/// assert_eq!(glider.count(), 5);
+///
+/// let blinker = stream.next().unwrap().unwrap();
+/// assert_eq!(blinker.comments(), ["#N Blinker"]);
+/// assert_eq!(blinker.header_data().unwrap().x, 3);
+/// assert!(stream.next().is_none());
+/// ```
+#[must_use]
+#[derive(Debug)]
+pub struct RleStream<I: Input> {
+    /// An iterator over lines of the file.
+    lines: I::Lines,
+}
+
+impl<I: Input> RleStream<I> {
+    /// Creates a new stream from input.
+    pub fn new(input: I) -> Self {
+        Self {
+            lines: input.lines(),
+        }
+    }
+}
+
+impl<R: Read> RleStream<BufReader<R>> {
+    /// Creates a new stream from something that implements [`Read`] trait,
+    /// e.g., a [`File`](std::fs::File).
+    pub fn new_from_file(file: R) -> Self {
+        Self::new(BufReader::new(file))
+    }
+}
+
+impl<I: Input> Clone for RleStream<I>
+where
+    I::Lines: Clone,
+{
+    fn clone(&self) -> Self {
+        Self {
+            lines: self.lines.clone(),
+        }
+    }
+}
+
+/// An iterator over the RLEs in a file.
+impl<I: Input> Iterator for RleStream<I> {
+    type Item = Result<Rle<IntoIter<String>>, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut lines = Vec::new();
+        let mut in_body = false;
+        while let Some(item) = self.lines.next() {
+            let line = match I::line(item) {
+                Ok(line) => line,
+                Err(e) => return Some(Err(Error::from(e))),
+            };
+            let bytes = line.as_ref();
+            let is_header = bytes.starts_with(b"#") || is_header_line(bytes);
+            let is_blank = bytes.iter().all(u8::is_ascii_whitespace);
+            let is_last = !is_header && bytes.contains(&b'!');
+            if in_body || !is_blank {
+                lines.push(String::from_utf8_lossy(bytes).into_owned());
+                in_body |= !is_header && !is_blank;
+            }
+            I::recycle(&mut self.lines, I::bytes(line));
+            if is_last {
+                break;
+            }
+        }
+        if in_body {
+            Some(Rle::new(lines.into_iter()))
+        } else {
+            None
+        }
+    }
+}
+
+/// Encode a state, e.g., `A` for `1`, `pA` for `25`.
+fn encode_state(state: u32) -> String {
+    let (prefix, suffix) = ((state - 1) / 24, (state - 1) % 24);
+    let mut string = String::new();
+    if prefix > 0 {
+        string.push(char::from(b'o' + prefix as u8));
+    }
+    string.push(char::from(b'A' + suffix as u8));
+    string
+}
+
+/// A token in the body of an RLE file, returned by [`Tokens`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Token {
+    /// A run count, e.g., `3` in `3o`. It applies to the next token.
+    Run(u64),
+    /// `b` or `.`: a dead cell.
+    Dead,
+    /// `o` or a multistate tag, e.g., `A` or `pA`: a living cell with the given state.
+    Alive(u32),
+    /// `?`: an unknown cell.
+    Unknown,
+    /// `$`: the end of a row.
+    Eol,
+    /// `!`: the end of the pattern.
+    End,
+}
+
+/// A low-level iterator over the [`Token`]s in the body of an RLE file.
+///
+/// It only splits the body into tokens, without computing the coordinates of the cells,
+/// so it can be used by syntax highlighters, linters or alternative decoders.
+/// Whitespaces are skipped, and the iteration stops after [`Token::End`].
+///
+/// # Example
+///
+/// ```rust
+/// use ca_formats::rle::{Token, Tokens};
+///
+/// let tokens = Tokens::new("2o$\n.pA!").collect::<Result<Vec<_>, _>>().unwrap();
+/// assert_eq!(
+///     tokens,
+///     [
+///         Token::Run(2),
+///         Token::Alive(1),
+///         Token::Eol,
+///         Token::Dead,
+///         Token::Alive(25),
+///         Token::End,
+///     ]
+/// );
+/// ```
+#[must_use]
+#[derive(Clone, Debug)]
+pub struct Tokens<'a> {
+    /// The body.
+    body: &'a [u8],
+    /// Byte offset of the next token.
+    offset: usize,
+    /// Byte offset of the last token.
+    start: usize,
+    /// Whether a [`Token::End`] has been returned.
+    finished: bool,
+}
+
+impl<'a> Tokens<'a> {
+    /// Creates a new tokenizer from the body of an RLE file, without the header line.
+    pub fn new<B: AsRef<[u8]> + ?Sized>(body: &'a B) -> Self {
+        Tokens {
+            body: body.as_ref(),
+            offset: 0,
+            start: 0,
+            finished: false,
+        }
+    }
+
+    /// The bytes of the last token returned, e.g., `pA` or `12`.
+    pub fn last_span(&self) -> std::ops::Range<usize> {
+        self.start..self.offset
+    }
+
+    /// The rest of the body that is not yet read, e.g., the trailing characters after `!`.
+    pub fn rest(&self) -> &'a [u8] {
+        &self.body[self.offset..]
+    }
+
+    /// Read a token starting at `self.start`.
+    fn token(&mut self) -> Result<Token, Error> {
+        let c = self.body[self.offset];
+        self.offset += 1;
+        let token = match c {
+            b'0'..=b'9' => {
+                let mut count = u64::from(c - b'0');
+                while let Some(&d) = self.body.get(self.offset).filter(|d| d.is_ascii_digit()) {
+                    count = count
+                        .checked_mul(10)
+                        .and_then(|count| count.checked_add(u64::from(d - b'0')))
+                        .ok_or(Error::RunCountOverflow(self.start))?;
+                    self.offset += 1;
+                }
+                Token::Run(count)
+            }
+            b'b' | b'.' => Token::Dead,
+            b'o' => Token::Alive(1),
+            b'?' => Token::Unknown,
+            b'$' => Token::Eol,
+            b'!' => Token::End,
+            b'A'..=b'X' => Token::Alive((c + 1 - b'A') as u32),
+            b'p'..=b'y' => match self.body.get(self.offset) {
+                Some(&d @ b'A'..=b'X') => {
+                    self.offset += 1;
+                    Token::Alive(24 * (c - b'o') as u32 + (d + 1 - b'A') as u32)
+                }
+                next => {
+                    let mut state_string = char::from(c).to_string();
+                    state_string.extend(next.map(|&d| char::from(d)));
+                    return Err(Error::InvalidState(state_string));
+                }
+            },
+            _ => return Err(Error::InvalidState(char::from(c).to_string())),
+        };
+        Ok(token)
+    }
+}
+
+impl Iterator for Tokens<'_> {
+    type Item = Result<Token, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.finished {
+            return None;
+        }
+        while self.body.get(self.offset)?.is_ascii_whitespace() {
+            self.offset += 1;
+        }
+        self.start = self.offset;
+        let token = self.token();
+        match token {
+            Ok(Token::End) => self.finished = true,
+            Err(_) => self.finished = true,
+            _ => (),
+        }
+        Some(token)
+    }
+}
+
+/// A run of cells with the same state in the body of an RLE file.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+struct Run {
+    /// Coordinates of the first cell in the run.
+    position: Coordinates,
+    /// State of the cells.
+    ///
+    /// It is `0` only when unknown cells are allowed.
+    state: u32,
+    /// Number of cells in the run.
+    count: i64,
+}
+
+/// Where a run starts in the input, returned by [`Locations`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Location {
+    /// Line number, starting from `1`, counting the comment lines and the header line.
+    pub line: usize,
+    /// Column in bytes, starting from `1`.
+    pub column: usize,
+}
+
+/// Where the parser is in the input, and where the current run starts.
+#[derive(Clone, Copy, Debug, Default)]
+struct Cursor {
+    /// Number of the current line, starting from `1`.
+    line: usize,
+    /// Number of bytes read from the current line.
+    column: usize,
+    /// Start of the run that is being read, i.e., its run count or its tag.
+    token: Location,
+    /// Start of the run of the current cell.
+    run: Location,
+}
+
+impl Cursor {
+    /// Location of the last byte read.
+    const fn location(&self) -> Location {
+        Location {
+            line: self.line,
+            column: self.column,
+        }
+    }
+}
+
+/// An iterator over the cells of an RLE, together with the [`Location`] of the run
+/// of each cell in the input, created by [`Rle::locations`].
+///
+/// A location is the start of the run, i.e., its run count, or its tag if there is
+/// no run count. The dead cells included by [`ParseOptions::include_dead`] are not
+/// in any run, and their locations are `None`.
+#[must_use]
+pub struct Locations<I: Input, S = u8> {
+    /// The parser.
+    rle: Rle<I, S>,
+}
+
+impl<I: Input, S> fmt::Debug for Locations<I, S>
+where
+    Rle<I, S>: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Locations").field("rle", &self.rle).finish()
+    }
+}
+
+impl<I: Input, S: State> Locations<I, S> {
+    /// The wrapped parser.
+    pub fn into_inner(self) -> Rle<I, S> {
+        self.rle
+    }
+}
+
+impl<I: Input, S: State> Iterator for Locations<I, S> {
+    type Item = Result<(CellData<S>, Option<Location>), Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let cell = match self.rle.next()? {
+            Ok(cell) => cell,
+            Err(e) => return Some(Err(e)),
+        };
+        let from_run = self.rle.dead_cells.is_none() || cell.state != S::default();
+        Some(Ok((cell, from_run.then_some(self.rle.cursor.run))))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.rle.size_hint()
+    }
+}
+
+/// Dead cells in the bounding box declared by the header line, for [`ParseOptions::include_dead`].
+///
+/// The cells are visited in the order of the rows, by a cursor that moves
+/// towards a target, skipping the living cells.
+#[derive(Clone, Debug)]
+struct DeadCells {
+    /// X coordinate of the left edge of the bounding box.
+    x_start: i64,
+    /// X coordinate just after the right edge of the bounding box.
+    x_end: i64,
+    /// Y coordinate just below the bottom edge of the bounding box.
+    y_end: i64,
+    /// Coordinates of the next cell that may be dead.
+    cursor: Coordinates,
+    /// Coordinates of the next living cell. Dead cells are returned until the cursor reaches it.
+    target: Coordinates,
+}
+
+impl DeadCells {
+    /// Creates a new instance for a bounding box whose upper left corner is `start`.
+    fn new(start: Coordinates, header_data: &HeaderData) -> Self {
+        let (x, y) = start;
+        DeadCells {
+            x_start: x,
+            x_end: x.saturating_add_unsigned(header_data.x),
+            y_end: y.saturating_add_unsigned(header_data.y),
+            cursor: start,
+            target: start,
+        }
+    }
+
+    /// Move the cursor to the cell after a living cell.
+    fn skip(&mut self, (x, y): Coordinates) {
+        self.cursor = (x + 1, y);
+    }
+}
+
+impl Iterator for DeadCells {
+    type Item = Coordinates;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.cursor.0 >= self.x_end {
+            self.cursor = (self.x_start, self.cursor.1 + 1);
+        }
+        let (x, y) = self.cursor;
+        if y >= self.y_end || (y, x) >= (self.target.1, self.target.0) {
+            return None;
+        }
+        self.cursor.0 += 1;
+        Some((x, y))
+    }
+}
+
+/// A state machine that decodes the body of an RLE file byte by byte.
+#[derive(Clone, Debug, Default)]
+struct Decoder {
+    /// Coordinates of the current cell.
+    position: Coordinates,
+
+    /// X coordinates of the upper left corner of the pattern.
+    x_start: i64,
+
+    /// Run count in the RLE string, i.e., the numbers before `b`, `o`, `$` and other tags.
+    run_count: i64,
+
+    /// Prefix in a multi-char state, i.e., `p` in `pA`.
+    state_prefix: Option<u8>,
+
+    /// Whether the `!` at the end of the pattern has been read.
+    finished: bool,
+
+    /// Translation of the decoded coordinates by the offsets of the parser,
+    /// so that runs whose translated coordinates are out of range are rejected.
+    shift: (i128, i128),
+
+    /// Whether the translated y coordinates are negated, as in [`Convention::y_up`].
+    y_up: bool,
+
+    /// The range of the translated x coordinates that runs are clipped to,
+    /// as in [`Rle::columns`].
+    columns: Option<(i128, i128)>,
+
+    /// Whether this RLE file allows unknown cells.
+    unknown: bool,
+
+    /// Number of bytes of the body read so far, counting a line break after each line.
+    offset: usize,
+
+    /// Offset of the first digit of the current run count.
+    count_start: usize,
+}
+
+impl Decoder {
+    /// Creates a new decoder, starting at the position in the `#CXRLE` line.
+    fn new(cxrle_data: Option<&CxrleData>) -> Self {
+        let position = cxrle_data.and_then(|data| data.pos).unwrap_or_default();
+        Decoder {
+            position,
+            x_start: position.0,
+            ..Decoder::default()
+        }
+    }
+
+    /// Move to a new block starting at `position`, discarding an unfinished run count.
+    fn move_to(&mut self, position: Coordinates) {
+        self.position = position;
+        self.x_start = position.0;
+        self.run_count = 0;
+        self.state_prefix = None;
+    }
+
+    /// Creates a new decoder with the same options, starting at the beginning of row `y`,
+    /// at `offset` bytes of the body.
+    #[cfg(feature = "rayon")]
+    fn at_row(&self, y: i64, offset: usize) -> Self {
+        Decoder {
+            position: (self.x_start, y),
+            offset,
+            x_start: self.x_start,
+            shift: self.shift,
+            y_up: self.y_up,
+            columns: self.columns,
+            unknown: self.unknown,
+            ..Decoder::default()
+        }
+    }
+
+    /// Decode a byte. Returns a run when a run of living cells is finished.
+    fn decode(&mut self, c: u8) -> Result<Option<Run>, Error> {
+        let offset = self.offset;
+        self.offset += 1;
+        if self.finished || c.is_ascii_whitespace() {
+            return Ok(None);
+        } else if c.is_ascii_digit() {
+            if self.run_count == 0 {
+                self.count_start = offset;
+            }
+            self.run_count = self
+                .run_count
+                .checked_mul(10)
+                .and_then(|count| count.checked_add((c - b'0') as i64))
+                .ok_or(Error::RunCountOverflow(self.count_start))?;
+            return Ok(None);
+        }
+        if self.run_count == 0 {
+            self.run_count = 1;
+        }
+        if let Some(prefix) = self.state_prefix {
+            if !(b'A'..=b'X').contains(&c) {
+                let mut state_string = char::from(prefix).to_string();
+                state_string.push(char::from(c));
+                return Err(Error::InvalidState(state_string));
+            }
+        }
+        let state = match c {
+            b'?' if self.unknown => {
+                self.advance()?;
+                return Ok(None);
+            }
+            b'b' | b'.' if self.unknown => 0,
+            b'b' | b'.' => {
+                self.advance()?;
+                return Ok(None);
             }
+            b'o' => 1,
+            b'A'..=b'X' => {
+                let prefix = self.state_prefix.take().unwrap_or(b'o');
+                24 * (prefix - b'o') as u32 + (c + 1 - b'A') as u32
+            }
+            b'p'..=b'y' => {
+                self.state_prefix = Some(c);
+                return Ok(None);
+            }
+            b'$' => {
+                self.position.1 = self
+                    .position
+                    .1
+                    .checked_add(self.run_count)
+                    .ok_or(Error::CoordinateOverflow(self.position))?;
+                self.position.0 = self.x_start;
+                self.run_count = 0;
+                return Ok(None);
+            }
+            b'!' => {
+                self.finished = true;
+                return Ok(None);
+            }
+            _ => return Err(Error::InvalidState(char::from(c).to_string())),
+        };
+        let run = Run {
+            position: self.position,
+            state,
+            count: self.run_count,
+        };
+        self.advance()?;
+        self.check(&run)?;
+        Ok(self.clip(run))
+    }
+
+    /// Whether a cell is in the range of [`Rle::columns`] after it is translated.
+    fn in_columns(&self, x: i64) -> bool {
+        self.columns.is_none_or(|(start, end)| {
+            let x = i128::from(x) + self.shift.0;
+            start <= x && x <= end
+        })
+    }
+
+    /// Clip a run to the range of [`Rle::columns`].
+    ///
+    /// Returns `None` if the run is entirely outside the range.
+    fn clip(&self, run: Run) -> Option<Run> {
+        let Some((start, end)) = self.columns else {
+            return Some(run);
+        };
+        let x_start = i128::from(run.position.0) + self.shift.0;
+        let x_end = x_start + i128::from(run.count) - 1;
+        let (clipped_start, clipped_end) = (x_start.max(start), x_end.min(end));
+        if clipped_start > clipped_end {
+            return None;
+        }
+        Some(Run {
+            position: (
+                run.position.0 + (clipped_start - x_start) as i64,
+                run.position.1,
+            ),
+            count: (clipped_end - clipped_start + 1) as i64,
+            ..run
+        })
+    }
+
+    /// Read a byte in a row that is skipped by [`Rle::skip_rows`],
+    /// without decoding the runs of cells.
+    fn skip(&mut self, c: u8) -> Result<(), Error> {
+        if let b'0'..=b'9' | b'$' | b'!' = c {
+            return self.decode(c).map(drop);
+        }
+        self.offset += 1;
+        match c {
+            b'p'..=b'y' => {}
+            _ if c.is_ascii_whitespace() => {}
+            _ => {
+                self.run_count = 0;
+                self.state_prefix = None;
+            }
+        }
+        Ok(())
+    }
+
+    /// Whether the decoder is between two runs, i.e., no run count or state prefix
+    /// has been read for the next run.
+    fn is_idle(&self) -> bool {
+        self.run_count == 0 && self.state_prefix.is_none()
+    }
+
+    /// Move the pen right by the run count.
+    fn advance(&mut self) -> Result<(), Error> {
+        self.position.0 = self
+            .position
+            .0
+            .checked_add(self.run_count)
+            .ok_or(Error::CoordinateOverflow(self.position))?;
+        self.run_count = 0;
+        Ok(())
+    }
+
+    /// Check that the cells of a run are still in range after they are translated
+    /// and converted into the convention.
+    fn check(&self, run: &Run) -> Result<(), Error> {
+        let (x, y) = run.position;
+        let (dx, dy) = self.shift;
+        let x_start = i128::from(x) + dx;
+        let x_end = x_start + i128::from(run.count) - 1;
+        let y = i128::from(y) + dy;
+        let in_range = |v: i128| i64::try_from(v).is_ok();
+        if in_range(x_start)
+            && in_range(x_end)
+            && in_range(y)
+            && !(self.y_up && y == i128::from(i64::MIN))
+        {
+            Ok(())
+        } else {
+            Err(Error::CoordinateOverflow(run.position))
+        }
+    }
+}
+
+/// What the [`PushParser`] is reading.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum PushMode {
+    /// The beginning of a line.
+    LineStart,
+    /// The beginning of a line that may still be a header line, e.g., `  x`,
+    /// which is kept in the buffer.
+    MaybeHeader,
+    /// A comment line or a header line before the body, which is kept in the buffer.
+    HeaderLine,
+    /// A comment line or a header line in the body, which is skipped.
+    SkippedLine,
+    /// A line in the body.
+    Body,
+}
+
+/// A push-style parser for RLE, for input that arrives in chunks, e.g., from a socket or a pipe.
+///
+/// Unlike [`Rle`], which pulls lines from its input, this parser is fed with chunks of bytes
+/// by [`feed`](PushParser::feed), and returns the cells that can be decoded from each chunk
+/// immediately. Chunks do not need to end at line boundaries.
+///
+/// Comment lines and header lines are recognized as in [`Rle`]: lines starting with `#` are
+/// comments (or the `#CXRLE` line), and lines starting with `x =` or `X =`, possibly after
+/// whitespaces, are header lines.
+///
+/// # Example
+///
+/// ```rust
+/// use ca_formats::rle::PushParser;
+///
+/// let mut parser = PushParser::new();
+/// let mut cells = Vec::new();
+/// for chunk in ["#CXRLE Pos=-1,-1\nx = 3, y", " = 3\nbo$2b", "o$3o!"] {
+///     for cell in parser.feed(chunk.as_bytes()) {
+///         cells.push(cell.unwrap().position);
+///     }
+/// }
+/// parser.finish().for_each(drop);
+///
+/// assert_eq!(parser.header_data().unwrap().x, 3);
+/// assert_eq!(cells, vec![(0, -1), (1, 0), (-1, 1), (0, 1), (1, 1)]);
+/// ```
+///
+/// Reading from the standard input:
+///
+/// ```rust,no_run
+/// use ca_formats::rle::PushParser;
+/// use std::io::{stdin, Read};
+///
+/// let mut parser = PushParser::new();
+/// let mut buffer = [0; 4096];
+/// let mut stdin = stdin().lock();
+/// loop {
+///     let len = stdin.read(&mut buffer).unwrap();
+///     if len == 0 {
+///         break;
+///     }
+///     for cell in parser.feed(&buffer[..len]) {
+///         println!("{:?}", cell.unwrap());
+///     }
+/// }
+/// for cell in parser.finish() {
+///     println!("{:?}", cell.unwrap());
+/// }
+/// ```
+#[must_use]
+#[derive(Clone, Debug)]
+pub struct PushParser<S = u8> {
+    /// Data from the `#CXRLE` line.
+    cxrle_data: Option<CxrleData>,
+
+    /// Data from the header line.
+    header_data: Option<HeaderData>,
+
+    /// Whether the body has started.
+    in_body: bool,
+
+    /// What the parser is reading.
+    mode: PushMode,
+
+    /// The current comment line or header line.
+    buffer: Vec<u8>,
+
+    /// Bytes of a line that turned out to be in the body, to be decoded
+    /// before the rest of the input.
+    replay: VecDeque<u8>,
+
+    /// The state machine that decodes the body.
+    decoder: Decoder,
+
+    /// Coordinates of the current cell.
+    position: Coordinates,
+
+    /// Remaining run count for the current cell.
+    alive_count: i64,
+
+    /// State of the current cell.
+    state: S,
+
+    /// Convention of the returned coordinates.
+    convention: Convention,
+}
+
+impl PushParser {
+    /// Creates a new push parser.
+    pub fn new() -> Self {
+        Self::new_with_options(ParseOptions::default())
+    }
+
+    /// Creates a new push parser with the [`ParseOptions`] that make sense for it:
+    /// the convention, and whether to allow unknown cells.
+    /// See [`Rle::new_with_options`].
+    pub fn new_with_options(options: ParseOptions) -> Self {
+        let decoder = Decoder {
+            unknown: options.unknown,
+            y_up: options.convention.y_up,
+            ..Decoder::default()
+        };
+        PushParser {
+            cxrle_data: None,
+            header_data: None,
+            in_body: false,
+            mode: PushMode::LineStart,
+            buffer: Vec::new(),
+            replay: VecDeque::new(),
+            decoder,
+            position: (0, 0),
+            alive_count: 0,
+            state: 1,
+            convention: options.convention,
+        }
+    }
+}
+
+impl Default for PushParser {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<S: State> PushParser<S> {
+    /// Data from the `#CXRLE` line.
+    ///
+    /// Only available after the line has been fed.
+    pub const fn cxrle_data(&self) -> Option<&CxrleData> {
+        self.cxrle_data.as_ref()
+    }
+
+    /// Data from the header line.
+    ///
+    /// Only available after the line has been fed.
+    pub const fn header_data(&self) -> Option<&HeaderData> {
+        self.header_data.as_ref()
+    }
+
+    /// Whether the `!` at the end of the pattern has been fed.
+    ///
+    /// Everything after it is ignored.
+    pub const fn is_finished(&self) -> bool {
+        self.decoder.finished
+    }
+
+    /// Use another type for the states of the cells, e.g., `u16`.
+    pub fn with_state_type<T: State>(self) -> PushParser<T> {
+        PushParser {
+            cxrle_data: self.cxrle_data,
+            header_data: self.header_data,
+            in_body: self.in_body,
+            mode: self.mode,
+            buffer: self.buffer,
+            replay: self.replay,
+            decoder: self.decoder,
+            position: self.position,
+            alive_count: self.alive_count,
+            state: T::try_from(self.state.into()).unwrap_or_default(),
+            convention: self.convention,
+        }
+    }
+
+    /// Feeds a chunk of input to the parser.
+    ///
+    /// Returns an iterator over the living cells that can be decoded so far.
+    /// Cells that are not taken from the iterator before it is dropped
+    /// are returned by the next call of `feed` or [`finish`](PushParser::finish).
+    pub fn feed<'a>(&'a mut self, chunk: &'a [u8]) -> Feed<'a, S> {
+        Feed {
+            parser: self,
+            chunk,
+            error: None,
+            finishing: false,
+        }
+    }
+
+    /// Tells the parser that the input has ended.
+    ///
+    /// Returns an iterator over the remaining living cells.
+    /// Anything fed after this is ignored.
+    pub fn finish(&mut self) -> Feed<'_, S> {
+        let error = self.end_line().err();
+        self.mode = PushMode::LineStart;
+        if self.replay.is_empty() {
+            self.decoder.finished = true;
+        }
+        Feed {
+            parser: self,
+            chunk: &[],
+            error,
+            finishing: true,
+        }
+    }
+
+    /// Start decoding the body, if it has not started yet.
+    fn start_body(&mut self) {
+        if !self.in_body {
+            self.in_body = true;
+            self.decoder = Decoder {
+                y_up: self.decoder.y_up,
+                unknown: self.decoder.unknown,
+                ..Decoder::new(self.cxrle_data.as_ref())
+            };
+        }
+    }
+
+    /// Parse the buffered comment line or header line.
+    fn end_line(&mut self) -> Result<(), Error> {
+        if self.mode == PushMode::MaybeHeader {
+            self.start_body();
+            self.replay.extend(self.buffer.drain(..));
+            return Ok(());
+        }
+        if self.mode != PushMode::HeaderLine {
+            return Ok(());
+        }
+        let line = mem::take(&mut self.buffer);
+        let line = line.strip_suffix(b"\r").unwrap_or(&line);
+        let lossy = || String::from_utf8_lossy(line).into_owned();
+        if line.starts_with(b"#CXRLE") {
+            let data = parse_cxrle(line);
+            self.cxrle_data =
+                Some(data.map_err(|column| Error::InvalidCxrleLine(lossy(), column))?);
+        } else if is_header_line(line) {
+            let data = parse_header(line);
+            self.header_data =
+                Some(data.map_err(|column| Error::InvalidHeaderLine(lossy(), column))?);
+        }
+        Ok(())
+    }
+
+    /// Read a byte. Returns a run when a run of living cells is finished.
+    fn push(&mut self, c: u8) -> Result<Option<Run>, Error> {
+        match self.mode {
+            PushMode::LineStart if c == b'#' => {
+                if self.in_body {
+                    self.mode = PushMode::SkippedLine;
+                } else {
+                    self.mode = PushMode::HeaderLine;
+                    self.buffer.push(c);
+                }
+                Ok(None)
+            }
+            PushMode::LineStart if matches!(c, b'x' | b'X' | b' ' | b'\t') => {
+                self.mode = PushMode::MaybeHeader;
+                self.buffer.push(c);
+                Ok(None)
+            }
+            PushMode::MaybeHeader => {
+                self.buffer.push(c);
+                if is_header_line(&self.buffer) {
+                    if self.in_body {
+                        self.buffer.clear();
+                        self.mode = PushMode::SkippedLine;
+                    } else {
+                        self.mode = PushMode::HeaderLine;
+                    }
+                } else if c == b'\n' || !is_header_prefix(&self.buffer) {
+                    self.end_line()?;
+                    self.mode = if c == b'\n' {
+                        PushMode::LineStart
+                    } else {
+                        PushMode::Body
+                    };
+                }
+                Ok(None)
+            }
+            PushMode::LineStart | PushMode::Body => {
+                self.start_body();
+                self.mode = if c == b'\n' {
+                    PushMode::LineStart
+                } else {
+                    PushMode::Body
+                };
+                self.decoder.decode(c)
+            }
+            PushMode::HeaderLine => {
+                if c == b'\n' {
+                    let result = self.end_line();
+                    self.mode = PushMode::LineStart;
+                    result?;
+                } else {
+                    self.buffer.push(c);
+                }
+                Ok(None)
+            }
+            PushMode::SkippedLine => {
+                if c == b'\n' {
+                    self.mode = PushMode::LineStart;
+                }
+                Ok(None)
+            }
+        }
+    }
+}
+
+/// An iterator over living cells decoded from a chunk of input,
+/// returned by [`PushParser::feed`] and [`PushParser::finish`].
+#[must_use]
+#[derive(Debug)]
+pub struct Feed<'a, S = u8> {
+    /// The parser.
+    parser: &'a mut PushParser<S>,
+    /// The remaining part of the chunk.
+    chunk: &'a [u8],
+    /// An error to be returned before anything else.
+    error: Option<Error>,
+    /// Whether the input has ended, so that the decoder is finished
+    /// after the replayed bytes.
+    finishing: bool,
+}
+
+impl<S: State> Iterator for Feed<'_, S> {
+    type Item = Result<CellData<S>, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(e) = self.error.take() {
+            return Some(Err(e));
+        }
+        let parser = &mut *self.parser;
+        loop {
+            if parser.alive_count > 0 {
+                parser.alive_count -= 1;
+                let cell = parser.position;
+                parser.position.0 += 1;
+                return Some(match parser.convention.apply(cell) {
+                    Some(position) => Ok(CellData {
+                        position,
+                        state: parser.state,
+                    }),
+                    None => Err(Error::CoordinateOverflow(cell)),
+                });
+            }
+            let result = if let Some(c) = parser.replay.pop_front() {
+                parser.decoder.decode(c)
+            } else {
+                if self.finishing {
+                    parser.decoder.finished = true;
+                }
+                let (&c, rest) = self.chunk.split_first()?;
+                self.chunk = rest;
+                parser.push(c)
+            };
+            match result {
+                Ok(Some(run)) => match S::try_from(run.state) {
+                    Ok(state) => {
+                        parser.state = state;
+                        parser.position = run.position;
+                        parser.alive_count = run.count;
+                    }
+                    Err(_) => return Some(Err(Error::InvalidState(encode_state(run.state)))),
+                },
+                Ok(None) => (),
+                Err(e) => return Some(Err(e)),
+            }
+        }
+    }
+}
+
+/// Maximal length of a line in the body of an RLE file written by [`write_rle`] or [`RleWriter`].
+const LINE_LENGTH: usize = 70;
+
+/// Encode a living state, e.g., `o` for `1` in 2-state rules, `pA` for `25`.
+fn state_tag(state: u32, multistate: bool) -> String {
+    if multistate {
+        encode_state(state)
+    } else {
+        String::from("o")
+    }
+}
+
+/// The rule of a History or Super rule without the annotation states,
+/// e.g., `B3/S23` for `LifeHistory`.
+fn history_base_rule(rule: &str) -> &str {
+    let base = ["History", "Super"]
+        .iter()
+        .find_map(|suffix| rule.strip_suffix(suffix))
+        .unwrap_or(rule);
+    if base.is_empty() || base == "Life" {
+        "B3/S23"
+    } else {
+        base
+    }
+}
+
+/// Writes cells in RLE format.
+///
+/// The cells can be given in any order. Cells with state `0` are ignored.
+/// If a position appears more than once, only the first cell is written.
+///
+/// The header line is always recomputed from the bounding box of the cells,
+/// so a stale header line of a parsed file, as reported by [`Rle::infer_header`],
+/// is never copied.
+/// If the upper left corner of the bounding box is not `(0, 0)`,
+/// a `#CXRLE` line is written to keep the coordinates of the cells.
+///
+/// If all the states are `1`, the cells are written in the 2-state syntax (`b`, `o`),
+/// otherwise in the multi-state syntax (`.`, `A`, `B`, ...).
+///
+/// # Example
+///
+/// ```rust
+/// use ca_formats::{rle::write_rle, CellData};
+///
+/// let cells = [(1, 0), (2, 1), (0, 2), (1, 2), (2, 2)].map(CellData::from);
+/// let mut rle = Vec::new();
+/// write_rle(&mut rle, Some("B3/S23"), cells).unwrap();
+/// assert_eq!(rle, b"x = 3, y = 3, rule = B3/S23\nbo$2bo$3o!\n");
+/// ```
+pub fn write_rle<W, S, C>(writer: W, rule: Option<&str>, cells: C) -> Result<(), IoError>
+where
+    W: Write,
+    S: State,
+    C: IntoIterator<Item = CellData<S>>,
+{
+    write_rle_with_gen(writer, rule, None, CxrlePolicy::default(), cells)
+}
+
+/// Writes cells in RLE format as a string to be copied to the clipboard,
+/// e.g., to be pasted into Golly.
+///
+/// Like a selection copied in Golly, it has only the header line and the body:
+/// the pattern is moved so that its upper left corner is at `(0, 0)`,
+/// and no `#CXRLE` line is written. Otherwise it is the same as [`write_rle`].
+/// [`Rle::from_clipboard`] reads it back.
+///
+/// # Example
+///
+/// ```rust
+/// use ca_formats::{rle::to_clipboard_string, CellData};
+///
+/// let cells = [(0, -1), (1, -1), (2, -1)].map(CellData::<u8>::from);
+/// let rle = to_clipboard_string(Some("B3/S23"), cells).unwrap();
+/// assert_eq!(rle, "x = 3, y = 1, rule = B3/S23\n3o!\n");
+/// ```
+pub fn to_clipboard_string<S, C>(rule: Option<&str>, cells: C) -> Result<String, IoError>
+where
+    S: State,
+    C: IntoIterator<Item = CellData<S>>,
+{
+    let mut rle = Vec::new();
+    write_rle_with_gen(&mut rle, rule, None, CxrlePolicy::Never, cells)?;
+    String::from_utf8(rle).map_err(|e| IoError::new(ErrorKind::InvalidData, e))
+}
+
+/// Writes cells in RLE format, with the generation in the `#CXRLE` line
+/// if the policy allows it.
+pub(crate) fn write_rle_with_gen<W, S, C>(
+    writer: W,
+    rule: Option<&str>,
+    gen: Option<u64>,
+    cxrle_policy: CxrlePolicy,
+    cells: C,
+) -> Result<(), IoError>
+where
+    W: Write,
+    S: State,
+    C: IntoIterator<Item = CellData<S>>,
+{
+    let mut cells = cells
+        .into_iter()
+        .filter(|cell| cell.state != S::default())
+        .collect::<Vec<_>>();
+    cells.sort_by_key(|cell| (cell.position.1, cell.position.0));
+    cells.dedup_by_key(|cell| cell.position);
+
+    let (mut x_min, mut x_max, mut y_min, mut y_max) = (0, -1, 0, -1);
+    if let Some(first) = cells.first() {
+        (x_min, x_max) = (first.position.0, first.position.0);
+        (y_min, y_max) = (first.position.1, cells[cells.len() - 1].position.1);
+        for cell in &cells {
+            x_min = x_min.min(cell.position.0);
+            x_max = x_max.max(cell.position.0);
+        }
+    }
+    let multistate = cells.iter().any(|cell| cell.state.into() > 1);
+
+    let mut rle_writer = RleWriter::new(writer)
+        .with_size(span(x_min, x_max)?, span(y_min, y_max)?)
+        .with_origin(x_min, y_min)
+        .with_cxrle_policy(cxrle_policy)
+        .multistate(multistate);
+    if let Some(rule) = rule {
+        rle_writer = rle_writer.with_rule(rule);
+    }
+    if let Some(gen) = gen {
+        rle_writer = rle_writer.with_gen(gen);
+    }
+    for cell in cells {
+        rle_writer.write_cell(cell)?;
+    }
+    rle_writer.finish()?;
+    Ok(())
+}
+
+/// A writer that writes cells in RLE format incrementally, without buffering the whole pattern.
+///
+/// The cells must be given in row-major order, i.e., sorted by the y coordinate,
+/// and then by the x coordinate, as returned by [`Rle`] or [`sorted`](crate::sorted).
+/// A cell that arrives out of order is an error of kind [`ErrorKind::InvalidInput`].
+/// With [`buffer_rows`](RleWriter::buffer_rows), the cells in each row are buffered,
+/// so that only the rows need to be in order.
+///
+/// The upper left corner in the `#CXRLE` line is given by
+/// [`with_origin`](RleWriter::with_origin). The cells must not be above or to the left of
+/// the upper left corner. Cells with state `0` are ignored.
+///
+/// If the size is given by [`with_size`](RleWriter::with_size), the header line is written
+/// when the first cell is written, so the options should be set before that. Otherwise,
+/// the body is buffered until [`finish`](RleWriter::finish), and the header line is
+/// written with the size of the bounding box of the cells, measured from the upper left
+/// corner.
+///
+/// # Example
+///
+/// ```rust
+/// use ca_formats::{rle::RleWriter, CellData};
+///
+/// let mut writer = RleWriter::new(Vec::new()).with_rule("B3/S23");
+/// for cell in [(1, 0), (2, 1), (0, 2), (1, 2), (2, 2)] {
+///     writer.write_cell(CellData::from(cell)).unwrap();
+/// }
+/// let rle = writer.finish().unwrap();
+/// assert_eq!(rle, b"x = 3, y = 3, rule = B3/S23\nbo$2bo$3o!\n");
+///
+/// let mut writer = RleWriter::new(Vec::new());
+/// writer.write_cell(CellData::from((1, 1))).unwrap();
+/// assert!(writer.write_cell(CellData::from((0, 1))).is_err());
+/// ```
+#[derive(Debug)]
+pub struct RleWriter<W: Write> {
+    /// The sink.
+    writer: Sink<W>,
+
+    /// Rulestring in the header line.
+    rule: Option<String>,
+
+    /// Width and height in the header line, or `None` if they are computed from the cells.
+    size: Option<(u64, u64)>,
+
+    /// Generation in the `#CXRLE` line.
+    gen: Option<u64>,
+
+    /// Population in the `#C population = N` comment.
+    population: Option<u64>,
+
+    /// When to write the `#CXRLE` line.
+    cxrle_policy: CxrlePolicy,
+
+    /// Coordinates of the upper left corner of the pattern.
+    origin: Coordinates,
+
+    /// Whether to use the multi-state syntax.
+    multistate: bool,
+
+    /// Whether to buffer the cells in each row.
+    buffer_rows: bool,
+
+    /// Whether to strip the annotation states of History and Super rules.
+    strip_history: bool,
+
+    /// Whether to compress the output with gzip.
+    #[cfg(feature = "flate2")]
+    gzip: bool,
+
+    /// Whether the header line has been written.
+    started: bool,
+
+    /// Position of the next cell that can be written without a `$` or a dead run.
+    ///
+    /// The x coordinate is an `i128`, because it is one past the last written cell,
+    /// which may be at `i64::MAX`.
+    position: (i128, i64),
+
+    /// The current run of living cells, as its state and its length.
+    run: Option<(u32, i64)>,
+
+    /// Length of the current line of the body.
+    line_length: usize,
+
+    /// Y coordinate and cells of the buffered row, as x coordinates and states.
+    row: Option<(i64, Vec<(i64, u32)>)>,
+
+    /// The body, buffered until the size is known, if it is not given.
+    body: Vec<u8>,
+
+    /// The largest x and y coordinates of the written cells.
+    extent: Option<Coordinates>,
+}
+
+impl<W: Write> RleWriter<W> {
+    /// Creates a new writer that writes to a sink, e.g., a [`File`](std::fs::File).
+    pub fn new(writer: W) -> Self {
+        RleWriter {
+            writer: Sink::new(writer),
+            rule: None,
+            size: None,
+            gen: None,
+            population: None,
+            cxrle_policy: CxrlePolicy::Auto,
+            origin: (0, 0),
+            multistate: false,
+            buffer_rows: false,
+            strip_history: false,
+            #[cfg(feature = "flate2")]
+            gzip: false,
+            started: false,
+            position: (0, 0),
+            run: None,
+            line_length: 0,
+            row: None,
+            body: Vec::new(),
+            extent: None,
+        }
+    }
+
+    /// Write a rulestring in the header line.
+    pub fn with_rule(mut self, rule: &str) -> Self {
+        self.rule = Some(rule.to_owned());
+        self
+    }
+
+    /// Write a width and a height in the header line.
+    ///
+    /// The body is then written directly to the sink, and the size is not checked
+    /// against the written cells. Without this option, the body is buffered until
+    /// [`finish`](RleWriter::finish), and the size is computed from the cells.
+    pub fn with_size(mut self, x: u64, y: u64) -> Self {
+        self.size = Some((x, y));
+        self
+    }
+
+    /// Write a generation in the `#CXRLE` line.
+    pub fn with_gen(mut self, gen: u64) -> Self {
+        self.gen = Some(gen);
+        self
+    }
+
+    /// Write a population in a `#C population = N` comment before the header line.
+    ///
+    /// It is not checked against the written cells.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use ca_formats::{rle::RleWriter, CellData};
+    ///
+    /// let mut writer = RleWriter::new(Vec::new()).with_size(3, 1).with_population(3);
+    /// for x in 0..3 {
+    ///     writer.write_cell(CellData::from((x, 0))).unwrap();
+    /// }
+    /// let rle = writer.finish().unwrap();
+    /// assert_eq!(rle, b"#C population = 3\nx = 3, y = 1\n3o!\n");
+    /// ```
+    pub fn with_population(mut self, population: u64) -> Self {
+        self.population = Some(population);
+        self
+    }
+
+    /// Set the coordinates of the upper left corner of the pattern.
+    ///
+    /// It is written in the `#CXRLE` line, depending on the
+    /// [`with_cxrle_policy`](RleWriter::with_cxrle_policy).
+    pub fn with_origin(mut self, x: i64, y: i64) -> Self {
+        self.origin = (x, y);
+        self.position = (i128::from(x), y);
+        self
+    }
+
+    /// Set when to write the `#CXRLE` line, [`CxrlePolicy::Auto`] by default.
+    pub fn with_cxrle_policy(mut self, policy: CxrlePolicy) -> Self {
+        self.cxrle_policy = policy;
+        self
+    }
+
+    /// Use the multi-state syntax (`.`, `A`, `B`, ...) instead of the 2-state syntax (`b`, `o`).
+    ///
+    /// In the 2-state syntax, cells with states other than `0` and `1` are errors.
+    /// Ignored with [`strip_history`](RleWriter::strip_history).
+    pub fn multistate(mut self, multistate: bool) -> Self {
+        self.multistate = multistate;
+        self
+    }
+
+    /// Buffer the cells in each row, so that the cells in a row can be given in any order.
+    ///
+    /// The rows must still be given in order. If a position appears more than once
+    /// in a row, only the first cell is written.
+    pub fn buffer_rows(mut self, buffer_rows: bool) -> Self {
+        self.buffer_rows = buffer_rows;
+        self
+    }
+
+    /// Strip the annotation states of a pattern in a History or Super rule,
+    /// e.g., `LifeHistory` or `B36/S23Super`, and write it as a 2-state pattern.
+    ///
+    /// Odd states are alive and even states are dead. The cells are written in the 2-state
+    /// syntax (`b`, `o`), even with [`multistate`](RleWriter::multistate), to match
+    /// the 2-state rule in the header line: the `History` or `Super` suffix is removed
+    /// from the rule, and `Life` becomes `B3/S23`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use ca_formats::{rle::RleWriter, CellData};
+    ///
+    /// let mut writer = RleWriter::new(Vec::new())
+    ///     .with_rule("LifeHistory")
+    ///     .multistate(true)
+    ///     .strip_history(true);
+    /// for (position, state) in [((0, 0), 1u8), ((1, 0), 2), ((2, 0), 3), ((3, 0), 6)] {
+    ///     writer.write_cell(CellData { position, state }).unwrap();
+    /// }
+    /// let rle = writer.finish().unwrap();
+    /// assert_eq!(rle, b"x = 3, y = 1, rule = B3/S23\nobo!\n");
+    /// ```
+    pub fn strip_history(mut self, strip_history: bool) -> Self {
+        self.strip_history = strip_history;
+        self
+    }
+
+    /// Compress the output with gzip, e.g., to write a `.rle.gz` file, as Golly does.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use ca_formats::{rle::RleWriter, CellData};
+    /// use flate2::read::GzDecoder;
+    /// use std::io::Read;
+    ///
+    /// let mut writer = RleWriter::new(Vec::new()).with_size(3, 1).gzip(true);
+    /// for x in 0..3 {
+    ///     writer.write_cell(CellData::from((x, 0))).unwrap();
+    /// }
+    /// let gz = writer.finish().unwrap();
+    /// assert_eq!(&gz[..2], b"\x1f\x8b");
+    ///
+    /// let mut rle = String::new();
+    /// GzDecoder::new(gz.as_slice()).read_to_string(&mut rle).unwrap();
+    /// assert_eq!(rle, "x = 3, y = 1\n3o!\n");
+    /// ```
+    #[cfg(feature = "flate2")]
+    #[cfg_attr(docs_rs, doc(cfg(feature = "flate2")))]
+    pub fn gzip(mut self, gzip: bool) -> Self {
+        self.gzip = gzip;
+        self
+    }
+
+    /// Write a cell.
+    pub fn write_cell<S: State>(&mut self, cell: CellData<S>) -> Result<(), IoError> {
+        let mut state = cell.state.into();
+        if self.strip_history {
+            state %= 2;
+        }
+        if state == 0 {
+            return Ok(());
+        } else if (!self.multistate && state > 1) || state > 264 {
+            return Err(IoError::new(
+                ErrorKind::InvalidInput,
+                format!("state {} can't be encoded in RLE", state),
+            ));
+        }
+        let (x, y) = cell.position;
+        if !self.buffer_rows {
+            return self.push_cell((x, y), state);
+        }
+        match &mut self.row {
+            Some((row_y, row)) if *row_y == y => {
+                row.push((x, state));
+                Ok(())
+            }
+            Some((row_y, _)) if *row_y > y => Err(out_of_order((x, y))),
+            _ => {
+                self.flush_row()?;
+                self.row = Some((y, vec![(x, state)]));
+                Ok(())
+            }
+        }
+    }
+
+    /// Write the end of the pattern, and return the sink.
+    pub fn finish(mut self) -> Result<W, IoError> {
+        self.flush_row()?;
+        self.start()?;
+        if let Some((state, count)) = self.run.take() {
+            self.push_run(count, &state_tag(state, self.multistate_syntax()))?;
+        }
+        self.push_run(1, "!")?;
+        writeln!(self.body())?;
+        if self.size.is_none() {
+            let size = match self.extent {
+                Some((x, y)) => (span(self.origin.0, x)?, span(self.origin.1, y)?),
+                None => (0, 0),
+            };
+            self.write_header(size)?;
+            self.writer.write_all(&self.body)?;
+        }
+        self.writer.finish()
+    }
+
+    /// Start writing the body. The header is written now if the size is known,
+    /// and at the end otherwise.
+    fn start(&mut self) -> Result<(), IoError> {
+        if self.started {
+            return Ok(());
+        }
+        self.started = true;
+        #[cfg(feature = "flate2")]
+        if self.gzip {
+            self.writer.compress();
+        }
+        match self.size {
+            Some(size) => self.write_header(size),
+            None => Ok(()),
+        }
+    }
+
+    /// Whether the cells are written in the multi-state syntax.
+    const fn multistate_syntax(&self) -> bool {
+        self.multistate && !self.strip_history
+    }
+
+    /// Where the body is written: the sink, or the buffer if the size is not known yet.
+    fn body(&mut self) -> &mut dyn Write {
+        if self.size.is_some() {
+            &mut self.writer
+        } else {
+            &mut self.body
+        }
+    }
+
+    /// Write the `#CXRLE` line and the header line.
+    fn write_header(&mut self, (x, y): (u64, u64)) -> Result<(), IoError> {
+        let write_cxrle = match self.cxrle_policy {
+            CxrlePolicy::Auto => self.gen.is_some() || self.origin != (0, 0),
+            CxrlePolicy::Always => true,
+            CxrlePolicy::Never => false,
+        };
+        if write_cxrle {
+            let cxrle_data = CxrleData {
+                pos: Some(self.origin),
+                gen: self.gen,
+                ..Default::default()
+            };
+            writeln!(self.writer, "{}", cxrle_data)?;
+        }
+        if let Some(population) = self.population {
+            writeln!(self.writer, "#C population = {}", population)?;
+        }
+        let header_data = HeaderData {
+            x,
+            y,
+            rule: self
+                .rule
+                .as_deref()
+                .map(|rule| {
+                    if self.strip_history {
+                        history_base_rule(rule)
+                    } else {
+                        rule
+                    }
+                })
+                .map(str::to_owned),
+            ..Default::default()
+        };
+        writeln!(self.writer, "{}", header_data)
+    }
+
+    /// Write the buffered row.
+    fn flush_row(&mut self) -> Result<(), IoError> {
+        if let Some((y, mut row)) = self.row.take() {
+            row.sort_by_key(|&(x, _)| x);
+            row.dedup_by_key(|&mut (x, _)| x);
+            for (x, state) in row {
+                self.push_cell((x, y), state)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Write a living cell, which must not be before the current position.
+    fn push_cell(&mut self, (x, y): Coordinates, state: u32) -> Result<(), IoError> {
+        let (current_x, current_y) = self.position;
+        if y < current_y || (y == current_y && i128::from(x) < current_x) || x < self.origin.0 {
+            return Err(out_of_order((x, y)));
+        }
+        self.start()?;
+        if let Some((run_state, count)) = self.run {
+            if y != current_y || i128::from(x) != current_x || run_state != state {
+                self.push_run(count, &state_tag(run_state, self.multistate_syntax()))?;
+                self.run = None;
+            }
+        }
+        if y != current_y {
+            let count = y
+                .checked_sub(current_y)
+                .ok_or_else(|| out_of_range((x, y)))?;
+            self.push_run(count, "$")?;
+            self.position = (i128::from(self.origin.0), y);
+        }
+        if i128::from(x) != self.position.0 {
+            let dead = if self.multistate_syntax() { "." } else { "b" };
+            let count =
+                i64::try_from(i128::from(x) - self.position.0).map_err(|_| out_of_range((x, y)))?;
+            self.push_run(count, dead)?;
+        }
+        self.run = Some(
+            self.run
+                .map_or((state, 1), |(state, count)| (state, count + 1)),
+        );
+        self.position = (i128::from(x) + 1, y);
+        self.extent = Some(
+            self.extent
+                .map_or((x, y), |(max_x, max_y)| (max_x.max(x), max_y.max(y))),
+        );
+        Ok(())
+    }
+
+    /// Write a run, breaking lines when necessary.
+    fn push_run(&mut self, count: i64, tag: &str) -> Result<(), IoError> {
+        let run = if count > 1 {
+            format!("{}{}", count, tag)
+        } else {
+            tag.to_string()
+        };
+        if self.line_length + run.len() > LINE_LENGTH {
+            writeln!(self.body())?;
+            self.line_length = 0;
+        }
+        self.body().write_all(run.as_bytes())?;
+        self.line_length += run.len();
+        Ok(())
+    }
+}
+
+/// The error for a cell whose distance from the previous cell or the origin
+/// doesn't fit in a run count of an [`RleWriter`].
+fn out_of_range((x, y): Coordinates) -> IoError {
+    IoError::new(
+        ErrorKind::InvalidInput,
+        format!("cell ({}, {}) is out of range", x, y),
+    )
+}
+
+/// The number of cells from `min` to `max`, as the width or the height in a header line.
+pub(crate) fn span(min: i64, max: i64) -> Result<u64, IoError> {
+    u64::try_from(i128::from(max) - i128::from(min) + 1).map_err(|_| {
+        IoError::new(
+            ErrorKind::InvalidInput,
+            format!("the span from {} to {} is too large", min, max),
+        )
+    })
+}
+
+/// The error for a cell that is given out of order to an [`RleWriter`].
+fn out_of_order((x, y): Coordinates) -> IoError {
+    IoError::new(
+        ErrorKind::InvalidInput,
+        format!("cell ({}, {}) is out of order", x, y),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rle_parse_cxrle() {
+        assert_eq!(
+            parse_cxrle(b"#CXRLE"),
+            Ok(CxrleData {
+                pos: None,
+                gen: None,
+                ..Default::default()
+            })
+        );
+        assert_eq!(
+            parse_cxrle(b"#CXRLE Pos=0,-1377 Gen=3480106827776"),
+            Ok(CxrleData {
+                pos: Some((0, -1377)),
+                gen: Some(3480106827776),
+                ..Default::default()
+            })
+        );
+        assert_eq!(
+            parse_cxrle(b"#CXRLE Gen = 3480106827776 Pos = 0, -1377"),
+            Ok(CxrleData {
+                pos: Some((0, -1377)),
+                gen: Some(3480106827776),
+                ..Default::default()
+            })
+        );
+        assert_eq!(
+            parse_cxrle(b"#CXRLE211Pos=0,-9dcdcs2,[a ccGen=348sss1068cscPos= -333,-1a6"),
+            Ok(CxrleData {
+                pos: Some((-333, -1)),
+                gen: Some(348),
+                ..Default::default()
+            })
+        );
+        assert_eq!(parse_cxrle(b"#CXRLE Pos=0,99999999999999999999"), Err(14));
+
+        for line in [
+            "#CXRLE",
+            "#CXRLE Pos=0,-1377 Gen=3480106827776",
+            "#CXRLE Gen=3 Layer=2 Name=glider",
+        ] {
+            let data = parse_cxrle(line.as_bytes()).unwrap();
+            assert_eq!(parse_cxrle(data.to_string().as_bytes()), Ok(data));
+        }
+
+        let data = parse_cxrle(b"#CXRLE Pos=0,-1 Layer=2 Name = glider x=1=2 Gen=3 3d=4").unwrap();
+        assert_eq!((data.pos, data.gen), (Some((0, -1)), Some(3)));
+        assert_eq!(
+            data.extra.into_iter().collect::<Vec<_>>(),
+            [
+                (String::from("Layer"), String::from("2")),
+                (String::from("Name"), String::from("glider"))
+            ]
+        );
+    }
+
+    #[test]
+    fn rle_parse_header() {
+        assert_eq!(parse_header(b"xxx"), Err(2));
+        assert_eq!(
+            parse_header(b"x = 3, y = 3, rule = B3/S23"),
+            Ok(HeaderData {
+                x: 3,
+                y: 3,
+                rule: Some(String::from("B3/S23")),
+                ..Default::default()
+            })
+        );
+        assert_eq!(
+            parse_header(b"x = 3, y = 3"),
+            Ok(HeaderData {
+                x: 3,
+                y: 3,
+                rule: None,
+                ..Default::default()
+            })
+        );
+        assert_eq!(parse_header(b"x = 3, y = -3"), Err(12));
+        assert_eq!(parse_header(b"x = 3, y = 3, rule =  "), Err(23));
+        assert_eq!(parse_header(b"x = 3, y = 3 z"), Err(14));
+        assert_eq!(parse_header(b"x = 99999999999999999999, y = 3"), Err(5));
+        assert_eq!(
+            parse_header(b"x = 3, y = 3, rule = Conway's Game of Life  "),
+            Ok(HeaderData {
+                x: 3,
+                y: 3,
+                rule: Some(String::from("Conway's Game of Life")),
+                ..Default::default()
+            })
+        );
+
+        assert_eq!(
+            parse_header(b"x=3,y=3"),
+            Ok(HeaderData {
+                x: 3,
+                y: 3,
+                ..Default::default()
+            })
+        );
+        let header_data =
+            parse_header(b" X = 3 ,\tY=2,Rule = B3/S23:T10,10, color = red, green , generation=10")
+                .unwrap();
+        assert_eq!((header_data.x, header_data.y), (3, 2));
+        assert_eq!(header_data.rule.as_deref(), Some("B3/S23:T10,10"));
+        assert_eq!(
+            header_data.extra.into_iter().collect::<Vec<_>>(),
+            [
+                (String::from("color"), String::from("red, green")),
+                (String::from("generation"), String::from("10"))
+            ]
+        );
+        assert_eq!(parse_header(b"x=3,y=3, 3d = yes"), Err(10));
+        assert_eq!(parse_header(b"x=3,y=3, color"), Err(15));
+        assert_eq!(parse_header(b"x=3,y=3, color = "), Err(18));
+        for line in [
+            "x = 3, y = 3",
+            "x = 0, y = 1, rule = B3/S23:T10,10",
+            "X=3,Y=2,Rule=B3/S23, color = red, green, generation=10",
+        ] {
+            let data = parse_header(line.as_bytes()).unwrap();
+            assert_eq!(parse_header(data.to_string().as_bytes()), Ok(data));
+        }
+        assert!(is_header_line(b"  X\t= 3"));
+        assert!(!is_header_line(b"xo$"));
+
+        let rle = Rle::new("X=3,Y=1,RULE=B3/S23\n3o!").unwrap();
+        assert_eq!(
+            rle.header_data().and_then(|data| data.rule.as_deref()),
+            Some("B3/S23")
+        );
+        assert_eq!(rle.count(), 3);
+    }
+
+    #[test]
+    fn rle_glider() -> Result<(), Error> {
+        const GLIDER: &str = r"#N Glider
+#O Richard K. Guy
+#C The smallest, most common, and first discovered spaceship. Diagonal, has period 4 and speed c/4.
+#C www.conwaylife.com/wiki/index.php?title=Glider
+x = 3, y = 3, rule = B3/S23
+bob$2bo$3o!";
+
+        let glider = Rle::new(GLIDER)?;
+
+        let _ = glider.clone();
+
+        assert_eq!(glider.cxrle_data, None);
+        assert_eq!(
+            glider.header_data,
+            Some(HeaderData {
+                x: 3,
+                y: 3,
+                rule: Some(String::from("B3/S23")),
+                ..Default::default()
+            })
+        );
+
+        let cells = glider
+            .map(|res| res.map(|c| c.position))
+            .collect::<Result<Vec<_>, _>>()?;
+        assert_eq!(cells, vec![(1, 0), (2, 1), (0, 2), (1, 2), (2, 2)]);
+        Ok(())
+    }
+
+    #[test]
+    fn rle_glider_cxrle() -> Result<(), Error> {
+        const GLIDER: &str = r"#CXRLE Pos=-1,-1
+x = 3, y = 3, rule = B3/S23
+bo$2bo$3o!";
+
+        let glider = Rle::new(GLIDER)?;
+
+        assert_eq!(
+            glider.cxrle_data,
+            Some(CxrleData {
+                pos: Some((-1, -1)),
+                gen: None,
+                ..Default::default()
+            })
+        );
+        assert_eq!(
+            glider.header_data,
+            Some(HeaderData {
+                x: 3,
+                y: 3,
+                rule: Some(String::from("B3/S23")),
+                ..Default::default()
+            })
+        );
+
+        let cells = glider
+            .map(|res| res.map(|c| c.position))
+            .collect::<Result<Vec<_>, _>>()?;
+        assert_eq!(cells, vec![(0, -1), (1, 0), (-1, 1), (0, 1), (1, 1)]);
+        Ok(())
+    }
+
+    #[test]
+    fn rle_owned_input() -> Result<(), Error> {
+        fn load(name: &str) -> Result<Rle<String>, Error> {
+            Rle::new(format!(
+                "#N {}\nx = 3, y = 3, rule = B3/S23\nbo$2bo$3o!",
+                name
+            ))
+        }
+
+        let glider = load("Glider")?;
+        assert_eq!(glider.header_data().map(|header| header.x), Some(3));
+        let cells = glider
+            .map(|res| res.map(|c| c.position))
+            .collect::<Result<Vec<_>, _>>()?;
+        assert_eq!(cells, vec![(1, 0), (2, 1), (0, 2), (1, 2), (2, 2)]);
+
+        let text: std::sync::Arc<str> = std::sync::Arc::from("bo!\r\n2o!");
+        let first = Rle::new(text.clone())?;
+        assert_eq!(first.clone().count(), 1);
+        let second = first.remains()?;
+        assert_eq!(second.count(), 2);
+        assert_eq!(Rle::new(text)?.count(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn rle_non_utf8() -> Result<(), Error> {
+        let rle = Rle::new(&b"#C Caf\xe9\nx = 2, y = 1\r\n2o!"[..])?;
+        assert_eq!(rle.count(), 2);
+
+        let rle = Rle::new(std::io::BufReader::new(&b"#C \xff\xfe\nbo\xff!"[..]))?;
+        let result = rle.collect::<Result<Vec<_>, _>>();
+        assert!(matches!(result, Err(Error::InvalidState(s)) if s == "\u{ff}"));
+
+        let result = Rle::new(&b"x = \xff"[..]);
+        assert!(matches!(result, Err(Error::InvalidHeaderLine(s, 5)) if s == "x = \u{fffd}"));
+        Ok(())
+    }
+
+    #[test]
+    fn rle_generations() -> Result<(), Error> {
+        const OSCILLATOR: &str = r"x = 3, y = 3, rule = 3457/357/5
+3A$B2A$.CD!";
+
+        let oscillator = Rle::new(OSCILLATOR)?;
+
+        assert_eq!(oscillator.cxrle_data, None);
+        assert_eq!(
+            oscillator.header_data,
+            Some(HeaderData {
+                x: 3,
+                y: 3,
+                rule: Some(String::from("3457/357/5")),
+                ..Default::default()
+            })
+        );
+
+        let cells = oscillator.collect::<Result<Vec<_>, _>>()?;
+        assert_eq!(
+            cells,
+            vec![
+                CellData {
+                    position: (0, 0),
+                    state: 1,
+                },
+                CellData {
+                    position: (1, 0),
+                    state: 1,
+                },
+                CellData {
+                    position: (2, 0),
+                    state: 1,
+                },
+                CellData {
+                    position: (0, 1),
+                    state: 2,
+                },
+                CellData {
+                    position: (1, 1),
+                    state: 1,
+                },
+                CellData {
+                    position: (2, 1),
+                    state: 1,
+                },
+                CellData {
+                    position: (1, 2),
+                    state: 3,
+                },
+                CellData {
+                    position: (2, 2),
+                    state: 4,
+                },
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn rle_generations_256() -> Result<(), Error> {
+        const OSCILLATOR: &str = r"x = 3, y = 3, rule = 23/3/256
+.AwH$vIxNrQ$2pU!";
+
+        let oscillator = Rle::new(OSCILLATOR)?;
+
+        assert_eq!(oscillator.cxrle_data, None);
+        assert_eq!(
+            oscillator.header_data,
+            Some(HeaderData {
+                x: 3,
+                y: 3,
+                rule: Some(String::from("23/3/256")),
+                ..Default::default()
+            })
+        );
+
+        let cells = oscillator.collect::<Result<Vec<_>, _>>()?;
+        assert_eq!(
+            cells,
+            vec![
+                CellData {
+                    position: (1, 0),
+                    state: 1,
+                },
+                CellData {
+                    position: (2, 0),
+                    state: 200,
+                },
+                CellData {
+                    position: (0, 1),
+                    state: 177,
+                },
+                CellData {
+                    position: (1, 1),
+                    state: 230,
+                },
+                CellData {
+                    position: (2, 1),
+                    state: 89,
+                },
+                CellData {
+                    position: (0, 2),
+                    state: 45,
+                },
+                CellData {
+                    position: (1, 2),
+                    state: 45,
+                },
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn rle_wide_states() -> Result<(), Error> {
+        const OSCILLATOR: &str = r"x = 2, y = 1, rule = 23/3/265
+yXpA!";
+
+        let narrow = Rle::new(OSCILLATOR)?;
+        let result = narrow.collect::<Result<Vec<_>, _>>();
+        assert!(matches!(result, Err(Error::InvalidState(s)) if s == "yX"));
+
+        let wide = Rle::new(OSCILLATOR)?.with_state_type::<u16>();
+        let cells = wide.collect::<Result<Vec<_>, _>>()?;
+        assert_eq!(
+            cells,
+            vec![
+                CellData {
+                    position: (0, 0),
+                    state: 264,
+                },
+                CellData {
+                    position: (1, 0),
+                    state: 25,
+                },
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn rle_write() -> Result<(), Error> {
+        let cells = vec![
+            CellData {
+                position: (3, -1),
+                state: 1,
+            },
+            CellData {
+                position: (0, 3),
+                state: 2,
+            },
+            CellData {
+                position: (1, 3),
+                state: 2,
+            },
+            CellData {
+                position: (2, 3),
+                state: 25,
+            },
+            CellData {
+                position: (2, 3),
+                state: 3,
+            },
+        ];
+        let mut rle = Vec::new();
+        write_rle(&mut rle, None, cells.clone())?;
+        assert_eq!(
+            String::from_utf8(rle.clone()).unwrap(),
+            "#CXRLE Pos=0,-1\nx = 4, y = 5\n3.A4$2BpA!\n"
+        );
+        let parsed = Rle::new(rle.as_slice())?.collect::<Result<Vec<_>, _>>()?;
+        assert_eq!(parsed, cells[..4]);
+
+        let cells = (0..100).map(|i| CellData::from((2 * i, 0)));
+        let mut rle = Vec::new();
+        write_rle(&mut rle, Some("B3/S23"), cells)?;
+        let rle = String::from_utf8(rle).unwrap();
+        assert!(rle.as_str().lines().all(|line| line.len() <= 70));
+        assert_eq!(Rle::new(rle.as_str())?.count(), 100);
+        Ok(())
+    }
+
+    #[test]
+    fn rle_for_each_chunk() -> Result<(), Error> {
+        const PATTERN: &str = "#CXRLE Pos=-1,0\nx = 5, y = 3\n5o$obAo$2bo3!";
+        let cells = Rle::new(PATTERN)?
+            .with_offset(1, 1)
+            .with_state_type::<u16>()
+            .collect::<Result<Vec<_>, _>>()?;
+        for chunk_size in [0, 1, 3, 4, 100] {
+            let mut chunks = Vec::new();
+            Rle::new(PATTERN)?
+                .with_offset(1, 1)
+                .with_state_type::<u16>()
+                .for_each_chunk(chunk_size, |chunk| chunks.push(chunk.to_vec()))?;
+            assert!(chunks.iter().all(|chunk| chunk.len() <= chunk_size.max(1)));
+            assert_eq!(chunks.concat(), cells);
+        }
+
+        let mut rle = Rle::new("3o$3o!")?;
+        rle.next().unwrap()?;
+        let mut count = 0;
+        rle.for_each_chunk(2, |chunk| count += chunk.len())?;
+        assert_eq!(count, 5);
+
+        let rle = Rle::new_with_options(
+            "x = 2, y = 2\nbo$o!",
+            ParseOptions::new().include_dead(true),
+        )?;
+        let mut cells = Vec::new();
+        rle.for_each_chunk(3, |chunk| cells.extend_from_slice(chunk))?;
+        assert_eq!(cells.len(), 4);
+
+        let mut count = 0;
+        let result = Rle::new("3o$2bz!")?.for_each_chunk(2, |chunk| count += chunk.len());
+        assert!(result.is_err());
+        assert_eq!(count, 3);
+        Ok(())
+    }
+
+    #[test]
+    fn rle_offset_lines() -> Result<(), Error> {
+        const PATTERN: &str =
+            "#CXRLE Pos=1,1\n#P -1 -1\nx = 0, y = 0\n2o$o\n#P 10 5\n2$3o\n#P 0 -3\nbo!";
+        let cells = Rle::new(PATTERN)?
+            .with_offset_lines()
+            .map(|c| c.map(|c| c.position))
+            .collect::<Result<Vec<_>, _>>()?;
+        assert_eq!(
+            cells,
+            vec![(0, 0), (1, 0), (0, 1), (11, 8), (12, 8), (13, 8), (2, -2)]
+        );
+        let mut count = 0;
+        Rle::new(PATTERN)?
+            .with_offset_lines()
+            .for_each_chunk(2, |chunk| count += chunk.len())?;
+        assert_eq!(count, 7);
+
+        let cells = Rle::new(PATTERN)?.collect::<Result<Vec<_>, _>>()?;
+        assert_eq!(cells.len(), 7);
+        assert_eq!(cells[3].position, (1, 4));
+
+        let rle = Rle::new("#P x 0\n2o\n#P 1\no!")?.with_offset_lines();
+        assert_eq!(rle.comments(), ["#P x 0"]);
+        let result = rle.collect::<Result<Vec<_>, _>>();
+        assert!(matches!(result, Err(Error::InvalidOffsetLine(_, 5))));
+        Ok(())
+    }
+
+    #[test]
+    fn rle_comment_prefixes() -> Result<(), Error> {
+        const PATTERNS: &str =
+            "#CXRLE Pos=1,0\n// Blinker\n;; note\n3o\n// end\n$!\nx = 1, y = 1\n// Dot\n;;\no!";
+        let rle = Rle::new_with_comment_prefixes(PATTERNS, &["//", ";;", ""])?;
+        assert_eq!(rle.comment_prefixes(), ["//", ";;"]);
+        assert_eq!(rle.comments(), ["// Blinker", ";; note"]);
+        assert_eq!(rle.cxrle_data().and_then(|data| data.pos), Some((1, 0)));
+        assert!(rle.warnings().is_empty());
+        let mut rle = rle.with_state_type::<u16>();
+        let cells = rle
+            .by_ref()
+            .map(|c| c.map(|c| c.position))
+            .collect::<Result<Vec<_>, _>>()?;
+        assert_eq!(cells, vec![(1, 0), (2, 0), (3, 0)]);
+        let dot = rle.remains()?;
+        assert_eq!(dot.comment_prefixes(), ["//", ";;"]);
+        assert_eq!(dot.comments(), ["// Dot", ";;"]);
+        assert_eq!(dot.count(), 1);
+
+        let mut rle = Rle::new_with_comment_prefixes("#N Blinker\n3o!", &["//"])?;
+        assert!(rle.comments().is_empty());
+        assert!(matches!(rle.next(), Some(Err(Error::InvalidState(_)))));
+        Ok(())
+    }
+
+    #[test]
+    fn rle_warnings() -> Result<(), Error> {
+        const PATTERN: &str =
+            "#CXRLE Pos=0,0\n#N Glider\n#CXRLE Pos=-1,-1\n# note\nx = 3, y = 3\nbo$2bo$3o!\n#C end";
+        let mut rle = Rle::new(PATTERN)?;
+        assert_eq!(
+            rle.warnings(),
+            [
+                Warning::DuplicateCxrle(String::from("#CXRLE Pos=-1,-1")),
+                Warning::UnknownDirective(String::from("# note")),
+            ]
+        );
+        assert_eq!(rle.by_ref().count(), 5);
+        assert!(rle.next().is_none());
+        assert_eq!(rle.warnings().len(), 2);
+
+        let mut rle =
+            Rle::new_with_options("x = 4, y = 2\n3o$o", ParseOptions::new().include_dead(true))?;
+        assert_eq!(rle.by_ref().count(), 8);
+        assert_eq!(
+            rle.warnings(),
+            [
+                Warning::MissingTerminator,
+                Warning::StaleHeader {
+                    declared: (4, 2),
+                    actual: (3, 2),
+                }
+            ]
+        );
+
+        let mut rle = Rle::new("3o!")?;
+        assert_eq!(rle.by_ref().count(), 3);
+        assert!(rle.warnings().is_empty());
+        let mut rle = Rle::new("3o")?;
+        assert_eq!(rle.by_ref().count(), 3);
+        assert_eq!(rle.warnings(), [Warning::MissingTerminator]);
+        Ok(())
+    }
+
+    #[test]
+    fn rle_writer() -> Result<(), Error> {
+        let mut writer = RleWriter::new(Vec::new())
+            .with_origin(-1, -2)
+            .with_gen(7)
+            .with_size(4, 3)
+            .multistate(true);
+        for (position, state) in [((0, -2), 1), ((1, -2), 1), ((-1, 0), 2), ((2, 0), 0u8)] {
+            writer.write_cell(CellData { position, state })?;
+        }
+        assert!(writer.write_cell(CellData::from((-1, 0))).is_err());
+        assert!(writer.write_cell(CellData::from((5, -1))).is_err());
+        assert!(writer.write_cell(CellData::from((-2, 0))).is_err());
+        let rle = writer.finish()?;
+        assert_eq!(
+            String::from_utf8(rle).unwrap(),
+            "#CXRLE Pos=-1,-2 Gen=7\nx = 4, y = 3\n.2A2$B!\n"
+        );
+
+        let mut writer = RleWriter::new(Vec::new());
+        assert!(writer
+            .write_cell(CellData {
+                position: (0, 0),
+                state: 2u8
+            })
+            .is_err());
+
+        let mut writer = RleWriter::new(Vec::new()).buffer_rows(true);
+        for cell in [(2, 0), (0, 0), (2, 0), (1, 1), (0, 1), (2, 2)] {
+            writer.write_cell(CellData::from(cell))?;
+        }
+        assert!(writer.write_cell(CellData::from((0, 1))).is_err());
+        let rle = writer.finish()?;
+        assert_eq!(rle, b"x = 3, y = 3\nobo$2o$2bo!\n");
+        let rle = RleWriter::new(Vec::new()).finish()?;
+        assert_eq!(rle, b"x = 0, y = 0\n!\n");
+
+        let mut writer = RleWriter::new(Vec::new()).with_origin(-3, -2);
+        for cell in [(-1, -2), (1, 0)] {
+            writer.write_cell(CellData::from(cell))?;
+        }
+        let rle = writer.finish()?;
+        assert_eq!(rle, b"#CXRLE Pos=-3,-2\nx = 5, y = 3\n2bo2$4bo!\n");
+
+        for multistate in [false, true] {
+            let history = Rle::new("x = 5, y = 2, rule = B36/S23History\n.ABCD$EF!")?;
+            let mut writer = RleWriter::new(Vec::new())
+                .with_rule("B36/S23History")
+                .multistate(multistate)
+                .strip_history(true);
+            for cell in history {
+                writer.write_cell(cell?)?;
+            }
+            let rle = writer.finish()?;
+            assert_eq!(rle, b"x = 4, y = 2, rule = B36/S23\nbobo$o!\n");
+        }
+        assert_eq!(history_base_rule("LifeSuper"), "B3/S23");
+        assert_eq!(history_base_rule("History"), "B3/S23");
+        assert_eq!(history_base_rule("B3/S23"), "B3/S23");
+        Ok(())
+    }
+
+    #[test]
+    fn rle_write_extremes() -> Result<(), Error> {
+        let cells = [(i64::MIN, 0), (i64::MAX, 0)].map(CellData::<u8>::from);
+        let error = write_rle(Vec::new(), None, cells).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidInput);
+
+        let mut writer = RleWriter::new(Vec::new()).with_origin(i64::MIN, i64::MIN);
+        writer.write_cell(CellData::from((i64::MIN, i64::MIN)))?;
+        assert!(writer
+            .write_cell(CellData::from((i64::MAX, i64::MIN)))
+            .is_err());
+        assert!(writer
+            .write_cell(CellData::from((i64::MIN, i64::MAX)))
+            .is_err());
+
+        let mut writer = RleWriter::new(Vec::new()).with_origin(i64::MAX, 0);
+        writer.write_cell(CellData::from((i64::MAX, 0)))?;
+        writer.write_cell(CellData::from((i64::MAX, 1)))?;
+        assert!(writer.write_cell(CellData::from((i64::MAX, 1))).is_err());
+        assert_eq!(
+            writer.finish()?,
+            b"#CXRLE Pos=9223372036854775807,0\nx = 1, y = 2\no$o!\n"
+        );
+
+        let cells = [(i64::MAX, i64::MAX)].map(CellData::<u8>::from);
+        let mut rle = Vec::new();
+        write_rle(&mut rle, None, cells)?;
+        assert_eq!(
+            rle,
+            b"#CXRLE Pos=9223372036854775807,9223372036854775807\nx = 1, y = 1\no!\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn rle_push_parser() -> Result<(), Error> {
+        const RLE: &str = "#N Test\r\n#CXRLE Pos=-1,-2\r\nx = 4, y = 3, rule = B3/S23\r\n\
+                           b2o$\r\n#C A comment in the body\r\n2$3o20b!\r\nignored";
+
+        let expected = Rle::new(RLE)?.collect::<Result<Vec<_>, _>>()?;
+
+        let mut parser = PushParser::new();
+        let mut cells = Vec::new();
+        for byte in RLE.as_bytes().chunks(1) {
+            for cell in parser.feed(byte) {
+                cells.push(cell?);
+            }
+        }
+        for cell in parser.finish() {
+            cells.push(cell?);
+        }
+        assert!(parser.is_finished());
+        assert_eq!(parser.cxrle_data().unwrap().pos, Some((-1, -2)));
+        assert_eq!(
+            parser.header_data().unwrap().rule.as_deref(),
+            Some("B3/S23")
+        );
+        assert_eq!(cells, expected);
+
+        for rle in [
+            " X = 2, y = 2\n 2o$\n x = 9, y = 9\nXA!",
+            "x = 2, y = 1\nXA",
+            "x = 2, y = 1\n\t\tX\n x\n",
+        ] {
+            let expected = Rle::new(rle)?.collect::<Result<Vec<_>, _>>()?;
+            let mut parser = PushParser::new();
+            let mut cells = Vec::new();
+            for byte in rle.as_bytes().chunks(1) {
+                for cell in parser.feed(byte) {
+                    cells.push(cell?);
+                }
+            }
+            for cell in parser.finish() {
+                cells.push(cell?);
+            }
+            assert!(parser.is_finished());
+            assert_eq!(parser.header_data().unwrap().x, 2);
+            assert_eq!(cells, expected);
+        }
+
+        let mut parser = PushParser::new();
+        parser.feed(b"x = 3, y = 1\n10o").next().unwrap()?;
+        let cells = parser.finish().collect::<Result<Vec<_>, _>>()?;
+        assert_eq!(cells.len(), 9);
+
+        let mut parser = PushParser::new();
+        parser.feed(b"x = 3, y").for_each(drop);
+        assert!(matches!(
+            parser.finish().next(),
+            Some(Err(Error::InvalidHeaderLine(_, 9)))
+        ));
+
+        let mut parser = PushParser::new().with_state_type::<u16>();
+        let cells = parser.feed(b"2yX!").collect::<Result<Vec<_>, _>>()?;
+        assert_eq!(cells[1].position, (1, 0));
+        assert_eq!(cells[1].state, 264);
+        Ok(())
+    }
+
+    #[test]
+    fn rle_offset() -> Result<(), Error> {
+        const RLES: &str = "#CXRLE Pos=-1,-1\nx = 3, y = 3\nbo$2bo$3o!\n\
+                            #CXRLE Pos=5,5\nx = 1, y = 1\no!";
+
+        let mut rle = Rle::new(RLES)?.ignore_cxrle_pos().with_offset(1, 2);
+        let cells = (&mut rle)
+            .map(|res| res.map(|c| c.position))
+            .collect::<Result<Vec<_>, _>>()?;
+        assert_eq!(cells, vec![(2, 2), (3, 3), (1, 4), (2, 4), (3, 4)]);
+
+        let cells = rle
+            .remains()?
+            .map(|res| res.map(|c| c.position))
+            .collect::<Result<Vec<_>, _>>()?;
+        assert_eq!(cells, vec![(1, 2)]);
+
+        let rle = Rle::new(RLES)?.with_offset(1, 2).remains()?;
+        let cells = rle
+            .map(|res| res.map(|c| c.position))
+            .collect::<Result<Vec<_>, _>>()?;
+        assert_eq!(cells, vec![(6, 7)]);
+        Ok(())
+    }
+
+    #[test]
+    fn rle_stream() -> Result<(), Error> {
+        const COLLECTION: &str = r"#C A collection of patterns.
+
+#N Glider
+#CXRLE Pos=-1,-1
+x = 3, y = 3, rule = B3/S23
+bo$
+2bo$
+3o!
+
+#N Blinker
+x = 3, y = 1
+3o!
+#N Block
+2o$2o!
+#C Nothing after this.
+";
+
+        let rles = RleStream::new(COLLECTION).collect::<Result<Vec<_>, _>>()?;
+        assert_eq!(rles.len(), 3);
+        assert_eq!(
+            rles[0].comments(),
+            ["#C A collection of patterns.", "#N Glider"]
+        );
+        assert_eq!(rles[0].cxrle_data().unwrap().pos, Some((-1, -1)));
+        assert_eq!(rles[1].comments(), ["#N Blinker"]);
+        assert_eq!(rles[2].header_data(), None);
+
+        let counts = rles.into_iter().map(Iterator::count).collect::<Vec<_>>();
+        assert_eq!(counts, vec![5, 3, 4]);
+
+        let glider = RleStream::new(COLLECTION.as_bytes()).next().unwrap()?;
+        let cells = glider
+            .map(|res| res.map(|c| c.position))
+            .collect::<Result<Vec<_>, _>>()?;
+        assert_eq!(cells, vec![(0, -1), (1, 0), (-1, 1), (0, 1), (1, 1)]);
+        Ok(())
+    }
+
+    #[test]
+    fn rle_cost_comment() -> Result<(), Error> {
+        assert_eq!(parse_cost_comment(b"#C costs 12 gliders"), Some(12));
+        assert_eq!(parse_cost_comment(b"#C Cost: 7 gliders."), Some(7));
+        assert_eq!(parse_cost_comment(b"#c cost=1 glider"), Some(1));
+        assert_eq!(parse_cost_comment(b"#C 3-glider synthesis"), Some(3));
+        assert_eq!(parse_cost_comment(b"#C 40 Gliders Synthesis  "), Some(40));
+        assert_eq!(parse_cost_comment(b"#C 3 gliders collide"), None);
+        assert_eq!(parse_cost_comment(b"#C costs many gliders"), None);
+        assert_eq!(parse_cost_comment(b"#C price 3 gliders"), None);
+        assert_eq!(parse_cost_comment(b"#C 3gliders"), None);
+        assert_eq!(parse_cost_comment(b"#N 3 gliders"), None);
+
+        let rle = Rle::new("#C costs 5 gliders\n#C 4-glider synthesis\no!")?;
+        assert_eq!(rle.synthesis_cost(), Some(4));
+        assert_eq!(
+            read_header("#C costs 5 gliders\no!")?.synthesis_cost,
+            Some(5)
+        );
+        assert_eq!(Rle::new("#C population = 5\no!")?.synthesis_cost(), None);
+        Ok(())
+    }
+
+    #[test]
+    fn rle_skip_rows() -> Result<(), Error> {
+        let text = std::fs::read_to_string("tests/sirrobin.rle")?;
+        let cells = Rle::new(text.as_str())?.collect::<Result<Vec<_>, _>>()?;
+        for n in [0, 1, 17, 40, 78, 79, 1000] {
+            let mut sirrobin = Rle::new(text.as_str())?;
+            sirrobin.skip_rows(n)?;
+            let expected = cells
+                .iter()
+                .filter(|cell| cell.position.1 >= n as i64)
+                .copied()
+                .collect::<Vec<_>>();
+            assert_eq!(sirrobin.collect::<Result<Vec<_>, _>>()?, expected);
+        }
+
+        let mut rle = Rle::new("x = 3, y = 6\n3o$o5$2pA!")?
+            .with_state_type::<u16>()
+            .with_offset(1, 1);
+        assert_eq!(
+            rle.next().transpose()?.map(|cell| cell.position),
+            Some((1, 1))
+        );
+        rle.skip_rows(2)?;
+        assert_eq!(rle.size_hint(), (0, None));
+        let cells = rle.collect::<Result<Vec<_>, _>>()?;
+        assert_eq!(
+            cells.iter().map(|cell| cell.position).collect::<Vec<_>>(),
+            [(1, 7), (2, 7)]
+        );
+        assert_eq!(cells[0].state, 25);
+
+        let mut rle = Rle::new_with_options(
+            "x = 2, y = 3\nbo2$oo!",
+            ParseOptions::new().include_dead(true),
+        )?;
+        rle.skip_rows(1)?;
+        let cells = rle.map(|cell| cell.map(|cell| (cell.position, cell.state)));
+        assert_eq!(
+            cells.collect::<Result<Vec<_>, _>>()?,
+            [((0, 1), 0), ((1, 1), 0), ((0, 2), 1), ((1, 2), 1)]
+        );
+
+        let mut rle = Rle::new("x = 1, y = 1\nz$o!")?;
+        rle.skip_rows(1)?;
+        assert_eq!(
+            rle.next().transpose()?.map(|cell| cell.position),
+            Some((0, 1))
+        );
+        let mut rle = Rle::new("99999999999999999999$o!")?;
+        assert!(rle.skip_rows(1).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn rle_columns() -> Result<(), Error> {
+        let text = std::fs::read_to_string("tests/sirrobin.rle")?;
+        let cells = Rle::new(text.as_str())?.collect::<Result<Vec<_>, _>>()?;
+        for (start, end) in [(0, 30), (5, 12), (-3, 0), (30, 40), (12, 11)] {
+            let rle = Rle::new(text.as_str())?.columns(start..=end);
+            let expected = cells
+                .iter()
+                .filter(|cell| (start..=end).contains(&cell.position.0))
+                .copied()
+                .collect::<Vec<_>>();
+            assert_eq!(rle.population()?, expected.len());
+            let mut rle = Rle::new(text.as_str())?.columns(start..=end);
+            rle.skip_rows(20)?;
+            let mut chunks = Vec::new();
+            rle.for_each_chunk(7, |chunk| chunks.extend_from_slice(chunk))?;
+            let expected = expected
+                .into_iter()
+                .filter(|cell| cell.position.1 >= 20)
+                .collect::<Vec<_>>();
+            assert_eq!(chunks, expected);
+        }
+
+        let options = ParseOptions::new().include_dead(true);
+        let rle = Rle::new_with_options("#CXRLE Pos=-2,0\nx = 5, y = 2\n5o$b3o!", options)?
+            .columns(..0)
+            .with_offset(1, 0);
+        let cells = rle.map(|cell| cell.map(|cell| (cell.position, cell.state)));
+        assert_eq!(
+            cells.collect::<Result<Vec<_>, _>>()?,
+            [((-1, 0), 1), ((-1, 1), 0)]
+        );
+        let rle = Rle::new("x = 3, y = 1\n3o!")?.columns(1..2);
+        assert_eq!(rle.bounding_box()?, Some((1, 0, 1, 0)));
+        let mut rle = Rle::new("x = 3, y = 1\n3o!")?.columns(5..);
+        assert!(rle.next().is_none());
+        assert!(rle.warnings().is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn rle_locations() -> Result<(), Error> {
+        let at = |line, column| Some(Location { line, column });
+        let rle = Rle::new("#CXRLE Pos=0,0\nx = 14, y = 2, rule = 23/3/3\n 12pA o$\n\nB!")?
+            .with_state_type::<u16>();
+        let cells = rle.locations().collect::<Result<Vec<_>, _>>()?;
+        assert_eq!(cells.len(), 14);
+        assert!(cells[..12]
+            .iter()
+            .all(|&(_, location)| location == at(3, 2)));
+        assert_eq!(cells[12].1, at(3, 7));
+        assert_eq!(
+            cells[13],
+            (
+                CellData {
+                    position: (0, 1),
+                    state: 2
+                },
+                at(5, 1)
+            )
+        );
+
+        let rle = Rle::new_with_options(
+            "x = 2, y = 2\nbo$\n2o!",
+            ParseOptions::new().include_dead(true),
+        )?;
+        let locations = rle
+            .locations()
+            .map(|cell| cell.map(|(_, location)| location));
+        assert_eq!(
+            locations.collect::<Result<Vec<_>, _>>()?,
+            [None, at(2, 2), at(3, 1), at(3, 1)]
+        );
+
+        let mut rle = Rle::new("o$bo$2bo!")?;
+        rle.skip_rows(1)?;
+        let mut locations = rle.locations();
+        assert_eq!(
+            locations.next().transpose()?.and_then(|cell| cell.1),
+            at(1, 4)
+        );
+        assert!(locations.into_inner().next().is_some());
+        Ok(())
+    }
+
+    #[test]
+    fn rle_population_comment() -> Result<(), Error> {
+        assert_eq!(parse_population_comment(b"#C population = 34"), Some(34));
+        assert_eq!(parse_population_comment(b"#c  Population=7  "), Some(7));
+        assert_eq!(parse_population_comment(b"#C population = many"), None);
+        assert_eq!(parse_population_comment(b"#C population = 3 cells"), None);
+        assert_eq!(parse_population_comment(b"#Cpopulation = 3"), None);
+        assert_eq!(parse_population_comment(b"#N population = 3"), None);
+
+        let rle = Rle::new("#C population = 3\n#C a blinker\n3o!")?;
+        assert_eq!(rle.declared_population(), Some(3));
+
+        let rle = Rle::new("#C population = 99999999999999\no!")?;
+        assert_eq!(rle.size_hint(), (0, None));
+        assert_eq!(rle.count(), 1);
+        assert_eq!(Rle::new("o!")?.size_hint(), (0, None));
+        Ok(())
+    }
+
+    #[test]
+    fn rle_population() -> Result<(), Error> {
+        const RLE: &str = "x = 30, y = 3, rule = 23/3/3\n#C comment\n30o$\n3bA2B$o!";
+        assert_eq!(Rle::new(RLE)?.population()?, 34);
+
+        let mut partial = Rle::new(RLE)?;
+        partial.nth(9);
+        assert_eq!(partial.population()?, 24);
+
+        let dead = Rle::new_with_options(RLE, ParseOptions::new().include_dead(true))?;
+        assert_eq!(dead.population()?, 34);
+
+        assert!(Rle::new("3o$2z!")?.population().is_err());
+        assert!(Rle::new("yX!")?.population().is_err());
+        assert_eq!(Rle::new("yX!")?.with_state_type::<u16>().population()?, 1);
+        Ok(())
+    }
+
+    #[test]
+    fn rle_bounding_box() -> Result<(), Error> {
+        const RLE: &str = "#CXRLE Pos=-5,-2\nx = 30, y = 3, rule = 23/3/3\n2b30o$\n3bA2B$!";
+        assert_eq!(Rle::new(RLE)?.bounding_box()?, Some((-3, -2, 26, -1)));
+
+        let mut partial = Rle::new(RLE)?.ignore_cxrle_pos();
+        partial.nth(28);
+        assert_eq!(partial.bounding_box()?, Some((3, 0, 31, 1)));
+
+        let convention = Convention {
+            yx: false,
+            y_up: true,
+        };
+        let options = ParseOptions::new().with_convention(convention);
+        let flipped = Rle::new_with_options(RLE, options)?.with_offset(5, 2);
+        assert_eq!(flipped.bounding_box()?, Some((2, -1, 31, 0)));
+
+        assert_eq!(Rle::new("x = 3, y = 3\n3b$!")?.bounding_box()?, None);
+        Ok(())
+    }
+
+    #[test]
+    fn rle_normalized_rule() {
+        for (rule, normalized) in [
+            ("B3/S23", "B3/S23"),
+            ("b3/s23", "B3/S23"),
+            ("23/3", "B3/S23"),
+            (" b3s32 ", "B3/S23"),
+            ("S23/B3", "B3/S23"),
+            ("B33/S2323", "B3/S23"),
+            ("23/36", "B36/S23"),
+            ("B0/S8", "B0/S8"),
+            ("/3", "B3/S"),
+            ("b2s34h", "B2/S34H"),
+            ("B2/S013V", "B2/S013V"),
+            ("345/2/4", "B2/S345/C4"),
+            ("g4b2s345", "B2/S345/C4"),
+            ("B3/S23/C2", "B3/S23"),
+            ("B2n3/S23-q", "B2n3/S23-q"),
+            ("B9/S23", "B9/S23"),
+            ("B3/S23/C1", "B3/S23/C1"),
+            ("B3/B3/S23", "B3/B3/S23"),
+            ("LifeHistory", "LifeHistory"),
+            ("B3/S23é", "B3/S23é"),
+            ("Bé", "Bé"),
+        ] {
+            let header_data = HeaderData {
+                rule: Some(String::from(rule)),
+                ..HeaderData::default()
+            };
+            assert_eq!(header_data.normalized_rule().as_deref(), Some(normalized));
+        }
+        assert_eq!(HeaderData::default().normalized_rule(), None);
+    }
+
+    #[test]
+    fn rle_tiles() -> Result<(), Error> {
+        const RLE: &str = "#CXRLE Pos=-11,-3\nx = 30, y = 12\n30o$3bo2bA$$bo20bo5$29bo$9bo!";
+        let mut expected = BTreeMap::new();
+        for cell in Rle::new(RLE)? {
+            let (x, y) = cell?.position;
+            let bit = 63 - 8 * y.rem_euclid(8) - x.rem_euclid(8);
+            *expected
+                .entry((y.div_euclid(8), x.div_euclid(8)))
+                .or_insert(0_u64) |= 1 << bit;
         }
+        let expected = expected
+            .into_iter()
+            .map(|((tile_y, tile_x), bits)| (tile_x, tile_y, bits))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            Rle::new(RLE)?.tiles().collect::<Result<Vec<_>, _>>()?,
+            expected
+        );
+
+        let mut rle = Rle::new(RLE)?;
+        rle.next().transpose()?;
+        let tiles = rle.tiles().collect::<Result<Vec<_>, _>>()?;
+        assert_eq!(tiles[0], (-2, -1, expected[0].2 & !(1 << 18)));
+        assert_eq!(tiles[1..], expected[1..]);
+
+        let shifted = Rle::new(RLE)?.ignore_cxrle_pos().with_offset(8, 0);
+        let tiles = shifted.tiles().collect::<Result<Vec<_>, _>>()?;
+        assert_eq!(tiles.len(), 6);
+        assert_eq!((tiles[0].0, tiles[0].1, tiles[0].2 >> 56), (1, 0, 0xff));
+        assert!(Rle::new("o2z!")?.tiles().any(|tile| tile.is_err()));
+        Ok(())
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    #[test]
+    fn rle_count_pass() -> Result<(), Error> {
+        const RLE: &str = "#CXRLE Pos=-11,-3\nx = 30, y = 12\n30o$3bo2bA$$bo20bo5$29bo$9bo!";
+        let convention = Convention {
+            y_up: true,
+            yx: true,
+        };
+        for rle in [
+            Rle::new(RLE)?,
+            Rle::new_with_options(RLE, ParseOptions::new().with_convention(convention))?
+                .with_offset(3, -4),
+            Rle::new_with_options(RLE, ParseOptions::new().include_dead(true))?,
+        ] {
+            let counts = rle.count_pass()?;
+            assert_eq!(counts.population, rle.clone().population()?);
+            assert_eq!(counts.bounding_box, rle.clone().bounding_box()?);
+        }
+
+        let mut rle = Rle::new(RLE)?;
+        rle.nth(28).transpose()?;
+        let counts = rle.count_pass()?;
+        assert_eq!(counts.population, 7);
+        assert_eq!(counts.bounding_box, Some((-10, -3, 18, 6)));
+        assert_eq!(rle.count(), 7);
+        assert_eq!(
+            Rle::new("x = 0, y = 0\n!")?.count_pass()?,
+            Counts::default()
+        );
+        assert!(Rle::new("o2z!")?.count_pass().is_err());
+        Ok(())
+    }
 
     #[test]
-    fn rle_parse_cxrle() {
+    fn rle_read_header() -> Result<(), Error> {
+        const RLE: &str =
+            "#N Glider\n#CXRLE Pos=-1,-1\n#C population = 5\nx = 3, y = 3\nbo$2bo$3o!";
+        let rle = Rle::new(RLE)?;
+        let metadata = read_header(RLE)?;
+        assert_eq!(metadata.comments, rle.comments());
+        assert_eq!(metadata.cxrle_data.as_ref(), rle.cxrle_data());
+        assert_eq!(metadata.header_data.as_ref(), rle.header_data());
+        assert_eq!(metadata.declared_population, Some(5));
+
+        let file = std::fs::File::open("tests/sirrobin.rle")?;
+        let metadata = read_header(BufReader::new(file))?;
         assert_eq!(
-            parse_cxrle("#CXRLE"),
-            Some(CxrleData {
-                pos: None,
-                gen: None
-            })
+            metadata.header_data.map(|data| (data.x, data.y)),
+            Some((31, 79))
         );
+        assert_eq!(read_header("")?, Metadata::default());
+        assert!(read_header("x = 3\nbo!").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn rle_reset() -> Result<(), Error> {
+        const RLE: &str = "#N Glider\n#CXRLE Pos=-1,-1\nx = 3, y = 3\nbo$2bo$3o!";
+        let expected = Rle::new_with_options(RLE, ParseOptions::new().include_dead(true))?
+            .with_offset(2, 0)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let reader = BufReader::new(std::io::Cursor::new(RLE));
+        let mut rle = Rle::new_with_options(reader, ParseOptions::new().include_dead(true))?
+            .with_offset(2, 0);
+        rle.nth(4).transpose()?;
+        let rle = rle.reset()?;
+        assert_eq!(rle.comments(), ["#N Glider"]);
+        assert_eq!(rle.collect::<Result<Vec<_>, _>>()?, expected);
+
+        let mut cursor = std::io::Cursor::new("#P 2 0\n2o\n#P 0 1\no!");
+        let mut rle = Rle::new(&mut cursor)?.with_offset_lines();
+        let cells = rle.by_ref().collect::<Result<Vec<_>, _>>()?;
+        assert_eq!(rle.reset()?.collect::<Result<Vec<_>, _>>()?, cells);
+        Ok(())
+    }
+
+    #[test]
+    fn rle_clipboard() -> Result<(), Box<dyn std::error::Error>> {
+        const PASTED: &str = "\u{feff}\n\r\n\t\r\n#C Copied from a forum post.\r\n\
+                              x = 3, y = 2, rule = B3/S23\r\n3o$\r\nobo!  \r\n";
+        let rle = Rle::from_clipboard(PASTED)?;
+        assert_eq!(rle.comments(), ["#C Copied from a forum post."]);
+        assert_eq!(rle.header_data().map(|data| (data.x, data.y)), Some((3, 2)));
+        let cells = rle.collect::<Result<Vec<CellData>, _>>()?;
+        assert_eq!(cells.len(), 5);
+
+        let copied = to_clipboard_string(Some("B3/S23"), cells.iter().copied())?;
+        assert_eq!(copied, "x = 3, y = 2, rule = B3/S23\n3o$obo!\n");
+        let parsed = Rle::from_clipboard(&copied)?.collect::<Result<Vec<_>, _>>()?;
+        assert_eq!(parsed, cells);
+
+        assert_eq!(Rle::from_clipboard("\n\n2o!")?.count(), 2);
+        assert_eq!(Rle::from_clipboard("")?.count(), 0);
+        let moved = cells.iter().map(|cell| CellData {
+            position: (cell.position.0 - 5, cell.position.1 + 5),
+            ..*cell
+        });
+        assert_eq!(to_clipboard_string(Some("B3/S23"), moved)?, copied);
+        let cell = CellData {
+            position: (0, 0),
+            state: 300_u16,
+        };
+        assert!(to_clipboard_string(None, [cell]).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn rle_huge_positions() -> Result<(), Error> {
+        let positions = |rle: Rle<&str>| {
+            rle.map(|cell| cell.map(|cell| cell.position))
+                .collect::<Result<Vec<_>, _>>()
+        };
+        let near_max = format!("#CXRLE Pos={},{}\n", i64::MAX - 2, i64::MAX - 1);
+        let rle = format!("{}2o!", near_max);
         assert_eq!(
-            parse_cxrle("#CXRLE Pos=0,-1377 Gen=3480106827776"),
-            Some(CxrleData {
-                pos: Some((0, -1377)),
-                gen: Some(3480106827776)
-            })
+            positions(Rle::new(rle.as_str())?)?,
+            [(i64::MAX - 2, i64::MAX - 1), (i64::MAX - 1, i64::MAX - 1)]
         );
+        let rle = format!("{}3o!", near_max);
         assert_eq!(
-            parse_cxrle("#CXRLE Gen = 3480106827776 Pos = 0, -1377"),
-            Some(CxrleData {
-                pos: Some((0, -1377)),
-                gen: Some(3480106827776)
-            })
+            positions(Rle::new(rle.as_str())?),
+            Err(Error::CoordinateOverflow((i64::MAX - 2, i64::MAX - 1)))
         );
+        let rle = format!("{}o2$o!", near_max);
+        assert!(positions(Rle::new(rle.as_str())?).is_err());
+        let rle = format!("{}2o!", near_max);
+        assert!(positions(Rle::new(rle.as_str())?.with_offset(2, 0)).is_err());
         assert_eq!(
-            parse_cxrle("#CXRLE211Pos=0,-9dcdcs2,[a ccGen=348sss1068cscPos= -333,-1a6"),
-            Some(CxrleData {
-                pos: Some((-333, -1)),
-                gen: Some(348)
-            })
+            Rle::new(rle.as_str())?
+                .ignore_cxrle_pos()
+                .with_offset(-1, 0)
+                .bounding_box(),
+            Ok(Some((-1, 0, 0, 0)))
         );
-    }
 
-    #[test]
-    fn rle_parse_header() {
-        assert_eq!(parse_header("xxx"), None);
+        let near_min = format!("#CXRLE Pos={},{}\nx = 2, y = 2\n", i64::MIN, i64::MIN);
+        let rle = format!("{}2o$bo!", near_min);
+        let mut parser = Rle::new(rle.as_str())?;
+        assert_eq!(parser.by_ref().map(Result::unwrap).count(), 3);
+        assert!(parser.warnings().is_empty());
         assert_eq!(
-            parse_header("x = 3, y = 3, rule = B3/S23"),
-            Some(HeaderData {
-                x: 3,
-                y: 3,
-                rule: Some(String::from("B3/S23"))
-            })
+            positions(Rle::new(rle.as_str())?.ignore_cxrle_pos())?,
+            [(0, 0), (1, 0), (1, 1)]
         );
+        let y_up = Convention {
+            y_up: true,
+            ..Convention::default()
+        };
+        let options = ParseOptions::new().with_convention(y_up);
+        assert!(positions(Rle::new_with_options(rle.as_str(), options)?).is_err());
+        let mut push_parser = PushParser::new_with_options(options);
+        assert!(push_parser.feed(rle.as_bytes()).any(|cell| cell.is_err()));
+
+        let rle = "x = 3, y = 3\nbo$\n 2b99999999999999999999o!";
+        assert_eq!(positions(Rle::new(rle)?), Err(Error::RunCountOverflow(7)));
+        let mut push_parser = PushParser::new();
         assert_eq!(
-            parse_header("x = 3, y = 3"),
-            Some(HeaderData {
-                x: 3,
-                y: 3,
-                rule: None
-            })
+            push_parser.feed(rle.as_bytes()).find_map(Result::err),
+            Some(Error::RunCountOverflow(7))
         );
-        assert_eq!(parse_header("x = 3, y = -3"), None);
+        let rle = format!("#CXRLE Pos=1,0\n#P {} 0\no\n#P 1 0\no!", i64::MAX);
         assert_eq!(
-            parse_header("x = 3, y = 3, rule = Conway's Game of Life  "),
-            Some(HeaderData {
-                x: 3,
-                y: 3,
-                rule: Some(String::from("Conway's Game of Life"))
-            })
+            positions(Rle::new(rle.as_str())?.with_offset_lines())?,
+            [(1, 0), (2, 0)]
         );
+        let rle = format!("#CXRLE Pos=1,0\no\n#P {} 0\no!", i64::MAX);
+        assert!(positions(Rle::new(rle.as_str())?.with_offset_lines()).is_err());
+        Ok(())
     }
 
     #[test]
-    fn rle_glider() -> Result<(), Error> {
-        const GLIDER: &str = r"#N Glider
-#O Richard K. Guy
-#C The smallest, most common, and first discovered spaceship. Diagonal, has period 4 and speed c/4.
-#C www.conwaylife.com/wiki/index.php?title=Glider
-x = 3, y = 3, rule = B3/S23
-bob$2bo$3o!";
-
-        let glider = Rle::new(GLIDER)?;
-
-        let _ = glider.clone();
-
-        assert_eq!(glider.cxrle_data, None);
+    fn rle_infer_header() -> Result<(), Error> {
+        let header = Rle::new("#CXRLE Pos=-5,-2\nx = 4, y = 3\n$2b3o$bA!")?.infer_header()?;
         assert_eq!(
-            glider.header_data,
-            Some(HeaderData {
-                x: 3,
+            header.inferred,
+            HeaderData {
+                x: 5,
                 y: 3,
-                rule: Some(String::from("B3/S23"))
-            })
+                rule: None,
+                ..Default::default()
+            }
         );
+        assert!(header.is_mismatched());
+        assert!(header.is_too_small());
 
-        let cells = glider
-            .map(|res| res.map(|c| c.position))
-            .collect::<Result<Vec<_>, _>>()?;
-        assert_eq!(cells, vec![(1, 0), (2, 1), (0, 2), (1, 2), (2, 2)]);
+        let header = Rle::new("x = 5, y = 3, rule = B3/S23\nbo$2bo$3o!")?.infer_header()?;
+        assert_eq!((header.inferred.x, header.inferred.y), (3, 3));
+        assert!(header.is_mismatched());
+        assert!(!header.is_too_small());
+
+        let header = Rle::new("x = 3, y = 3\nbo$2bo$3o!")?.infer_header()?;
+        assert!(!header.is_mismatched());
+
+        let header = Rle::new("3o!")?.infer_header()?;
+        assert_eq!(header.declared, None);
+        assert!(header.is_too_small());
+
+        let header = Rle::new("x = 0, y = 0\n!")?.infer_header()?;
+        assert_eq!((header.inferred.x, header.inferred.y), (0, 0));
+        assert!(!header.is_mismatched());
+
+        let header = Rle::new("#P -5 -5\n2o!")?
+            .with_offset_lines()
+            .infer_header()?;
+        assert_eq!((header.inferred.x, header.inferred.y), (2, 1));
+        let header = Rle::new("#P -5 -5\n2o$o\n#P 3 1\n2bo!")?
+            .with_offset_lines()
+            .infer_header()?;
+        assert_eq!((header.inferred.x, header.inferred.y), (11, 7));
         Ok(())
     }
 
+    #[cfg(feature = "rayon")]
     #[test]
-    fn rle_glider_cxrle() -> Result<(), Error> {
-        const GLIDER: &str = r"#CXRLE Pos=-1,-1
-x = 3, y = 3, rule = B3/S23
-bo$2bo$3o!";
+    fn rle_par_cells() -> Result<(), Error> {
+        const RLE: &str = "#CXRLE Pos=-5,-2\nx = 30, y = 9, rule = 23/3/3\n2b30A$\n3bA2B$3$\nA.B 1\n0B2$.3A$B!\n3A!";
+        let convention = Convention {
+            yx: true,
+            y_up: true,
+        };
+        let options = ParseOptions::new().with_convention(convention);
+        let rle = || Ok::<_, Error>(Rle::new_with_options(RLE, options)?.with_offset(1, 2));
+        let cells = rle()?.collect::<Result<Vec<_>, _>>()?;
+        assert_eq!(rle()?.par_cells()?, cells);
 
-        let glider = Rle::new(GLIDER)?;
+        let mut partial = rle()?;
+        partial.nth(9);
+        assert_eq!(partial.par_cells()?, cells[10..]);
 
+        let glider = Rle::new("bo$2bo$3o!")?.with_state_type::<u16>();
+        assert_eq!(glider.par_cells()?.len(), 5);
+
+        assert!(Rle::new("3o$2o$o$3o$p$o!")?.par_cells().is_err());
+        assert!(Rle::new("3o$2o$o$3o$2o$yX!")?.par_cells().is_err());
         assert_eq!(
-            glider.cxrle_data,
-            Some(CxrleData {
-                pos: Some((-1, -1)),
-                gen: None
-            })
-        );
-        assert_eq!(
-            glider.header_data,
-            Some(HeaderData {
-                x: 3,
-                y: 3,
-                rule: Some(String::from("B3/S23"))
-            })
+            Rle::new("3o$2o$o$\n3o$2o$99999999999999999999o!")?.par_cells(),
+            Err(Error::RunCountOverflow(15))
         );
+        Ok(())
+    }
 
-        let cells = glider
-            .map(|res| res.map(|c| c.position))
+    #[test]
+    fn rle_include_dead() -> Result<(), Error> {
+        const RLE: &str = "#CXRLE Pos=-1,-1\nx = 3, y = 4\nbo2$o!";
+
+        let cells = Rle::new_with_options(RLE, ParseOptions::new().include_dead(true))?
             .collect::<Result<Vec<_>, _>>()?;
-        assert_eq!(cells, vec![(0, -1), (1, 0), (-1, 1), (0, 1), (1, 1)]);
+        let states = cells.iter().map(|c| c.state).collect::<Vec<_>>();
+        assert_eq!(states, vec![0, 1, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0]);
+        assert_eq!(cells[0].position, (-1, -1));
+        assert_eq!(cells[11].position, (1, 2));
+
+        let cells =
+            Rle::new_with_options("x = 2, y = 1\n3o!", ParseOptions::new().include_dead(true))?
+                .collect::<Result<Vec<_>, _>>()?;
+        assert_eq!(cells.len(), 3);
+
+        let cells = Rle::new_with_options("3o$o!", ParseOptions::new().include_dead(true))?
+            .collect::<Result<Vec<_>, _>>()?;
+        assert_eq!(cells.len(), 4);
         Ok(())
     }
 
     #[test]
-    fn rle_generations() -> Result<(), Error> {
-        const OSCILLATOR: &str = r"x = 3, y = 3, rule = 3457/357/5
-3A$B2A$.CD!";
+    #[allow(deprecated)]
+    fn rle_options_order() -> Result<(), Error> {
+        const RLE: &str = "x = 3, y = 2\n3o$o2b!";
+        let cells = |rle: Rle<&str>| {
+            rle.map(|cell| cell.map(|cell| (cell.position, cell.state)))
+                .collect::<Result<Vec<_>, _>>()
+        };
 
-        let oscillator = Rle::new(OSCILLATOR)?;
+        let dead = ParseOptions::new().include_dead(true);
+        assert_eq!(cells(Rle::new_with_options(RLE, dead)?)?.len(), 6);
 
-        assert_eq!(oscillator.cxrle_data, None);
+        let unknown = cells(Rle::new_with_options(
+            RLE,
+            ParseOptions::new().unknown(true),
+        )?)?;
+        let both = ParseOptions::new().include_dead(true).unknown(true);
+        assert_eq!(both, ParseOptions::new().unknown(true).include_dead(true));
+        assert_eq!(cells(Rle::new_with_options(RLE, both)?)?, unknown);
         assert_eq!(
-            oscillator.header_data,
-            Some(HeaderData {
-                x: 3,
-                y: 3,
-                rule: Some(String::from("3457/357/5"))
-            })
+            cells(Rle::new_with_options(RLE, dead)?.with_unknown())?,
+            unknown
+        );
+        assert_eq!(cells(Rle::new(RLE)?.with_unknown())?, unknown);
+        Ok(())
+    }
+
+    #[test]
+    fn rle_strict() -> Result<(), Error> {
+        let strict = ParseOptions::new().strict(true);
+        assert_eq!(
+            Rle::new_with_options("#X Glider\nbo$2bo$3o!", strict).unwrap_err(),
+            Error::Strict(Warning::UnknownDirective(String::from("#X Glider")))
         );
 
-        let cells = oscillator.collect::<Result<Vec<_>, _>>()?;
+        let cells = Rle::new_with_options("x = 2, y = 3\nbo$2bo$3o!", strict)?.collect::<Vec<_>>();
+        assert_eq!(cells.len(), 6);
         assert_eq!(
-            cells,
-            vec![
-                CellData {
-                    position: (0, 0),
-                    state: 1,
-                },
-                CellData {
-                    position: (1, 0),
-                    state: 1,
-                },
-                CellData {
-                    position: (2, 0),
-                    state: 1,
-                },
-                CellData {
-                    position: (0, 1),
-                    state: 2,
-                },
-                CellData {
-                    position: (1, 1),
-                    state: 1,
-                },
-                CellData {
-                    position: (2, 1),
-                    state: 1,
-                },
-                CellData {
-                    position: (1, 2),
-                    state: 3,
-                },
-                CellData {
-                    position: (2, 2),
-                    state: 4,
-                },
-            ]
+            cells[5],
+            Err(Error::Strict(Warning::StaleHeader {
+                declared: (2, 3),
+                actual: (3, 3),
+            }))
         );
+
+        let glider = Rle::new_with_options("x = 3, y = 3\nbo$2bo$3o!", strict)?;
+        assert_eq!(glider.collect::<Result<Vec<_>, _>>()?.len(), 5);
         Ok(())
     }
 
     #[test]
-    fn rle_generations_256() -> Result<(), Error> {
-        const OSCILLATOR: &str = r"x = 3, y = 3, rule = 23/3/256
-.AwH$vIxNrQ$2pU!";
+    fn rle_max_cells() -> Result<(), Error> {
+        let options = ParseOptions::new().with_max_cells(5);
+        let glider = Rle::new_with_options("bo$2bo$3o!", options)?;
+        assert_eq!(glider.collect::<Result<Vec<_>, _>>()?.len(), 5);
 
-        let oscillator = Rle::new(OSCILLATOR)?;
+        let cells = Rle::new_with_options("1000000000000o!", options)?.collect::<Vec<_>>();
+        assert_eq!(cells.len(), 6);
+        assert_eq!(cells[5], Err(Error::TooManyCells(5)));
 
-        assert_eq!(oscillator.cxrle_data, None);
-        assert_eq!(
-            oscillator.header_data,
-            Some(HeaderData {
-                x: 3,
-                y: 3,
-                rule: Some(String::from("23/3/256"))
-            })
-        );
+        let options = options.include_dead(true);
+        let rle = Rle::new_with_options("x = 100000, y = 100000\no!", options)?;
+        assert_eq!(rle.collect::<Vec<_>>().len(), 6);
+        Ok(())
+    }
 
-        let cells = oscillator.collect::<Result<Vec<_>, _>>()?;
-        assert_eq!(
-            cells,
-            vec![
-                CellData {
-                    position: (1, 0),
-                    state: 1,
-                },
-                CellData {
-                    position: (2, 0),
-                    state: 200,
-                },
-                CellData {
-                    position: (0, 1),
-                    state: 177,
-                },
-                CellData {
-                    position: (1, 1),
-                    state: 230,
-                },
-                CellData {
-                    position: (2, 1),
-                    state: 89,
-                },
-                CellData {
-                    position: (0, 2),
-                    state: 45,
-                },
-                CellData {
-                    position: (1, 2),
-                    state: 45,
-                },
-            ]
-        );
+    #[test]
+    fn rle_convention() -> Result<(), Error> {
+        const GLIDER: &str = "#CXRLE Pos=-1,-1\nx = 3, y = 3, rule = B3/S23\nbo$2bo$3o!";
+
+        let convention = Convention {
+            yx: true,
+            y_up: false,
+        };
+        let options = ParseOptions::new().with_convention(convention);
+        let glider = Rle::new_with_options(GLIDER, options)?;
+        let cells = glider
+            .map(|res| res.map(|c| c.position))
+            .collect::<Result<Vec<_>, _>>()?;
+        assert_eq!(cells, vec![(-1, 0), (0, 1), (1, -1), (1, 0), (1, 1)]);
+
+        let convention = Convention {
+            yx: false,
+            y_up: true,
+        };
+        let mut parser =
+            PushParser::new_with_options(ParseOptions::new().with_convention(convention));
+        let cells = parser
+            .feed(GLIDER.as_bytes())
+            .map(|res| res.map(|c| c.position))
+            .collect::<Result<Vec<_>, _>>()?;
+        assert_eq!(cells, vec![(0, 1), (1, 0), (-1, -1), (0, -1), (1, -1)]);
+        Ok(())
+    }
+
+    #[test]
+    fn rle_convention_overflow() -> Result<(), Error> {
+        const CELL: &str = "#CXRLE Pos=0,-9223372036854775808\no!";
+
+        let convention = Convention {
+            yx: false,
+            y_up: true,
+        };
+        let options = ParseOptions::new().with_convention(convention);
+        let overflow = Error::CoordinateOverflow((0, i64::MIN));
+        let mut rle = Rle::new_with_options(CELL, options)?;
+        assert_eq!(rle.clone().bounding_box(), Err(overflow.clone()));
+        assert_eq!(rle.next(), Some(Err(overflow.clone())));
+
+        let mut parser = PushParser::new_with_options(options);
+        let mut cells = parser.feed(CELL.as_bytes());
+        assert_eq!(cells.next(), Some(Err(overflow)));
         Ok(())
     }
 
@@ -650,7 +5440,8 @@ bob$2bo$3o!";
             Some(HeaderData {
                 x: 3,
                 y: 3,
-                rule: Some(String::from("B3/S23"))
+                rule: Some(String::from("B3/S23")),
+                ..Default::default()
             })
         );
 
@@ -673,19 +5464,77 @@ bob$2bo$3o!";
     }
 
     #[test]
-    #[cfg(feature = "unknown")]
+    #[cfg(feature = "serde")]
+    fn rle_serde() -> Result<(), Box<dyn std::error::Error>> {
+        const GLIDER: &str = r"#CXRLE Pos=-1,-1
+x = 3, y = 3, rule = B3/S23
+bo$2bo$3o!";
+
+        let glider = Rle::new(GLIDER)?;
+
+        let header_data = glider.header_data().unwrap();
+        let json = serde_json::to_string(header_data)?;
+        assert_eq!(json, r#"{"x":3,"y":3,"rule":"B3/S23"}"#);
+        assert_eq!(&serde_json::from_str::<HeaderData>(&json)?, header_data);
+
+        let cxrle_data = glider.cxrle_data().unwrap();
+        let json = serde_json::to_string(cxrle_data)?;
+        assert_eq!(&serde_json::from_str::<CxrleData>(&json)?, cxrle_data);
+
+        let cells = glider.collect::<Result<Vec<_>, _>>()?;
+        let json = serde_json::to_string(&cells)?;
+        assert_eq!(serde_json::from_str::<Vec<CellData>>(&json)?, cells);
+        Ok(())
+    }
+
+    #[test]
+    fn rle_tokens() {
+        let mut tokens = Tokens::new("12b2?\r\nyO$ !3o");
+        let mut spans = Vec::new();
+        while let Some(token) = tokens.next() {
+            spans.push((token.unwrap(), tokens.last_span()));
+        }
+        assert_eq!(
+            spans,
+            [
+                (Token::Run(12), 0..2),
+                (Token::Dead, 2..3),
+                (Token::Run(2), 3..4),
+                (Token::Unknown, 4..5),
+                (Token::Alive(255), 7..9),
+                (Token::Eol, 9..10),
+                (Token::End, 11..12),
+            ]
+        );
+        assert_eq!(tokens.rest(), b"3o");
+
+        let errors = ["pZ", "y", "2c", "99999999999999999999o"]
+            .map(|body| Tokens::new(body).find_map(Result::err));
+        assert_eq!(
+            errors,
+            [
+                Some(Error::InvalidState(String::from("pZ"))),
+                Some(Error::InvalidState(String::from("y"))),
+                Some(Error::InvalidState(String::from("c"))),
+                Some(Error::RunCountOverflow(0)),
+            ]
+        );
+    }
+
+    #[test]
     fn rle_glider_with_unknown() -> Result<(), Error> {
         const GLIDER: &str = r"#CXRLE Pos=-1,-1
 x = 3, y = 3, rule = B3/S23
 5?$?bob?$?2bo?$?3o?$5?!";
 
-        let glider = Rle::new(GLIDER)?.with_unknown();
+        let glider = Rle::new_with_options(GLIDER, ParseOptions::new().unknown(true))?;
 
         assert_eq!(
             glider.cxrle_data(),
             Some(&CxrleData {
                 pos: Some((-1, -1)),
-                gen: None
+                gen: None,
+                ..Default::default()
             })
         );
         assert_eq!(
@@ -693,7 +5542,8 @@ x = 3, y = 3, rule = B3/S23
             Some(&HeaderData {
                 x: 3,
                 y: 3,
-                rule: Some(String::from("B3/S23"))
+                rule: Some(String::from("B3/S23")),
+                ..Default::default()
             })
         );
 
@@ -0,0 +1,310 @@
+//! A reader for the parts of [Golly's `.rule` files](https://golly.sourceforge.io/Help/formats.html#rule)
+//! that describe the states, so that multi-state patterns can be rendered with the colors
+//! and the names of their rule.
+//!
+//! Only the `@RULE` line, the number of states in `@TABLE` or `@TREE`, the `@COLORS` section,
+//! and an optional `@NAMES` section are read. The transitions are not, since this crate
+//! does not simulate patterns.
+
+use crate::{CellData, State};
+use displaydoc::Display;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, str::FromStr};
+use thiserror::Error;
+
+/// Errors that can be returned when parsing a rule file.
+#[derive(Clone, Debug, Eq, Error, Display, PartialEq)]
+pub enum Error {
+    /// The file has no `@RULE` line.
+    MissingRule,
+    /// Invalid line in the `@COLORS` section: {0}.
+    InvalidColor(String),
+    /// Invalid line in the `@NAMES` section: {0}.
+    InvalidName(String),
+}
+
+/// A color as red, green and blue components.
+pub type Rgb = [u8; 3];
+
+/// The states of a rule, read from a Golly `.rule` file.
+///
+/// The `@COLORS` section has lines like `1 255 0 0`, which give the color of a state,
+/// or lines like `255 0 0 255 255 0`, which give a gradient over the living states.
+/// A gradient needs the number of states, from `n_states:` in `@TABLE`
+/// or `num_states=` in `@TREE`, and is ignored without it.
+///
+/// State names are not part of Golly's format. They are read from an optional
+/// `@NAMES` section, where each line is a state and its name, e.g., `1 Alive`.
+///
+/// Lines starting with `#` are comments.
+///
+/// # Example
+///
+/// ```rust
+/// use ca_formats::rulefile::RuleFile;
+///
+/// const RULE: &str = "@RULE Ants\n\n@TABLE\nn_states:3\n\n\
+///                     @COLORS\n0 0 0 0\n1 255 0 0 # red\n2 0 0 255\n\n@NAMES\n1 Worker\n";
+///
+/// let rule_file = RuleFile::parse(RULE).unwrap();
+/// assert_eq!(rule_file.name, "Ants");
+/// assert_eq!(rule_file.states, Some(3));
+/// assert_eq!(rule_file.colors[&1], [255, 0, 0]);
+/// assert_eq!(rule_file.state_info(1).name, Some("Worker"));
+/// assert_eq!(rule_file.state_info(2).name, None);
+/// ```
+#[derive(Clone, Debug, Eq, PartialEq, Default, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct RuleFile {
+    /// Name of the rule, from the `@RULE` line.
+    pub name: String,
+    /// Number of states, including the dead state, if it is given.
+    pub states: Option<u32>,
+    /// Colors of the states.
+    pub colors: BTreeMap<u32, Rgb>,
+    /// Names of the states.
+    pub names: BTreeMap<u32, String>,
+}
+
+/// The section of a rule file that is being read.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Section {
+    /// Lines before the first section, or in a section that is not read.
+    Other,
+    /// The `@TABLE` section.
+    Table,
+    /// The `@TREE` section.
+    Tree,
+    /// The `@COLORS` section.
+    Colors,
+    /// The `@NAMES` section.
+    Names,
+}
+
+impl RuleFile {
+    /// Parses the text of a rule file.
+    pub fn parse(text: &str) -> Result<Self, Error> {
+        let mut name = None;
+        let mut rule_file = RuleFile::default();
+        let mut gradients = Vec::new();
+        let mut section = Section::Other;
+        for line in text.lines() {
+            let line = line.trim();
+            if let Some(header) = line.strip_prefix('@') {
+                let (keyword, rest) = header
+                    .split_once(char::is_whitespace)
+                    .unwrap_or((header, ""));
+                section = match keyword {
+                    "RULE" => {
+                        name = Some(rest.trim().to_string());
+                        Section::Other
+                    }
+                    "TABLE" => Section::Table,
+                    "TREE" => Section::Tree,
+                    "COLORS" => Section::Colors,
+                    "NAMES" => Section::Names,
+                    _ => Section::Other,
+                };
+                continue;
+            } else if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            match section {
+                Section::Table => {
+                    if let Some(states) = line.strip_prefix("n_states:") {
+                        rule_file.states = states.trim().parse().ok();
+                    }
+                }
+                Section::Tree => {
+                    if let Some(states) = line.strip_prefix("num_states=") {
+                        rule_file.states = states.trim().parse().ok();
+                    }
+                }
+                Section::Colors => {
+                    let numbers = line
+                        .split('#')
+                        .next()
+                        .unwrap_or_default()
+                        .split_whitespace()
+                        .map(str::parse)
+                        .collect::<Result<Vec<u32>, _>>()
+                        .ok()
+                        .filter(|numbers| numbers.iter().skip(numbers.len() % 2).all(|&c| c < 256))
+                        .ok_or_else(|| Error::InvalidColor(line.to_string()))?;
+                    let rgb =
+                        |i: usize| [numbers[i] as u8, numbers[i + 1] as u8, numbers[i + 2] as u8];
+                    match numbers.len() {
+                        4 => {
+                            rule_file.colors.insert(numbers[0], rgb(1));
+                        }
+                        6 => gradients.push((rgb(0), rgb(3))),
+                        _ => return Err(Error::InvalidColor(line.to_string())),
+                    }
+                }
+                Section::Names => {
+                    let (state, state_name) = line
+                        .split_once(char::is_whitespace)
+                        .and_then(|(state, state_name)| {
+                            Some((state.parse().ok()?, state_name.trim()))
+                        })
+                        .ok_or_else(|| Error::InvalidName(line.to_string()))?;
+                    rule_file.names.insert(state, state_name.to_string());
+                }
+                Section::Other => (),
+            }
+        }
+        if let Some(states) = rule_file.states {
+            for (start, end) in gradients {
+                let living = states.saturating_sub(1);
+                for state in 1..=living {
+                    let color = (0..3).map(|i| {
+                        let (start, end) = (i32::from(start[i]), i32::from(end[i]));
+                        let steps = (living - 1).max(1) as i32;
+                        (start + (end - start) * (state as i32 - 1) / steps) as u8
+                    });
+                    let mut rgb = [0; 3];
+                    for (component, value) in rgb.iter_mut().zip(color) {
+                        *component = value;
+                    }
+                    rule_file.colors.entry(state).or_insert(rgb);
+                }
+            }
+        }
+        rule_file.name = name
+            .filter(|name| !name.is_empty())
+            .ok_or(Error::MissingRule)?;
+        Ok(rule_file)
+    }
+
+    /// The name and the color of a state, if they are given.
+    pub fn state_info(&self, state: u32) -> StateInfo<'_> {
+        StateInfo {
+            name: self.names.get(&state).map(String::as_str),
+            color: self.colors.get(&state).copied(),
+        }
+    }
+
+    /// Annotates the cells returned by a parser with the names and the colors of their states.
+    /// See [`Annotated`].
+    pub fn annotate<C: IntoIterator>(&self, cells: C) -> Annotated<'_, C::IntoIter> {
+        Annotated {
+            rule_file: self,
+            cells: cells.into_iter(),
+        }
+    }
+}
+
+impl FromStr for RuleFile {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Error> {
+        Self::parse(s)
+    }
+}
+
+/// The name and the color of a state, returned by [`RuleFile::state_info`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Default, Hash)]
+pub struct StateInfo<'a> {
+    /// Name of the state, from the `@NAMES` section.
+    pub name: Option<&'a str>,
+    /// Color of the state, from the `@COLORS` section.
+    pub color: Option<Rgb>,
+}
+
+/// An iterator adapter that pairs each cell returned by a parser with the [`StateInfo`]
+/// of its state, returned by [`RuleFile::annotate`].
+///
+/// # Example
+///
+/// ```rust
+/// use ca_formats::{rle::Rle, rulefile::RuleFile};
+///
+/// let rule_file = RuleFile::parse("@RULE Test\n@COLORS\n1 255 0 0\n@NAMES\n2 Wire").unwrap();
+/// let rle = Rle::new("x = 2, y = 1, rule = Test\nAB!").unwrap();
+/// let cells = rule_file.annotate(rle).collect::<Result<Vec<_>, _>>().unwrap();
+/// assert_eq!(cells[0].1.color, Some([255, 0, 0]));
+/// assert_eq!(cells[1].1.name, Some("Wire"));
+/// ```
+#[must_use]
+#[derive(Clone, Debug)]
+pub struct Annotated<'a, C> {
+    /// The rule file.
+    rule_file: &'a RuleFile,
+    /// The cells.
+    cells: C,
+}
+
+impl<'a, C> Annotated<'a, C> {
+    /// The rule file, e.g., to look up other states.
+    pub const fn rule_file(&self) -> &'a RuleFile {
+        self.rule_file
+    }
+
+    /// The wrapped parser.
+    pub fn into_inner(self) -> C {
+        self.cells
+    }
+}
+
+impl<'a, C, S, E> Iterator for Annotated<'a, C>
+where
+    C: Iterator<Item = Result<CellData<S>, E>>,
+    S: State,
+{
+    type Item = Result<(CellData<S>, StateInfo<'a>), E>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let rule_file = self.rule_file;
+        self.cells
+            .next()
+            .map(|cell| cell.map(|cell| (cell, rule_file.state_info(cell.state.into()))))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.cells.size_hint()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rulefile_parse() -> Result<(), Error> {
+        const RULE: &str = "# A comment before the rule.\n\
+                            @RULE   Gradient \n\
+                            @TREE\nnum_states=4\nnum_neighbors=8\n\
+                            @COLORS\n# comment\n0 10 20 30\n255 0 0 255 255 0\n3 1 2 3\n\
+                            @ICONS\n1 2 3 4\n\
+                            @NAMES\n0 Dead\n3 Very alive\n";
+        let rule_file = RULE.parse::<RuleFile>()?;
+        assert_eq!(rule_file.name, "Gradient");
+        assert_eq!(rule_file.states, Some(4));
+        assert_eq!(
+            rule_file.colors.values().collect::<Vec<_>>(),
+            [&[10, 20, 30], &[255, 0, 0], &[255, 127, 0], &[1, 2, 3]]
+        );
+        assert_eq!(rule_file.state_info(3).name, Some("Very alive"));
+        assert_eq!(rule_file.state_info(7), StateInfo::default());
+
+        let rule_file = RuleFile::parse("@RULE NoStates\n@COLORS\n0 0 0 0 255 255")?;
+        assert!(rule_file.colors.is_empty());
+        assert_eq!(
+            RuleFile::parse("@TABLE\nn_states:2"),
+            Err(Error::MissingRule)
+        );
+        assert_eq!(
+            RuleFile::parse("@RULE A\n@COLORS\n1 256 0 0"),
+            Err(Error::InvalidColor(String::from("1 256 0 0")))
+        );
+        assert!(RuleFile::parse("@RULE A\n@COLORS\n1 2 3").is_err());
+        assert!(RuleFile::parse("@RULE A\n@NAMES\nAlive").is_err());
+
+        let cells = [Ok::<_, Error>(CellData::from((0, 0)))];
+        let annotated = rule_file.annotate(cells);
+        assert_eq!(annotated.rule_file().name, "NoStates");
+        assert_eq!(annotated.into_inner().count(), 1);
+        Ok(())
+    }
+}
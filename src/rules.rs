@@ -0,0 +1,154 @@
+//! Parsed rules, using the [`ca-rules`](https://crates.io/crates/ca-rules) crate.
+//!
+//! The rulestrings in the parsed files, e.g., [`HeaderData::rule`](crate::rle::HeaderData::rule),
+//! are kept as strings. The methods like [`HeaderData::parsed_rule`](crate::rle::HeaderData::parsed_rule)
+//! parse them into a [`Rule`].
+
+pub use ca_rules::ParseRuleError;
+use ca_rules::{ParseHexGen, ParseLifeGen, ParseNeumannGen, ParseNtHexGen, ParseNtLifeGen};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// Neighborhood of a rule.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Neighborhood {
+    /// Totalistic rules with the Moore neighborhood, e.g., `B3/S23`.
+    Moore,
+    /// Totalistic rules with the hexagonal neighborhood, e.g., `B2/S34H`.
+    Hexagonal,
+    /// Totalistic rules with the von Neumann neighborhood, e.g., `B2/S013V`.
+    VonNeumann,
+    /// Non-totalistic rules with the Moore neighborhood, e.g., `B2-a/S12`.
+    NonTotalisticMoore,
+    /// Non-totalistic rules with the hexagonal neighborhood, e.g., `B2o/S2m34H`.
+    NonTotalisticHexagonal,
+}
+
+/// A rule parsed by [`ca-rules`](https://crates.io/crates/ca-rules).
+///
+/// # Example
+///
+/// ```rust
+/// use ca_formats::rules::{Neighborhood, Rule};
+///
+/// let rule = Rule::parse("3457/357/5").unwrap();
+/// assert_eq!(rule.neighborhood, Neighborhood::Moore);
+/// assert_eq!(rule.b, vec![3, 5, 7]);
+/// assert_eq!(rule.s, vec![3, 4, 5, 7]);
+/// assert_eq!(rule.state_count(), 5);
+/// ```
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Rule {
+    /// Neighborhood of the rule.
+    pub neighborhood: Neighborhood,
+    /// Birth conditions.
+    ///
+    /// For totalistic rules, they are the numbers of living neighbors.
+    /// For non-totalistic rules, they are the neighborhood configurations
+    /// encoded as bits, as in [`ca-rules`](https://crates.io/crates/ca-rules).
+    pub b: Vec<u8>,
+    /// Survival conditions, in the same encoding as [`b`](Rule::b).
+    pub s: Vec<u8>,
+    /// Number of states. `2` for rules that are not Generations rules.
+    pub gen: usize,
+}
+
+impl Rule {
+    /// Parses a rulestring.
+    ///
+    /// Totalistic rules are tried before non-totalistic ones, so that `B3/S23`
+    /// is parsed as a rule with the [`Moore`](Neighborhood::Moore) neighborhood.
+    pub fn parse(rule: &str) -> Result<Self, ParseRuleError> {
+        <Self as ParseLifeGen>::parse_rule(rule)
+            .or_else(|_| <Self as ParseHexGen>::parse_rule(rule))
+            .or_else(|_| <Self as ParseNeumannGen>::parse_rule(rule))
+            .or_else(|_| <Self as ParseNtHexGen>::parse_rule(rule))
+            .or_else(|_| <Self as ParseNtLifeGen>::parse_rule(rule))
+    }
+
+    /// Number of states of the rule.
+    pub const fn state_count(&self) -> usize {
+        self.gen
+    }
+}
+
+impl ParseLifeGen for Rule {
+    fn from_bsg(b: Vec<u8>, s: Vec<u8>, gen: usize) -> Self {
+        Rule {
+            neighborhood: Neighborhood::Moore,
+            b,
+            s,
+            gen,
+        }
+    }
+}
+
+impl ParseHexGen for Rule {
+    fn from_bsg(b: Vec<u8>, s: Vec<u8>, gen: usize) -> Self {
+        Rule {
+            neighborhood: Neighborhood::Hexagonal,
+            b,
+            s,
+            gen,
+        }
+    }
+}
+
+impl ParseNeumannGen for Rule {
+    fn from_bsg(b: Vec<u8>, s: Vec<u8>, gen: usize) -> Self {
+        Rule {
+            neighborhood: Neighborhood::VonNeumann,
+            b,
+            s,
+            gen,
+        }
+    }
+}
+
+impl ParseNtLifeGen for Rule {
+    fn from_bsg(b: Vec<u8>, s: Vec<u8>, gen: usize) -> Self {
+        Rule {
+            neighborhood: Neighborhood::NonTotalisticMoore,
+            b,
+            s,
+            gen,
+        }
+    }
+}
+
+impl ParseNtHexGen for Rule {
+    fn from_bsg(b: Vec<u8>, s: Vec<u8>, gen: usize) -> Self {
+        Rule {
+            neighborhood: Neighborhood::NonTotalisticHexagonal,
+            b,
+            s,
+            gen,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rules_parse() -> Result<(), ParseRuleError> {
+        let life = Rule::parse("B3/S23")?;
+        assert_eq!(life.neighborhood, Neighborhood::Moore);
+        assert_eq!(life.b, vec![3]);
+        assert_eq!(life.s, vec![2, 3]);
+        assert_eq!(life.state_count(), 2);
+
+        let hex = Rule::parse("B2/S34H")?;
+        assert_eq!(hex.neighborhood, Neighborhood::Hexagonal);
+
+        let nt = Rule::parse("B2-a/S12")?;
+        assert_eq!(nt.neighborhood, Neighborhood::NonTotalisticMoore);
+
+        assert_eq!(Rule::parse("23/3/256")?.state_count(), 256);
+        assert!(Rule::parse("WireWorld").is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,293 @@
+//! Rulestrings that are kept exactly as they are written.
+//!
+//! Besides the usual `B3/S23`, a pattern file may use a rule like `MAP…` (a base64 string
+//! of 512 bits), `W110`, `B3/S23:T10,10` or the name of a rule table, e.g., `LifeHistory`.
+//! [`RuleString`] accepts all of them, and the writers of [`Pattern`](crate::Pattern)
+//! write them back unchanged.
+//!
+//! [`RuleInfo`] is the rule of a parser, wherever its format writes it, so that conversion
+//! code doesn't need to know about the header line of an RLE or the `#R` line of a Macrocell.
+
+use crate::rle::normalize_rule;
+#[cfg(feature = "ca-rules")]
+use crate::rules::{ParseRuleError, Rule};
+use displaydoc::Display;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::{fmt, ops::Deref, str::FromStr};
+use thiserror::Error;
+
+/// Errors that can be returned when creating a [`RuleString`].
+#[derive(Clone, Copy, Debug, Eq, Error, Display, PartialEq)]
+pub enum Error {
+    /// The rulestring is empty.
+    Empty,
+    /// The rulestring starts or ends with a whitespace.
+    Whitespace,
+    /// The rulestring contains a line break.
+    LineBreak,
+}
+
+/// A rulestring, e.g., `B3/S23`, `MAP…`, `W110` or `LifeHistory`.
+///
+/// The validation is minimal: the rulestring must be non-empty, must not start or end with
+/// a whitespace, and must not contain a line break, so that it can be written in a header line
+/// and parsed back to the same string. The rulestring is never changed, unless
+/// [`normalized`](RuleString::normalized) is asked for.
+///
+/// It dereferences to a [`str`].
+///
+/// # Example
+///
+/// ```rust
+/// use ca_formats::RuleString;
+///
+/// let rule = RuleString::new("b3s32").unwrap();
+/// assert_eq!(rule.as_str(), "b3s32");
+/// assert_eq!(rule.normalized().as_str(), "B3/S23");
+/// assert!(RuleString::new(" B3/S23").is_err());
+/// assert!(RuleString::new("W110").is_ok());
+/// ```
+#[derive(Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(try_from = "String", into = "String")
+)]
+pub struct RuleString(String);
+
+impl RuleString {
+    /// Checks a rulestring.
+    pub fn new<T: Into<String>>(rule: T) -> Result<Self, Error> {
+        let rule = rule.into();
+        if rule.is_empty() {
+            Err(Error::Empty)
+        } else if rule.contains(['\n', '\r']) {
+            Err(Error::LineBreak)
+        } else if rule.starts_with(|c: char| c.is_ascii_whitespace())
+            || rule.ends_with(|c: char| c.is_ascii_whitespace())
+        {
+            Err(Error::Whitespace)
+        } else {
+            Ok(RuleString(rule))
+        }
+    }
+
+    /// The rulestring as a string slice.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// The rulestring as a [`String`].
+    pub fn into_string(self) -> String {
+        self.0
+    }
+
+    /// The rulestring in a canonical form. See
+    /// [`HeaderData::normalized_rule`](crate::rle::HeaderData::normalized_rule).
+    ///
+    /// Rules that are not totalistic are returned unchanged.
+    pub fn normalized(&self) -> Self {
+        normalize_rule(&self.0).map_or_else(|| self.clone(), RuleString)
+    }
+
+    /// Parses the rulestring into a [`Rule`].
+    #[cfg(feature = "ca-rules")]
+    #[cfg_attr(docs_rs, doc(cfg(feature = "ca-rules")))]
+    pub fn parsed(&self) -> Result<Rule, ParseRuleError> {
+        Rule::parse(&self.0)
+    }
+}
+
+/// Where a format writes the rule of a pattern.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum RuleOrigin {
+    /// `rule = …` in the header line of an RLE, e.g., `rule = B3/S23`.
+    Header,
+    /// The `#R` line of a Macrocell or a Life 1.05 file, e.g., `#R 23/3`.
+    RLine,
+    /// The rule slug in the prefix of an apgcode, e.g., `b3s23/C1/`.
+    ApgcodePrefix,
+}
+
+/// The rule of a pattern, and where it is written.
+///
+/// Returned by [`CaParser::rule_info`](crate::parser::CaParser::rule_info) and the
+/// `rule_info` methods of the parsers.
+///
+/// Different formats may write the same rule differently, e.g., `B3/S23` in the header line
+/// of an RLE, `23/3` in the `#R` line of a Life 1.05 file, and `b3s23` in an apgcode.
+/// [`is_equivalent`](RuleInfo::is_equivalent) compares the [normalized](RuleString::normalized)
+/// rules.
+///
+/// # Example
+///
+/// ```rust
+/// use ca_formats::{apgcode::ApgCode, macrocell::Macrocell, rle::Rle, RuleOrigin};
+///
+/// let rle = Rle::new("x = 3, y = 3, rule = B3/S23\nbo$2bo$3o!").unwrap();
+/// let macrocell = Macrocell::new("[M2]\n#R 23/3\n$$$$$$*$.*$\n.......*$\n**$\n4 0 1 2 3").unwrap();
+/// let apgcode = ApgCode::new_with_context("b3s23/C1/xq4_153").unwrap();
+///
+/// let rle_rule = rle.rule_info().unwrap();
+/// assert_eq!(rle_rule.origin, RuleOrigin::Header);
+/// assert_eq!(macrocell.rule_info().unwrap().origin, RuleOrigin::RLine);
+/// assert!(rle_rule.is_equivalent(&macrocell.rule_info().unwrap()));
+/// assert!(rle_rule.is_equivalent(&apgcode.rule_info().unwrap()));
+/// ```
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct RuleInfo {
+    /// The rulestring, as it is written.
+    pub rule: RuleString,
+    /// Where the rule is written.
+    pub origin: RuleOrigin,
+}
+
+impl RuleInfo {
+    /// The rule of a parser, if it is specified and is a valid [`RuleString`].
+    pub(crate) fn from_parser(rule: Option<&str>, origin: RuleOrigin) -> Option<Self> {
+        Some(RuleInfo {
+            rule: RuleString::new(rule?).ok()?,
+            origin,
+        })
+    }
+
+    /// The rulestring in a canonical form. See [`RuleString::normalized`].
+    pub fn normalized(&self) -> RuleString {
+        self.rule.normalized()
+    }
+
+    /// Whether two rules are the same after [normalization](RuleString::normalized),
+    /// wherever they are written.
+    pub fn is_equivalent(&self, other: &RuleInfo) -> bool {
+        self.normalized() == other.normalized()
+    }
+}
+
+impl Deref for RuleString {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl AsRef<str> for RuleString {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Display for RuleString {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl FromStr for RuleString {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Error> {
+        Self::new(s)
+    }
+}
+
+impl TryFrom<String> for RuleString {
+    type Error = Error;
+
+    fn try_from(rule: String) -> Result<Self, Error> {
+        Self::new(rule)
+    }
+}
+
+impl TryFrom<&str> for RuleString {
+    type Error = Error;
+
+    fn try_from(rule: &str) -> Result<Self, Error> {
+        Self::new(rule)
+    }
+}
+
+impl From<RuleString> for String {
+    fn from(rule: RuleString) -> Self {
+        rule.0
+    }
+}
+
+impl PartialEq<str> for RuleString {
+    fn eq(&self, other: &str) -> bool {
+        self.0 == other
+    }
+}
+
+impl PartialEq<&str> for RuleString {
+    fn eq(&self, other: &&str) -> bool {
+        self.0 == *other
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{life::Life105, macrocell::Macrocell, rle::Rle, CellData, Pattern};
+    use std::error::Error as StdError;
+
+    #[test]
+    fn rulestring_roundtrip() -> Result<(), Box<dyn StdError>> {
+        let rules = [
+            "MAPARYXfhZofugWaH7oaIDogBZofuhogOiAaIDogIAAgAAWaH7oaIDogGiA6ICAgIAAgAAWaH7oaIDogIAAgAAgAA+/",
+            "W110",
+            "LifeHistory",
+            "B3/S23:T10,10",
+            "b3s32",
+            "23/3",
+            "B2o/S2m34H",
+        ];
+        for rule in rules {
+            let rule = RuleString::new(rule)?;
+            let pattern = Pattern {
+                cells: [(0, 0), (1, 1)].map(CellData::from).to_vec(),
+                rule: Some(rule.clone()),
+                ..Pattern::default()
+            };
+
+            let mut rle = Vec::new();
+            pattern.write_rle(&mut rle)?;
+            let parsed = Pattern::from_rle(Rle::new(rle.as_slice())?)?;
+            assert_eq!(parsed.rule.as_ref(), Some(&rule));
+
+            let mut macrocell = Vec::new();
+            pattern.write_macrocell(&mut macrocell)?;
+            let parsed = Pattern::from_macrocell(Macrocell::new(macrocell.as_slice())?)?;
+            assert_eq!(parsed.rule.as_ref(), Some(&rule));
+
+            let mut life = Vec::new();
+            pattern.write_life105(&mut life)?;
+            let parsed = Pattern::<u8>::from_life105(Life105::new(life.as_slice())?)?;
+            assert_eq!(parsed.rule.as_ref(), Some(&rule));
+        }
+
+        assert_eq!(RuleString::new("23/3")?.normalized(), "B3/S23");
+        assert_eq!(RuleString::new("W110")?.normalized(), "W110");
+        assert_eq!(RuleString::new("Bé")?.normalized(), "Bé");
+        assert_eq!(RuleString::new(""), Err(Error::Empty));
+        assert_eq!(RuleString::new("B3/S23 "), Err(Error::Whitespace));
+        assert_eq!(RuleString::new("B3\nS23"), Err(Error::LineBreak));
+        assert_eq!(String::from(RuleString::new("B3/S23")?), "B3/S23");
+
+        let rle = RuleInfo::from_parser(Some("B3/S23"), RuleOrigin::Header).unwrap();
+        let life = RuleInfo::from_parser(Some("23/3"), RuleOrigin::RLine).unwrap();
+        let apgcode = RuleInfo::from_parser(Some("b3s23"), RuleOrigin::ApgcodePrefix).unwrap();
+        let highlife = RuleInfo::from_parser(Some("23/36"), RuleOrigin::RLine).unwrap();
+        assert!(rle.is_equivalent(&life) && life.is_equivalent(&apgcode));
+        assert!(!rle.is_equivalent(&highlife));
+        assert_eq!(
+            RuleInfo::from_parser(Some(" B3/S23"), RuleOrigin::Header),
+            None
+        );
+        assert_eq!(RuleInfo::from_parser(None, RuleOrigin::Header), None);
+        Ok(())
+    }
+}
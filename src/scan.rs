@@ -0,0 +1,253 @@
+//! A small scanner for header lines and node lines.
+//!
+//! The errors are the columns, starting from `1`, where the lines stop matching.
+
+use std::str::{from_utf8, FromStr};
+
+/// A cursor over the bytes of a line.
+#[derive(Clone, Debug)]
+pub(crate) struct Scanner<'a> {
+    /// The line.
+    line: &'a [u8],
+    /// Index of the next byte.
+    position: usize,
+}
+
+impl<'a> Scanner<'a> {
+    /// Creates a scanner at the beginning of a line.
+    pub(crate) const fn new(line: &'a [u8]) -> Self {
+        Scanner { line, position: 0 }
+    }
+
+    /// Column of the next byte, starting from `1`.
+    pub(crate) const fn column(&self) -> usize {
+        self.position + 1
+    }
+
+    /// Skips a literal if the rest of the line starts with it.
+    pub(crate) fn eat(&mut self, literal: &[u8]) -> bool {
+        let matched = self.line[self.position..].starts_with(literal);
+        if matched {
+            self.position += literal.len();
+        }
+        matched
+    }
+
+    /// Skips a literal, or returns an error.
+    pub(crate) fn expect(&mut self, literal: &[u8]) -> Result<(), usize> {
+        if self.eat(literal) {
+            Ok(())
+        } else {
+            Err(self.column())
+        }
+    }
+
+    /// Skips zero or more whitespaces, i.e., `\s*`.
+    ///
+    /// Returns whether any whitespace is skipped.
+    pub(crate) fn skip_whitespace(&mut self) -> bool {
+        let start = self.position;
+        while self
+            .line
+            .get(self.position)
+            .is_some_and(u8::is_ascii_whitespace)
+        {
+            self.position += 1;
+        }
+        self.position > start
+    }
+
+    /// Skips one or more whitespaces, i.e., `\s+`, or returns an error.
+    pub(crate) fn expect_whitespace(&mut self) -> Result<(), usize> {
+        if self.skip_whitespace() {
+            Ok(())
+        } else {
+            Err(self.column())
+        }
+    }
+
+    /// Reads the digits of a number, i.e., `\d+`, or `-?\d+` if `signed` is `true`,
+    /// together with the column where they start.
+    ///
+    /// Returns `None` without moving if there is no digit.
+    pub(crate) fn digits(&mut self, signed: bool) -> Option<(&'a str, usize)> {
+        let start = self.position;
+        let rest = &self.line[start..];
+        let sign = usize::from(signed && rest.starts_with(b"-"));
+        let digits = rest[sign..]
+            .iter()
+            .take_while(|c| c.is_ascii_digit())
+            .count();
+        if digits == 0 {
+            return None;
+        }
+        self.position = start + sign + digits;
+        let digits = from_utf8(&self.line[start..self.position]).ok()?;
+        Some((digits, start + 1))
+    }
+
+    /// Reads a number, i.e., `\d+`, or `-?\d+` if `signed` is `true`.
+    pub(crate) fn number<T: FromStr>(&mut self, signed: bool) -> Result<T, usize> {
+        let digits = self.digits(signed).ok_or(self.column())?;
+        parse_number(digits)
+    }
+
+    /// Reads the rest of the line with trailing whitespaces removed, i.e., `(.*\S)\s*$`.
+    ///
+    /// The rest must be non-empty and valid UTF-8.
+    pub(crate) fn rest(&mut self) -> Result<&'a str, usize> {
+        let rest = self.line[self.position..].trim_ascii_end();
+        if rest.is_empty() {
+            return Err(self.column());
+        }
+        let rest = from_utf8(rest).map_err(|e| self.column() + e.valid_up_to())?;
+        self.position = self.line.len();
+        Ok(rest)
+    }
+
+    /// Reads an identifier, i.e., `[A-Za-z_][A-Za-z0-9_]*`.
+    ///
+    /// Returns `None` without moving if there is no identifier.
+    pub(crate) fn identifier(&mut self) -> Option<&'a str> {
+        let start = self.position;
+        let rest = &self.line[start..];
+        if !rest
+            .first()
+            .is_some_and(|c| c.is_ascii_alphabetic() || *c == b'_')
+        {
+            return None;
+        }
+        let len = rest
+            .iter()
+            .take_while(|c| c.is_ascii_alphanumeric() || **c == b'_')
+            .count();
+        self.position = start + len;
+        from_utf8(&self.line[start..self.position]).ok()
+    }
+
+    /// Reads a run of non-whitespace bytes, i.e., `\S+`.
+    ///
+    /// Returns `None` without moving if there is no such byte, or if they are not valid UTF-8.
+    pub(crate) fn word(&mut self) -> Option<&'a str> {
+        let start = self.position;
+        let len = self.line[start..]
+            .iter()
+            .take_while(|c| !c.is_ascii_whitespace())
+            .count();
+        let word = from_utf8(&self.line[start..start + len]).ok()?;
+        if word.is_empty() {
+            return None;
+        }
+        self.position = start + len;
+        Some(word)
+    }
+
+    /// Reads the value of a `key = value` pair in a comma-separated list,
+    /// with whitespaces around it removed.
+    ///
+    /// The value ends at the first comma followed by another `key =`, or at the end
+    /// of the line, so that it may contain commas, e.g., `B3/S23:T10,10`.
+    /// The value must be non-empty and valid UTF-8.
+    pub(crate) fn value(&mut self) -> Result<&'a str, usize> {
+        self.skip_whitespace();
+        let start = self.position;
+        let mut end = self.line.len();
+        for (i, &c) in self.line.iter().enumerate().skip(start) {
+            if c == b',' {
+                let mut next = Scanner::new(self.line);
+                next.position = i + 1;
+                next.skip_whitespace();
+                if next.identifier().is_some() && {
+                    next.skip_whitespace();
+                    next.eat(b"=")
+                } {
+                    end = i;
+                    break;
+                }
+            }
+        }
+        let value = self.line[start..end].trim_ascii_end();
+        if value.is_empty() {
+            return Err(self.column());
+        }
+        let value = from_utf8(value).map_err(|e| self.column() + e.valid_up_to())?;
+        self.position = end;
+        Ok(value)
+    }
+
+    /// Checks that only whitespaces remain, i.e., `\s*$`.
+    pub(crate) fn end(&mut self) -> Result<(), usize> {
+        self.skip_whitespace();
+        if self.position == self.line.len() {
+            Ok(())
+        } else {
+            Err(self.column())
+        }
+    }
+
+    /// The next byte, if any.
+    pub(crate) fn peek(&self) -> Option<u8> {
+        self.line.get(self.position).copied()
+    }
+
+    /// Moves to the next byte. Returns `false` at the end of the line.
+    pub(crate) fn advance(&mut self) -> bool {
+        if self.position < self.line.len() {
+            self.position += 1;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+/// Parses the digits read by [`Scanner::digits`].
+///
+/// Numbers that don't fit in the type are errors at the beginning of the number.
+pub(crate) fn parse_number<T: FromStr>((digits, column): (&str, usize)) -> Result<T, usize> {
+    digits.parse().map_err(|_| column)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scan_line() {
+        let mut scanner = Scanner::new(b"x  = -12,3 rule = B3/S23  ");
+        assert!(scanner.eat(b"x"));
+        assert!(scanner.skip_whitespace());
+        assert_eq!(scanner.expect(b"y"), Err(4));
+        assert_eq!(scanner.expect(b"="), Ok(()));
+        assert_eq!(scanner.expect_whitespace(), Ok(()));
+        assert_eq!(scanner.number::<u64>(false), Err(6));
+        assert_eq!(scanner.clone().digits(true), Some(("-12", 6)));
+        assert_eq!(scanner.number::<i64>(true), Ok(-12));
+        assert_eq!(scanner.expect(b","), Ok(()));
+        assert_eq!(scanner.number::<u8>(false), Ok(3));
+        assert_eq!(scanner.end(), Err(12));
+        assert!(scanner.eat(b"rule = "));
+        assert_eq!(scanner.rest(), Ok("B3/S23"));
+        assert_eq!(scanner.end(), Ok(()));
+
+        let mut scanner = Scanner::new(b"color = red, green,size=2, rule = B3/S23:T3,3 ,");
+        assert_eq!(scanner.identifier(), Some("color"));
+        assert_eq!(scanner.expect(b" = "), Ok(()));
+        assert_eq!(scanner.value(), Ok("red, green"));
+        assert!(scanner.eat(b","));
+        assert_eq!(scanner.identifier(), Some("size"));
+        assert!(scanner.eat(b"="));
+        assert_eq!(scanner.value(), Ok("2"));
+        assert!(scanner.eat(b", "));
+        assert_eq!(scanner.identifier(), Some("rule"));
+        assert!(scanner.eat(b" = "));
+        assert_eq!(scanner.value(), Ok("B3/S23:T3,3 ,"));
+        assert_eq!(scanner.end(), Ok(()));
+        assert_eq!(Scanner::new(b"1a").identifier(), None);
+        assert_eq!(Scanner::new(b"  , a=1").value(), Err(3));
+
+        assert_eq!(Scanner::new(b"256").number::<u8>(false), Err(1));
+        assert_eq!(Scanner::new(b"  ").rest(), Err(1));
+        assert_eq!(Scanner::new(b"ab\xff").rest(), Err(3));
+    }
+}
@@ -0,0 +1,422 @@
+//! Type-erased cell streams, for applications that choose the format at runtime.
+//!
+//! [`open`] reads a file, guesses its [`Format`], and returns a `Box<dyn CellSource>`,
+//! an iterator over the cells together with the metadata common to all formats.
+
+use crate::{
+    apgcode::ApgCode,
+    input::Normalized,
+    life::{Life105, Life106},
+    macrocell::Macrocell,
+    order::Ordering,
+    parser::CaParser,
+    plaintext::Plaintext,
+    rle::Rle,
+    CellData, ParseOptions, RuleInfo,
+};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::{error::Error as StdError, fs::read_to_string, path::Path};
+
+/// A boxed error of any parser.
+pub type DynError = Box<dyn StdError + Send + Sync>;
+
+/// Formats of patterns.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum Format {
+    /// RLE.
+    Rle,
+    /// Plaintext.
+    Plaintext,
+    /// Life 1.05.
+    Life105,
+    /// Life 1.06.
+    Life106,
+    /// Macrocell.
+    Macrocell,
+    /// apgcode, with or without the rule and the symmetry.
+    Apgcode,
+}
+
+impl Format {
+    /// Guess the format of a pattern from its content.
+    ///
+    /// Falls back to RLE if nothing else matches.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use ca_formats::source::Format;
+    ///
+    /// assert_eq!(Format::detect("x = 3, y = 3\nbo$2bo$3o!"), Format::Rle);
+    /// assert_eq!(Format::detect("!Name: Glider\n.O\n..O\nOOO"), Format::Plaintext);
+    /// assert_eq!(Format::detect("b3s23/C1/xq4_153"), Format::Apgcode);
+    /// ```
+    pub fn detect(text: &str) -> Self {
+        let trimmed = text.trim_start_matches('\u{feff}').trim_start();
+        if trimmed.starts_with("[M2]") {
+            return Format::Macrocell;
+        } else if trimmed.starts_with("#Life 1.05") {
+            return Format::Life105;
+        } else if trimmed.starts_with("#Life 1.06") {
+            return Format::Life106;
+        } else if trimmed.split('/').any(|segment| {
+            ["xs", "xp", "xq"].iter().any(|p| segment.starts_with(p)) && segment.contains('_')
+        }) && !trimmed.trim_end().contains(char::is_whitespace)
+        {
+            return Format::Apgcode;
+        }
+        for line in trimmed.lines() {
+            if line.starts_with('!') {
+                return Format::Plaintext;
+            } else if line.starts_with("#P") {
+                return Format::Life105;
+            } else if line.starts_with('#') || line.trim().is_empty() {
+                continue;
+            } else if line.starts_with('x') {
+                return Format::Rle;
+            } else if line
+                .chars()
+                .all(|c| matches!(c, '.' | 'O' | '*') || c.is_whitespace())
+            {
+                return Format::Plaintext;
+            } else if line.split_whitespace().all(|n| n.parse::<i64>().is_ok()) {
+                return Format::Life106;
+            } else {
+                return Format::Rle;
+            }
+        }
+        Format::Rle
+    }
+
+    /// The format of a file extension, e.g., `rle`, `cells` or `mc`.
+    ///
+    /// `None` for unknown extensions, and for `lif` and `life`,
+    /// which are used by both Life 1.05 and Life 1.06.
+    pub fn from_extension(extension: &str) -> Option<Self> {
+        match extension.to_ascii_lowercase().as_str() {
+            "rle" => Some(Format::Rle),
+            "cells" => Some(Format::Plaintext),
+            "mc" => Some(Format::Macrocell),
+            _ => None,
+        }
+    }
+}
+
+/// A type-erased stream of cells, with the metadata common to all formats.
+///
+/// Returned by [`open`] and [`from_string`]. The errors of the parsers are boxed.
+pub trait CellSource: Iterator<Item = Result<CellData, DynError>> {
+    /// Format of the pattern.
+    fn format(&self) -> Format;
+
+    /// Comment lines before the cells.
+    fn comments(&self) -> &[String];
+
+    /// The rulestring, or the rule slug of an apgcode.
+    fn rule(&self) -> Option<&str>;
+
+    /// The rule, and where the format writes it. See [`CaParser::rule_info`].
+    fn rule_info(&self) -> Option<&RuleInfo>;
+
+    /// The order of the cells. See [`CaParser::ordering`].
+    fn ordering(&self) -> Ordering;
+}
+
+/// A [`CellSource`] that wraps the cells of a parser.
+struct Source<C> {
+    /// Format of the pattern.
+    format: Format,
+    /// Comment lines before the cells.
+    comments: Vec<String>,
+    /// The rulestring.
+    rule: Option<String>,
+    /// The rule, and where it is written.
+    rule_info: Option<RuleInfo>,
+    /// The order of the cells.
+    ordering: Ordering,
+    /// The cells.
+    cells: C,
+}
+
+impl<C> Source<C> {
+    /// Takes the metadata of a parser.
+    fn new<P: CaParser>(format: Format, parser: &P, cells: C) -> Self {
+        Source {
+            format,
+            comments: parser.comments().to_vec(),
+            rule: parser.rule().map(String::from),
+            rule_info: parser.rule_info(),
+            ordering: parser.ordering(),
+            cells,
+        }
+    }
+
+    /// Replaces the cells.
+    fn with_cells<D>(self, cells: D) -> Source<D> {
+        Source {
+            format: self.format,
+            comments: self.comments,
+            rule: self.rule,
+            rule_info: self.rule_info,
+            ordering: self.ordering,
+            cells,
+        }
+    }
+}
+
+impl<C, T, E> Iterator for Source<C>
+where
+    C: Iterator<Item = Result<T, E>>,
+    T: Into<CellData>,
+    E: Into<DynError>,
+{
+    type Item = Result<CellData, DynError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.cells
+            .next()
+            .map(|cell| cell.map(Into::into).map_err(Into::into))
+    }
+}
+
+impl<C, T, E> CellSource for Source<C>
+where
+    C: Iterator<Item = Result<T, E>>,
+    T: Into<CellData>,
+    E: Into<DynError>,
+{
+    fn format(&self) -> Format {
+        self.format
+    }
+
+    fn comments(&self) -> &[String] {
+        &self.comments
+    }
+
+    fn rule(&self) -> Option<&str> {
+        self.rule.as_deref()
+    }
+
+    fn rule_info(&self) -> Option<&RuleInfo> {
+        self.rule_info.as_ref()
+    }
+
+    fn ordering(&self) -> Ordering {
+        self.ordering
+    }
+}
+
+/// Parse a pattern in a string as a [`CellSource`].
+///
+/// The format is [detected](Format::detect) from the content if it is `None`.
+/// A byte order mark, classic Mac OS line endings and trailing whitespaces are removed
+/// as in [`Normalized`]. Macrocell and apgcode patterns are decoded at once,
+/// and other formats lazily.
+///
+/// # Example
+///
+/// ```rust
+/// use ca_formats::source::{from_string, Format};
+///
+/// let glider = from_string(String::from("#N Glider\nbo$2bo$3o!"), None).unwrap();
+/// assert_eq!(glider.format(), Format::Rle);
+/// assert_eq!(glider.comments(), ["#N Glider"]);
+/// assert_eq!(glider.count(), 5);
+/// ```
+pub fn from_string(text: String, format: Option<Format>) -> Result<Box<dyn CellSource>, DynError> {
+    from_string_with_options(text, format, ParseOptions::default())
+}
+
+/// Parse a pattern in a string as a [`CellSource`], with [`ParseOptions`]
+/// applied to the parser of its format. See [`from_string`].
+///
+/// # Example
+///
+/// ```rust
+/// use ca_formats::{source::from_string_with_options, ParseOptions};
+///
+/// let options = ParseOptions::new().include_dead(true).lenient(true);
+/// let rle = from_string_with_options(String::from("x = 3, y = 1\nbo!"), None, options).unwrap();
+/// assert_eq!(rle.count(), 3);
+/// let plaintext = from_string_with_options(String::from("!\n-*"), None, options).unwrap();
+/// let cells = plaintext.map(|cell| cell.unwrap().position).collect::<Vec<_>>();
+/// assert_eq!(cells, [(0, 0), (1, 0)]);
+/// ```
+pub fn from_string_with_options(
+    text: String,
+    format: Option<Format>,
+    options: ParseOptions,
+) -> Result<Box<dyn CellSource>, DynError> {
+    let format = format.unwrap_or_else(|| Format::detect(&text));
+    Ok(match format {
+        Format::Rle => {
+            let rle = Rle::new_with_options(Normalized::new(text), options)?;
+            Box::new(Source::new(format, &rle, ()).with_cells(rle))
+        }
+        Format::Plaintext => {
+            let plaintext = Plaintext::new_with_options(Normalized::new(text), options)?;
+            Box::new(Source::new(format, &plaintext, ()).with_cells(plaintext.cells()))
+        }
+        Format::Life105 => {
+            let life = Life105::new_with_options(Normalized::new(text), options)?;
+            Box::new(Source::new(format, &life, ()).with_cells(life))
+        }
+        Format::Life106 => {
+            let life = Life106::new_with_options(Normalized::new(text), options);
+            Box::new(Source::new(format, &life, ()).with_cells(life))
+        }
+        Format::Macrocell => {
+            let macrocell = Macrocell::new_with_options(Normalized::new(text), options)?;
+            let source = Source::new(format, &macrocell, ());
+            let cells = macrocell.cells()?;
+            Box::new(source.with_cells(cells.into_iter().map(Ok::<_, DynError>)))
+        }
+        Format::Apgcode => {
+            let apgcode = ApgCode::new_with_context_and_options(text.trim(), options)?;
+            let source = Source::new(format, &apgcode, ());
+            let cells = apgcode.collect::<Result<Vec<_>, _>>()?;
+            Box::new(source.with_cells(cells.into_iter().map(Ok::<_, DynError>)))
+        }
+    })
+}
+
+/// Open a pattern file as a [`CellSource`].
+///
+/// The format is given by the extension of the file, if it is [known](Format::from_extension),
+/// and [detected](Format::detect) from the content otherwise. See [`from_string`].
+///
+/// # Example
+///
+/// ```rust
+/// let sirrobin = ca_formats::open("tests/sirrobin.rle").unwrap();
+/// assert_eq!(sirrobin.rule(), Some("B3/S23"));
+/// assert_eq!(sirrobin.count(), 282);
+/// ```
+pub fn open<P: AsRef<Path>>(path: P) -> Result<Box<dyn CellSource>, DynError> {
+    open_with_options(path, ParseOptions::default())
+}
+
+/// Open a pattern file as a [`CellSource`], with [`ParseOptions`] applied to the parser
+/// of its format. See [`open`].
+pub fn open_with_options<P: AsRef<Path>>(
+    path: P,
+    options: ParseOptions,
+) -> Result<Box<dyn CellSource>, DynError> {
+    let path = path.as_ref();
+    let text = read_to_string(path)?;
+    let format = path
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .and_then(Format::from_extension);
+    from_string_with_options(text, format, options)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Convention;
+
+    #[test]
+    fn source_detect() {
+        assert_eq!(
+            Format::detect("#N Glider\nx = 3, y = 3\nbo$2bo$3o!"),
+            Format::Rle
+        );
+        assert_eq!(Format::detect("bo$2bo$3o!"), Format::Rle);
+        assert_eq!(
+            Format::detect("!Name: Glider\n.O.\n..O\nOOO"),
+            Format::Plaintext
+        );
+        assert_eq!(Format::detect(".O.\n..O\nOOO"), Format::Plaintext);
+        assert_eq!(Format::detect("#Life 1.05\n#P 0 0\n.*"), Format::Life105);
+        assert_eq!(Format::detect("#Life 1.06\n0 -1"), Format::Life106);
+        assert_eq!(Format::detect("0 -1\n1 0"), Format::Life106);
+        assert_eq!(
+            Format::detect("[M2] (golly 3.4)\n**$\n4 0 1 0 0"),
+            Format::Macrocell
+        );
+        assert_eq!(Format::detect("xq4_153\n"), Format::Apgcode);
+        assert_eq!(Format::detect("b3s23/C1/xq4_153"), Format::Apgcode);
+        assert_eq!(
+            Format::detect("https://catagolue.hatsya.com/object/xq4_153/b3s23"),
+            Format::Apgcode
+        );
+    }
+
+    #[test]
+    fn source_formats() -> Result<(), DynError> {
+        for (text, format) in [
+            ("#N Glider\r\nx = 3, y = 3\r\nbo$2bo$3o!", Format::Rle),
+            ("\u{feff}!Name: Glider\n.O\n..O\nOOO", Format::Plaintext),
+            ("#Life 1.05\n#P 0 0\n.*\n..*\n***", Format::Life105),
+            ("#Life 1.06\n1 0\n2 1\n0 2\n1 2\n2 2", Format::Life106),
+            (
+                "[M2]\n$$$$$$*$.*$\n.......*$\n**$\n4 0 1 2 3",
+                Format::Macrocell,
+            ),
+            ("xq4_153\n", Format::Apgcode),
+        ] {
+            assert_eq!(Format::detect(text), format);
+            let source = from_string(String::from(text), None)?;
+            assert_eq!(source.format(), format);
+            assert_eq!(source.collect::<Result<Vec<_>, _>>()?.len(), 5);
+        }
+
+        let source = from_string(String::from("#C comment\n3o!"), Some(Format::Rle))?;
+        assert_eq!(source.comments(), ["#C comment"]);
+        assert_eq!(source.ordering(), Ordering::RowMajor);
+        let mut source = from_string(String::from("3o$2z!"), Some(Format::Rle))?;
+        assert!(source.any(|cell| cell.is_err()));
+        assert!(from_string(String::from("xq4_15!"), Some(Format::Apgcode)).is_err());
+        assert!(open("tests/missing.rle").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn source_options() -> Result<(), DynError> {
+        let convention = Convention {
+            yx: true,
+            y_up: true,
+        };
+        let options = ParseOptions::new().with_convention(convention);
+        for path in [
+            "tests/sirrobin.rle",
+            "tests/sirrobin.cells",
+            "tests/sirrobin.mc",
+        ] {
+            let expected = open(path)?
+                .map(|cell| cell.map(|cell| convention.apply(cell.position).unwrap()))
+                .collect::<Result<Vec<_>, _>>()?;
+            let cells = open_with_options(path, options)?
+                .map(|cell| cell.map(|cell| cell.position))
+                .collect::<Result<Vec<_>, _>>()?;
+            assert_eq!(cells, expected);
+        }
+
+        let options = ParseOptions::new().include_dead(true).lenient(true);
+        let text = String::from("x = 2, y = 2\n2o$o!");
+        assert_eq!(from_string_with_options(text, None, options)?.count(), 4);
+        let text = String::from("!\n#_o");
+        let source = from_string_with_options(text, Some(Format::Plaintext), options)?;
+        assert_eq!(source.collect::<Result<Vec<_>, _>>()?.len(), 3);
+        assert!(
+            from_string(String::from("!\n#_o"), Some(Format::Plaintext))?.any(|cell| cell.is_err())
+        );
+
+        let text = String::from("x = 3, y = 1\n?ob!");
+        let source = from_string_with_options(text, None, ParseOptions::new().unknown(true))?;
+        assert_eq!(source.collect::<Result<Vec<_>, _>>()?.len(), 2);
+
+        let options = ParseOptions::new().strict(true).with_max_cells(2);
+        let text = String::from("#X Glider\nbo$2bo$3o!");
+        assert!(from_string_with_options(text, None, options).is_err());
+        let source = from_string_with_options(String::from("bo$2bo$3o!"), None, options)?;
+        assert!(source.collect::<Result<Vec<_>, _>>().is_err());
+        let text = String::from("xq4_1 53");
+        assert!(from_string_with_options(text, Some(Format::Apgcode), options).is_err());
+        Ok(())
+    }
+}
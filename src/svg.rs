@@ -0,0 +1,246 @@
+//! A writer for [SVG](https://www.w3.org/TR/SVG2/) images of patterns.
+//!
+//! Each horizontal run of cells with the same state is drawn as a single rectangle,
+//! so that the images of small patterns stay small and scale well in wiki pages and blogs.
+
+use crate::{CellData, State};
+use std::{
+    collections::BTreeMap,
+    io::{Error as IoError, Write},
+};
+
+/// How [`write_svg`] draws the cells.
+///
+/// # Example
+///
+/// ```rust
+/// use ca_formats::svg::Style;
+///
+/// let style = Style::new()
+///     .with_cell_size(8)
+///     .with_colors(["#ff0000", "#ffff00"])
+///     .with_background("white")
+///     .with_grid("#cccccc");
+/// assert_eq!(style.cell_size, 8);
+/// ```
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub struct Style {
+    /// Width and height of a cell, in pixels. The default is `10`.
+    pub cell_size: u32,
+    /// Colors of the states `1`, `2`, and so on, in any CSS syntax, e.g., `#ff0000`.
+    ///
+    /// States without a color use the last color. The default is black for all states.
+    pub colors: Vec<String>,
+    /// Color of the background. The default is transparent, i.e., `None`.
+    pub background: Option<String>,
+    /// Color of the grid lines between the cells. The default is no grid, i.e., `None`.
+    pub grid: Option<String>,
+}
+
+impl Default for Style {
+    fn default() -> Self {
+        Style {
+            cell_size: 10,
+            colors: vec![String::from("black")],
+            background: None,
+            grid: None,
+        }
+    }
+}
+
+impl Style {
+    /// The default style.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the width and height of a cell, in pixels.
+    pub fn with_cell_size(mut self, cell_size: u32) -> Self {
+        self.cell_size = cell_size;
+        self
+    }
+
+    /// Set the colors of the states `1`, `2`, and so on.
+    pub fn with_colors<I, T>(mut self, colors: I) -> Self
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<String>,
+    {
+        self.colors = colors.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Set the color of the background.
+    pub fn with_background<T: Into<String>>(mut self, background: T) -> Self {
+        self.background = Some(background.into());
+        self
+    }
+
+    /// Draw grid lines between the cells in a color.
+    pub fn with_grid<T: Into<String>>(mut self, grid: T) -> Self {
+        self.grid = Some(grid.into());
+        self
+    }
+
+    /// The color of a living state.
+    fn color(&self, state: u32) -> &str {
+        self.colors
+            .get(state as usize - 1)
+            .or(self.colors.last())
+            .map_or("black", String::as_str)
+    }
+}
+
+/// Escape a string for an attribute value.
+fn escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('"', "&quot;")
+        .replace('<', "&lt;")
+}
+
+/// Write the living cells as an SVG image.
+///
+/// The image covers the bounding box of the living cells. Each horizontal run of cells
+/// with the same state is a `<rect>` element. The cells can be given in any order.
+/// If a position appears more than once, only the last cell counts.
+/// Cells with state `0` are not drawn.
+///
+/// # Example
+///
+/// ```rust
+/// use ca_formats::{rle::Rle, svg::{write_svg, Style}};
+///
+/// let glider = Rle::new("bo$2bo$3o!").unwrap().map(Result::unwrap);
+/// let mut svg = Vec::new();
+/// write_svg(&mut svg, &Style::new(), glider).unwrap();
+/// let svg = String::from_utf8(svg).unwrap();
+/// assert!(svg.contains(r#"viewBox="0 0 30 30""#));
+/// assert!(svg.contains(r#"<rect x="0" y="20" width="30" height="10" fill="black"/>"#));
+/// ```
+pub fn write_svg<W, S, C>(mut writer: W, style: &Style, cells: C) -> Result<(), IoError>
+where
+    W: Write,
+    S: State,
+    C: IntoIterator<Item = CellData<S>>,
+{
+    let mut states = BTreeMap::new();
+    for cell in cells {
+        let (x, y) = cell.position;
+        states.insert((y, x), cell.state.into());
+    }
+    states.retain(|_, state: &mut u32| *state != 0);
+    let (min_x, max_x) = states
+        .keys()
+        .fold(None, |range, &(_, x)| match range {
+            Some((min_x, max_x)) => Some((x.min(min_x), x.max(max_x))),
+            None => Some((x, x)),
+        })
+        .unwrap_or((0, -1));
+    let min_y = states.keys().next().map_or(0, |&(y, _)| y);
+    let max_y = states.keys().next_back().map_or(-1, |&(y, _)| y);
+    let size = i64::from(style.cell_size);
+    let (width, height) = ((max_x - min_x + 1) * size, (max_y - min_y + 1) * size);
+
+    writeln!(
+        writer,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{0}" height="{1}" viewBox="0 0 {0} {1}">"#,
+        width, height
+    )?;
+    if let Some(background) = &style.background {
+        writeln!(
+            writer,
+            r#"<rect width="{}" height="{}" fill="{}"/>"#,
+            width,
+            height,
+            escape(background)
+        )?;
+    }
+    let mut run: Option<((i64, i64), i64, u32)> = None;
+    for (&(y, x), &state) in states.iter().chain([(&(i64::MAX, i64::MAX), &0)]) {
+        if let Some(((run_x, run_y), length, run_state)) = run {
+            if run_y == y && run_x.checked_add(length) == Some(x) && run_state == state {
+                run = Some(((run_x, run_y), length + 1, run_state));
+                continue;
+            }
+            writeln!(
+                writer,
+                r#"<rect x="{}" y="{}" width="{}" height="{}" fill="{}"/>"#,
+                (run_x - min_x) * size,
+                (run_y - min_y) * size,
+                length * size,
+                size,
+                escape(style.color(run_state))
+            )?;
+        }
+        run = Some(((x, y), 1, state));
+    }
+    if let Some(grid) = &style.grid {
+        let mut path = String::new();
+        for x in (0..=width).step_by(size.max(1) as usize) {
+            path.push_str(&format!("M{} 0V{}", x, height));
+        }
+        for y in (0..=height).step_by(size.max(1) as usize) {
+            path.push_str(&format!("M0 {}H{}", y, width));
+        }
+        writeln!(
+            writer,
+            r#"<path d="{}" stroke="{}" stroke-width="1" fill="none"/>"#,
+            path,
+            escape(grid)
+        )?;
+    }
+    writeln!(writer, "</svg>")?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{rle::Rle, ParseOptions};
+    use std::error::Error as StdError;
+
+    #[test]
+    fn svg_write() -> Result<(), Box<dyn StdError>> {
+        let options = ParseOptions::new().include_dead(true);
+        let cells = Rle::new_with_options("x = 4, y = 2, rule = 23/3/3\n2AB$3.A!", options)?
+            .collect::<Result<Vec<_>, _>>()?;
+        let style = Style::new()
+            .with_cell_size(2)
+            .with_colors(["red", "\"yellow\""])
+            .with_background("white")
+            .with_grid("gray");
+        let mut svg = Vec::new();
+        write_svg(&mut svg, &style, cells.into_iter().rev())?;
+        assert_eq!(
+            String::from_utf8(svg)?,
+            concat!(
+                r#"<svg xmlns="http://www.w3.org/2000/svg" width="8" height="4" viewBox="0 0 8 4">"#,
+                "\n",
+                r#"<rect width="8" height="4" fill="white"/>"#,
+                "\n",
+                r#"<rect x="0" y="0" width="4" height="2" fill="red"/>"#,
+                "\n",
+                r#"<rect x="4" y="0" width="2" height="2" fill="&quot;yellow&quot;"/>"#,
+                "\n",
+                r#"<rect x="6" y="2" width="2" height="2" fill="red"/>"#,
+                "\n",
+                r#"<path d="M0 0V4M2 0V4M4 0V4M6 0V4M8 0V4M0 0H8M0 2H8M0 4H8" stroke="gray" stroke-width="1" fill="none"/>"#,
+                "\n</svg>\n"
+            )
+        );
+
+        let mut svg = Vec::new();
+        write_svg(&mut svg, &Style::new(), Vec::<CellData>::new())?;
+        assert_eq!(
+            String::from_utf8(svg)?,
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"0\" height=\"0\" viewBox=\"0 0 0 0\">\n</svg>\n"
+        );
+
+        let mut svg = Vec::new();
+        let cells = [(0, 0), (2, 0), (1, 0)].map(CellData::from);
+        write_svg(&mut svg, &Style::new(), cells)?;
+        assert_eq!(String::from_utf8(svg)?.matches("<rect").count(), 1);
+        Ok(())
+    }
+}
@@ -0,0 +1,239 @@
+//! Helpers for testing that patterns survive a round-trip through the writers and the parsers.
+//!
+//! The generators are deterministic, so that a failing case can be reproduced from its seed.
+//!
+//! # Example
+//!
+//! ```rust
+//! use ca_formats::testing::{assert_roundtrip_plaintext, assert_roundtrip_rle, RandomCells};
+//!
+//! for cells in RandomCells::new(42).size(16, 16).states(3).take(10) {
+//!     assert_roundtrip_rle(&cells);
+//! }
+//!
+//! for cells in RandomCells::new(42).size(16, 16).take(10) {
+//!     let cells = cells.iter().map(|cell| cell.position).collect::<Vec<_>>();
+//!     assert_roundtrip_plaintext(&cells);
+//! }
+//! ```
+
+use crate::{
+    plaintext::{write_plaintext, Plaintext},
+    rle::{write_rle, Rle},
+    CellData, Coordinates,
+};
+
+/// A deterministic generator of random sets of cells.
+///
+/// As an iterator, it generates infinitely many cell sets.
+#[must_use]
+#[derive(Clone, Debug)]
+pub struct RandomCells {
+    /// State of the xorshift generator.
+    seed: u64,
+    /// Width of the generated patterns.
+    width: i64,
+    /// Height of the generated patterns.
+    height: i64,
+    /// Probability that a cell is alive.
+    density: f64,
+    /// Number of states, including the dead state.
+    states: u8,
+    /// Coordinates of the upper left corner.
+    offset: Coordinates,
+}
+
+impl RandomCells {
+    /// Creates a new generator from a seed.
+    ///
+    /// By default, it generates 2-state patterns in an 8x8 square at `(0, 0)`, with density `0.5`.
+    pub fn new(seed: u64) -> Self {
+        RandomCells {
+            seed: seed.max(1),
+            width: 8,
+            height: 8,
+            density: 0.5,
+            states: 2,
+            offset: (0, 0),
+        }
+    }
+
+    /// Sets the size of the generated patterns.
+    pub fn size(mut self, width: u32, height: u32) -> Self {
+        self.width = width.into();
+        self.height = height.into();
+        self
+    }
+
+    /// Sets the probability that a cell is alive.
+    pub fn density(mut self, density: f64) -> Self {
+        self.density = density;
+        self
+    }
+
+    /// Sets the number of states, including the dead state.
+    ///
+    /// Living cells get a random state from `1` to `states - 1`.
+    pub fn states(mut self, states: u8) -> Self {
+        self.states = states.max(2);
+        self
+    }
+
+    /// Sets the coordinates of the upper left corner of the generated patterns.
+    pub fn offset(mut self, offset: Coordinates) -> Self {
+        self.offset = offset;
+        self
+    }
+
+    /// Next random number.
+    fn next_u64(&mut self) -> u64 {
+        self.seed ^= self.seed << 13;
+        self.seed ^= self.seed >> 7;
+        self.seed ^= self.seed << 17;
+        self.seed
+    }
+
+    /// Generates a set of living cells, in row-major order.
+    pub fn generate(&mut self) -> Vec<CellData> {
+        let mut cells = Vec::new();
+        for y in 0..self.height {
+            for x in 0..self.width {
+                if (self.next_u64() >> 11) as f64 / (1_u64 << 53) as f64 >= self.density {
+                    continue;
+                }
+                let state = 1 + (self.next_u64() % u64::from(self.states - 1)) as u8;
+                cells.push(CellData {
+                    position: (self.offset.0 + x, self.offset.1 + y),
+                    state,
+                });
+            }
+        }
+        cells
+    }
+}
+
+/// An infinite iterator over random cell sets.
+impl Iterator for RandomCells {
+    type Item = Vec<CellData>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        Some(self.generate())
+    }
+}
+
+/// Sort the living cells in row-major order and remove duplicated positions.
+fn normalize(cells: &[CellData]) -> Vec<CellData> {
+    let mut cells = cells
+        .iter()
+        .copied()
+        .filter(|cell| cell.state != 0)
+        .collect::<Vec<_>>();
+    cells.sort_by_key(|cell| (cell.position.1, cell.position.0));
+    cells.dedup_by_key(|cell| cell.position);
+    cells
+}
+
+/// Translate the cells so that the upper left corner of the bounding box is `(0, 0)`.
+fn normalize_coordinates(cells: &[Coordinates]) -> Vec<Coordinates> {
+    let x_min = cells.iter().map(|&(x, _)| x).min().unwrap_or_default();
+    let y_min = cells.iter().map(|&(_, y)| y).min().unwrap_or_default();
+    let mut cells = cells
+        .iter()
+        .map(|&(x, y)| (x - x_min, y - y_min))
+        .collect::<Vec<_>>();
+    cells.sort_by_key(|&(x, y)| (y, x));
+    cells.dedup();
+    cells
+}
+
+/// Asserts that the cells are unchanged after being written in RLE format and parsed again.
+///
+/// The order of the cells, dead cells and duplicated positions are not taken into account.
+///
+/// # Panics
+///
+/// Panics if the round-trip fails. The message contains the written RLE.
+#[track_caller]
+pub fn assert_roundtrip_rle(cells: &[CellData]) {
+    let mut rle = Vec::new();
+    write_rle(&mut rle, None, cells.iter().copied()).expect("failed to write RLE");
+    let rle = String::from_utf8(rle).expect("the written RLE is not valid UTF-8");
+    let parsed = Rle::new(rle.as_str())
+        .and_then(|parsed| parsed.collect::<Result<Vec<_>, _>>())
+        .unwrap_or_else(|e| panic!("failed to parse the written RLE: {}\n{}", e, rle));
+    assert_eq!(
+        normalize(&parsed),
+        normalize(cells),
+        "RLE round-trip failed:\n{}",
+        rle
+    );
+}
+
+/// Asserts that the cells are unchanged, up to translation,
+/// after being written in Plaintext format and parsed again.
+///
+/// The order of the cells and duplicated positions are not taken into account.
+///
+/// # Panics
+///
+/// Panics if the round-trip fails. The message contains the written Plaintext.
+#[track_caller]
+pub fn assert_roundtrip_plaintext(cells: &[Coordinates]) {
+    let mut plaintext = Vec::new();
+    write_plaintext(&mut plaintext, cells.iter().copied()).expect("failed to write Plaintext");
+    let plaintext = String::from_utf8(plaintext).expect("the written Plaintext is not valid UTF-8");
+    let parsed = Plaintext::new(plaintext.as_str())
+        .and_then(|parsed| parsed.collect::<Result<Vec<_>, _>>())
+        .unwrap_or_else(|e| {
+            panic!(
+                "failed to parse the written Plaintext: {}\n{}",
+                e, plaintext
+            )
+        });
+    assert_eq!(
+        normalize_coordinates(&parsed),
+        normalize_coordinates(cells),
+        "Plaintext round-trip failed:\n{}",
+        plaintext
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn testing_random_cells() {
+        let mut random = RandomCells::new(1).size(4, 3).density(1.0).offset((-2, 5));
+        let cells = random.generate();
+        assert_eq!(cells.len(), 12);
+        assert_eq!(cells[0].position, (-2, 5));
+        assert_eq!(cells[11].position, (1, 7));
+        assert!(cells.iter().all(|cell| cell.state == 1));
+
+        assert!(RandomCells::new(1).density(0.0).generate().is_empty());
+        assert_eq!(
+            RandomCells::new(7).take(3).collect::<Vec<_>>(),
+            RandomCells::new(7).take(3).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn testing_roundtrip() {
+        for cells in RandomCells::new(3)
+            .size(100, 20)
+            .density(0.3)
+            .offset((-50, 7))
+            .take(10)
+        {
+            assert_roundtrip_rle(&cells);
+            let cells = cells.iter().map(|cell| cell.position).collect::<Vec<_>>();
+            assert_roundtrip_plaintext(&cells);
+        }
+        for cells in RandomCells::new(5).size(30, 30).states(255).take(10) {
+            assert_roundtrip_rle(&cells);
+        }
+        assert_roundtrip_rle(&[]);
+        assert_roundtrip_plaintext(&[]);
+    }
+}
@@ -0,0 +1,374 @@
+//! Bounded grids, as in the suffixes of rulestrings in
+//! [Golly](https://golly.sourceforge.io/Help/bounded.html), e.g., `B3/S23:T30,20`.
+//!
+//! [`BoundedGrid`] parses the suffix, and [`Wrap`] wraps the coordinates of the cells
+//! returned by a parser into the grid, so that they are valid in the bounded universe.
+
+use crate::{scan::Scanner, CellData, Coordinates};
+use displaydoc::Display;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+use thiserror::Error;
+
+/// Errors that can be returned when parsing a bounded grid.
+#[derive(Clone, Debug, Eq, Error, Display, PartialEq)]
+pub enum Error {
+    /// Invalid bounded grid at column {1}: {0}.
+    InvalidGrid(String, usize),
+}
+
+/// How the edges of a [`BoundedGrid`] are joined.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Topology {
+    /// `P`: the edges are not joined, and there are no cells outside the grid.
+    Plane,
+    /// `T`: the opposite edges are joined, possibly with a shift.
+    Torus,
+    /// `K`: the opposite edges are joined, one pair of them with a twist.
+    KleinBottle,
+    /// `C`: the opposite edges are joined, both pairs of them with a twist.
+    CrossSurface,
+    /// `S`: the top edge is joined to the left edge, and the right edge to the bottom edge.
+    Sphere,
+}
+
+/// A bounded grid, e.g., `T30,20` in the rulestring `B3/S23:T30,20`.
+///
+/// As in Golly, the grid is centered at `(0, 0)`: the x coordinates go from
+/// `-(width / 2)` to `width - width / 2 - 1`, and similarly for the y coordinates.
+/// A width or a height of `0` means that the grid is infinite in that direction.
+///
+/// # Example
+///
+/// ```rust
+/// use ca_formats::topology::{BoundedGrid, Topology};
+///
+/// let grid = BoundedGrid::from_rule("B3/S23:T10+2,4").unwrap().unwrap();
+/// assert_eq!(grid.topology, Topology::Torus);
+/// assert_eq!((grid.width, grid.height, grid.x_shift), (10, 4, 2));
+/// assert_eq!(grid.wrap((6, 0)), Some((-4, 0)));
+/// assert_eq!(grid.wrap((0, 2)), Some((2, -2)));
+/// assert_eq!(BoundedGrid::from_rule("B3/S23").unwrap(), None);
+/// ```
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct BoundedGrid {
+    /// How the edges are joined.
+    pub topology: Topology,
+    /// Width of the grid, or `0` if it is infinite horizontally.
+    pub width: u32,
+    /// Height of the grid, or `0` if it is infinite vertically.
+    pub height: u32,
+    /// Shift of the x coordinates when crossing the top or the bottom edge of a torus,
+    /// e.g., `2` in `T10+2,4`.
+    pub x_shift: i32,
+    /// Shift of the y coordinates when crossing the left or the right edge of a torus,
+    /// e.g., `-1` in `T10,4-1`.
+    pub y_shift: i32,
+    /// Whether the x coordinates are reversed when crossing the top or the bottom edge,
+    /// e.g., `*` in `K10*,4`.
+    pub x_twist: bool,
+    /// Whether the y coordinates are reversed when crossing the left or the right edge,
+    /// e.g., `*` in `K10,4*`.
+    pub y_twist: bool,
+}
+
+/// A dimension of the grid in the suffix, e.g., `10+2` or `4*`.
+#[derive(Clone, Copy)]
+struct Dimension {
+    /// The size.
+    size: u32,
+    /// The shift after the size.
+    shift: i32,
+    /// Whether there is a `*` after the size.
+    twist: bool,
+}
+
+/// Read a dimension of the grid, with an optional shift or `*`.
+fn parse_dimension(scanner: &mut Scanner) -> Result<Dimension, usize> {
+    let size = scanner.number(false)?;
+    let shift = if scanner.eat(b"+") {
+        scanner.number(false)?
+    } else if scanner.peek() == Some(b'-') {
+        scanner.number(true)?
+    } else {
+        0
+    };
+    let twist = scanner.eat(b"*");
+    Ok(Dimension { size, shift, twist })
+}
+
+/// The range of the coordinates in a dimension of the grid, as in Golly.
+fn range(size: u32) -> (i128, i128) {
+    let start = -i128::from(size / 2);
+    (start, start + i128::from(size) - 1)
+}
+
+impl BoundedGrid {
+    /// Parses a bounded grid without the `:`, e.g., `T30,20`.
+    pub fn parse(suffix: &str) -> Result<Self, Error> {
+        let mut scanner = Scanner::new(suffix.as_bytes());
+        Self::scan(&mut scanner)
+            .and_then(|grid| scanner.end().map(|()| grid))
+            .map_err(|column| Error::InvalidGrid(suffix.to_string(), column))
+    }
+
+    /// Parses the bounded grid after the `:` of a rulestring, e.g., `B3/S23:T30,20`.
+    ///
+    /// Returns `Ok(None)` if the rulestring has no `:`.
+    pub fn from_rule(rule: &str) -> Result<Option<Self>, Error> {
+        rule.split_once(':')
+            .map(|(_, suffix)| Self::parse(suffix))
+            .transpose()
+    }
+
+    /// Read a bounded grid, and check that the shifts and the twists fit the topology.
+    fn scan(scanner: &mut Scanner) -> Result<Self, usize> {
+        let topology = match scanner.peek().map(|c| c.to_ascii_uppercase()) {
+            Some(b'P') => Topology::Plane,
+            Some(b'T') => Topology::Torus,
+            Some(b'K') => Topology::KleinBottle,
+            Some(b'C') => Topology::CrossSurface,
+            Some(b'S') => Topology::Sphere,
+            _ => return Err(scanner.column()),
+        };
+        scanner.advance();
+        let start = scanner.column();
+        let x = parse_dimension(scanner)?;
+        let y = if topology != Topology::Sphere && scanner.eat(b",") {
+            parse_dimension(scanner)?
+        } else {
+            x
+        };
+        let shifts = (x.shift != 0, y.shift != 0);
+        let twists = (x.twist, y.twist);
+        let valid = match topology {
+            Topology::Torus => twists == (false, false) && shifts != (true, true),
+            Topology::KleinBottle => twists.0 != twists.1 && shifts == (false, false),
+            Topology::CrossSurface => {
+                shifts == (false, false) && twists == (false, false) && x.size != 0 && y.size != 0
+            }
+            _ => shifts == (false, false) && twists == (false, false),
+        };
+        let bounded =
+            (x.size != 0 || !(x.twist || shifts.0)) && (y.size != 0 || !(y.twist || shifts.1));
+        if !valid || !bounded {
+            return Err(start);
+        }
+        Ok(BoundedGrid {
+            topology,
+            width: x.size,
+            height: y.size,
+            x_shift: x.shift,
+            y_shift: y.shift,
+            x_twist: x.twist || topology == Topology::CrossSurface,
+            y_twist: y.twist || topology == Topology::CrossSurface,
+        })
+    }
+
+    /// Whether a cell is in the grid.
+    pub fn contains(&self, (x, y): Coordinates) -> bool {
+        let inside = |v: i64, size: u32| {
+            let (start, end) = range(size);
+            size == 0 || (start..=end).contains(&i128::from(v))
+        };
+        inside(x, self.width) && inside(y, self.height)
+    }
+
+    /// Wraps the coordinates of a cell into the grid, by joining the edges.
+    ///
+    /// Returns `None` if the cell is outside a plane or a sphere, where the coordinates
+    /// outside the grid don't correspond to cells in the grid.
+    /// On a cross-surface, the coordinates are first wrapped vertically, then horizontally.
+    pub fn wrap(&self, (x, y): Coordinates) -> Option<Coordinates> {
+        if self.contains((x, y)) {
+            return Some((x, y));
+        }
+        if matches!(self.topology, Topology::Plane | Topology::Sphere) {
+            return None;
+        }
+        let (mut x, mut y) = (i128::from(x), i128::from(y));
+        if self.y_shift != 0 {
+            (x, y) = self.wrap_x(x, y);
+            (y, x) = self.wrap_y(y, x);
+        } else {
+            (y, x) = self.wrap_y(y, x);
+            (x, y) = self.wrap_x(x, y);
+        }
+        Some((x as i64, y as i64))
+    }
+
+    /// Wraps the x coordinate across the left and the right edges, and adjusts
+    /// the y coordinate with the shift or the twist.
+    fn wrap_x(&self, x: i128, y: i128) -> (i128, i128) {
+        wrap_dimension(x, y, self.width, self.height, self.y_shift, self.y_twist)
+    }
+
+    /// Wraps the y coordinate across the top and the bottom edges, and adjusts
+    /// the x coordinate with the shift or the twist.
+    fn wrap_y(&self, y: i128, x: i128) -> (i128, i128) {
+        wrap_dimension(y, x, self.height, self.width, self.x_shift, self.x_twist)
+    }
+
+    /// Wraps the coordinates of the cells returned by a parser. See [`Wrap`].
+    pub fn wrap_cells<C: IntoIterator>(&self, cells: C) -> Wrap<C::IntoIter> {
+        Wrap {
+            grid: *self,
+            cells: cells.into_iter(),
+        }
+    }
+}
+
+/// Wraps a coordinate `v` into a dimension of size `size`, and adjusts the coordinate `w`
+/// of the other dimension of size `other_size` with a shift or a twist
+/// for each crossing of the edges.
+fn wrap_dimension(
+    v: i128,
+    w: i128,
+    size: u32,
+    other_size: u32,
+    shift: i32,
+    twist: bool,
+) -> (i128, i128) {
+    if size == 0 {
+        return (v, w);
+    }
+    let (start, end) = range(size);
+    let crossings = (v - start).div_euclid(i128::from(size));
+    let v = v - crossings * i128::from(size);
+    let mut w = w + crossings * i128::from(shift);
+    if twist && crossings % 2 != 0 {
+        let (other_start, other_end) = range(other_size);
+        w = other_start + other_end - w;
+    }
+    if other_size != 0 && shift != 0 {
+        let (other_start, _) = range(other_size);
+        w = (w - other_start).rem_euclid(i128::from(other_size)) + other_start;
+    }
+    debug_assert!(start <= v && v <= end);
+    (v, w)
+}
+
+impl FromStr for BoundedGrid {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Error> {
+        Self::parse(s)
+    }
+}
+
+/// An iterator adapter that wraps the coordinates of the cells into a [`BoundedGrid`],
+/// created by [`BoundedGrid::wrap_cells`].
+///
+/// Cells that are outside a plane or a sphere are skipped. Different cells may be
+/// wrapped to the same position, e.g., when a pattern is larger than a torus.
+/// Errors are returned as they are.
+///
+/// # Example
+///
+/// ```rust
+/// use ca_formats::{rle::Rle, topology::BoundedGrid};
+///
+/// let rle = Rle::new("x = 4, y = 1, rule = B3/S23:T4,4\n4o!").unwrap();
+/// let rule = rle.rule_info().unwrap().rule;
+/// let grid = BoundedGrid::from_rule(&rule).unwrap().unwrap();
+/// let cells = grid.wrap_cells(rle).map(|cell| cell.unwrap().position);
+/// assert_eq!(cells.collect::<Vec<_>>(), [(0, 0), (1, 0), (-2, 0), (-1, 0)]);
+/// ```
+#[must_use]
+#[derive(Clone, Debug)]
+pub struct Wrap<C> {
+    /// The grid.
+    grid: BoundedGrid,
+    /// The cells.
+    cells: C,
+}
+
+impl<C> Wrap<C> {
+    /// The grid.
+    pub const fn grid(&self) -> &BoundedGrid {
+        &self.grid
+    }
+}
+
+impl<C, S, E> Iterator for Wrap<C>
+where
+    C: Iterator<Item = Result<CellData<S>, E>>,
+{
+    type Item = Result<CellData<S>, E>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            match self.cells.next()? {
+                Ok(cell) => {
+                    if let Some(position) = self.grid.wrap(cell.position) {
+                        return Some(Ok(CellData { position, ..cell }));
+                    }
+                }
+                Err(e) => return Some(Err(e)),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn topology_wrap() -> Result<(), Error> {
+        let torus = BoundedGrid::parse("T4,3")?;
+        assert_eq!(
+            (torus.wrap((2, 0)), torus.wrap((-3, -2))),
+            (Some((-2, 0)), Some((1, 1)))
+        );
+        assert_eq!(torus.wrap((i64::MAX, i64::MIN)), Some((-1, 1)));
+        let shifted = BoundedGrid::parse("t4,3-1")?;
+        assert_eq!(
+            (shifted.y_shift, shifted.wrap((2, 0))),
+            (-1, Some((-2, -1)))
+        );
+        assert_eq!(shifted.wrap((2, -1)), Some((-2, 1)));
+
+        let klein = BoundedGrid::parse("K4*,3")?;
+        assert!(klein.x_twist && !klein.y_twist);
+        assert_eq!(klein.wrap((-2, 2)), Some((1, -1)));
+        assert_eq!(klein.wrap((-2, 5)), Some((-2, -1)));
+        let cross = BoundedGrid::parse("C4,4")?;
+        assert_eq!(cross.wrap((2, -3)), Some((1, -2)));
+
+        let tube = BoundedGrid::parse("T0,4")?;
+        assert_eq!(tube.wrap((1000, 2)), Some((1000, -2)));
+        let plane = BoundedGrid::parse("P5")?;
+        assert_eq!(
+            (plane.height, plane.wrap((2, -2)), plane.wrap((3, 0))),
+            (5, Some((2, -2)), None)
+        );
+        let sphere = BoundedGrid::from_rule("B3/S23:S6")?;
+        assert_eq!(sphere.map(|grid| grid.height), Some(6));
+
+        for invalid in [
+            "T4*,3", "K4,3", "K4*,3*", "T4+1,3+1", "P4+1", "S4,4", "T0+1,3", "C4,0", "X4", "T4,",
+            "T4,3;",
+        ] {
+            assert_eq!(
+                BoundedGrid::parse(invalid).map_err(|Error::InvalidGrid(grid, _)| grid),
+                Err(invalid.to_string())
+            );
+        }
+
+        let cells = [
+            Ok::<_, Error>(CellData::from((7, 0))),
+            Ok(CellData::from((4, 9))),
+        ];
+        let wrapped = BoundedGrid::parse("P10,0")?.wrap_cells(cells);
+        assert_eq!(wrapped.grid().width, 10);
+        assert_eq!(
+            wrapped.collect::<Result<Vec<_>, _>>()?,
+            [CellData::from((4, 9))]
+        );
+        Ok(())
+    }
+}
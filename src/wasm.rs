@@ -0,0 +1,210 @@
+//! Thin [`wasm-bindgen`](https://crates.io/crates/wasm-bindgen) wrappers around the parsers,
+//! for Life viewers running in a browser.
+//!
+//! Cells are returned as flat arrays of coordinates, i.e., `[x0, y0, x1, y1, ...]`,
+//! which become `Int32Array`s in JavaScript. States of the cells are returned in
+//! a separate array.
+//!
+//! Errors are thrown as JavaScript `Error`s.
+//!
+//! The wrappers are exported by any `cdylib` crate that depends on this crate with
+//! the `wasm` feature enabled, e.g., when it is built with `wasm-pack`.
+//!
+//! # Example
+//!
+//! ```javascript
+//! import { parse_rle, RlePattern } from "ca-formats";
+//!
+//! const cells = parse_rle("x = 3, y = 3\nbo$2bo$3o!");
+//! // Int32Array [1, 0, 2, 1, 0, 2, 1, 2, 2, 2]
+//!
+//! const glider = new RlePattern("x = 3, y = 3, rule = B3/S23\nbo$2bo$3o!");
+//! console.log(glider.width, glider.height, glider.rule);
+//! // 3 3 "B3/S23"
+//! ```
+
+use crate::{
+    apgcode::{self, ApgCode},
+    plaintext::{self, Plaintext},
+    rle::{self, Rle},
+    CellData, Coordinates,
+};
+use displaydoc::Display;
+use thiserror::Error;
+use wasm_bindgen::prelude::*;
+
+/// Errors that can be returned by the wrappers.
+#[derive(Clone, Debug, Eq, Error, Display, PartialEq)]
+pub enum Error {
+    /// {0}
+    Rle(#[from] rle::Error),
+    /// {0}
+    Plaintext(#[from] plaintext::Error),
+    /// {0}
+    ApgCode(#[from] apgcode::Error),
+    /// Coordinates out of the range of 32-bit integers: {0:?}.
+    OutOfRange(Coordinates),
+}
+
+/// Append the coordinates to a flat array.
+fn push_coordinates(flat: &mut Vec<i32>, (x, y): Coordinates) -> Result<(), Error> {
+    let out_of_range = |_| Error::OutOfRange((x, y));
+    flat.push(i32::try_from(x).map_err(out_of_range)?);
+    flat.push(i32::try_from(y).map_err(out_of_range)?);
+    Ok(())
+}
+
+/// Flatten an iterator of coordinates.
+fn flatten<C, E>(cells: C) -> Result<Vec<i32>, Error>
+where
+    C: IntoIterator<Item = Result<Coordinates, E>>,
+    Error: From<E>,
+{
+    let mut flat = Vec::new();
+    for cell in cells {
+        push_coordinates(&mut flat, cell?)?;
+    }
+    Ok(flat)
+}
+
+/// A parsed RLE file.
+#[wasm_bindgen]
+#[derive(Clone, Debug, Default)]
+pub struct RlePattern {
+    /// Flat array of coordinates.
+    cells: Vec<i32>,
+    /// States of the cells.
+    states: Vec<u8>,
+    /// Data from the header line.
+    header_data: Option<rle::HeaderData>,
+    /// Data from the `#CXRLE` line.
+    cxrle_data: Option<rle::CxrleData>,
+}
+
+impl RlePattern {
+    /// Parse an RLE string.
+    fn parse(text: &str) -> Result<Self, Error> {
+        let rle = Rle::new(text)?;
+        let mut pattern = RlePattern {
+            header_data: rle.header_data().cloned(),
+            cxrle_data: rle.cxrle_data().cloned(),
+            ..RlePattern::default()
+        };
+        for cell in rle {
+            let CellData { position, state } = cell?;
+            push_coordinates(&mut pattern.cells, position)?;
+            pattern.states.push(state);
+        }
+        Ok(pattern)
+    }
+}
+
+#[wasm_bindgen]
+impl RlePattern {
+    /// Parses an RLE string.
+    #[wasm_bindgen(constructor)]
+    pub fn new(text: &str) -> Result<RlePattern, JsError> {
+        Ok(Self::parse(text)?)
+    }
+
+    /// Coordinates of the living cells, as a flat array `[x0, y0, x1, y1, ...]`.
+    #[wasm_bindgen(getter)]
+    pub fn cells(&self) -> Vec<i32> {
+        self.cells.clone()
+    }
+
+    /// States of the living cells, in the same order as [`cells`](RlePattern::cells).
+    #[wasm_bindgen(getter)]
+    pub fn states(&self) -> Vec<u8> {
+        self.states.clone()
+    }
+
+    /// Width in the header line.
+    #[wasm_bindgen(getter)]
+    pub fn width(&self) -> Option<u64> {
+        self.header_data.as_ref().map(|data| data.x)
+    }
+
+    /// Height in the header line.
+    #[wasm_bindgen(getter)]
+    pub fn height(&self) -> Option<u64> {
+        self.header_data.as_ref().map(|data| data.y)
+    }
+
+    /// Rulestring in the header line.
+    #[wasm_bindgen(getter)]
+    pub fn rule(&self) -> Option<String> {
+        self.header_data.as_ref()?.rule.clone()
+    }
+
+    /// Coordinates of the upper left corner in the `#CXRLE` line, as `[x, y]`.
+    #[wasm_bindgen(getter)]
+    pub fn pos(&self) -> Option<Vec<i64>> {
+        let (x, y) = self.cxrle_data.as_ref()?.pos?;
+        Some(vec![x, y])
+    }
+
+    /// Generation in the `#CXRLE` line.
+    #[wasm_bindgen(getter)]
+    pub fn generation(&self) -> Option<u64> {
+        self.cxrle_data.as_ref()?.gen
+    }
+}
+
+/// Parses an RLE string, and returns the coordinates of the living cells
+/// as a flat array `[x0, y0, x1, y1, ...]`.
+#[wasm_bindgen]
+pub fn parse_rle(text: &str) -> Result<Vec<i32>, JsError> {
+    Ok(flatten(
+        Rle::new(text)?.map(|cell| cell.map(|cell| cell.position)),
+    )?)
+}
+
+/// Parses a Plaintext string, and returns the coordinates of the living cells
+/// as a flat array `[x0, y0, x1, y1, ...]`.
+#[wasm_bindgen]
+pub fn parse_plaintext(text: &str) -> Result<Vec<i32>, JsError> {
+    Ok(flatten(Plaintext::new(text)?)?)
+}
+
+/// Parses an apgcode, and returns the coordinates of the living cells
+/// as a flat array `[x0, y0, x1, y1, ...]`.
+#[wasm_bindgen]
+pub fn parse_apgcode(text: &str) -> Result<Vec<i32>, JsError> {
+    Ok(flatten(ApgCode::new(text)?)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wasm_rle_pattern() -> Result<(), Error> {
+        let glider =
+            RlePattern::parse("#CXRLE Pos=-1,-1\nx = 3, y = 3, rule = B3/S23\nbo$2bA$3o!")?;
+        assert_eq!(glider.cells(), vec![0, -1, 1, 0, -1, 1, 0, 1, 1, 1]);
+        assert_eq!(glider.states(), vec![1; 5]);
+        assert_eq!(glider.width(), Some(3));
+        assert_eq!(glider.height(), Some(3));
+        assert_eq!(glider.rule().as_deref(), Some("B3/S23"));
+        assert_eq!(glider.pos(), Some(vec![-1, -1]));
+        assert_eq!(glider.generation(), None);
+
+        let far = RlePattern::parse("#CXRLE Pos=3000000000,0\no!");
+        assert!(matches!(far, Err(Error::OutOfRange((3000000000, 0)))));
+        Ok(())
+    }
+
+    #[test]
+    fn wasm_flatten() -> Result<(), Error> {
+        let glider = vec![(1, 0), (2, 1), (0, 2), (1, 2), (2, 2)];
+        let flat = vec![1, 0, 2, 1, 0, 2, 1, 2, 2, 2];
+        assert_eq!(flatten(Plaintext::new(".O.\n..O\nOOO")?)?, flat);
+        assert_eq!(
+            flatten(glider.into_iter().map(Ok::<_, plaintext::Error>))?,
+            flat
+        );
+        assert_eq!(flatten(ApgCode::new("xq4_153")?)?.len(), 10);
+        Ok(())
+    }
+}
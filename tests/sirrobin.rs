@@ -1,8 +1,9 @@
 use ca_formats::{
-    apgcode::ApgCode,
+    apgcode::{encode_wechsler, ApgCode},
     macrocell::Macrocell,
     plaintext::Plaintext,
     rle::{HeaderData, Rle},
+    sorted, Pattern,
 };
 use std::{error::Error, fs::File};
 
@@ -16,7 +17,8 @@ fn rle_sirrobin() -> Result<(), Box<dyn Error>> {
         Some(&HeaderData {
             x: 31,
             y: 79,
-            rule: Some(String::from("B3/S23"))
+            rule: Some(String::from("B3/S23")),
+            ..HeaderData::default()
         })
     );
 
@@ -35,6 +37,17 @@ fn plaintext_sirrobin() -> Result<(), Box<dyn Error>> {
     Ok(())
 }
 
+#[test]
+fn population_sirrobin() -> Result<(), Box<dyn Error>> {
+    let file = File::open("tests/sirrobin.rle")?;
+    assert_eq!(Rle::new_from_file(file)?.population()?, 282);
+
+    let file = File::open("tests/sirrobin.cells")?;
+    assert_eq!(Plaintext::new_from_file(file)?.population()?, 282);
+
+    Ok(())
+}
+
 #[test]
 fn apgcode_sirrobin() -> Result<(), Box<dyn Error>> {
     let apgcode = "xq6_yyocxukcy6gocs20h0a38bac2qq73uszyjo4w8y0e4mo0vu0o606s6444u08clav0h03g440qq1333333x11zy9ecec2ik032i210sw3f0hy011w70401011033547442zy0emj896he1e1kif6q2gc50ew9qb30dzgo403gg066m32w11z34407q441n6zy311";
@@ -47,6 +60,16 @@ fn apgcode_sirrobin() -> Result<(), Box<dyn Error>> {
     Ok(())
 }
 
+#[test]
+fn encode_wechsler_sirrobin() -> Result<(), Box<dyn Error>> {
+    let apgcode = "xq6_yyocxukcy6gocs20h0a38bac2qq73uszyjo4w8y0e4mo0vu0o606s6444u08clav0h03g440qq1333333x11zy9ecec2ik032i210sw3f0hy011w70401011033547442zy0emj896he1e1kif6q2gc50ew9qb30dzgo403gg066m32w11z34407q441n6zy311";
+    let cells = ApgCode::new(apgcode)?.collect::<Result<Vec<_>, _>>()?;
+
+    assert_eq!(encode_wechsler(cells)?, &apgcode[4..]);
+
+    Ok(())
+}
+
 #[test]
 fn macrocell_sirrobin() -> Result<(), Box<dyn Error>> {
     let file = File::open("tests/sirrobin.mc")?;
@@ -58,3 +81,82 @@ fn macrocell_sirrobin() -> Result<(), Box<dyn Error>> {
 
     Ok(())
 }
+
+#[test]
+fn macrocell_cells_sirrobin() -> Result<(), Box<dyn Error>> {
+    let file = File::open("tests/sirrobin.mc")?;
+    let sirrobin = Macrocell::new_from_file(file)?;
+
+    assert_eq!(sirrobin.cells()?.len(), 282);
+
+    Ok(())
+}
+
+#[test]
+fn sorted_sirrobin() -> Result<(), Box<dyn Error>> {
+    let file = File::open("tests/sirrobin.rle")?;
+    let rle = Rle::new_from_file(file)?
+        .map(|cell| cell.map(|cell| cell.position))
+        .collect::<Result<Vec<_>, _>>()?;
+    assert_eq!(
+        sorted(rle.iter().copied().map(Ok::<_, ()>)),
+        Ok(rle.clone())
+    );
+
+    let file = File::open("tests/sirrobin.cells")?;
+    let plaintext = Plaintext::new_from_file(file)?.collect::<Result<Vec<_>, _>>()?;
+    assert_eq!(plaintext, rle);
+
+    let file = File::open("tests/sirrobin.mc")?;
+    let mut macrocell = Pattern::from_macrocell(Macrocell::new_from_file(file)?)?;
+    let (x, y, _, _) = macrocell.bounding_box().unwrap();
+    macrocell.sort();
+    let macrocell = macrocell
+        .cells
+        .iter()
+        .map(|cell| (cell.position.0 - x, cell.position.1 - y))
+        .collect::<Vec<_>>();
+    assert_eq!(macrocell, rle);
+
+    let apgcode = ApgCode::new("xq4_153")?;
+    let cells = apgcode.clone().collect::<Result<Vec<_>, _>>()?;
+    let sorted_cells = sorted(apgcode)?;
+    assert_ne!(cells, sorted_cells);
+    assert_eq!(sorted_cells, vec![(0, 0), (1, 0), (2, 0), (2, 1), (1, 2)]);
+
+    Ok(())
+}
+
+#[test]
+fn bounding_box_sirrobin() -> Result<(), Box<dyn Error>> {
+    let file = File::open("tests/sirrobin.rle")?;
+    let rle = Rle::new_from_file(file)?.bounding_box()?;
+    assert_eq!(rle, Some((0, 0, 30, 78)));
+
+    let file = File::open("tests/sirrobin.cells")?;
+    let plaintext = Plaintext::new_from_file(file)?.bounding_box()?;
+    assert_eq!(plaintext, rle);
+
+    let file = File::open("tests/sirrobin.mc")?;
+    let macrocell = Macrocell::new_from_file(file)?.bounding_box()?;
+    let file = File::open("tests/sirrobin.mc")?;
+    let pattern = Pattern::from_macrocell(Macrocell::new_from_file(file)?)?;
+    let (min_x, min_y, max_x, max_y) = pattern.bounding_box().unwrap();
+    assert_eq!(macrocell, pattern.bounding_box());
+    assert_eq!(max_x - min_x, 30);
+    assert_eq!(max_y - min_y, 78);
+
+    Ok(())
+}
+
+#[cfg(feature = "rayon")]
+#[test]
+fn par_cells_sirrobin() -> Result<(), Box<dyn Error>> {
+    let file = File::open("tests/sirrobin.rle")?;
+    let cells = Rle::new_from_file(file)?.collect::<Result<Vec<_>, _>>()?;
+
+    let file = File::open("tests/sirrobin.rle")?;
+    assert_eq!(Rle::new_from_file(file)?.par_cells()?, cells);
+
+    Ok(())
+}